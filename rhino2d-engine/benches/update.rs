@@ -0,0 +1,115 @@
+use std::time::Duration;
+
+use criterion::{criterion_group, criterion_main, Criterion};
+use rhino2d_engine::PuppetEngine;
+use rhino2d_io::{testutil, InochiPuppet};
+use serde_json::{json, Value};
+
+/// Builds a puppet with `count` drawables, with many of them sharing a Z-Sort value.
+///
+/// The Z-Sort of each of the first `animated` drawables is driven by a parameter named after its
+/// index.
+fn drawables(count: u32, animated: u32) -> InochiPuppet {
+    let children = (0..count)
+        .map(|i| json!({ "type": "Drawable", "uuid": i + 1, "zsort": (i % 16) as f32 }))
+        .collect::<Vec<Value>>();
+    let params = (0..animated)
+        .map(|i| {
+            json!({
+                "uuid": 100_000 + i, "name": i.to_string(),
+                "bindings": [{ "node": i + 1, "param_name": "zSort", "values": [[0.0, 16.0]] }],
+            })
+        })
+        .collect::<Vec<Value>>();
+    puppet(children, params)
+}
+
+/// Builds a puppet with `count` chains of `depth` nested nodes below the root node.
+///
+/// `depth` is limited by the nesting depth the JSON parser accepts.
+fn nested(count: u32, depth: u32) -> InochiPuppet {
+    let chain = |chain: u32| {
+        (0..depth).rev().fold(None, |child: Option<Value>, level| {
+            let mut node = json!({
+                "type": "Node", "uuid": 1 + chain * depth + level,
+                "transform": { "trans": [1.0, 0.0, 0.0], "rot": [0.0, 0.0, 0.1], "scale": [1.0, 1.0] },
+            });
+            if let Some(child) = child {
+                node["children"] = json!([child]);
+            }
+            Some(node)
+        })
+    };
+    puppet((0..count).filter_map(chain).collect(), Vec::new())
+}
+
+fn puppet(children: Vec<Value>, params: Vec<Value>) -> InochiPuppet {
+    let root = json!({ "type": "Node", "uuid": 0, "name": "root", "children": children });
+    testutil::puppet(&root.to_string(), &Value::from(params).to_string())
+}
+
+fn update(c: &mut Criterion) {
+    let mut engine = PuppetEngine::new(&drawables(10_000, 0)).unwrap();
+    c.bench_function("update 10k drawables", |b| {
+        b.iter(|| {
+            // Unchanged models are not recomputed, so invalidate the last frame.
+            engine.set_global_opacity(1.0);
+            engine.update(Duration::from_millis(16)).len()
+        })
+    });
+
+    let mut engine = PuppetEngine::new(&drawables(5000, 5)).unwrap();
+    let mut frame = 0;
+    c.bench_function("update 5k drawables, 5 changing Z-Sorts", |b| {
+        b.iter(|| {
+            frame += 1;
+            for i in 0..5 {
+                let value = ((frame * 7 + i * 3) % 11) as f32 / 10.0;
+                engine.set_param(&*i.to_string(), [value, 0.0]);
+            }
+            engine.update(Duration::from_millis(16)).len()
+        })
+    });
+}
+
+fn update_nested(c: &mut Criterion) {
+    let mut engine = PuppetEngine::new(&nested(300, 30)).unwrap();
+    c.bench_function("update 300 chains of 30 nested nodes", |b| {
+        b.iter(|| {
+            engine.set_global_opacity(1.0);
+            engine.update(Duration::from_millis(16)).len()
+        })
+    });
+}
+
+/// Compares updating on a single thread with updating subtrees in parallel, at the model sizes
+/// around the default threshold of the `parallel` feature.
+#[cfg(feature = "parallel")]
+fn update_parallel(c: &mut Criterion) {
+    use rhino2d_engine::EngineConfig;
+
+    for (name, puppet) in [
+        ("500 drawables", drawables(500, 0)),
+        ("1k drawables", drawables(1000, 0)),
+        ("10k drawables", drawables(10_000, 0)),
+        ("300 chains of 30 nested nodes", nested(300, 30)),
+    ] {
+        for (mode, threshold) in [("sequential", usize::MAX), ("parallel", 0)] {
+            let mut config = EngineConfig::new();
+            config.set_parallel_threshold(threshold);
+            let mut engine = PuppetEngine::with_config(&puppet, &config).unwrap();
+            c.bench_function(&format!("update {name}, {mode}"), |b| {
+                b.iter(|| {
+                    engine.set_global_opacity(1.0);
+                    engine.update(Duration::from_millis(16)).len()
+                })
+            });
+        }
+    }
+}
+
+#[cfg(not(feature = "parallel"))]
+criterion_group!(benches, update, update_nested);
+#[cfg(feature = "parallel")]
+criterion_group!(benches, update, update_nested, update_parallel);
+criterion_main!(benches);
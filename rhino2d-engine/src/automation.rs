@@ -0,0 +1,265 @@
+//! Automations, which animate parameters without user input.
+
+use std::time::Duration;
+
+use rhino2d_io::automation::{
+    Automation, AutomationAxis, AutomationBinding, PhysicsAutomation, SineType,
+};
+use rhino2d_io::Vec2;
+
+use crate::param::{ParamHandle, ParamId, ParamMap, ParamWrites};
+
+/// Number of times the distance constraints of a verlet chain are enforced per step.
+///
+/// Enforcing the constraint between two nodes moves them, which violates the constraints with
+/// their other neighbors again, so a few iterations are needed for the chain to settle.
+const RELAXATION_ITERATIONS: usize = 4;
+
+/// The automations of a puppet.
+#[derive(Debug)]
+pub(crate) struct Automations {
+    sines: Vec<Sine>,
+    chains: Vec<Chain>,
+    /// Time since the engine was created, in seconds.
+    time: f32,
+}
+
+impl Automations {
+    /// Lowers the automations of a puppet.
+    ///
+    /// Bindings referring to a parameter that does not exist are ignored.
+    pub(crate) fn lower(params: &ParamMap, io: &[Automation]) -> Self {
+        let mut sines = Vec::new();
+        let mut chains = Vec::new();
+        for automation in io {
+            let bindings = lower_bindings(params, automation.bindings());
+            match automation {
+                Automation::Sine(sine) => sines.push(Sine {
+                    speed: sine.speed(),
+                    sine_type: sine.sine_type(),
+                    bindings,
+                }),
+                Automation::Physics(physics) => chains.extend(Chain::lower(physics, bindings)),
+            }
+        }
+
+        Self {
+            sines,
+            chains,
+            time: 0.0,
+        }
+    }
+
+    /// Returns every physics automation to its initial state.
+    pub(crate) fn reset_physics(&mut self) {
+        for chain in &mut self.chains {
+            chain.reset();
+        }
+    }
+
+    /// Sets the time since the engine was created, which the automations are evaluated at.
+    pub(crate) fn seek(&mut self, time: Duration) {
+        self.time = time.as_secs_f32();
+    }
+
+    /// Advances the physics automations by one fixed step of length `step`.
+    ///
+    /// Called along with the physics simulation of the nodes, so that the physics automations
+    /// advance in the same fixed steps.
+    pub(crate) fn step_physics(&mut self, step: Duration) {
+        let h = step.as_secs_f32();
+        for chain in &mut self.chains {
+            chain.step(h);
+        }
+    }
+
+    /// Advances the automations by `delta` without writing their output.
+    pub(crate) fn advance(&mut self, delta: Duration) {
+        self.time += delta.as_secs_f32();
+    }
+
+    /// Advances the automations by `delta` and adds their output to `writes`.
+    ///
+    /// Physics automations are advanced by [`Automations::step_physics`] instead, and only
+    /// written here.
+    pub(crate) fn update(&mut self, delta: Duration, writes: &mut ParamWrites) {
+        self.advance(delta);
+        for chain in &self.chains {
+            chain.write(writes);
+        }
+        for sine in &self.sines {
+            let phase = self.time * sine.speed;
+            let wave = match sine.sine_type {
+                SineType::Sin => phase.sin(),
+                SineType::Cos => phase.cos(),
+                // The tangent diverges towards every odd multiple of π/2. Clamping it to the range
+                // of the other waves keeps the parameter within the binding's range, so it
+                // saturates at the range's ends for the middle half of each period instead.
+                SineType::Tan => phase.tan().clamp(-1.0, 1.0),
+                _ => continue,
+            };
+            for binding in &sine.bindings {
+                binding.write(wave, writes);
+            }
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Sine {
+    speed: f32,
+    sine_type: SineType,
+    bindings: Vec<Binding>,
+}
+
+#[derive(Debug)]
+struct Binding {
+    param: ParamHandle,
+    axis: usize,
+    range: Vec2,
+}
+
+impl Binding {
+    /// Adds the value mapped from -1..1 to the binding's range to `writes`.
+    fn write(&self, value: f32, writes: &mut ParamWrites) {
+        let t = (value + 1.0) * 0.5;
+        let [min, max] = self.range;
+        writes.add_axis(&self.param, self.axis, min + (max - min) * t);
+    }
+}
+
+fn lower_bindings(params: &ParamMap, io: &[AutomationBinding]) -> Vec<Binding> {
+    io.iter()
+        .filter_map(|binding| {
+            Some(Binding {
+                param: params.get(ParamId::Name(binding.param()))?.clone(),
+                axis: match binding.axis() {
+                    AutomationAxis::X => 0,
+                    AutomationAxis::Y => 1,
+                },
+                range: binding.range(),
+            })
+        })
+        .collect()
+}
+
+/// A chain of nodes simulated with verlet integration, hanging from its first node.
+///
+/// The first node is the fixed anchor of the chain. Every other node is pulled down by gravity,
+/// and kept at its [distance][rhino2d_io::automation::VerletNode::distance] to the previous
+/// node. The bindings of the automation are driven by the nodes after the anchor, in order: the
+/// horizontal offset of a node from the anchor, relative to the length of the chain up to that
+/// node, is mapped from -1..1 to the binding's range.
+#[derive(Debug)]
+struct Chain {
+    nodes: Vec<VerletNode>,
+    /// The nodes as specified by the model, for resetting the simulation.
+    initial: Vec<VerletNode>,
+    /// Fraction of the velocity of the nodes lost per simulation step.
+    damping: f32,
+    /// Acceleration of the nodes, in units per second squared. Positive values pull down.
+    gravity: f32,
+    bindings: Vec<Binding>,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct VerletNode {
+    position: Vec2,
+    /// Position in the previous step. The difference to `position` is the node's velocity.
+    old_position: Vec2,
+    /// Distance to the previous node in the chain.
+    distance: f32,
+    /// Length of the chain from the anchor to this node, when fully stretched.
+    reach: f32,
+}
+
+impl Chain {
+    /// Lowers a physics automation. Returns `None` if it has no nodes to simulate.
+    fn lower(io: &PhysicsAutomation, bindings: Vec<Binding>) -> Option<Self> {
+        if io.nodes().len() < 2 {
+            return None;
+        }
+        let mut reach = 0.0;
+        let nodes: Vec<_> = io
+            .nodes()
+            .iter()
+            .enumerate()
+            .map(|(i, node)| {
+                if i > 0 {
+                    reach += node.distance().max(0.0);
+                }
+                VerletNode {
+                    position: node.position(),
+                    old_position: node.old_position(),
+                    distance: node.distance().max(0.0),
+                    reach,
+                }
+            })
+            .collect();
+        Some(Self {
+            initial: nodes.clone(),
+            nodes,
+            damping: io.damping().clamp(0.0, 1.0),
+            gravity: io.gravity() * io.bounciness(),
+            bindings,
+        })
+    }
+
+    fn reset(&mut self) {
+        self.nodes.clone_from(&self.initial);
+    }
+
+    /// Advances the simulation by one step of `h` seconds.
+    ///
+    /// Verlet integration assumes that every step has the same length, since the velocity of a
+    /// node is derived from the distance it moved in the previous step.
+    fn step(&mut self, h: f32) {
+        for node in &mut self.nodes[1..] {
+            let [x, y] = node.position;
+            let [old_x, old_y] = node.old_position;
+            let keep = 1.0 - self.damping;
+            node.old_position = node.position;
+            node.position = [
+                x + (x - old_x) * keep,
+                y + (y - old_y) * keep + self.gravity * h * h,
+            ];
+        }
+        for _ in 0..RELAXATION_ITERATIONS {
+            self.constrain();
+        }
+    }
+
+    /// Moves every pair of adjacent nodes towards their desired distance.
+    fn constrain(&mut self) {
+        for i in 1..self.nodes.len() {
+            let [a, b] = [self.nodes[i - 1].position, self.nodes[i].position];
+            let diff = [b[0] - a[0], b[1] - a[1]];
+            let dist = diff[0].hypot(diff[1]);
+            if dist == 0.0 || !dist.is_finite() {
+                continue;
+            }
+            let error = (dist - self.nodes[i].distance) / dist;
+            // The anchor is fixed, so the node attached to it moves by the full correction.
+            let share = if i == 1 { 1.0 } else { 0.5 };
+            let offset = [diff[0] * error * share, diff[1] * error * share];
+            if i > 1 {
+                let a = &mut self.nodes[i - 1].position;
+                *a = [a[0] + offset[0], a[1] + offset[1]];
+            }
+            let b = &mut self.nodes[i].position;
+            *b = [b[0] - offset[0], b[1] - offset[1]];
+        }
+    }
+
+    fn write(&self, writes: &mut ParamWrites) {
+        let anchor = self.nodes[0].position;
+        for (node, binding) in self.nodes[1..].iter().zip(&self.bindings) {
+            let offset = if node.reach > 0.0 {
+                ((node.position[0] - anchor[0]) / node.reach).clamp(-1.0, 1.0)
+            } else {
+                0.0
+            };
+            binding.write(offset, writes);
+        }
+    }
+}
@@ -0,0 +1,47 @@
+//! Saved parameter poses.
+
+use std::collections::BTreeMap;
+
+use rhino2d_io::{Uuid, Vec2};
+
+/// A set of parameter values, eg. making up a facial expression.
+///
+/// Presets can be captured from a [`PuppetEngine`] with [`PuppetEngine::capture_expression`], or
+/// built by hand, and blended with [`PuppetEngine::crossfade`]. Parameters are identified by their
+/// UUID, so a preset stays valid when parameters are renamed.
+///
+/// [`PuppetEngine`]: crate::PuppetEngine
+/// [`PuppetEngine::capture_expression`]: crate::PuppetEngine::capture_expression
+/// [`PuppetEngine::crossfade`]: crate::PuppetEngine::crossfade
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ExpressionPreset {
+    values: BTreeMap<Uuid, Vec2>,
+}
+
+impl ExpressionPreset {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the value the preset assigns to the parameter `param`, if it contains one.
+    pub fn get(&self, param: Uuid) -> Option<Vec2> {
+        self.values.get(&param).copied()
+    }
+
+    /// Sets the value the preset assigns to the parameter `param`.
+    ///
+    /// The Y component of `value` is ignored for 1D parameters.
+    pub fn set(&mut self, param: Uuid, value: Vec2) {
+        self.values.insert(param, value);
+    }
+
+    /// Removes the parameter `param` from the preset.
+    pub fn remove(&mut self, param: Uuid) {
+        self.values.remove(&param);
+    }
+
+    /// Returns the UUID and value of every parameter in the preset, in ascending UUID order.
+    pub fn values(&self) -> impl Iterator<Item = (Uuid, Vec2)> + '_ {
+        self.values.iter().map(|(uuid, value)| (*uuid, *value))
+    }
+}
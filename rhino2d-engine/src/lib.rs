@@ -4,23 +4,61 @@
 //! which order, but does not do the rendering itself. That step is delegated to other crates.
 
 pub mod atomic;
+mod automation;
+pub mod expression;
 pub mod node;
 mod ord;
 pub mod param;
+mod physics;
+#[cfg(feature = "serde")]
+mod pose;
+#[cfg(feature = "replay")]
+pub mod replay;
+pub mod scene;
+pub mod tracking;
 
 use core::fmt;
-use std::time::Duration;
+use std::{collections::HashMap, sync::Arc, time::Duration};
 
-use node::{Node, Transform};
+use automation::Automations;
+use expression::ExpressionPreset;
+use nalgebra::{Matrix4, Vector4};
+use node::{Mesh, MeshView, Node, NodeIter, NodeIterMut, PhysicsState, Transform};
 use ord::TotalF32;
-use param::ParamMap;
-use rhino2d_io::{Uuid, Vec2};
+use param::{ParamHandle, ParamId, ParamMap, ParamRef, ParamTarget, ParamWrites, Ramp};
+use rhino2d_io::node::{BlendMode, MaskMode};
+use rhino2d_io::{Uuid, Vec2, Vec3};
+use tracking::TrackingInput;
 
+#[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct RenderCommand {
     node: Uuid,
     zsort: f32,
     transform: Transform,
+    /// Index of `transform` in the transform buffer, assigned by [`RenderBuffer::push`].
+    transform_index: u32,
     deform: Option<Vec<Vec2>>,
+    tint: Vec3,
+    opacity: f32,
+    blend_mode: BlendMode,
+    mask_mode: Option<MaskMode>,
+    /// Shared with the part, so that it isn't copied every frame. `None` for nodes that aren't
+    /// parts.
+    masked_by: Option<Arc<[Uuid]>>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    is_mask: bool,
+    /// Like `masked_by`.
+    textures: Option<Arc<[u32]>>,
+    #[cfg_attr(feature = "serde", serde(default = "default_mask_threshold"))]
+    mask_threshold: f32,
+    /// Number of commands following this one that belong to the composite node, or `None` if the
+    /// node isn't a composite.
+    #[cfg_attr(feature = "serde", serde(default))]
+    composite_len: Option<u32>,
+    /// Not serialized, since it would duplicate the mesh for every frame.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    mesh: Option<Arc<Mesh>>,
 }
 
 impl RenderCommand {
@@ -29,7 +67,7 @@ impl RenderCommand {
         self.node
     }
 
-    /// Returns the node's computed Z-Sort value.
+    /// Returns the node's computed Z-Sort value, including its Z translation.
     pub fn zsort(&self) -> f32 {
         self.zsort
     }
@@ -39,6 +77,12 @@ impl RenderCommand {
         self.transform
     }
 
+    /// Returns the index of the node's global transform in the
+    /// [transform buffer][PuppetEngine::transform_buffer].
+    pub fn transform_index(&self) -> usize {
+        self.transform_index as usize
+    }
+
     /// Returns the node's vertex deformations, if there is any.
     ///
     /// If this returns `Some`, the number of entries in the slice will match the number of vertices
@@ -46,75 +90,3806 @@ impl RenderCommand {
     pub fn deform(&self) -> Option<&[Vec2]> {
         self.deform.as_deref()
     }
+
+    /// Returns the color to multiply the node's texture with.
+    ///
+    /// This is the tint of the node, multiplied with the puppet's
+    /// [global tint][PuppetEngine::set_global_tint] unless the node is inside a
+    /// [composite][RenderCommand::composite_len]. Nodes that don't specify a tint are white.
+    pub fn tint(&self) -> Vec3 {
+        self.tint
+    }
+
+    /// Returns the opacity to render the node with.
+    ///
+    /// This is the opacity of the node, multiplied with the puppet's
+    /// [global opacity][PuppetEngine::set_global_opacity] unless the node is inside a
+    /// [composite][RenderCommand::composite_len].
+    pub fn opacity(&self) -> f32 {
+        self.opacity
+    }
+
+    /// Returns how to blend the node onto the pixels below it.
+    ///
+    /// This is [`BlendMode::Normal`] for nodes that are neither parts nor composites.
+    pub fn blend_mode(&self) -> BlendMode {
+        self.blend_mode
+    }
+
+    /// Returns how the nodes in [`RenderCommand::masked_by`] affect the node, or `None` if the
+    /// node isn't a part or doesn't specify a mask mode.
+    pub fn mask_mode(&self) -> Option<MaskMode> {
+        self.mask_mode
+    }
+
+    /// Returns the nodes masking the node.
+    ///
+    /// This is empty for nodes that aren't parts. The masking nodes may be outside of the
+    /// composites the node is in, see [`RenderCommand::composite_len`].
+    pub fn masked_by(&self) -> &[Uuid] {
+        self.masked_by.as_deref().unwrap_or_default()
+    }
+
+    /// Returns whether the node is a mask node, which is only drawn into the stencil buffer.
+    ///
+    /// Mask nodes are never drawn into the color buffer. Like other commands, their place in the
+    /// render buffer follows their Z-Sort value, but that order is irrelevant: a renderer draws
+    /// the nodes in [`RenderCommand::masked_by`] into the stencil buffer right before the part
+    /// they mask, wherever they are in the buffer. Parts can mask other parts as well, in which
+    /// case they are drawn into the stencil buffer for the masked part, and into the color buffer
+    /// at their own position.
+    pub fn is_mask(&self) -> bool {
+        self.is_mask
+    }
+
+    /// Returns the indices of the puppet textures to draw the node with.
+    ///
+    /// This is empty for nodes that aren't parts.
+    pub fn textures(&self) -> &[u32] {
+        self.textures.as_deref().unwrap_or_default()
+    }
+
+    /// Returns the alpha value below which the node's pixels are discarded when it is drawn as a
+    /// mask.
+    ///
+    /// This is 0.5 for nodes that are neither parts nor composites.
+    pub fn mask_threshold(&self) -> f32 {
+        self.mask_threshold
+    }
+
+    /// If the node is a [composite][Node::Composite], returns the number of commands that follow
+    /// this one and make up the composite's contents.
+    ///
+    /// A renderer draws these commands into an offscreen buffer instead of its render target, and
+    /// then draws the buffer onto the render target with the composite's [tint], [opacity], and
+    /// [blend mode]. The contents may include further composites, which are drawn into buffers
+    /// of their own, so a renderer needs a stack of buffers as deep as the deepest nesting.
+    ///
+    /// The commands of a composite's subtree are always kept together, right after the composite's
+    /// command, and sorted by Z-Sort value only among each other. The composite as a whole is
+    /// sorted among its siblings by its own Z-Sort value, so a node inside a composite can never be
+    /// drawn between two nodes outside of it, no matter how their Z-Sort values compare. Nested
+    /// composites work the same way, at every level.
+    ///
+    /// The [global tint and opacity][PuppetEngine::set_global_tint] are only applied to commands
+    /// outside of any composite, since they would otherwise affect the contents twice.
+    ///
+    /// Masks don't follow the nesting of composites: a part inside a composite can be
+    /// [masked by][RenderCommand::masked_by] a node outside of it, and the other way around. The
+    /// masking node is drawn into the mask of the target the masked part is drawn onto (the
+    /// composite's offscreen buffer, for a part inside of it) with its own global transform,
+    /// regardless of where its own command is in the render order.
+    ///
+    /// [tint]: RenderCommand::tint
+    /// [opacity]: RenderCommand::opacity
+    /// [blend mode]: RenderCommand::blend_mode
+    pub fn composite_len(&self) -> Option<usize> {
+        self.composite_len.map(|len| len as usize)
+    }
+
+    /// Returns the mesh of the node, with the node's mesh deformation applied.
+    ///
+    /// This is `None` for nodes that aren't drawable, and for commands that were deserialized.
+    pub fn mesh(&self) -> Option<MeshView<'_>> {
+        self.mesh.as_deref().map(|mesh| MeshView {
+            mesh,
+            deform: self.deform.as_deref(),
+        })
+    }
+}
+
+#[cfg(feature = "serde")]
+fn default_mask_threshold() -> f32 {
+    node::DEFAULT_MASK_THRESHOLD
+}
+
+/// Computes the screen-space axis-aligned bounding box of a rendered node.
+///
+/// `mesh_bounds` is the minimum and maximum corner of the node's mesh in model space, including
+/// any deformation. `view_proj` is the view-projection matrix of the renderer in column-major
+/// order. All four corners of the mesh bounds are transformed, so the result also encloses meshes
+/// that are rotated.
+///
+/// Returns the minimum and maximum corner of the box, in normalized device coordinates.
+pub fn screen_aabb(
+    cmd: &RenderCommand,
+    mesh_bounds: [Vec2; 2],
+    view_proj: &[f32; 16],
+) -> [Vec2; 2] {
+    let mut min = [f32::INFINITY; 2];
+    let mut max = [f32::NEG_INFINITY; 2];
+    for p in screen_corners(cmd, mesh_bounds, view_proj) {
+        for i in 0..2 {
+            min[i] = min[i].min(p[i]);
+            max[i] = max[i].max(p[i]);
+        }
+    }
+    [min, max]
+}
+
+/// Finds the rendered nodes whose mesh intersects a screen-space rectangle, eg. for box
+/// selection.
+///
+/// `mesh_bounds` returns the mesh bounds of a node like they are passed to [`screen_aabb`], or
+/// `None` for nodes that can't be selected (like nodes that aren't parts). `view_proj` is the
+/// view-projection matrix of the renderer in column-major order, and `rect` is the minimum and
+/// maximum corner of the rectangle, in normalized device coordinates.
+///
+/// The transformed mesh bounds are tested exactly, so rotated nodes are only returned if their
+/// rotated bounds overlap the rectangle, not just their bounding box. Returns the UUIDs of the
+/// intersecting nodes in the order of `commands`.
+pub fn nodes_in_rect(
+    commands: &[RenderCommand],
+    mut mesh_bounds: impl FnMut(Uuid) -> Option<[Vec2; 2]>,
+    view_proj: &[f32; 16],
+    rect: [Vec2; 2],
+) -> Vec<Uuid> {
+    let [min, max] = rect;
+    let rect_corners = [min, [max[0], min[1]], max, [min[0], max[1]]];
+    commands
+        .iter()
+        .filter(|cmd| {
+            let Some(bounds) = mesh_bounds(cmd.node) else {
+                return false;
+            };
+            let [a, b, c, d] = screen_corners(cmd, bounds, view_proj);
+            // Both shapes are convex, so they are disjoint iff one of their edge normals separates
+            // them.
+            let quad = [a, b, d, c];
+            let axes = [[1.0, 0.0], [0.0, 1.0]].into_iter().chain((0..4).map(|i| {
+                let [p, q] = [quad[i], quad[(i + 1) % 4]];
+                [q[1] - p[1], p[0] - q[0]]
+            }));
+            for axis in axes {
+                let project = |points: &[Vec2; 4]| {
+                    points
+                        .iter()
+                        .fold([f32::INFINITY, f32::NEG_INFINITY], |[lo, hi], p| {
+                            let v = p[0] * axis[0] + p[1] * axis[1];
+                            [lo.min(v), hi.max(v)]
+                        })
+                };
+                let [quad_min, quad_max] = project(&quad);
+                let [rect_min, rect_max] = project(&rect_corners);
+                if quad_max < rect_min || rect_max < quad_min {
+                    return false;
+                }
+            }
+            true
+        })
+        .map(|cmd| cmd.node)
+        .collect()
+}
+
+/// Transforms the corners of `mesh_bounds` to normalized device coordinates.
+///
+/// The corners are returned in the order `[min, (max x, min y), (min x, max y), max]`.
+fn screen_corners(cmd: &RenderCommand, mesh_bounds: [Vec2; 2], view_proj: &[f32; 16]) -> [Vec2; 4] {
+    let mat = Matrix4::from_column_slice(view_proj)
+        * Matrix4::from_column_slice(cmd.transform.as_column_major_data());
+    let [[x0, y0], [x1, y1]] = mesh_bounds;
+    [[x0, y0], [x1, y0], [x0, y1], [x1, y1]].map(|[x, y]| {
+        let p = mat * Vector4::new(x, y, 0.0, 1.0);
+        [p.x / p.w, p.y / p.w]
+    })
 }
 
 /// Records rendering commands while nodes are being updated.
 struct RenderBuffer {
     commands: Vec<RenderCommand>,
+    /// Sort keys of `commands` in their sorted order, reused across frames to avoid reallocating
+    /// them.
+    ///
+    /// Each key is paired with the index of its command, which breaks ties between equal Z-Sort
+    /// values in favor of the command that was pushed first.
+    keys: Vec<(TotalF32, u32)>,
+    /// Sorted keys of the commands at every nesting level: the top level, followed by the
+    /// contents of every composite in `composites`. Kept across frames, so that every level can
+    /// start out in the order of the previous frame.
+    levels: Vec<Vec<(TotalF32, u32)>>,
+    /// Position and [`RenderCommand::composite_len`] of every composite in `commands`, in the
+    /// frame `levels` were sorted in.
+    composites: Vec<(u32, u32)>,
+    /// Scratch space for the composites of the current frame, to compare them with `composites`.
+    next_composites: Vec<(u32, u32)>,
+    /// Global transform of every command, indexed by [`RenderCommand::transform_index`].
+    transforms: Vec<[f32; 16]>,
+    /// Maps the UUID of every command's node to its [`RenderCommand::transform_index`].
+    transform_indices: HashMap<Uuid, u32>,
 }
 
 impl RenderBuffer {
-    fn push(&mut self, cmd: RenderCommand) {
+    fn new() -> Self {
+        Self {
+            commands: Vec::new(),
+            keys: Vec::new(),
+            levels: Vec::new(),
+            composites: Vec::new(),
+            next_composites: Vec::new(),
+            transforms: Vec::new(),
+            transform_indices: HashMap::new(),
+        }
+    }
+
+    /// Discards the commands of the previous frame.
+    fn begin(&mut self) {
+        self.commands.clear();
+    }
+
+    fn push(&mut self, mut cmd: RenderCommand) {
+        cmd.transform_index = self.commands.len() as u32;
         self.commands.push(cmd);
     }
 
+    /// Pushes `commands` in order, as if each was passed to [`RenderBuffer::push`].
+    #[cfg(feature = "parallel")]
+    fn append(&mut self, commands: Vec<RenderCommand>) {
+        self.commands.reserve(commands.len());
+        for cmd in commands {
+            self.push(cmd);
+        }
+    }
+
+    /// Copies the final transform of every command into `transforms`.
+    fn fill_transforms(&mut self) {
+        self.transforms.resize(self.commands.len(), [0.0; 16]);
+        for cmd in &self.commands {
+            self.transforms[cmd.transform_index as usize] = cmd.transform.to_array();
+        }
+    }
+
+    /// Records the transform index of every command's node in `transform_indices`.
+    ///
+    /// If several nodes share a UUID, the one pushed first is recorded.
+    fn index_transforms(&mut self) {
+        self.transform_indices.clear();
+        for cmd in &self.commands {
+            self.transform_indices
+                .entry(cmd.node)
+                .or_insert(cmd.transform_index);
+        }
+    }
+
     fn finish(&mut self) {
-        // Sort by Z-Sort value, *de*scending.
-        self.commands.sort_by_key(|cmd| TotalF32(-cmd.zsort));
+        // Composites are sorted level by level, see `RenderCommand::composite_len`.
+        self.next_composites.clear();
+        self.next_composites.extend(
+            self.commands
+                .iter()
+                .enumerate()
+                .filter_map(|(i, cmd)| Some((i as u32, cmd.composite_len?))),
+        );
+        // Nodes push their commands in the same order every frame, so unless the number of
+        // commands or the composites among them changed, `levels` still holds the order of every
+        // level in the previous frame.
+        let reuse =
+            self.keys.len() == self.commands.len() && self.next_composites == self.composites;
+        std::mem::swap(&mut self.composites, &mut self.next_composites);
+        self.levels.resize_with(self.composites.len() + 1, Vec::new);
+        sort_level(
+            &self.commands,
+            0..self.commands.len(),
+            &mut self.levels[0],
+            reuse,
+        );
+        for (level, &(start, len)) in self.levels[1..].iter_mut().zip(&self.composites) {
+            let start = start as usize + 1;
+            sort_level(&self.commands, start..start + len as usize, level, reuse);
+        }
+        self.keys.clear();
+        flatten_levels(&self.levels, &self.composites, 0, &mut self.keys);
+
+        // `keys[i].1` is now the index of the command that belongs at position `i`. Move every
+        // command into place by following the cycles of that permutation.
+        for start in 0..self.keys.len() {
+            let mut pos = start;
+            while self.keys[pos].1 & VISITED == 0 {
+                let src = self.keys[pos].1 as usize;
+                self.keys[pos].1 |= VISITED;
+                if src == start {
+                    break;
+                }
+                self.commands.swap(pos, src);
+                pos = src;
+            }
+        }
 
         // Now `commands` has the back-most node in the front, which is the typical render order.
     }
 }
 
+/// Sorts the keys of the commands directly in `range`, which make up the contents of a composite
+/// (or the whole buffer), into `level`.
+///
+/// The commands inside the composites among them are skipped, since they are sorted in levels of
+/// their own. If `reuse` is set, `level` holds the keys of the same commands from the previous
+/// frame, in their previous order.
+fn sort_level(
+    commands: &[RenderCommand],
+    range: std::ops::Range<usize>,
+    level: &mut Vec<(TotalF32, u32)>,
+    reuse: bool,
+) {
+    // Sort by Z-Sort value, *de*scending. Since every key is unique, an unstable (and
+    // non-allocating) sort produces the same order as a stable one.
+    if reuse {
+        // Typically, only a few Z-Sort values change between frames, and an insertion sort can fix
+        // their position faster than a full sort.
+        for key in level.iter_mut() {
+            key.0 = sort_key(commands[key.1 as usize].zsort);
+        }
+        let budget = level.len() * INSERTION_SORT_BUDGET;
+        if insertion_sort(level, budget) {
+            return;
+        }
+    } else {
+        level.clear();
+        let mut i = range.start;
+        while i < range.end {
+            level.push((sort_key(commands[i].zsort), i as u32));
+            i += 1 + commands[i].composite_len.unwrap_or(0) as usize;
+        }
+    }
+    level.sort_unstable();
+}
+
+/// Appends the sorted keys of `levels[level]` to `keys`, each followed by the keys of the
+/// composite's contents if it is a composite.
+///
+/// `composites` holds the position and length of every composite, and the contents of the
+/// composite at `composites[i]` are in `levels[i + 1]`.
+fn flatten_levels(
+    levels: &[Vec<(TotalF32, u32)>],
+    composites: &[(u32, u32)],
+    level: usize,
+    keys: &mut Vec<(TotalF32, u32)>,
+) {
+    for &key in &levels[level] {
+        keys.push(key);
+        if let Ok(i) = composites.binary_search_by_key(&key.1, |&(start, _)| start) {
+            flatten_levels(levels, composites, i + 1, keys);
+        }
+    }
+}
+
+/// Computes the key a command with the given Z-Sort value is sorted by.
+///
+/// Sorting by the key in ascending order sorts by Z-Sort value in descending order. NaN Z-Sort
+/// values (regardless of their sign bit) are placed behind everything else, including
+/// `f32::INFINITY`, and `-0.0` is treated like `0.0`, so that neither affects the order of
+/// otherwise tied commands.
+fn sort_key(zsort: f32) -> TotalF32 {
+    if zsort.is_nan() {
+        // Negative NaN is the smallest value in the total order.
+        TotalF32(-f32::NAN.copysign(1.0))
+    } else {
+        // Adding 0 turns `-0.0` into `0.0`.
+        TotalF32(-zsort + 0.0)
+    }
+}
+
+/// Marks a command index in [`RenderBuffer::keys`] whose command has been moved into place.
+const VISITED: u32 = 1 << 31;
+
+/// Number of element moves per command after which the incremental sort of a
+/// [`RenderBuffer`] falls back to a full sort.
+const INSERTION_SORT_BUDGET: usize = 8;
+
+/// Sorts `slice` via insertion sort, giving up after moving elements `budget` times.
+///
+/// Returns whether `slice` was fully sorted.
+fn insertion_sort<T: Ord>(slice: &mut [T], mut budget: usize) -> bool {
+    for i in 1..slice.len() {
+        let mut j = i;
+        while j > 0 && slice[j - 1] > slice[j] {
+            if budget == 0 {
+                return false;
+            }
+            budget -= 1;
+            slice.swap(j - 1, j);
+            j -= 1;
+        }
+    }
+    true
+}
+
 pub struct PuppetEngine {
     root_node: Node,
+    /// Path from the root node to every node in the tree, by UUID. See [`Node::paths`].
+    node_paths: HashMap<Uuid, Box<[usize]>>,
+    /// Whether the tree contains any [`PathDeform`][node::PathDeform] nodes.
+    has_path_deforms: bool,
+    params: ParamMap,
     render_buffer: RenderBuffer,
+    physics: physics::World,
+    automations: Automations,
+    /// Parameter values computed by automations and physics during the current frame.
+    param_writes: ParamWrites,
+    automation_ramp: Ramp,
+    physics_ramp: Ramp,
+    /// Length of a step of the physics simulation in [`PuppetEngine::update`].
+    physics_timestep: Duration,
+    /// Time passed to [`PuppetEngine::update`] that the physics simulation hasn't stepped over
+    /// yet. Always less than `physics_timestep`.
+    physics_accumulator: Duration,
+    /// Total time the physics simulation was advanced by in [`PuppetEngine::update`].
+    physics_time: Duration,
+    config: EngineConfig,
+    transition: Option<Transition>,
+    global_tint: Vec3,
+    global_opacity: f32,
+    /// Number of [`RENDER_STEP`]s the physics simulation was advanced by [`PuppetEngine::render_at`].
+    render_steps: u64,
+    /// Parameter values that rendering the last frame in [`PuppetEngine::render_at`] changed, and
+    /// the values they had at the last step of the simulation.
+    render_restore: Vec<(ParamHandle, Vec2)>,
+    /// The [parameter generation][ParamMap::generation] the render commands were computed at, or
+    /// `None` if they are out of date for another reason.
+    rendered_generation: Option<u64>,
+}
+
+/// Fixed time step the physics simulation is advanced by in [`PuppetEngine::render_at`].
+const RENDER_STEP: Duration = Duration::from_nanos(1_000_000_000 / 120);
+
+/// Maximum number of physics steps simulated by a single [`PuppetEngine::update`].
+///
+/// After a long pause (eg. when the application was suspended or stopped in a debugger), catching
+/// up on the missed time would make the next frame take even longer, so it is dropped instead.
+const MAX_PHYSICS_STEPS: u32 = 12;
+
+/// An ongoing transition from the transforms of the model before a [`PuppetEngine::hot_reload`].
+struct Transition {
+    /// The global transform each node was last rendered with before the reload.
+    from: HashMap<Uuid, Transform>,
+    elapsed: Duration,
+}
+
+/// Default for [`EngineConfig::set_parallel_threshold`].
+#[cfg(feature = "parallel")]
+const DEFAULT_PARALLEL_THRESHOLD: usize = 1000;
+
+/// Options controlling how a [`PuppetEngine`] is built.
+#[derive(Debug, Clone, Default)]
+pub struct EngineConfig {
+    deform_precision: DeformPrecision,
+    reload_transition: Duration,
+    toggle_ramp: Duration,
+    rotation_direction: RotationDirection,
+    shortest_arc_rotations: bool,
+    apply_solo: bool,
+    #[cfg(feature = "parallel")]
+    parallel_threshold: Option<usize>,
+}
+
+impl EngineConfig {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn deform_precision(&self) -> DeformPrecision {
+        self.deform_precision
+    }
+
+    /// Sets the precision used to store the mesh deformation keyframes of the model.
+    pub fn set_deform_precision(&mut self, precision: DeformPrecision) {
+        self.deform_precision = precision;
+    }
+
+    pub fn reload_transition(&self) -> Duration {
+        self.reload_transition
+    }
+
+    /// Sets the time it takes nodes to move to their new transform after a
+    /// [`PuppetEngine::hot_reload`].
+    ///
+    /// Defaults to [`Duration::ZERO`], which makes nodes jump to their new transform immediately.
+    pub fn set_reload_transition(&mut self, duration: Duration) {
+        self.reload_transition = duration;
+    }
+
+    pub fn toggle_ramp(&self) -> Duration {
+        self.toggle_ramp
+    }
+
+    /// Sets the time it takes the parameter values driven by automations or physics to fade in or
+    /// out when they are enabled or disabled.
+    ///
+    /// See [`PuppetEngine::set_automations_enabled`] and [`PuppetEngine::set_physics_enabled`].
+    /// Defaults to [`Duration::ZERO`], which toggles them immediately.
+    pub fn set_toggle_ramp(&mut self, duration: Duration) {
+        self.toggle_ramp = duration;
+    }
+
+    pub fn rotation_direction(&self) -> RotationDirection {
+        self.rotation_direction
+    }
+
+    /// Sets the direction positive rotation angles of the model turn nodes in.
+    ///
+    /// Defaults to [`RotationDirection::Clockwise`], which matches how Inochi Creator displays
+    /// models.
+    pub fn set_rotation_direction(&mut self, direction: RotationDirection) {
+        self.rotation_direction = direction;
+    }
+
+    pub fn shortest_arc_rotations(&self) -> bool {
+        self.shortest_arc_rotations
+    }
+
+    /// Sets whether rotation bindings interpolate between their keyframes along the shortest arc.
+    ///
+    /// By default, the angles of neighboring keyframes are interpolated numerically. When they lie
+    /// on both sides of the ±180° boundary, eg. at 170° and -170°, the node spins 340° through 0°
+    /// between them. With this option enabled, it turns 20° through 180° instead.
+    ///
+    /// This only affects bindings of rotation targets. Defaults to `false`, which matches
+    /// Inochi2D.
+    pub fn set_shortest_arc_rotations(&mut self, enabled: bool) {
+        self.shortest_arc_rotations = enabled;
+    }
+
+    pub fn apply_solo(&self) -> bool {
+        self.apply_solo
+    }
+
+    /// Sets whether the editor's solo flag of parts is applied.
+    ///
+    /// When enabled and any part of the model is [soloed][node::Part::solo], every part that isn't
+    /// soloed is drawn fully transparent, like in Inochi Creator. Models are sometimes exported
+    /// with parts still soloed, so this defaults to `false`, which draws every part.
+    pub fn set_apply_solo(&mut self, enabled: bool) {
+        self.apply_solo = enabled;
+    }
+
+    #[cfg(feature = "parallel")]
+    pub fn parallel_threshold(&self) -> usize {
+        self.parallel_threshold
+            .unwrap_or(DEFAULT_PARALLEL_THRESHOLD)
+    }
+
+    /// Sets the number of nodes from which [`PuppetEngine::update`] updates the subtrees of the
+    /// model on multiple threads.
+    ///
+    /// Smaller models are updated faster on a single thread, and every model is updated on a
+    /// single thread if the rayon thread pool has only one thread. The render commands are the same
+    /// either way. Defaults to 1000 nodes.
+    #[cfg(feature = "parallel")]
+    pub fn set_parallel_threshold(&mut self, nodes: usize) {
+        self.parallel_threshold = Some(nodes);
+    }
+}
+
+/// Direction in which positive rotation angles turn a node, as seen on screen.
+///
+/// Model space has its Y axis pointing down, so the rotation that turns the X axis towards the Y
+/// axis appears clockwise on screen. For example, with the default [`RotationDirection::Clockwise`],
+/// a node rotated by π/2 around the Z axis moves a point at `(1, 0)` to `(0, 1)`, which is below
+/// the node's origin. With [`RotationDirection::CounterClockwise`], every rotation angle is negated
+/// instead, moving that point to `(0, -1)`, above the origin.
+///
+/// This applies to the rotation specified by the model as well as rotations driven by parameters.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RotationDirection {
+    /// Positive angles rotate clockwise on screen, like in Inochi Creator.
+    #[default]
+    Clockwise,
+    /// Positive angles rotate counter-clockwise on screen.
+    CounterClockwise,
+}
+
+/// Precision of the mesh deformation keyframes stored by the engine.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DeformPrecision {
+    /// Store deformations as `f32` values.
+    #[default]
+    F32,
+    /// Store deformations as `f16` values, halving their memory footprint.
+    ///
+    /// `f16` has 11 bits of precision, so an offset of 500 pixels is rounded to the nearest
+    /// 0.25 pixels, and smaller offsets are stored more precisely. Deformations are converted back
+    /// to `f32` before being interpolated.
+    F16,
 }
 
 impl PuppetEngine {
+    /// Creates an engine for `puppet`, using the default [`EngineConfig`].
+    ///
+    /// Use [`PuppetEngine::with_config`] to customize the engine (eg. the precision of stored
+    /// mesh deformations).
     pub fn new(puppet: &rhino2d_io::InochiPuppet) -> Result<Self> {
-        let mut param_map = ParamMap::lower(puppet.params())?;
+        Self::with_config(puppet, &EngineConfig::default())
+    }
+
+    pub fn with_config(puppet: &rhino2d_io::InochiPuppet, config: &EngineConfig) -> Result<Self> {
+        let mut param_map = ParamMap::lower(puppet.params(), config)?;
+        node::check_joint_bindings(puppet.root_node())?;
+        let mut root_node = Node::from_io(&mut param_map, config, puppet.root_node())?;
+        if config.apply_solo {
+            root_node.apply_solo();
+        }
         Ok(Self {
-            root_node: Node::from_io(&mut param_map, puppet.root_node())?,
-            render_buffer: RenderBuffer {
-                commands: Vec::new(),
-            },
+            node_paths: root_node.paths(),
+            has_path_deforms: NodeIter::new(&root_node)
+                .any(|(_, node)| matches!(node, Node::PathDeform(_))),
+            root_node,
+            automations: Automations::lower(&param_map, puppet.automations()),
+            param_writes: ParamWrites::default(),
+            automation_ramp: Ramp::new(),
+            physics_ramp: Ramp::new(),
+            physics_timestep: RENDER_STEP,
+            physics_accumulator: Duration::ZERO,
+            physics_time: Duration::ZERO,
+            params: param_map,
+            render_buffer: RenderBuffer::new(),
+            physics: physics::World::from_io(puppet.physics()),
+            config: config.clone(),
+            transition: None,
+            global_tint: [1.0; 3],
+            global_opacity: 1.0,
+            render_steps: 0,
+            render_restore: Vec::new(),
+            rendered_generation: None,
         })
     }
 
-    pub fn update(&mut self, delta: Duration) -> &[RenderCommand] {
-        self.root_node.update(delta, &mut self.render_buffer);
+    /// Replaces the animated model with `puppet`, keeping the engine's configuration and global
+    /// tint and opacity.
+    ///
+    /// Parameters that still exist in `puppet` (as identified by their UUID) keep their current
+    /// value. All other parameters start out at their default value.
+    ///
+    /// If a [reload transition][EngineConfig::set_reload_transition] is configured, nodes present
+    /// in both models ease from the transform they were last rendered with to their new transform,
+    /// instead of jumping there.
+    ///
+    /// If `puppet` can not be loaded, an error is returned and the previous model is kept.
+    pub fn hot_reload(&mut self, puppet: &rhino2d_io::InochiPuppet) -> Result<()> {
+        let mut engine = Self::with_config(puppet, &self.config)?;
+        engine.global_tint = self.global_tint;
+        engine.global_opacity = self.global_opacity;
+        engine.automation_ramp = self.automation_ramp;
+        engine.physics_ramp = self.physics_ramp;
+        engine.physics_timestep = self.physics_timestep;
+        for (uuid, param) in self.params.handles() {
+            if let Some(new) = engine.params.get(uuid.into()) {
+                new.set(param.value());
+            }
+        }
+        if !self.config.reload_transition.is_zero() {
+            engine.transition = Some(Transition {
+                from: self
+                    .render_buffer
+                    .commands
+                    .iter()
+                    .map(|cmd| (cmd.node, cmd.transform))
+                    .collect(),
+                elapsed: Duration::ZERO,
+            });
+        }
+        *self = engine;
+        Ok(())
+    }
 
-        self.render_buffer.finish();
-        &self.render_buffer.commands
+    /// Returns the UUID and name of every parameter of the puppet.
+    ///
+    /// Parameters are enumerated in ascending UUID order, independent of their order in the model
+    /// file.
+    pub fn params(&self) -> impl Iterator<Item = (Uuid, &str)> + '_ {
+        self.params.iter()
     }
-}
 
-pub type Result<T, E = Error> = std::result::Result<T, E>;
+    /// Returns the nodes driven by a parameter, along with the node property it drives.
+    ///
+    /// A node is listed once for every one of its properties the parameter affects. If the puppet
+    /// has no matching parameter, an empty list is returned.
+    pub fn nodes_for_param<'a>(&self, param: impl Into<ParamId<'a>>) -> Vec<(Uuid, ParamTarget)> {
+        self.params.targets(param.into()).to_vec()
+    }
 
-#[derive(Debug)]
-pub struct Error {
-    msg: String,
-}
+    /// Returns a reference to a parameter, which can be used to set its value.
+    ///
+    /// Unlike [`PuppetEngine::set_param`], setting a value through the reference fails if the
+    /// value's number of components doesn't match the parameter. Returns `None` if the puppet has
+    /// no matching parameter.
+    pub fn param<'a>(&self, param: impl Into<ParamId<'a>>) -> Option<ParamRef> {
+        self.params.get(param.into()).map(ParamRef::new)
+    }
 
-impl fmt::Display for Error {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        f.write_str(&self.msg)
+    /// Returns a reference to the parameter with the given UUID.
+    ///
+    /// Unlike names, UUIDs stay the same when a parameter is renamed. This is equivalent to
+    /// calling [`PuppetEngine::param`] with the UUID.
+    pub fn param_by_uuid(&self, uuid: Uuid) -> Option<ParamRef> {
+        self.param(uuid)
     }
-}
 
-impl std::error::Error for Error {}
+    /// Sets the value of a parameter.
+    ///
+    /// The Y component of `value` is ignored for 1D parameters. Returns `false` if the puppet has
+    /// no matching parameter.
+    pub fn set_param<'a>(&self, param: impl Into<ParamId<'a>>, value: Vec2) -> bool {
+        self.params.set(param.into(), value)
+    }
 
-impl Error {
-    fn unsupported(what: impl AsRef<str>) -> Self {
-        Self {
-            msg: format!("model uses unsupported feature: {}", what.as_ref()),
+    /// Sets the values of several parameters as one batch.
+    ///
+    /// The batch is applied atomically with respect to [`PuppetEngine::update`]: since setting
+    /// parameters borrows the engine immutably and updating it borrows it mutably, an update can
+    /// never run while a batch is only partially applied, and the next update sees every value of
+    /// the batch. This holds even when the engine is shared between threads.
+    ///
+    /// The Y component of each value is ignored for 1D parameters. If the puppet has no parameter
+    /// matching one of the entries, no parameter is changed and `false` is returned. When a
+    /// parameter appears more than once, the last entry wins.
+    pub fn set_params(&self, values: &[(ParamId<'_>, Vec2)]) -> bool {
+        if values.iter().any(|&(id, _)| self.params.get(id).is_none()) {
+            return false;
         }
+        for &(id, value) in values {
+            self.set_param(id, value);
+        }
+        true
     }
 
-    fn invalid(what: impl AsRef<str>) -> Self {
-        Self {
-            msg: format!("invalid model: {}", what.as_ref()),
+    /// Mutes or unmutes a parameter.
+    ///
+    /// The bindings of a muted parameter have no effect, so the properties it drives fall back to
+    /// the values specified by the model (as modified by other parameters). This helps finding out
+    /// which parameter causes a glitch in a rig. The parameter's value can still be set while it is
+    /// muted, and takes effect once it is unmuted again.
+    ///
+    /// Returns `false` if the puppet has no matching parameter.
+    pub fn set_param_muted<'a>(&self, param: impl Into<ParamId<'a>>, muted: bool) -> bool {
+        self.params.set_muted(param.into(), muted)
+    }
+
+    /// Resets a parameter to its default value.
+    ///
+    /// Returns `false` if the puppet has no matching parameter.
+    pub fn reset_param<'a>(&self, param: impl Into<ParamId<'a>>) -> bool {
+        self.params.reset(param.into())
+    }
+
+    /// Resets every parameter of the puppet to its default value.
+    pub fn reset_all_params(&self) {
+        self.params.reset_all();
+    }
+
+    /// Sets the conventionally named parameters of the puppet from face tracking data.
+    ///
+    /// See [`TrackingInput`] for the parameters that are set. Parameters the puppet doesn't have,
+    /// and values that aren't tracked, are ignored.
+    pub fn apply_tracking(&self, input: &TrackingInput) {
+        tracking::apply(&self.params, input);
+    }
+
+    /// Captures the current value of every parameter of the puppet in an [`ExpressionPreset`].
+    pub fn capture_expression(&self) -> ExpressionPreset {
+        let mut preset = ExpressionPreset::new();
+        for (uuid, handle) in self.params.handles() {
+            preset.set(uuid, handle.value());
+        }
+        preset
+    }
+
+    /// Sets every parameter in `from` or `to` to a blend of both presets.
+    ///
+    /// At a `t` of 0.0, parameters take on their value in `from`, and at 1.0 their value in `to`.
+    /// Values in between are interpolated linearly, so animating `t` over time smoothly changes
+    /// from one expression to the other. A parameter missing from one of the presets is blended
+    /// with its default value instead. Parameters that are in neither preset are left unchanged,
+    /// and parameters the puppet doesn't have are ignored.
+    pub fn crossfade(&self, from: &ExpressionPreset, to: &ExpressionPreset, t: f32) {
+        for (uuid, handle) in self.params.handles() {
+            let (a, b) = (from.get(uuid), to.get(uuid));
+            if a.is_none() && b.is_none() {
+                continue;
+            }
+            let default = handle.default_value();
+            let (a, b) = (a.unwrap_or(default), b.unwrap_or(default));
+            handle.set([0, 1].map(|i| a[i] + (b[i] - a[i]) * t));
+        }
+    }
+
+    /// Measures how far each node moves when sweeping a parameter across its range.
+    ///
+    /// The parameter is evaluated at its minimum and maximum value (for 2D parameters, at every
+    /// corner of its range), and the largest distance between the positions a node's origin takes
+    /// on is reported for every node of the puppet. This helps finding rigging mistakes, like
+    /// parameters that move parts much further than intended.
+    ///
+    /// The parameter's value is restored afterwards. If the puppet has no matching parameter, an
+    /// empty map is returned.
+    pub fn parameter_influence<'a>(&self, param: impl Into<ParamId<'a>>) -> HashMap<Uuid, f32> {
+        let Some(handle) = self.params.get(param.into()) else {
+            return HashMap::new();
+        };
+
+        let value = handle.value();
+        let [min, max] = handle.range();
+        let mut positions: Vec<HashMap<Uuid, Vec2>> = Vec::with_capacity(4);
+        for corner in [min, [max[0], min[1]], [min[0], max[1]], max] {
+            handle.set(corner);
+            let mut origins = HashMap::new();
+            self.root_node
+                .origins(self.config.rotation_direction, &mut origins);
+            positions.push(origins);
+        }
+        handle.set(value);
+
+        positions[0]
+            .keys()
+            .map(|node| {
+                let mut max_distance: f32 = 0.0;
+                for (i, a) in positions.iter().enumerate() {
+                    for b in &positions[i + 1..] {
+                        let [ax, ay] = a[node];
+                        let [bx, by] = b[node];
+                        max_distance = max_distance.max((ax - bx).hypot(ay - by));
+                    }
+                }
+                (*node, max_distance)
+            })
+            .collect()
+    }
+
+    /// Returns the rectangle spanned by the model's meshes, at the current parameter values.
+    ///
+    /// The minimum and maximum corner of the rectangle are returned, in model space. Mesh
+    /// deformations are not taken into account. Returns `None` if the model has no meshes.
+    pub fn model_bounds(&self) -> Option<[Vec2; 2]> {
+        self.root_node.bounds(self.config.rotation_direction)
+    }
+
+    /// Returns the rectangle spanned by the meshes rendered by the last update.
+    ///
+    /// Unlike [`PuppetEngine::model_bounds`], this includes mesh deformations, and skips disabled
+    /// nodes and masks, which aren't visible themselves. The minimum and maximum corner of the
+    /// rectangle are returned, in model space. Returns `None` if no mesh was rendered.
+    pub fn bounds(&self) -> Option<[Vec2; 2]> {
+        let mut bounds: Option<[Vec2; 2]> = None;
+        for cmd in &self.render_buffer.commands {
+            let Some(mesh) = cmd.mesh().filter(|_| !cmd.is_mask) else {
+                continue;
+            };
+            for vertex in mesh.vertices() {
+                let [x, y] = cmd.transform.transform_point(vertex);
+                let [min, max] = bounds.get_or_insert([[x, y]; 2]);
+                *min = [min[0].min(x), min[1].min(y)];
+                *max = [max[0].max(x), max[1].max(y)];
+            }
+        }
+        bounds
+    }
+
+    /// Returns the position of a node's origin relative to the [model bounds], at the current
+    /// parameter values.
+    ///
+    /// The minimum corner of the bounds maps to `[0.0, 0.0]` and the maximum corner to
+    /// `[1.0, 1.0]`, independent of the model's size in pixels. Nodes outside the bounds map to
+    /// values outside of that range. Along an axis on which the bounds have no extent (including
+    /// when the model has no meshes at all), the position is `0.5`.
+    ///
+    /// Returns `None` if the puppet has no node with the UUID `node`.
+    ///
+    /// [model bounds]: PuppetEngine::model_bounds
+    pub fn normalized_origin(&self, node: Uuid) -> Option<Vec2> {
+        let mut origins = HashMap::new();
+        self.root_node
+            .origins(self.config.rotation_direction, &mut origins);
+        let origin = *origins.get(&node)?;
+
+        let [min, max] = self.model_bounds().unwrap_or([origin; 2]);
+        Some([0, 1].map(|i| {
+            let size = max[i] - min[i];
+            if size > 0.0 {
+                (origin[i] - min[i]) / size
+            } else {
+                0.5
+            }
+        }))
+    }
+
+    pub fn global_tint(&self) -> Vec3 {
+        self.global_tint
+    }
+
+    /// Sets a color every node's tint is multiplied with, eg. to match the lighting of a scene.
+    ///
+    /// Defaults to white, which has no effect.
+    pub fn set_global_tint(&mut self, tint: Vec3) {
+        self.global_tint = tint;
+        self.rendered_generation = None;
+    }
+
+    pub fn global_opacity(&self) -> f32 {
+        self.global_opacity
+    }
+
+    /// Sets a factor every node's opacity is multiplied with, eg. to fade out the whole puppet.
+    ///
+    /// Defaults to 1.0, which has no effect.
+    pub fn set_global_opacity(&mut self, opacity: f32) {
+        self.global_opacity = opacity;
+        self.rendered_generation = None;
+    }
+
+    /// Returns the node with the UUID `node`.
+    ///
+    /// The node's [global transform][node::NodeBase::global_transform] and
+    /// [Z-Sort value][node::NodeBase::zsort] reflect the last update. Returns `None` if the
+    /// puppet has no node with the given UUID.
+    pub fn node(&self, node: Uuid) -> Option<&Node> {
+        self.root_node.at_path(self.node_paths.get(&node)?)
+    }
+
+    /// Returns the node with the UUID `node`, for modification.
+    ///
+    /// Changes take effect with the next update. Returns `None` if the puppet has no node with
+    /// the given UUID.
+    pub fn node_mut(&mut self, node: Uuid) -> Option<&mut Node> {
+        self.rendered_generation = None;
+        self.root_node.at_path_mut(self.node_paths.get(&node)?)
+    }
+
+    /// Returns an iterator over every node of the puppet, along with its depth in the node tree.
+    ///
+    /// Nodes are visited in depth-first order, starting with the root node at depth 0, and every
+    /// node is followed by its subtree.
+    pub fn iter_nodes(&self) -> NodeIter<'_> {
+        NodeIter::new(&self.root_node)
+    }
+
+    /// Like [`PuppetEngine::iter_nodes`], but allows modifying the nodes.
+    ///
+    /// Since a node owns its children, this yields a [`NodeMut`][node::NodeMut] handle instead of
+    /// a `&mut Node`. Use [`PuppetEngine::node_mut`] to access a single node in full.
+    pub fn iter_nodes_mut(&mut self) -> NodeIterMut<'_> {
+        self.rendered_generation = None;
+        NodeIterMut::new(&mut self.root_node)
+    }
+
+    /// Returns the state of the physics simulation of the [`SimplePhysics`] node `node`, as of
+    /// the last [`PuppetEngine::update`].
+    ///
+    /// Returns `None` if the puppet has no physics node with the given UUID.
+    ///
+    /// [`SimplePhysics`]: node::SimplePhysics
+    pub fn physics_state(&self, node: Uuid) -> Option<PhysicsState> {
+        match self.node(node)? {
+            Node::SimplePhysics(node) => Some(node.state()),
+            _ => None,
+        }
+    }
+
+    /// Enables or disables rendering of the node `node` and its subtree.
+    ///
+    /// Nodes start out enabled as specified by the model. See [`node::NodeBase::enabled`].
+    ///
+    /// Returns `false` if the puppet has no node with the given UUID.
+    pub fn set_node_enabled(&mut self, node: Uuid, enabled: bool) -> bool {
+        match self.node_mut(node) {
+            Some(node) => {
+                node.set_enabled(enabled);
+                true
+            }
+            None => false,
         }
     }
+
+    pub fn lod(&self) -> u32 {
+        self.params.lod()
+    }
+
+    /// Sets the level of detail the puppet is animated with.
+    ///
+    /// Parameters with a [LOD level][Self::set_param_lod] above `lod` are skipped: their bindings
+    /// behave as if the parameter was at its default value, and physics nodes driving them are not
+    /// simulated. This saves CPU time for puppets that don't need to be animated in full detail,
+    /// eg. because they are far away.
+    ///
+    /// Defaults to [`u32::MAX`], which evaluates all parameters.
+    pub fn set_lod(&mut self, lod: u32) {
+        self.params.set_lod(lod);
+    }
+
+    /// Sets the LOD level of a parameter.
+    ///
+    /// Less important parameters (eg. subtle deformations) should be given higher levels, so that
+    /// they are skipped first when lowering the engine's [level of detail][Self::set_lod]. All
+    /// parameters start out with level 0, so they're only skipped when explicitly configured.
+    ///
+    /// Returns `false` if the puppet has no matching parameter.
+    pub fn set_param_lod<'a>(&mut self, param: impl Into<ParamId<'a>>, level: u32) -> bool {
+        self.params.set_lod_level(param.into(), level)
+    }
+
+    pub fn automations_enabled(&self) -> bool {
+        self.automation_ramp.is_enabled()
+    }
+
+    /// Enables or disables the automations of the puppet.
+    ///
+    /// Disabled automations stop driving their parameters, which keep the value they were last
+    /// set to. The automations' output fades in or out over the
+    /// [toggle ramp][EngineConfig::set_toggle_ramp] instead of snapping on or off. Automations are
+    /// enabled by default.
+    pub fn set_automations_enabled(&mut self, enabled: bool) {
+        self.automation_ramp.set_enabled(enabled);
+    }
+
+    pub fn physics_enabled(&self) -> bool {
+        self.physics_ramp.is_enabled()
+    }
+
+    /// Enables or disables the physics simulation of the puppet.
+    ///
+    /// Like [`PuppetEngine::set_automations_enabled`], the output of the simulation fades in or
+    /// out over the [toggle ramp][EngineConfig::set_toggle_ramp]. Once it has faded out, the
+    /// simulation is paused, and it restarts from rest when it is enabled again. Physics
+    /// automations advance in the same fixed steps, so they are paused along with it. Physics is
+    /// enabled by default.
+    pub fn set_physics_enabled(&mut self, enabled: bool) {
+        if enabled && self.physics_ramp.weight() == 0.0 {
+            self.root_node.reset_physics();
+        }
+        self.physics_ramp.set_enabled(enabled);
+    }
+
+    pub fn physics_timestep(&self) -> Duration {
+        self.physics_timestep
+    }
+
+    /// Sets the length of the steps the physics simulation advances in.
+    ///
+    /// [`PuppetEngine::update`] advances the simulation in steps of this fixed length, regardless
+    /// of the frame rate, which keeps the simulation stable and its result independent of the
+    /// frame rate. Time left over at the end of a frame is carried over to the next one, and the
+    /// simulation's output is interpolated between the last two steps. This delays the output by
+    /// up to one step.
+    ///
+    /// The default timestep is 1/120 of a second. Shorter timesteps are more accurate, but cost
+    /// more CPU time. Timesteps longer than 100 ms are clamped to 100 ms. At most 12 steps are
+    /// simulated per update, and time beyond that is skipped.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `timestep` is zero.
+    pub fn set_physics_timestep(&mut self, timestep: Duration) {
+        assert!(!timestep.is_zero(), "physics timestep must not be zero");
+        let timestep = timestep.min(physics::MAX_TIMESTEP);
+        self.physics_timestep = timestep;
+        self.physics_accumulator = self.physics_accumulator.min(timestep);
+    }
+
+    /// Returns the total time the physics simulation has been advanced by in
+    /// [`PuppetEngine::update`].
+    ///
+    /// This is a multiple of the [physics timestep][PuppetEngine::set_physics_timestep]. It falls
+    /// behind the time passed to [`PuppetEngine::update`] when time is skipped after a long pause,
+    /// or while physics is disabled.
+    pub fn physics_time(&self) -> Duration {
+        self.physics_time
+    }
+
+    /// Advances the animation by `delta` and computes the render commands of the puppet.
+    ///
+    /// If no parameter was changed since the last update, and nothing else that affects the render
+    /// commands changed either, the commands of the last update are returned without recomputing
+    /// them. Automations and physics drive their parameters in every update while they are
+    /// enabled, so this only applies to puppets that have neither, or whose parameters they drive
+    /// don't change.
+    pub fn update(&mut self, delta: Duration) -> &[RenderCommand] {
+        let ramp = self.config.toggle_ramp;
+        // A system that has faded out writes once more at weight 0, which restores the base values
+        // of its parameters, and then stops writing.
+        let automations_fading = self.automation_ramp.weight() > 0.0;
+        let automation_weight = self.automation_ramp.advance(delta, ramp);
+        let physics_fading = self.physics_ramp.weight() > 0.0;
+        let physics_weight = self.physics_ramp.advance(delta, ramp);
+        let alpha = if physics_weight > 0.0 {
+            self.advance_physics(delta)
+        } else {
+            self.physics_accumulator = Duration::ZERO;
+            0.0
+        };
+        if automation_weight > 0.0 || automations_fading {
+            self.param_writes.set_weight(automation_weight);
+            self.automations.update(delta, &mut self.param_writes);
+        } else {
+            self.automations.advance(delta);
+        }
+        if physics_weight > 0.0 || physics_fading {
+            self.param_writes.set_weight(physics_weight);
+            self.root_node.write_physics(alpha, &mut self.param_writes);
+        }
+        self.finish_update(delta)
+    }
+
+    /// Advances the physics simulation by `delta` in fixed steps.
+    ///
+    /// Returns the fraction of a step left over, for interpolating the output of the simulation.
+    fn advance_physics(&mut self, delta: Duration) -> f32 {
+        let step = self.physics_timestep;
+        self.physics_accumulator += delta;
+        let mut steps = 0;
+        while self.physics_accumulator >= step {
+            if steps == MAX_PHYSICS_STEPS {
+                self.physics_accumulator = Duration::from_nanos(
+                    (self.physics_accumulator.as_nanos() % step.as_nanos()) as u64,
+                );
+                break;
+            }
+            self.physics_accumulator -= step;
+            self.root_node
+                .step_physics(step, &self.physics, self.config.rotation_direction);
+            self.automations.step_physics(step);
+            self.physics_time += step;
+            steps += 1;
+        }
+        self.physics_accumulator.as_secs_f32() / step.as_secs_f32()
+    }
+
+    /// Applies the parameter values computed for the frame, and computes its render commands.
+    ///
+    /// The commands of the previous frame are kept if nothing changed since they were computed.
+    fn finish_update(&mut self, delta: Duration) -> &[RenderCommand] {
+        self.param_writes.apply();
+        let generation = self.params.generation();
+        if self.rendered_generation == Some(generation) && self.transition.is_none() {
+            return &self.render_buffer.commands;
+        }
+        self.rendered_generation = Some(generation);
+
+        self.render_buffer.begin();
+        #[cfg(feature = "parallel")]
+        if self.node_paths.len() >= self.config.parallel_threshold()
+            && rayon::current_num_threads() > 1
+        {
+            self.root_node.update_parallel(
+                delta,
+                self.config.rotation_direction,
+                &mut self.render_buffer,
+            );
+        } else {
+            self.root_node.update(
+                delta,
+                self.config.rotation_direction,
+                &mut self.render_buffer,
+            );
+        }
+        #[cfg(not(feature = "parallel"))]
+        self.root_node.update(
+            delta,
+            self.config.rotation_direction,
+            &mut self.render_buffer,
+        );
+        self.render_buffer.index_transforms();
+        if self.has_path_deforms {
+            let rbuf = &mut self.render_buffer;
+            node::apply_path_deforms(&self.root_node, &rbuf.transform_indices, &mut rbuf.commands);
+        }
+
+        self.render_buffer.finish();
+        self.apply_transition(delta);
+        if self.global_tint != [1.0; 3] || self.global_opacity != 1.0 {
+            // The contents of composites are skipped, since the composite itself is tinted.
+            let mut i = 0;
+            while let Some(cmd) = self.render_buffer.commands.get_mut(i) {
+                for (c, global) in cmd.tint.iter_mut().zip(self.global_tint) {
+                    *c *= global;
+                }
+                cmd.opacity *= self.global_opacity;
+                i += 1 + cmd.composite_len.unwrap_or(0) as usize;
+            }
+        }
+        self.render_buffer.fill_transforms();
+        &self.render_buffer.commands
+    }
+
+    /// Returns the global transforms of the nodes rendered by the last update, as column-major
+    /// matrices.
+    ///
+    /// The buffer is indexed by [`RenderCommand::transform_index`], and has one entry for every
+    /// render command. Renderers that transform vertices on the GPU can upload it once per frame
+    /// and look up the transform of each draw in it, instead of updating per-draw uniforms. The
+    /// index of a node does not change between frames, as long as the model is not reloaded.
+    pub fn transform_buffer(&self) -> &[[f32; 16]] {
+        &self.render_buffer.transforms
+    }
+
+    /// Returns the index of `node`'s global transform in the
+    /// [transform buffer][PuppetEngine::transform_buffer].
+    ///
+    /// Returns `None` if `node` was not rendered by the last update.
+    pub fn transform_index(&self, node: Uuid) -> Option<usize> {
+        self.render_buffer
+            .transform_indices
+            .get(&node)
+            .map(|&index| index as usize)
+    }
+
+    /// Exports the global transform of every node, as computed by the last update, as JSON.
+    ///
+    /// The result is a JSON object mapping the UUID of every node to an object with its `name`,
+    /// `position` (`[x, y]`), `rotation` (in radians), and `scale` (`[x, y]`). It reflects
+    /// everything that affected the last update, including parameters, physics, and automations.
+    /// This is meant for feeding poses to external tools, and for debugging.
+    ///
+    /// Transforms that can not be represented as translation, rotation, and scale (eg. because a
+    /// node's parents are scaled non-uniformly) are approximated. Non-finite values are written as
+    /// `null`.
+    #[cfg(feature = "serde")]
+    pub fn export_pose_json(&self) -> String {
+        pose::to_json(&self.render_buffer.commands, |uuid| {
+            self.node(uuid).map_or("", |node| node.name())
+        })
+    }
+
+    /// Computes the render commands of the puppet at `time`, for rendering animations offline.
+    ///
+    /// The parameters in `param_overrides` are set to the given values first. Then, the physics
+    /// simulation is advanced from the previously rendered time in fixed steps, so that rendering
+    /// the same timestamp always produces the same result, regardless of which timestamps were
+    /// rendered before and how long that took. Rendering an earlier timestamp than before restarts
+    /// the simulation from time 0. Physics automations are simulated in the same steps, and all
+    /// other automations are evaluated at exactly `time`.
+    ///
+    /// This keeps its own simulation time, so it should not be mixed with [`PuppetEngine::update`].
+    /// Disabled automations and physics are skipped without fading them out.
+    pub fn render_at<'a>(
+        &mut self,
+        time: Duration,
+        param_overrides: &[(ParamId<'a>, Vec2)],
+    ) -> &[RenderCommand] {
+        let steps = (time.as_nanos() / RENDER_STEP.as_nanos()) as u64;
+        // Automations and physics nodes are evaluated between the simulation steps when rendering
+        // a frame. Undo that, so that the next steps don't depend on which frames were rendered.
+        for (param, value) in self.render_restore.drain(..) {
+            param.set(value);
+        }
+        if steps < self.render_steps {
+            self.root_node.reset_physics();
+            self.automations.reset_physics();
+            self.render_steps = 0;
+        }
+        for &(param, value) in param_overrides {
+            self.params.set(param, value);
+        }
+
+        while self.render_steps < steps {
+            let step_time = RENDER_STEP.as_nanos() as u64 * self.render_steps;
+            self.render_steps += 1;
+            self.automations.seek(Duration::from_nanos(step_time));
+            if self.physics_ramp.is_enabled() {
+                self.root_node.step_physics(
+                    RENDER_STEP,
+                    &self.physics,
+                    self.config.rotation_direction,
+                );
+                self.automations.step_physics(RENDER_STEP);
+                self.root_node.write_physics(1.0, &mut self.param_writes);
+            }
+            if self.automation_ramp.is_enabled() {
+                self.automations.update(RENDER_STEP, &mut self.param_writes);
+            }
+            self.param_writes.apply();
+        }
+
+        let simulated: Vec<_> = self
+            .params
+            .handles()
+            .map(|(_, param)| param.value())
+            .collect();
+        self.automations.seek(time);
+        // Like `update(Duration::ZERO)`, but bypassing the fixed-timestep accumulator. The empty
+        // step re-anchors the physics nodes at their position at `time`.
+        self.param_writes.set_weight(self.automation_ramp.weight());
+        self.automations
+            .update(Duration::ZERO, &mut self.param_writes);
+        let weight = self.physics_ramp.weight();
+        if weight > 0.0 {
+            self.root_node.step_physics(
+                Duration::ZERO,
+                &self.physics,
+                self.config.rotation_direction,
+            );
+            self.param_writes.set_weight(weight);
+            self.root_node.write_physics(1.0, &mut self.param_writes);
+        }
+        self.finish_update(Duration::ZERO);
+        self.render_restore = self
+            .params
+            .handles()
+            .zip(simulated)
+            .filter(|((_, param), value)| param.value() != *value)
+            .map(|((_, param), value)| (param.clone(), value))
+            .collect();
+        &self.render_buffer.commands
+    }
+
+    fn apply_transition(&mut self, delta: Duration) {
+        let Some(transition) = &mut self.transition else {
+            return;
+        };
+        transition.elapsed += delta;
+        let t = transition.elapsed.as_secs_f32() / self.config.reload_transition.as_secs_f32();
+        if t >= 1.0 {
+            self.transition = None;
+            return;
+        }
+
+        // Smoothstep, so that nodes don't start or stop moving abruptly.
+        let t = t * t * (3.0 - 2.0 * t);
+        for cmd in &mut self.render_buffer.commands {
+            if let Some(from) = transition.from.get(&cmd.node) {
+                cmd.transform = from.lerp(&cmd.transform, t);
+            }
+        }
+    }
+}
+
+/// A puppet frozen in its rest pose.
+///
+/// The render commands of the rest pose are computed once, when the [`StaticPuppet`] is created.
+/// Afterwards, the puppet can not be animated, but it doesn't need any of the state of a
+/// [`PuppetEngine`] either, making it much cheaper to keep around.
+pub struct StaticPuppet {
+    commands: Box<[RenderCommand]>,
+    transforms: Box<[[f32; 16]]>,
+}
+
+impl StaticPuppet {
+    /// Computes the rest pose of `puppet`, with all parameters at their default values.
+    pub fn new(puppet: &rhino2d_io::InochiPuppet) -> Result<Self> {
+        let mut engine = PuppetEngine::new(puppet)?;
+        engine.update(Duration::ZERO);
+        Ok(Self {
+            commands: std::mem::take(&mut engine.render_buffer.commands).into_boxed_slice(),
+            transforms: std::mem::take(&mut engine.render_buffer.transforms).into_boxed_slice(),
+        })
+    }
+
+    /// Returns the render commands of the rest pose, in the same order as
+    /// [`PuppetEngine::update`].
+    pub fn commands(&self) -> &[RenderCommand] {
+        &self.commands
+    }
+
+    /// Returns the global transforms of the rest pose, like [`PuppetEngine::transform_buffer`].
+    pub fn transform_buffer(&self) -> &[[f32; 16]] {
+        &self.transforms
+    }
+}
+
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+#[derive(Debug)]
+pub struct Error {
+    msg: String,
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.msg)
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl Error {
+    fn unsupported(what: impl AsRef<str>) -> Self {
+        Self {
+            msg: format!("model uses unsupported feature: {}", what.as_ref()),
+        }
+    }
+
+    fn invalid(what: impl AsRef<str>) -> Self {
+        Self {
+            msg: format!("invalid model: {}", what.as_ref()),
+        }
+    }
+
+    /// Creates an error caused by an incorrect use of the API, rather than by the model.
+    fn usage(msg: impl Into<String>) -> Self {
+        Self { msg: msg.into() }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rhino2d_io::testutil::{puppet, uuid};
+    use serde_json::{json, Value};
+
+    /// Creates the JSON data of a 1D parameter, with the entries of `extra` added to it.
+    fn param_1d(uuid: u64, name: &str, extra: Value, bindings: &[Value]) -> Value {
+        let mut param = json!({ "uuid": uuid, "name": name, "bindings": bindings });
+        for (key, value) in extra.as_object().unwrap() {
+            param[key] = value.clone();
+        }
+        param
+    }
+
+    /// Creates the JSON data of a binding of a 1D parameter to `target` of the node `node`.
+    fn binding(node: u64, target: &str, values: &[f32]) -> Value {
+        json!({ "node": node, "param_name": target, "values": [values] })
+    }
+
+    /// Joins parameters into the JSON array expected by `puppet`.
+    fn params(params: &[Value]) -> String {
+        Value::from(params).to_string()
+    }
+
+    #[test]
+    fn nodes_for_param() {
+        let puppet = puppet(
+            r#"{
+                "type": "Node", "uuid": 1, "name": "root",
+                "children": [
+                    { "type": "Node", "uuid": 2, "name": "a" },
+                    { "type": "Node", "uuid": 3, "name": "b" },
+                    { "type": "Node", "uuid": 4, "name": "c" }
+                ]
+            }"#,
+            r#"[
+                {
+                    "uuid": 10, "name": "Head Tilt",
+                    "bindings": [
+                        { "node": 2, "param_name": "transform.t.x", "values": [[0, 10]] },
+                        { "node": 3, "param_name": "transform.r.z", "values": [[0, 1]] }
+                    ]
+                },
+                {
+                    "uuid": 11, "name": "Other",
+                    "bindings": [
+                        { "node": 4, "param_name": "zSort", "values": [[0, 1]] }
+                    ]
+                }
+            ]"#,
+        );
+        let engine = PuppetEngine::new(&puppet).unwrap();
+
+        let expected = [
+            (uuid(2), ParamTarget::TranslationX),
+            (uuid(3), ParamTarget::RotationZ),
+        ];
+        assert_eq!(engine.nodes_for_param("Head Tilt"), expected);
+        assert_eq!(engine.nodes_for_param(uuid(10)), expected);
+        assert_eq!(engine.nodes_for_param("Nonexistent"), []);
+    }
+
+    #[test]
+    fn param_enumeration_order() {
+        let nodes = r#"{ "type": "Node", "uuid": 1 }"#;
+        let params = r#"[
+            { "uuid": 30, "name": "c" },
+            { "uuid": 10, "name": "a" },
+            { "uuid": 20, "name": "b" }
+        ]"#;
+        let a = PuppetEngine::new(&puppet(nodes, params)).unwrap();
+        let b = PuppetEngine::new(&puppet(nodes, params)).unwrap();
+
+        let a = a.params().collect::<Vec<_>>();
+        let b = b.params().collect::<Vec<_>>();
+        assert_eq!(a, b);
+        assert_eq!(a, [(uuid(10), "a"), (uuid(20), "b"), (uuid(30), "c")]);
+    }
+
+    #[test]
+    fn f16_deformation() {
+        let puppet = puppet(
+            r#"{
+                "type": "Drawable", "uuid": 1, "name": "mesh",
+                "mesh": {
+                    "verts": [0, 0, 100, 0, 0, 100, 100, 100],
+                    "indices": [0, 1, 2, 2, 1, 3],
+                    "origin": [0, 0]
+                }
+            }"#,
+            r#"[{
+                "uuid": 10, "name": "Deform", "defaults": [0.3, 0.0],
+                "bindings": [{
+                    "node": 1, "param_name": "deform",
+                    "values": [[
+                        [[0, 0], [0, 0], [0, 0], [0, 0]],
+                        [[0.3, -0.7], [12.25, 3.1], [-150.6, 0.05], [7.7, 480.9]]
+                    ]]
+                }]
+            }]"#,
+        );
+        let mut config = EngineConfig::new();
+        let mut f32_engine = PuppetEngine::with_config(&puppet, &config).unwrap();
+        config.set_deform_precision(DeformPrecision::F16);
+        let mut f16_engine = PuppetEngine::with_config(&puppet, &config).unwrap();
+
+        let f32_deform = f32_engine.update(Duration::ZERO)[0].deform().unwrap();
+        let f16_deform = f16_engine.update(Duration::ZERO)[0].deform().unwrap();
+        assert_eq!(f32_deform.len(), 4);
+        assert_eq!(f32_deform[3], [7.7 * 0.3, 480.9 * 0.3]);
+        for (a, b) in f32_deform.iter().zip(f16_deform) {
+            assert!((a[0] - b[0]).abs() < 0.1, "{a:?} != {b:?}");
+            assert!((a[1] - b[1]).abs() < 0.1, "{a:?} != {b:?}");
+        }
+    }
+
+    #[test]
+    fn mesh_view() {
+        let puppet = puppet(
+            r#"{ "type": "Node", "uuid": 1, "children": [
+                { "type": "Part", "uuid": 2 },
+                {
+                    "type": "Drawable", "uuid": 3,
+                    "mesh": { "verts": [0, 0, 10, 0, 0, 10], "indices": [0, 2, 1], "origin": [0, 0] }
+                }
+            ] }"#,
+            r#"[{
+                "uuid": 10, "name": "Deform", "defaults": [1.0, 0.0],
+                "bindings": [{
+                    "node": 3, "param_name": "deform",
+                    "values": [[[[0, 0], [0, 0], [0, 0]], [[1, 2], [0, 0], [-3, 0]]]]
+                }]
+            }]"#,
+        );
+        let mut engine = PuppetEngine::new(&puppet).unwrap();
+        let commands = engine.update(Duration::ZERO);
+        let mesh = |node| {
+            commands
+                .iter()
+                .find(|cmd| cmd.node() == uuid(node))
+                .unwrap()
+                .mesh()
+        };
+
+        assert!(mesh(1).is_none());
+
+        let part = mesh(2).unwrap();
+        let vertices = |mesh: MeshView<'_>| mesh.vertices().collect::<Vec<_>>();
+        assert_eq!(vertices(part), [[0.0, 0.0], [1.0, 0.0], [0.0, 1.0]]);
+        assert_eq!(vertices(part), part.base_vertices());
+        assert_eq!(part.uvs(), [[0.0, 0.0], [1.0, 0.0], [0.0, 1.0]]);
+        assert_eq!(part.indices(), [0, 1, 2]);
+
+        let drawable = mesh(3).unwrap();
+        assert_eq!(vertices(drawable), [[1.0, 2.0], [10.0, 0.0], [-3.0, 10.0]]);
+        assert_eq!(
+            drawable.base_vertices(),
+            [[0.0, 0.0], [10.0, 0.0], [0.0, 10.0]]
+        );
+        assert!(drawable.uvs().is_empty());
+        assert_eq!(drawable.indices(), [0, 2, 1]);
+    }
+
+    #[test]
+    fn physics_drives_earlier_nodes() {
+        // The physics node comes after the node it drives in the tree, and outputs a length of 1.0
+        // at rest, so the driven node has to move on the very first frame.
+        let puppet = puppet(
+            r#"{
+                "type": "Node", "uuid": 1,
+                "children": [
+                    { "type": "Node", "uuid": 2 },
+                    { "type": "SimplePhysics", "uuid": 3, "param": 10 }
+                ]
+            }"#,
+            r#"[{
+                "uuid": 10, "name": "Hair", "is_vec2": true,
+                "min": [-1.0, 0.0], "max": [1.0, 2.0],
+                "axis_points": [[0.0, 1.0], [0.0, 1.0]],
+                "bindings": [{
+                    "node": 2, "param_name": "transform.t.y",
+                    "values": [[0.0, 0.0], [100.0, 100.0]]
+                }]
+            }]"#,
+        );
+        let mut engine = PuppetEngine::new(&puppet).unwrap();
+        let cmds = engine.update(Duration::from_millis(16));
+        let cmd = cmds.iter().find(|cmd| cmd.node() == uuid(2)).unwrap();
+        assert_eq!(cmd.transform().as_column_major_data()[13], 50.0);
+    }
+
+    #[test]
+    fn static_puppet_matches_engine() {
+        let puppet = puppet(
+            r#"{
+                "type": "Node", "uuid": 1,
+                "children": [
+                    { "type": "Drawable", "uuid": 2, "zsort": 1.0 },
+                    { "type": "Part", "uuid": 3, "children": [{ "type": "Node", "uuid": 4 }] }
+                ]
+            }"#,
+            r#"[{
+                "uuid": 10, "name": "Pose", "defaults": [0.25, 0.0],
+                "bindings": [
+                    { "node": 2, "param_name": "deform", "values": [[
+                        [[0, 0], [0, 0], [0, 0]],
+                        [[4, 0], [0, 4], [4, 4]]
+                    ]] },
+                    { "node": 3, "param_name": "transform.t.x", "values": [[0, 8]] },
+                    { "node": 4, "param_name": "zSort", "values": [[0, -2]] }
+                ]
+            }]"#,
+        );
+        let mut engine = PuppetEngine::new(&puppet).unwrap();
+        let expected = engine.update(Duration::from_millis(16));
+        let actual = StaticPuppet::new(&puppet).unwrap();
+        let actual = actual.commands();
+
+        assert_eq!(actual.len(), expected.len());
+        for (a, e) in actual.iter().zip(expected) {
+            assert_eq!(a.node(), e.node());
+            assert_eq!(a.zsort(), e.zsort());
+            assert_eq!(
+                a.transform().as_column_major_data(),
+                e.transform().as_column_major_data()
+            );
+            assert_eq!(a.deform(), e.deform());
+        }
+        assert_eq!(
+            actual[0].deform(),
+            Some(&[[1.0, 0.0], [0.0, 1.0], [1.0, 1.0]][..])
+        );
+    }
+
+    #[test]
+    fn reset_params() {
+        let puppet = puppet(
+            r#"{ "type": "Node", "uuid": 1 }"#,
+            &params(&[
+                param_1d(
+                    10,
+                    "Tilt",
+                    json!({ "defaults": [0.5, 0.0] }),
+                    &[binding(1, "transform.t.x", &[0.0, 10.0])],
+                ),
+                param_1d(
+                    11,
+                    "Nod",
+                    json!({ "defaults": [0.25, 0.0] }),
+                    &[binding(1, "transform.t.y", &[0.0, 10.0])],
+                ),
+            ]),
+        );
+        let mut engine = PuppetEngine::new(&puppet).unwrap();
+        let translation = |engine: &mut PuppetEngine| {
+            let data = engine.update(Duration::ZERO)[0].transform();
+            let data = data.as_column_major_data();
+            [data[12], data[13]]
+        };
+        assert_eq!(translation(&mut engine), [5.0, 2.5]);
+
+        engine.params.handle(uuid(10)).unwrap().set([1.0, 0.0]);
+        engine.params.handle(uuid(11)).unwrap().set([1.0, 0.0]);
+        assert_eq!(translation(&mut engine), [10.0, 10.0]);
+
+        assert!(engine.reset_param("Tilt"));
+        assert!(!engine.reset_param("Nonexistent"));
+        assert_eq!(translation(&mut engine), [5.0, 10.0]);
+
+        engine.reset_all_params();
+        assert_eq!(translation(&mut engine), [5.0, 2.5]);
+    }
+
+    #[test]
+    fn param_refs() {
+        let puppet = puppet(
+            r#"{ "type": "Node", "uuid": 1 }"#,
+            r#"[
+                {
+                    "uuid": 10, "name": "Tilt",
+                    "bindings": [{ "node": 1, "param_name": "transform.t.x", "values": [[0, 10]] }]
+                },
+                {
+                    "uuid": 11, "name": "Look", "is_vec2": true, "axis_points": [[0, 1], [0, 1]],
+                    "bindings": [{ "node": 1, "param_name": "transform.t.y", "values": [[0, 10], [0, 10]] }]
+                }
+            ]"#,
+        );
+        let mut engine = PuppetEngine::new(&puppet).unwrap();
+        let tilt = engine.param("Tilt").unwrap();
+        let look = engine.param(uuid(11)).unwrap();
+        assert!(engine.param("Nonexistent").is_none());
+        assert!(!tilt.is_2d());
+        assert!(look.is_2d());
+
+        tilt.set(0.5).unwrap();
+        look.set_xy(1.0, 0.25).unwrap();
+        let data = engine.update(Duration::ZERO)[0].transform();
+        let data = data.as_column_major_data();
+        assert_eq!([data[12], data[13]], [5.0, 10.0]);
+        assert_eq!(look.value(), [1.0, 0.25]);
+
+        // Setting the wrong number of axes fails without changing the value.
+        assert!(tilt.set_xy(1.0, 1.0).is_err());
+        assert!(look.set(0.0).is_err());
+        assert_eq!(tilt.value(), [0.5, 0.0]);
+        assert_eq!(look.value(), [1.0, 0.25]);
+
+        look.reset();
+        assert_eq!(look.value(), [0.0, 0.0]);
+    }
+
+    #[test]
+    fn param_by_uuid() {
+        let puppet = puppet(
+            r#"{ "type": "Node", "uuid": 1 }"#,
+            r#"[{
+                "uuid": 10, "name": "Tilt",
+                "bindings": [{ "node": 1, "param_name": "transform.t.x", "values": [[0, 10]] }]
+            }]"#,
+        );
+        let mut engine = PuppetEngine::new(&puppet).unwrap();
+        let tilt = engine.param_by_uuid(uuid(10)).unwrap();
+        tilt.set(1.0).unwrap();
+        let data = engine.update(Duration::ZERO)[0].transform();
+        assert_eq!(data.as_column_major_data()[12], 10.0);
+        assert!(engine.param_by_uuid(uuid(12)).is_none());
+    }
+
+    #[test]
+    fn apply_tracking() {
+        let puppet = puppet(
+            r#"{ "type": "Node", "uuid": 1 }"#,
+            r#"[
+                {
+                    "uuid": 10, "name": "Head:: Yaw-Pitch", "is_vec2": true,
+                    "min": [-1.0, -1.0], "max": [1.0, 1.0], "defaults": [0.0, 0.5],
+                    "axis_points": [[0, 1], [0, 1]],
+                    "bindings": [{ "node": 1, "param_name": "transform.t.x", "values": [[0, 0], [10, 10]] }]
+                },
+                { "uuid": 11, "name": "Eye:: Left:: Blink" },
+                {
+                    "uuid": 12, "name": "Eye:: Right:: X-Y", "is_vec2": true,
+                    "min": [-1.0, -1.0], "max": [1.0, 1.0], "axis_points": [[0, 1], [0, 1]]
+                }
+            ]"#,
+        );
+        let engine = PuppetEngine::new(&puppet).unwrap();
+        let mut input = TrackingInput::new();
+        input.set_head_yaw(0.75);
+        input.set_left_eye_open(0.25);
+        input.set_mouth_open(1.0);
+        input.set_right_eye_x(-0.5);
+        input.set_right_eye_y(0.25);
+        engine.apply_tracking(&input);
+
+        // The untracked pitch keeps its value.
+        assert_eq!(
+            engine.param("Head:: Yaw-Pitch").unwrap().value(),
+            [0.75, 0.5]
+        );
+        assert_eq!(
+            engine.param("Eye:: Left:: Blink").unwrap().value(),
+            [0.75, 0.0]
+        );
+        assert_eq!(
+            engine.param("Eye:: Right:: X-Y").unwrap().value(),
+            [-0.5, 0.25]
+        );
+    }
+
+    #[test]
+    fn set_params_batch() {
+        let puppet = puppet(
+            r#"{ "type": "Node", "uuid": 1 }"#,
+            r#"[
+                {
+                    "uuid": 10, "name": "Tilt",
+                    "bindings": [{ "node": 1, "param_name": "transform.t.x", "values": [[0, 10]] }]
+                },
+                {
+                    "uuid": 11, "name": "Nod",
+                    "bindings": [{ "node": 1, "param_name": "transform.t.y", "values": [[0, 10]] }]
+                }
+            ]"#,
+        );
+        let mut engine = PuppetEngine::new(&puppet).unwrap();
+        let translation = |engine: &mut PuppetEngine| {
+            let data = engine.update(Duration::ZERO)[0].transform();
+            let data = data.as_column_major_data();
+            [data[12], data[13]]
+        };
+        assert_eq!(translation(&mut engine), [0.0, 0.0]);
+
+        assert!(engine.set_params(&[
+            (ParamId::Name("Tilt"), [0.5, 0.0]),
+            (ParamId::Uuid(uuid(11)), [1.0, 0.0]),
+        ]));
+        assert_eq!(translation(&mut engine), [5.0, 10.0]);
+
+        // A batch with an unknown parameter is rejected as a whole.
+        assert!(!engine.set_params(&[
+            (ParamId::Name("Tilt"), [1.0, 0.0]),
+            (ParamId::Name("Nonexistent"), [1.0, 0.0]),
+        ]));
+        assert_eq!(translation(&mut engine), [5.0, 10.0]);
+    }
+
+    #[test]
+    fn rotation_direction() {
+        let puppet = puppet(
+            r#"{ "type": "Node", "uuid": 1 }"#,
+            r#"[{
+                "uuid": 10, "name": "Spin",
+                "bindings": [{ "node": 1, "param_name": "transform.r.z", "values": [[0, 1.5707964]] }]
+            }]"#,
+        );
+        // Returns where the X axis points to after the node is spun by 90°.
+        let x_axis = |direction| {
+            let mut config = EngineConfig::new();
+            config.set_rotation_direction(direction);
+            let mut engine = PuppetEngine::with_config(&puppet, &config).unwrap();
+            engine.set_param("Spin", [1.0, 0.0]);
+            let data = engine.update(Duration::ZERO)[0].transform();
+            let data = data.as_column_major_data();
+            [data[0], data[1]].map(f32::round)
+        };
+
+        // Y points down, so turning X towards Y is clockwise.
+        assert_eq!(x_axis(RotationDirection::Clockwise), [0.0, 1.0]);
+        assert_eq!(x_axis(RotationDirection::CounterClockwise), [0.0, -1.0]);
+    }
+
+    #[test]
+    fn normalized_origin() {
+        let puppet = puppet(
+            r#"{
+                "type": "Node", "uuid": 1,
+                "children": [
+                    {
+                        "type": "Part", "uuid": 2,
+                        "transform": { "trans": [100, 50, 0], "rot": [0, 0, 0], "scale": [1, 1] },
+                        "mesh": {
+                            "verts": [-40, -20, 40, -20, 40, 20, -40, 20],
+                            "uvs": [0, 0, 1, 0, 1, 1, 0, 1],
+                            "indices": [0, 1, 2, 0, 2, 3],
+                            "origin": [0, 0]
+                        },
+                        "children": [
+                            { "type": "Node", "uuid": 3 },
+                            {
+                                "type": "Node", "uuid": 4,
+                                "transform": { "trans": [40, -10, 0], "rot": [0, 0, 0], "scale": [1, 1] }
+                            }
+                        ]
+                    }
+                ]
+            }"#,
+            r#"[{
+                "uuid": 10, "name": "Move",
+                "bindings": [{ "node": 2, "param_name": "transform.t.x", "values": [[0, 50]] }]
+            }]"#,
+        );
+        let engine = PuppetEngine::new(&puppet).unwrap();
+        assert_eq!(engine.model_bounds(), Some([[60.0, 30.0], [140.0, 70.0]]));
+        assert_eq!(engine.normalized_origin(uuid(3)), Some([0.5, 0.5]));
+        assert_eq!(engine.normalized_origin(uuid(4)), Some([1.0, 0.25]));
+        assert_eq!(engine.normalized_origin(uuid(1)), Some([-0.75, -0.75]));
+        assert_eq!(engine.normalized_origin(uuid(99)), None);
+
+        // The bounds move along with the part.
+        engine.set_param("Move", [1.0, 0.0]);
+        assert_eq!(engine.normalized_origin(uuid(3)), Some([0.5, 0.5]));
+
+        let empty = rhino2d_io::testutil::puppet(r#"{ "type": "Node", "uuid": 1 }"#, "[]");
+        let engine = PuppetEngine::new(&empty).unwrap();
+        assert_eq!(engine.model_bounds(), None);
+        assert_eq!(engine.normalized_origin(uuid(1)), Some([0.5, 0.5]));
+    }
+
+    #[test]
+    fn transform_buffer() {
+        let puppet = puppet(
+            r#"{
+                "type": "Node", "uuid": 1,
+                "children": [
+                    { "type": "Node", "uuid": 2, "zsort": 1.0 },
+                    { "type": "Node", "uuid": 3, "children": [{ "type": "Node", "uuid": 4 }] }
+                ]
+            }"#,
+            r#"[{
+                "uuid": 10, "name": "Move",
+                "bindings": [
+                    { "node": 3, "param_name": "transform.t.x", "values": [[0, 10]] },
+                    { "node": 3, "param_name": "zSort", "values": [[0, 2]] }
+                ]
+            }]"#,
+        );
+        let mut engine = PuppetEngine::new(&puppet).unwrap();
+        let index = engine.transform_index(uuid(3));
+        assert_eq!(index, None);
+
+        for value in [0.0, 1.0] {
+            engine.set_param("Move", [value, 0.0]);
+            engine.update(Duration::ZERO);
+            assert_eq!(
+                engine.transform_buffer().len(),
+                engine.render_buffer.commands.len()
+            );
+            for cmd in &engine.render_buffer.commands {
+                assert_eq!(
+                    engine.transform_buffer()[cmd.transform_index()],
+                    cmd.transform().as_column_major_data(),
+                );
+                assert_eq!(
+                    engine.transform_index(cmd.node()),
+                    Some(cmd.transform_index())
+                );
+            }
+        }
+
+        // Indices are assigned in tree order, independent of the draw order.
+        let index = engine.transform_index(uuid(3)).unwrap();
+        assert_eq!(index, 2);
+        assert_eq!(engine.transform_buffer()[index][12], 10.0);
+        assert_eq!(engine.render_buffer.commands[0].node(), uuid(3));
+    }
+
+    #[test]
+    fn crossfade() {
+        let puppet = puppet(
+            r#"{ "type": "Node", "uuid": 1 }"#,
+            r#"[
+                { "uuid": 10, "name": "Smile" },
+                {
+                    "uuid": 11, "name": "Look", "is_vec2": true, "defaults": [0.5, 0.5],
+                    "axis_points": [[0.0, 1.0], [0.0, 1.0]]
+                },
+                { "uuid": 12, "name": "Frown", "defaults": [0.2, 0.0] },
+                { "uuid": 13, "name": "Blink", "defaults": [0.1, 0.0] }
+            ]"#,
+        );
+        let engine = PuppetEngine::new(&puppet).unwrap();
+        engine.set_param("Blink", [0.9, 0.0]);
+
+        let mut smile = ExpressionPreset::new();
+        smile.set(uuid(10), [1.0, 0.0]);
+        smile.set(uuid(11), [0.0, 1.0]);
+        smile.set(uuid(99), [1.0, 1.0]);
+        let mut frown = ExpressionPreset::new();
+        frown.set(uuid(10), [0.0, 0.0]);
+        frown.set(uuid(12), [0.8, 0.0]);
+
+        engine.crossfade(&smile, &frown, 0.5);
+        let values = engine.capture_expression();
+        assert_eq!(values.get(uuid(10)), Some([0.5, 0.0]));
+        assert_eq!(values.get(uuid(11)), Some([0.25, 0.75]));
+        assert_eq!(values.get(uuid(12)), Some([0.5, 0.0]));
+        assert_eq!(values.get(uuid(13)), Some([0.9, 0.0]));
+        assert_eq!(values.get(uuid(99)), None);
+
+        engine.crossfade(&smile, &frown, 0.0);
+        assert_eq!(engine.capture_expression().get(uuid(11)), Some([0.0, 1.0]));
+        engine.crossfade(&smile, &frown, 1.0);
+        assert_eq!(engine.capture_expression().get(uuid(11)), Some([0.5, 0.5]));
+    }
+
+    #[test]
+    fn muted_param() {
+        let puppet = puppet(
+            r#"{
+                "type": "Node", "uuid": 1,
+                "transform": { "trans": [3, 4, 0], "rot": [0, 0, 0], "scale": [1, 1] }
+            }"#,
+            &params(&[
+                param_1d(
+                    10,
+                    "Move",
+                    json!({}),
+                    &[binding(1, "transform.t.x", &[0.0, 10.0])],
+                ),
+                param_1d(
+                    11,
+                    "Lift",
+                    json!({}),
+                    &[binding(1, "transform.t.y", &[0.0, 10.0])],
+                ),
+            ]),
+        );
+        let mut engine = PuppetEngine::new(&puppet).unwrap();
+        let translation = |engine: &mut PuppetEngine| {
+            let data = engine.update(Duration::ZERO)[0].transform();
+            let data = data.as_column_major_data();
+            [data[12], data[13]]
+        };
+        engine.set_param("Move", [1.0, 0.0]);
+        engine.set_param("Lift", [0.5, 0.0]);
+        assert_eq!(translation(&mut engine), [13.0, 9.0]);
+
+        assert!(engine.set_param_muted("Move", true));
+        assert!(!engine.set_param_muted("Nonexistent", true));
+        assert_eq!(translation(&mut engine), [3.0, 9.0]);
+
+        engine.set_param("Move", [0.5, 0.0]);
+        assert_eq!(translation(&mut engine), [3.0, 9.0]);
+
+        engine.set_param_muted("Move", false);
+        assert_eq!(translation(&mut engine), [8.0, 9.0]);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn export_pose_json() {
+        let puppet = puppet(
+            r#"{
+                "type": "Node", "uuid": 1, "name": "root",
+                "transform": { "trans": [3, 4, 0], "rot": [0, 0, 0], "scale": [1, 1] },
+                "children": [{
+                    "type": "Node", "uuid": 2, "name": "arm \"left\"",
+                    "transform": { "trans": [0, 0, 0], "rot": [0, 0, 0.5], "scale": [2, 2] }
+                }]
+            }"#,
+            r#"[{
+                "uuid": 10, "name": "Move",
+                "bindings": [{ "node": 1, "param_name": "transform.t.x", "values": [[0, 10]] }]
+            }]"#,
+        );
+        let mut engine = PuppetEngine::new(&puppet).unwrap();
+        engine.set_param("Move", [1.0, 0.0]);
+        let commands = engine.update(Duration::ZERO);
+        let transform = commands.iter().find(|cmd| cmd.node() == uuid(1)).unwrap();
+        let data = transform.transform();
+        let data = data.as_column_major_data();
+        let (x, y) = (data[12] as f64, data[13] as f64);
+
+        let json: serde_json::Value = serde_json::from_str(&engine.export_pose_json()).unwrap();
+        let vec2 = |value: &serde_json::Value| [0, 1].map(|i| value[i].as_f64().unwrap());
+        assert_eq!(json.as_object().unwrap().len(), 2);
+        assert_eq!(json["1"]["name"], "root");
+        assert_eq!(json["2"]["name"], "arm \"left\"");
+        assert_eq!(vec2(&json["1"]["position"]), [x, y]);
+        assert_eq!(vec2(&json["1"]["position"]), [13.0, 4.0]);
+        assert_eq!(json["1"]["rotation"].as_f64(), Some(0.0));
+        assert_eq!(vec2(&json["1"]["scale"]), [1.0, 1.0]);
+
+        let rotation = json["2"]["rotation"].as_f64().unwrap();
+        assert!((rotation - 0.5).abs() < 1e-6, "{rotation}");
+        let [sx, sy] = vec2(&json["2"]["scale"]);
+        assert!(
+            (sx - 2.0).abs() < 1e-6 && (sy - 2.0).abs() < 1e-6,
+            "{sx} {sy}"
+        );
+    }
+
+    #[test]
+    fn partially_keyed_deformation() {
+        let puppet = puppet(
+            r#"{
+                "type": "Drawable", "uuid": 1,
+                "mesh": {
+                    "verts": [0, 0, 1, 0, 0, 1, 1, 1],
+                    "uvs": [0, 0, 1, 0, 0, 1, 1, 1],
+                    "indices": [0, 1, 2, 2, 1, 3],
+                    "origin": [0, 0]
+                }
+            }"#,
+            r#"[{
+                "uuid": 10, "name": "Bend", "axis_points": [[0.0, 0.5, 1.0], [0.0]],
+                "bindings": [{
+                    "node": 1, "param_name": "deform",
+                    "values": [[
+                        [[0, 0], [0, 0], [2, 2], [0, -2]],
+                        [[99, 99], [99, 99], [99, 99], [99, 99]],
+                        [[4, 0], [0, 4], [2, 2], [0, 0]]
+                    ]],
+                    "isSet": [[true, false, true]]
+                }]
+            }]"#,
+        );
+        let mut engine = PuppetEngine::new(&puppet).unwrap();
+
+        // Every vertex of the unset middle keyframe is interpolated from the neighboring
+        // keyframes, ignoring the values stored for it. That includes the vertices that the
+        // rigger didn't move between the keyframes, which keep their offset instead of being
+        // reset to rest.
+        for (value, expected) in [
+            (0.0, [[0.0, 0.0], [0.0, 0.0], [2.0, 2.0], [0.0, -2.0]]),
+            (0.5, [[2.0, 0.0], [0.0, 2.0], [2.0, 2.0], [0.0, -1.0]]),
+            (0.75, [[3.0, 0.0], [0.0, 3.0], [2.0, 2.0], [0.0, -0.5]]),
+            (1.0, [[4.0, 0.0], [0.0, 4.0], [2.0, 2.0], [0.0, 0.0]]),
+        ] {
+            engine.set_param("Bend", [value, 0.0]);
+            let deform = engine.update(Duration::ZERO)[0].deform().unwrap().to_vec();
+            assert_eq!(deform, expected, "{value}");
+        }
+    }
+
+    #[test]
+    fn nearest_interpolation() {
+        let puppet = puppet(
+            r#"{
+                "type": "Node", "uuid": 1,
+                "transform": { "trans": [0, 0, 0], "rot": [0, 0, 0], "scale": [1, 1] }
+            }"#,
+            r#"[
+                {
+                    "uuid": 10, "name": "Toggle",
+                    "bindings": [{
+                        "node": 1, "param_name": "transform.t.x", "values": [[0, 10]],
+                        "interpolate_mode": "Nearest"
+                    }]
+                },
+                {
+                    "uuid": 11, "name": "Grid", "is_vec2": true,
+                    "axis_points": [[0.0, 1.0], [0.0, 1.0]],
+                    "bindings": [{
+                        "node": 1, "param_name": "transform.t.y", "values": [[0, 10], [100, 110]],
+                        "interpolate_mode": "Nearest"
+                    }]
+                }
+            ]"#,
+        );
+        let mut engine = PuppetEngine::new(&puppet).unwrap();
+        let mut translation = |toggle: f32, grid: Vec2| {
+            engine.set_param("Toggle", [toggle, 0.0]);
+            engine.set_param("Grid", grid);
+            let data = engine.update(Duration::ZERO)[0].transform();
+            let data = data.as_column_major_data();
+            [data[12], data[13]]
+        };
+
+        // The midpoint between two axis points snaps to the later one.
+        assert_eq!(translation(0.49, [0.49, 0.49]), [0.0, 0.0]);
+        assert_eq!(translation(0.5, [0.5, 0.49]), [10.0, 10.0]);
+        assert_eq!(translation(0.5, [0.49, 0.5]), [10.0, 100.0]);
+        assert_eq!(translation(0.51, [0.5, 0.5]), [10.0, 110.0]);
+        assert_eq!(translation(1.0, [1.0, 0.0]), [10.0, 10.0]);
+    }
+
+    #[test]
+    fn nested_transforms() {
+        // Two branches of nested nodes, with a node locked to the root in the first one.
+        fn chain(first: u64, depth: u64) -> serde_json::Value {
+            let uuid = first + depth;
+            let mut node = serde_json::json!({
+                "type": "Node", "uuid": uuid, "lockToRoot": uuid == 13,
+                "transform": { "trans": [1, 2, 0], "rot": [0, 0, 0.3], "scale": [1.1, 0.9] },
+            });
+            if depth > 0 {
+                node["children"] = serde_json::json!([chain(first, depth - 1)]);
+            }
+            node
+        }
+        let root = serde_json::json!({
+            "type": "Node", "uuid": 1,
+            "children": [chain(10, 5), chain(20, 3)],
+        });
+        let puppet = puppet(&root.to_string(), "[]");
+
+        fn expected(node: &rhino2d_io::node::Node, parent: Transform, out: &mut Vec<[f32; 16]>) {
+            let local = Transform::from_io(node.transform(), RotationDirection::default());
+            let global = if node.lock_to_root() {
+                local
+            } else {
+                parent * local
+            };
+            out.push(global.to_array());
+            for child in node.children() {
+                expected(child, global, out);
+            }
+        }
+        let mut transforms = Vec::new();
+        expected(puppet.root_node(), Transform::identity(), &mut transforms);
+
+        let mut engine = PuppetEngine::new(&puppet).unwrap();
+        for _ in 0..2 {
+            engine.update(Duration::ZERO);
+            let mut actual = engine.render_buffer.commands.clone();
+            actual.sort_by_key(|cmd| cmd.transform_index());
+            let actual = actual
+                .iter()
+                .map(|cmd| cmd.transform().to_array())
+                .collect::<Vec<_>>();
+            assert_eq!(actual, transforms);
+        }
+    }
+
+    #[test]
+    fn child_transforms() {
+        let origins = |parent: &str, child: &str| {
+            let puppet = puppet(
+                &format!(
+                    r#"{{
+                        "type": "Node", "uuid": 1, "transform": {parent},
+                        "children": [{{ "type": "Node", "uuid": 2, "transform": {child} }}]
+                    }}"#
+                ),
+                "[]",
+            );
+            let mut engine = PuppetEngine::new(&puppet).unwrap();
+            engine.update(Duration::ZERO);
+            let commands = engine.render_buffer.commands.clone();
+            [1, 2].map(|node| {
+                let cmd = commands
+                    .iter()
+                    .find(|cmd| cmd.node() == uuid(node))
+                    .unwrap();
+                let data = cmd.transform().to_array();
+                [data[12], data[13]]
+            })
+        };
+
+        let parent = r#"{ "trans": [10, 0, 0], "rot": [0, 0, 0], "scale": [1, 1] }"#;
+        let child = r#"{ "trans": [0, 10, 0], "rot": [0, 0, 0], "scale": [1, 1] }"#;
+        assert_eq!(origins(parent, child), [[10.0, 0.0], [10.0, 10.0]]);
+
+        // A node's own rotation and scale don't affect its position, but are applied to the
+        // position of its children.
+        let parent = r#"{ "trans": [10, 0, 0], "rot": [0, 0, 1.5707964], "scale": [2, 2] }"#;
+        let child = r#"{ "trans": [0, 10, 0], "rot": [0, 0, 1], "scale": [3, 3] }"#;
+        let [parent, child] = origins(parent, child);
+        assert_eq!(parent, [10.0, 0.0]);
+        assert!(
+            (child[0] + 10.0).abs() < 1e-4 && child[1].abs() < 1e-4,
+            "{child:?}"
+        );
+    }
+
+    #[test]
+    fn multiplicative_scale() {
+        let scale_x = |merge_mode: &str| {
+            let puppet = puppet(
+                r#"{
+                    "type": "Node", "uuid": 1,
+                    "transform": { "trans": [0, 0, 0], "rot": [0, 0, 0], "scale": [2, 1] }
+                }"#,
+                &params(&[param_1d(
+                    10,
+                    "Squash",
+                    json!({ "defaults": [1.0, 0.0], "merge_mode": merge_mode }),
+                    &[binding(1, "transform.s.x", &[1.0, 0.5])],
+                )]),
+            );
+            let mut engine = PuppetEngine::new(&puppet).unwrap();
+            engine.update(Duration::ZERO)[0]
+                .transform()
+                .as_column_major_data()[0]
+        };
+
+        assert_eq!(scale_x("Additive"), 3.0);
+        assert_eq!(scale_x("Multiplicative"), 1.0);
+        assert_eq!(scale_x("Forced"), 1.0);
+    }
+
+    #[test]
+    fn multiplicative_offsets() {
+        // Multiplying a translation or rotation scales the node's value, not the parameter offset
+        // that starts out at 0.
+        let puppet = puppet(
+            r#"{
+                "type": "Node", "uuid": 1,
+                "transform": { "trans": [4, 0, 0], "rot": [0, 0, 1], "scale": [1, 1] }
+            }"#,
+            &params(&[param_1d(
+                10,
+                "Halve",
+                json!({ "defaults": [1.0, 0.0], "merge_mode": "Multiplicative" }),
+                &[
+                    binding(1, "transform.t.x", &[1.0, 0.5]),
+                    binding(1, "transform.r.z", &[1.0, 0.5]),
+                ],
+            )]),
+        );
+        let mut engine = PuppetEngine::new(&puppet).unwrap();
+        let data = engine.update(Duration::ZERO)[0].transform().to_array();
+        assert_eq!(data[12], 2.0);
+        assert!((data[0] - 0.5f32.cos()).abs() < 1e-6, "{data:?}");
+    }
+
+    #[test]
+    fn edge_behavior() {
+        let offset_x = |edge_behavior: &str, value: f32| {
+            let puppet = puppet(
+                r#"{ "type": "Node", "uuid": 1 }"#,
+                &params(&[param_1d(
+                    10,
+                    "Lean",
+                    json!({ "edge_behavior": edge_behavior }),
+                    &[binding(1, "transform.t.x", &[0.0, 10.0])],
+                )]),
+            );
+            let mut engine = PuppetEngine::new(&puppet).unwrap();
+            engine.param("Lean").unwrap().set(value).unwrap();
+            engine.update(Duration::ZERO)[0]
+                .transform()
+                .as_column_major_data()[12]
+        };
+
+        assert_eq!(offset_x("Clamp", 0.5), 5.0);
+        assert_eq!(offset_x("Clamp", 2.0), 10.0);
+        assert_eq!(offset_x("Clamp", -1.0), 0.0);
+        assert_eq!(offset_x("Extrapolate", 0.5), 5.0);
+        assert_eq!(offset_x("Extrapolate", 2.0), 20.0);
+        assert_eq!(offset_x("Extrapolate", -1.0), -10.0);
+    }
+
+    #[test]
+    fn lod_skips_params() {
+        let puppet = puppet(
+            r#"{
+                "type": "Drawable", "uuid": 1,
+                "mesh": { "verts": [0, 0, 1, 0, 0, 1], "indices": [0, 1, 2], "origin": [0, 0] }
+            }"#,
+            &params(&[
+                param_1d(
+                    10,
+                    "Body",
+                    json!({}),
+                    &[binding(1, "transform.t.x", &[0.0, 10.0])],
+                ),
+                param_1d(
+                    11,
+                    "Breath",
+                    json!({ "defaults": [0.5, 0.0] }),
+                    &[
+                        binding(1, "transform.t.y", &[0.0, 10.0]),
+                        json!({ "node": 1, "param_name": "deform", "values": [[
+                            [[0, 0], [0, 0], [0, 0]],
+                            [[2, 0], [2, 0], [2, 0]]
+                        ]] }),
+                    ],
+                ),
+            ]),
+        );
+        let mut engine = PuppetEngine::new(&puppet).unwrap();
+        assert!(engine.set_param_lod("Breath", 2));
+        assert!(!engine.set_param_lod("Nonexistent", 2));
+        engine.set_param("Body", [1.0, 0.0]);
+        engine.set_param("Breath", [1.0, 0.0]);
+
+        let state = |engine: &mut PuppetEngine| {
+            let cmd = &engine.update(Duration::ZERO)[0];
+            let transform = cmd.transform();
+            let data = transform.as_column_major_data();
+            ([data[12], data[13]], cmd.deform().unwrap()[0])
+        };
+        assert_eq!(state(&mut engine), ([10.0, 10.0], [2.0, 0.0]));
+
+        // "Breath" falls back to its default value, "Body" is still evaluated.
+        engine.set_lod(1);
+        assert_eq!(state(&mut engine), ([10.0, 5.0], [1.0, 0.0]));
+
+        engine.set_lod(2);
+        assert_eq!(state(&mut engine), ([10.0, 10.0], [2.0, 0.0]));
+    }
+
+    #[test]
+    fn parameter_influence() {
+        let puppet = puppet(
+            r#"{
+                "type": "Node", "uuid": 1,
+                "children": [
+                    { "type": "Node", "uuid": 2, "children": [{ "type": "Node", "uuid": 3 }] },
+                    { "type": "Node", "uuid": 4 }
+                ]
+            }"#,
+            r#"[
+                {
+                    "uuid": 10, "name": "Fling", "min": [-1.0, 0.0], "max": [1.0, 0.0],
+                    "axis_points": [[0.0, 0.5, 1.0], [0.0]],
+                    "bindings": [
+                        { "node": 2, "param_name": "transform.t.x", "values": [[-300, 0, 300]] }
+                    ]
+                },
+                {
+                    "uuid": 11, "name": "Nudge", "is_vec2": true,
+                    "axis_points": [[0.0, 1.0], [0.0, 1.0]],
+                    "bindings": [
+                        { "node": 4, "param_name": "transform.t.x", "values": [[0, 3], [0, 3]] },
+                        { "node": 4, "param_name": "transform.t.y", "values": [[0, 0], [4, 4]] }
+                    ]
+                }
+            ]"#,
+        );
+        let engine = PuppetEngine::new(&puppet).unwrap();
+        engine.set_param("Fling", [0.5, 0.0]);
+
+        let influence = engine.parameter_influence("Fling");
+        assert_eq!(influence.len(), 4);
+        assert_eq!(influence[&uuid(1)], 0.0);
+        assert_eq!(influence[&uuid(2)], 600.0);
+        assert_eq!(influence[&uuid(3)], 600.0);
+        assert_eq!(influence[&uuid(4)], 0.0);
+        assert_eq!(
+            engine.params.get(uuid(10).into()).unwrap().value(),
+            [0.5, 0.0]
+        );
+
+        let influence = engine.parameter_influence(uuid(11));
+        assert_eq!(influence[&uuid(2)], 0.0);
+        assert_eq!(influence[&uuid(4)], 5.0);
+
+        assert!(engine.parameter_influence("Nonexistent").is_empty());
+    }
+
+    #[test]
+    fn transform_decomposition() {
+        fn assert_close(actual: &[f32], expected: &[f32]) {
+            for (a, e) in actual.iter().zip(expected) {
+                assert!((a - e).abs() < 1e-5, "{actual:?} != {expected:?}");
+            }
+        }
+
+        for (rot, scale) in [
+            ([0.0, 0.0, 0.5], [2.0, 0.5]),
+            ([0.1, -0.2, 0.3], [1.5, 3.0]),
+            ([0.0, 0.0, -2.0], [1.0, -0.5]),
+            ([0.3, 0.2, 0.1], [0.5, -2.0]),
+        ] {
+            let mut io = rhino2d_io::node::Transform::new();
+            io.set_translation([1.0, -2.0, 3.0]);
+            io.set_rotation(rot);
+            io.set_scale(scale);
+            let transform = Transform::from_io(&io, RotationDirection::Clockwise);
+            assert_close(&transform.translation(), &[1.0, -2.0, 3.0]);
+            assert_close(&transform.rotation_euler(), &rot);
+            assert_close(&transform.scale(), &scale);
+
+            let transform = Transform::from_io(&io, RotationDirection::CounterClockwise);
+            assert_close(&transform.rotation_euler(), &rot.map(|angle| -angle));
+        }
+
+        // A negative X scale is reported as a negative Y scale and a half turn.
+        let mut io = rhino2d_io::node::Transform::new();
+        io.set_scale([-2.0, 1.0]);
+        let transform = Transform::from_io(&io, RotationDirection::Clockwise);
+        assert_close(&transform.scale(), &[2.0, -1.0]);
+        assert_close(&transform.rotation_euler()[2..], &[std::f32::consts::PI]);
+    }
+
+    #[test]
+    fn z_translation_offsets_zsort() {
+        let puppet = puppet(
+            r#"{ "type": "Node", "uuid": 1, "children": [
+                {
+                    "type": "Part", "uuid": 2, "zsort": 1.0,
+                    "transform": { "trans": [0, 0, 0.5], "rot": [0, 0, 0], "scale": [1, 1] },
+                    "children": [{ "type": "Part", "uuid": 3, "zsort": 1.0 }]
+                },
+                { "type": "Part", "uuid": 4, "zsort": 2.0 }
+            ] }"#,
+            r#"[{
+                "uuid": 10, "name": "Depth",
+                "bindings": [{ "node": 2, "param_name": "transform.t.z", "values": [[0, 2]] }]
+            }]"#,
+        );
+        let mut engine = PuppetEngine::new(&puppet).unwrap();
+        assert_eq!(engine.node(uuid(2)).unwrap().zsort(), 1.5);
+
+        let zsorts = |engine: &mut PuppetEngine| {
+            engine
+                .update(Duration::ZERO)
+                .iter()
+                .map(|cmd| (cmd.node().raw(), cmd.zsort()))
+                .collect::<Vec<_>>()
+        };
+        // The child's Z-Sort is not affected by its parent's Z translation.
+        assert_eq!(
+            zsorts(&mut engine),
+            [(4, 2.0), (2, 1.5), (3, 1.0), (1, 0.0)]
+        );
+        engine.set_param("Depth", [1.0, 0.0]);
+        assert_eq!(
+            zsorts(&mut engine),
+            [(2, 3.5), (4, 2.0), (3, 1.0), (1, 0.0)]
+        );
+    }
+
+    #[test]
+    fn rendered_bounds() {
+        let puppet = puppet(
+            r#"{ "type": "Node", "uuid": 1, "children": [
+                {
+                    "type": "Part", "uuid": 2,
+                    "transform": { "trans": [10, 20, 0], "rot": [0, 0, 0], "scale": [2, 1] }
+                },
+                {
+                    "type": "Mask", "uuid": 3,
+                    "transform": { "trans": [-50, 0, 0], "rot": [0, 0, 0], "scale": [1, 1] }
+                },
+                {
+                    "type": "Part", "uuid": 4, "enabled": false,
+                    "transform": { "trans": [50, 0, 0], "rot": [0, 0, 0], "scale": [1, 1] }
+                }
+            ] }"#,
+            r#"[{
+                "uuid": 10, "name": "Stretch",
+                "bindings": [{ "node": 2, "param_name": "deform", "values": [[
+                    [[0, 0], [0, 0], [0, 0]],
+                    [[0, 0], [3, 0], [0, 0]]
+                ]] }]
+            }]"#,
+        );
+        let mut engine = PuppetEngine::new(&puppet).unwrap();
+        assert_eq!(engine.bounds(), None);
+        engine.update(Duration::ZERO);
+        // The default mesh spans [0, 0] to [1, 1].
+        assert_eq!(engine.bounds(), Some([[10.0, 20.0], [12.0, 21.0]]));
+
+        engine.set_param("Stretch", [1.0, 0.0]);
+        engine.update(Duration::ZERO);
+        assert_eq!(engine.bounds(), Some([[10.0, 20.0], [18.0, 21.0]]));
+    }
+
+    #[test]
+    fn path_deform() {
+        // Vertex 2 is bound to both joints, and vertex 3 to none.
+        let joints = r#"{
+            "type": "PathDeform", "uuid": 2, "joints": [[0, 0], [1, 0]],
+            "transform": { "trans": [5, 5, 0], "rot": [0, 0, 0], "scale": [1, 1] },
+            "bindings": [{ "bound_to": 3, "bind_data": [[0, 2], [1, 2]] }]
+        }"#;
+        let quad = r#"{
+            "type": "Part", "uuid": 3,
+            "transform": { "trans": [0, 0, 0], "rot": [0, 0, 0], "scale": [2, 1] },
+            "mesh": {
+                "verts": [0, 0, 1, 0, 0, 1, 1, 1], "uvs": [0, 0, 1, 0, 0, 1, 1, 1],
+                "indices": [0, 1, 2, 2, 1, 3], "origin": [0, 0]
+            }
+        }"#;
+        let bend = r#"[{
+            "uuid": 10, "name": "Bend",
+            "bindings": [{ "node": 2, "param_name": "deform", "values": [[
+                [[0, 0], [0, 0]],
+                [[2, 0], [0, 4]]
+            ]] }]
+        }]"#;
+        let model = |joints: &str| {
+            puppet(
+                &format!(r#"{{ "type": "Node", "uuid": 1, "children": [{joints}, {quad}] }}"#),
+                bend,
+            )
+        };
+        let mut engine = PuppetEngine::new(&model(joints)).unwrap();
+        let deform = |engine: &mut PuppetEngine| {
+            let cmds = engine.update(Duration::ZERO);
+            let cmd = cmds.iter().find(|cmd| cmd.node() == uuid(3)).unwrap();
+            (
+                cmd.deform().map(<[Vec2]>::to_vec),
+                cmd.mesh().unwrap().vertices().collect::<Vec<_>>(),
+            )
+        };
+        assert_eq!(deform(&mut engine).0, Some(vec![[0.0; 2]; 4]));
+
+        // Offsets are converted into the space of the part, which is scaled by 2 along X.
+        engine.set_param("Bend", [1.0, 0.0]);
+        let (offsets, vertices) = deform(&mut engine);
+        assert_eq!(
+            offsets.unwrap(),
+            [[1.0, 0.0], [0.0, 4.0], [0.5, 2.0], [0.0, 0.0]]
+        );
+        assert_eq!(vertices, [[1.0, 0.0], [1.0, 4.0], [0.5, 3.0], [1.0, 1.0]]);
+
+        // Disabled `PathDeform` nodes don't deform anything.
+        engine.set_node_enabled(uuid(2), false);
+        assert_eq!(deform(&mut engine).0, None);
+
+        // Bindings must refer to existing vertices of drawables.
+        let invalid = [
+            joints.replace(r#""bound_to": 3"#, r#""bound_to": 1"#),
+            joints.replace("[1, 2]]", "[1, 4]]"),
+            joints.replace("[[0, 2], [1, 2]]", "[[0], [1], [2]]"),
+        ];
+        for joints in invalid {
+            assert!(PuppetEngine::new(&model(&joints)).is_err(), "{joints}");
+        }
+    }
+
+    #[test]
+    fn composites_are_sorted_separately() {
+        let puppet = puppet(
+            r#"{ "type": "Node", "uuid": 1, "children": [
+                { "type": "Part", "uuid": 2 },
+                {
+                    "type": "Composite", "uuid": 3, "zsort": -1.0, "tint": [1.0, 0.5, 0.5],
+                    "opacity": 0.8, "blend_mode": "Multiply", "mask_threshold": 0.25,
+                    "children": [
+                        { "type": "Part", "uuid": 4, "zsort": 5.0 },
+                        { "type": "Part", "uuid": 5, "zsort": -5.0 },
+                        { "type": "Composite", "uuid": 7, "zsort": 1.0, "children": [
+                            { "type": "Part", "uuid": 8 }
+                        ] }
+                    ]
+                },
+                { "type": "Part", "uuid": 6, "zsort": -0.5 }
+            ] }"#,
+            r#"[{
+                "uuid": 10, "name": "Raise",
+                "bindings": [{ "node": 5, "param_name": "zSort", "values": [[0, 20]] }]
+            }]"#,
+        );
+        let mut engine = PuppetEngine::new(&puppet).unwrap();
+        engine.set_global_opacity(0.5);
+        let order = |engine: &mut PuppetEngine| {
+            engine
+                .update(Duration::ZERO)
+                .iter()
+                .map(|cmd| (cmd.node().raw(), cmd.composite_len()))
+                .collect::<Vec<_>>()
+        };
+
+        // Part 4 is behind everything in the composite, but not behind part 6.
+        assert_eq!(
+            order(&mut engine),
+            [
+                (1, None),
+                (2, None),
+                (6, None),
+                (3, Some(4)),
+                (4, None),
+                (7, Some(1)),
+                (8, None),
+                (5, None),
+            ]
+        );
+        let cmds = engine.update(Duration::ZERO);
+        assert_eq!(cmds[3].tint(), [1.0, 0.5, 0.5]);
+        assert_eq!(cmds[3].opacity(), 0.4);
+        assert_eq!(cmds[3].blend_mode(), BlendMode::Multiply);
+        assert_eq!(cmds[3].mask_threshold(), 0.25);
+        assert_eq!(cmds[2].opacity(), 0.5);
+        assert_eq!(cmds[4].opacity(), 1.0);
+
+        engine.set_param("Raise", [1.0, 0.0]);
+        assert_eq!(
+            order(&mut engine)[3..],
+            [(3, Some(4)), (5, None), (4, None), (7, Some(1)), (8, None)]
+        );
+    }
+
+    #[test]
+    fn render_order_is_stable() {
+        let mut rbuf = RenderBuffer::new();
+        for frame in 0..2 {
+            rbuf.begin();
+            for (node, zsort) in [(1, 0.0), (2, 1.0), (3, 0.0), (4, -1.0), (5, 1.0), (6, 0.0)] {
+                rbuf.push(RenderCommand {
+                    node: uuid(node),
+                    zsort,
+                    transform: Transform::identity(),
+                    transform_index: 0,
+                    deform: None,
+                    tint: [1.0; 3],
+                    opacity: 1.0,
+                    blend_mode: BlendMode::Normal,
+                    mask_mode: None,
+                    masked_by: None,
+                    is_mask: false,
+                    textures: None,
+                    mask_threshold: 0.5,
+                    composite_len: None,
+                    mesh: None,
+                });
+            }
+            rbuf.finish();
+
+            let order = rbuf
+                .commands
+                .iter()
+                .map(|cmd| cmd.node.raw())
+                .collect::<Vec<_>>();
+            assert_eq!(order, [2, 5, 1, 3, 6, 4], "frame {frame}");
+        }
+    }
+
+    #[test]
+    fn equal_zsorts_render_in_tree_order() {
+        // Parts with a Z-Sort of 0 or -0 are rendered in the order they appear in the tree.
+        let puppet = puppet(
+            r#"{ "type": "Node", "uuid": 1, "children": [
+                { "type": "Part", "uuid": 2, "zsort": -0.0, "children": [
+                    { "type": "Part", "uuid": 3 },
+                    { "type": "Part", "uuid": 4, "zsort": -0.0 }
+                ] },
+                { "type": "Part", "uuid": 5 },
+                { "type": "Node", "uuid": 6, "zsort": 1.0, "children": [
+                    { "type": "Part", "uuid": 7, "zsort": -1.0 }
+                ] },
+                { "type": "Part", "uuid": 8, "zsort": -0.0 }
+            ] }"#,
+            "[]",
+        );
+        let mut engine = PuppetEngine::new(&puppet).unwrap();
+        for _ in 0..2 {
+            let order = engine
+                .update(Duration::from_millis(16))
+                .iter()
+                .map(|cmd| cmd.node().raw())
+                .collect::<Vec<_>>();
+            assert_eq!(order, [6, 1, 2, 3, 4, 5, 8, 7]);
+        }
+    }
+
+    #[test]
+    fn render_order_extreme_zsort() {
+        let mut rbuf = RenderBuffer::new();
+        let zsorts = [
+            (1, 0.0),
+            (2, f32::NAN),
+            (3, f32::MAX),
+            (4, f32::NEG_INFINITY),
+            (5, -0.0),
+            (6, f32::MIN),
+            (7, -f32::NAN),
+            (8, f32::INFINITY),
+            (9, 1e30),
+            (10, -1e30),
+        ];
+        for frame in 0..2 {
+            rbuf.begin();
+            for (node, zsort) in zsorts {
+                rbuf.push(RenderCommand {
+                    node: uuid(node),
+                    zsort,
+                    transform: Transform::identity(),
+                    transform_index: 0,
+                    deform: None,
+                    tint: [1.0; 3],
+                    opacity: 1.0,
+                    blend_mode: BlendMode::Normal,
+                    mask_mode: None,
+                    masked_by: None,
+                    is_mask: false,
+                    textures: None,
+                    mask_threshold: 0.5,
+                    composite_len: None,
+                    mesh: None,
+                });
+            }
+            rbuf.finish();
+
+            let order = rbuf
+                .commands
+                .iter()
+                .map(|cmd| cmd.node.raw())
+                .collect::<Vec<_>>();
+            assert_eq!(order, [2, 7, 8, 3, 9, 1, 5, 10, 6, 4], "frame {frame}");
+        }
+    }
+
+    #[test]
+    fn incremental_render_order() {
+        // Simple LCG, to get reproducible pseudo-random values.
+        let mut state = 1u32;
+        let mut random = |max: u32| {
+            state = state.wrapping_mul(1664525).wrapping_add(1013904223);
+            (state >> 8) % max
+        };
+
+        let mut rbuf = RenderBuffer::new();
+        let mut zsorts = vec![0.0; 200];
+        for frame in 0..100 {
+            // Vary between changing a handful and changing all of the Z-Sort values, so that both
+            // the incremental and the full sort are exercised. Few distinct values ensure ties.
+            let changes = if frame % 10 == 9 { 200 } else { random(6) };
+            for _ in 0..changes {
+                zsorts[random(200) as usize] = random(8) as f32;
+            }
+            // Change the command count occasionally.
+            let count = if frame % 25 == 24 { 150 } else { 200 };
+
+            rbuf.begin();
+            for (i, &zsort) in zsorts[..count].iter().enumerate() {
+                rbuf.push(RenderCommand {
+                    node: uuid(i as u64),
+                    zsort,
+                    transform: Transform::identity(),
+                    transform_index: 0,
+                    deform: None,
+                    tint: [1.0; 3],
+                    opacity: 1.0,
+                    blend_mode: BlendMode::Normal,
+                    mask_mode: None,
+                    masked_by: None,
+                    is_mask: false,
+                    textures: None,
+                    mask_threshold: 0.5,
+                    composite_len: None,
+                    mesh: None,
+                });
+            }
+            rbuf.finish();
+
+            let mut expected = (0..count as u64).collect::<Vec<_>>();
+            expected.sort_by_key(|&i| sort_key(zsorts[i as usize]));
+            let actual = rbuf
+                .commands
+                .iter()
+                .map(|cmd| cmd.node.raw())
+                .collect::<Vec<_>>();
+            assert_eq!(actual, expected, "frame {frame}");
+        }
+    }
+
+    #[test]
+    fn automations_compose_additively() {
+        let mut puppet = puppet(
+            r#"{ "type": "Node", "uuid": 1 }"#,
+            r#"[
+                { "uuid": 10, "name": "Sway", "min": [-2.0, 0.0], "max": [2.0, 0.0] },
+                { "uuid": 11, "name": "Look", "is_vec2": true, "axis_points": [[0.0, 1.0], [0.0, 1.0]] }
+            ]"#,
+        );
+        // A cosine with a speed of 0 stays at its peak, outputting the end of its range.
+        for (param, axis, range) in [("Sway", 0, 0.25), ("Sway", 0, 0.5), ("Look", 1, 0.5)] {
+            let automation = serde_json::from_value(serde_json::json!({
+                "type": "sine",
+                "name": "idle",
+                "speed": 0.0,
+                "sine_type": 1,
+                "bindings": [{ "param": param, "axis": axis, "range": [0.0, range] }],
+            }))
+            .unwrap();
+            puppet.push_automation(automation);
+        }
+
+        let mut engine = PuppetEngine::new(&puppet).unwrap();
+        engine.set_param("Look", [0.75, 0.0]);
+        for _ in 0..3 {
+            engine.update(Duration::from_millis(16));
+            let sway = engine.params.get(uuid(10).into()).unwrap().value();
+            assert_eq!(sway, [0.75, 0.0]);
+            // The X axis is not automated and keeps the value set by the user.
+            let look = engine.params.get(uuid(11).into()).unwrap().value();
+            assert_eq!(look, [0.75, 0.5]);
+        }
+    }
+
+    #[test]
+    fn idle_updates_reuse_commands() {
+        let mut puppet = puppet(
+            r#"{ "type": "Node", "uuid": 1, "children": [{ "type": "Part", "uuid": 2 }] }"#,
+            &params(&[param_1d(
+                10,
+                "Move",
+                json!({}),
+                &[binding(2, "transform.t.x", &[0.0, 8.0])],
+            )]),
+        );
+        // Marks the commands of the last update, to tell whether the next one recomputed them.
+        fn mark(engine: &mut PuppetEngine) {
+            for cmd in &mut engine.render_buffer.commands {
+                cmd.opacity = 0.5;
+            }
+        }
+        fn recomputed(engine: &mut PuppetEngine) -> bool {
+            engine.update(Duration::from_millis(16))[0].opacity() == 1.0
+        }
+
+        let mut engine = PuppetEngine::new(&puppet).unwrap();
+        engine.update(Duration::from_millis(16));
+        mark(&mut engine);
+        assert!(!recomputed(&mut engine));
+
+        // Setting a parameter, even to its current value, makes the next update recompute.
+        let param = engine.param("Move").unwrap();
+        param.set(0.0).unwrap();
+        assert!(recomputed(&mut engine));
+        mark(&mut engine);
+        assert!(!recomputed(&mut engine));
+        engine.set_param_muted("Move", true);
+        assert!(recomputed(&mut engine));
+        mark(&mut engine);
+        engine.set_global_tint([1.0, 0.5, 0.5]);
+        assert!(recomputed(&mut engine));
+        mark(&mut engine);
+        engine.set_node_enabled(uuid(2), true);
+        assert!(recomputed(&mut engine));
+
+        // Time-based automations drive their parameters in every update.
+        let automation = serde_json::from_value(serde_json::json!({
+            "type": "sine",
+            "name": "idle",
+            "speed": 1.0,
+            "sine_type": 0,
+            "bindings": [{ "param": "Move", "axis": 0, "range": [0.0, 1.0] }],
+        }))
+        .unwrap();
+        puppet.push_automation(automation);
+        let mut engine = PuppetEngine::new(&puppet).unwrap();
+        for _ in 0..3 {
+            engine.update(Duration::from_millis(16));
+            mark(&mut engine);
+            assert!(recomputed(&mut engine));
+        }
+    }
+
+    #[test]
+    fn automation_toggle_ramp() {
+        let mut puppet = puppet(
+            r#"{ "type": "Node", "uuid": 1 }"#,
+            r#"[{ "uuid": 10, "name": "Sway" }]"#,
+        );
+        let automation = serde_json::from_value(serde_json::json!({
+            "type": "sine",
+            "name": "idle",
+            "speed": 0.0,
+            "sine_type": 1,
+            "bindings": [{ "param": "Sway", "axis": 0, "range": [0.0, 1.0] }],
+        }))
+        .unwrap();
+        puppet.push_automation(automation);
+
+        let mut config = EngineConfig::new();
+        config.set_toggle_ramp(Duration::from_millis(100));
+        let mut engine = PuppetEngine::with_config(&puppet, &config).unwrap();
+        engine.set_automations_enabled(false);
+        assert!(!engine.automations_enabled());
+        engine.update(Duration::from_secs(1));
+        let sway = |engine: &PuppetEngine| engine.param("Sway").unwrap().value()[0];
+        assert_eq!(sway(&engine), 0.0);
+
+        // Disabled automations don't write their parameters, which keeps the render commands
+        // cached.
+        let generation = engine.params.generation();
+        engine.update(Duration::from_millis(25));
+        assert_eq!(engine.params.generation(), generation);
+
+        // The automation's output of 1.0 fades in linearly over 4 frames.
+        let frames = |engine: &mut PuppetEngine| {
+            [0; 5].map(|_| {
+                engine.update(Duration::from_millis(25));
+                sway(engine)
+            })
+        };
+        let assert_close = |actual: [f32; 5], expected: [f32; 5]| {
+            let close = actual
+                .iter()
+                .zip(expected)
+                .all(|(a, e)| (a - e).abs() < 1e-5);
+            assert!(close, "{actual:?} != {expected:?}");
+        };
+        engine.set_automations_enabled(true);
+        assert_close(frames(&mut engine), [0.25, 0.5, 0.75, 1.0, 1.0]);
+
+        // Fading out blends towards the value the parameter had when the fade started.
+        engine.set_param("Sway", [0.0, 0.0]);
+        engine.set_automations_enabled(false);
+        assert_close(frames(&mut engine), [0.75, 0.5, 0.25, 0.0, 0.0]);
+    }
+
+    #[test]
+    fn sine_automation_phase() {
+        let mut puppet = puppet(
+            r#"{ "type": "Node", "uuid": 1 }"#,
+            r#"[{ "uuid": 10, "name": "Sin" }, { "uuid": 11, "name": "Tan" }]"#,
+        );
+        // A speed of π makes the waves complete a period every 2 seconds.
+        for (param, sine_type) in [("Sin", 0), ("Tan", 2)] {
+            let automation = serde_json::from_value(serde_json::json!({
+                "type": "sine",
+                "name": "idle",
+                "speed": std::f32::consts::PI,
+                "sine_type": sine_type,
+                "bindings": [{ "param": param, "axis": 0, "range": [0.0, 1.0] }],
+            }))
+            .unwrap();
+            puppet.push_automation(automation);
+        }
+
+        let mut engine = PuppetEngine::new(&puppet).unwrap();
+        let value = |engine: &PuppetEngine, param| engine.param(param).unwrap().value()[0];
+        let close = |a: f32, b: f32| (a - b).abs() < 1e-5;
+        engine.update(Duration::ZERO);
+        assert!(close(value(&engine, "Sin"), 0.5));
+        assert!(close(value(&engine, "Tan"), 0.5));
+
+        // An eighth of a period in, the tangent reaches the end of the range.
+        engine.update(Duration::from_millis(250));
+        assert!(close(value(&engine, "Tan"), 1.0));
+        engine.update(Duration::from_millis(150));
+        assert_eq!(value(&engine, "Tan"), 1.0);
+
+        // A quarter of a period in, the sine peaks.
+        engine.update(Duration::from_millis(100));
+        assert!(close(value(&engine, "Sin"), 1.0));
+        engine.update(Duration::from_millis(1000));
+        assert!(close(value(&engine, "Sin"), 0.0));
+    }
+
+    #[test]
+    fn physics_automation_swings() {
+        let mut puppet = puppet(
+            r#"{ "type": "Node", "uuid": 1 }"#,
+            r#"[{ "uuid": 10, "name": "Swing", "min": [-1.0, 0.0], "max": [1.0, 0.0] }]"#,
+        );
+        // A chain held out horizontally, which falls and swings to the other side.
+        let automation = serde_json::from_value(serde_json::json!({
+            "type": "physics",
+            "name": "pendulum",
+            "bindings": [{ "param": "Swing", "axis": 0, "range": [-1.0, 1.0] }],
+            "nodes": [
+                { "distance": 0.0, "position": [0.0, 0.0], "old_position": [0.0, 0.0] },
+                { "distance": 1.0, "position": [1.0, 0.0], "old_position": [1.0, 0.0] },
+            ],
+            "damping": 0.0,
+            "bounciness": 1.0,
+            "gravity": 20.0,
+        }))
+        .unwrap();
+        puppet.push_automation(automation);
+
+        let mut engine = PuppetEngine::new(&puppet).unwrap();
+        let swing = |engine: &PuppetEngine| engine.param("Swing").unwrap().value()[0];
+        engine.update(Duration::from_millis(16));
+        let first = swing(&engine);
+        assert!(first > 0.9 && first <= 1.0, "{first}");
+
+        let mut min = first;
+        let mut last = first;
+        for _ in 0..100 {
+            engine.update(Duration::from_millis(16));
+            let value = swing(&engine);
+            assert!((-1.0..=1.0).contains(&value), "{value}");
+            min = min.min(value);
+            last = value;
+        }
+        assert!(min < -0.5, "chain should swing past the anchor: {min}");
+        assert_ne!(last, min);
+    }
+
+    #[test]
+    fn screen_aabb_of_rotated_node() {
+        let transform: rhino2d_io::node::Transform = serde_json::from_value(serde_json::json!({
+            "trans": [10.0, 0.0, 0.0],
+            "rot": [0.0, 0.0, std::f32::consts::FRAC_PI_4],
+            "scale": [1.0, 1.0],
+        }))
+        .unwrap();
+        let cmd = RenderCommand {
+            node: uuid(1),
+            zsort: 0.0,
+            transform: Transform::from_io(&transform, RotationDirection::default()),
+            transform_index: 0,
+            deform: None,
+            tint: [1.0; 3],
+            opacity: 1.0,
+            blend_mode: BlendMode::Normal,
+            mask_mode: None,
+            masked_by: None,
+            is_mask: false,
+            textures: None,
+            mask_threshold: 0.5,
+            composite_len: None,
+            mesh: None,
+        };
+        // Scales X by 0.5.
+        #[rustfmt::skip]
+        let view_proj = [
+            0.5, 0.0, 0.0, 0.0,
+            0.0, 1.0, 0.0, 0.0,
+            0.0, 0.0, 1.0, 0.0,
+            0.0, 0.0, 0.0, 1.0,
+        ];
+
+        let [min, max] = screen_aabb(&cmd, [[-1.0, -1.0], [1.0, 1.0]], &view_proj);
+        // Rotating the unit square by 45° makes its bounding box wider, by a factor of sqrt(2).
+        let sqrt_2 = std::f32::consts::SQRT_2;
+        assert!((max[0] - min[0] - sqrt_2).abs() < 1e-5, "{min:?} {max:?}");
+        assert!(
+            (max[1] - min[1] - 2.0 * sqrt_2).abs() < 1e-5,
+            "{min:?} {max:?}"
+        );
+
+        let mat = Matrix4::from_column_slice(&view_proj)
+            * Matrix4::from_column_slice(cmd.transform.as_column_major_data());
+        for [x, y] in [[-1.0, -1.0], [1.0, -1.0], [-1.0, 1.0], [1.0, 1.0]] {
+            let p = mat * Vector4::new(x, y, 0.0, 1.0);
+            assert!(min[0] <= p.x && p.x <= max[0], "{p:?} {min:?} {max:?}");
+            assert!(min[1] <= p.y && p.y <= max[1], "{p:?} {min:?} {max:?}");
+        }
+    }
+
+    #[test]
+    fn box_selection() {
+        let puppet = puppet(
+            r#"{
+                "type": "Node", "uuid": 1,
+                "children": [
+                    {
+                        "type": "Part", "uuid": 2,
+                        "transform": { "trans": [0.0, 0.0, 0.0], "rot": [0.0, 0.0, 0.7853982], "scale": [1.0, 1.0] }
+                    },
+                    {
+                        "type": "Part", "uuid": 3,
+                        "transform": { "trans": [5.0, 0.0, 0.0], "rot": [0.0, 0.0, 0.0], "scale": [1.0, 1.0] }
+                    }
+                ]
+            }"#,
+            "[]",
+        );
+        let mut engine = PuppetEngine::new(&puppet).unwrap();
+        let commands = engine.update(Duration::ZERO);
+        let mesh_bounds = |node| (node != uuid(1)).then_some([[-1.0, -1.0], [1.0, 1.0]]);
+        #[rustfmt::skip]
+        let identity = [
+            1.0, 0.0, 0.0, 0.0,
+            0.0, 1.0, 0.0, 0.0,
+            0.0, 0.0, 1.0, 0.0,
+            0.0, 0.0, 0.0, 1.0,
+        ];
+        let select = |rect| nodes_in_rect(commands, mesh_bounds, &identity, rect);
+
+        assert_eq!(select([[4.5, -0.5], [6.0, 0.5]]), [uuid(3)]);
+        assert_eq!(select([[-2.0, -2.0], [6.0, 2.0]]), [uuid(2), uuid(3)]);
+        // The corner of the rectangle is inside the bounding box of the rotated part, but outside
+        // the part itself.
+        assert!(select([[1.0, 1.0], [2.0, 2.0]]).is_empty());
+        assert_eq!(select([[0.5, 0.5], [2.0, 2.0]]), [uuid(2)]);
+    }
+
+    #[test]
+    fn mask_nodes() {
+        let nodes = r#"{
+            "type": "Node", "uuid": 1,
+            "children": [
+                {
+                    "type": "Part", "uuid": 2, "zsort": 1.0, "mask_mode": "Mask", "masked_by": [3]
+                },
+                { "type": "Mask", "uuid": 3, "zsort": -1.0 }
+            ]
+        }"#;
+        let sliding = puppet(
+            nodes,
+            r#"[{
+                "uuid": 10, "name": "Slide",
+                "bindings": [{ "node": 3, "param_name": "transform.t.x", "values": [[0, 10]] }]
+            }]"#,
+        );
+        let mut engine = PuppetEngine::new(&sliding).unwrap();
+        engine.set_param("Slide", [1.0, 0.0]);
+        let commands = engine.update(Duration::ZERO);
+        let find = |node| {
+            commands
+                .iter()
+                .find(|cmd| cmd.node() == uuid(node))
+                .unwrap()
+        };
+
+        let part = find(2);
+        assert!(!part.is_mask());
+        assert_eq!(part.mask_mode(), Some(MaskMode::Mask));
+        assert_eq!(part.masked_by(), [uuid(3)]);
+
+        let mask = find(3);
+        assert!(mask.is_mask());
+        assert!(mask.masked_by().is_empty());
+        assert!(mask.textures().is_empty());
+        assert_eq!(mask.mesh().unwrap().indices(), [0, 1, 2]);
+        assert_eq!(mask.transform().as_column_major_data()[12], 10.0);
+
+        // Masks aren't drawn, so they can't be tinted.
+        let tinted = puppet(
+            nodes,
+            r#"[{
+                "uuid": 10, "name": "Fade",
+                "bindings": [{ "node": 3, "param_name": "opacity", "values": [[1, 0]] }]
+            }]"#,
+        );
+        assert!(PuppetEngine::new(&tinted).is_err());
+    }
+
+    #[test]
+    fn part_render_state() {
+        let puppet = puppet(
+            r#"{
+                "type": "Node", "uuid": 1,
+                "children": [
+                    {
+                        "type": "Part", "uuid": 2, "tint": [1.0, 0.5, 0.25], "opacity": 0.5,
+                        "blend_mode": "Multiply", "mask_mode": "Dodge", "masked_by": [3],
+                        "textures": [4]
+                    },
+                    { "type": "Drawable", "uuid": 3 }
+                ]
+            }"#,
+            r#"[{
+                "uuid": 10, "name": "Fade",
+                "bindings": [
+                    { "node": 2, "param_name": "opacity", "values": [[1, 0]] },
+                    { "node": 2, "param_name": "tint.g", "values": [[1, 2]] }
+                ]
+            }]"#,
+        );
+        let mut engine = PuppetEngine::new(&puppet).unwrap();
+        engine.set_param("Fade", [0.5, 0.0]);
+        let commands = engine.update(Duration::ZERO);
+        let find = |node| {
+            commands
+                .iter()
+                .find(|cmd| cmd.node() == uuid(node))
+                .unwrap()
+        };
+
+        let part = find(2);
+        assert_eq!(part.opacity(), 0.25);
+        assert_eq!(part.tint(), [1.0, 0.75, 0.25]);
+        assert_eq!(part.blend_mode(), BlendMode::Multiply);
+        assert_eq!(part.mask_mode(), Some(MaskMode::Dodge));
+        assert_eq!(part.masked_by(), [uuid(3)]);
+        assert_eq!(part.textures(), [4]);
+
+        let drawable = find(3);
+        assert_eq!(drawable.blend_mode(), BlendMode::Normal);
+        assert_eq!(drawable.mask_mode(), None);
+        assert!(drawable.masked_by().is_empty());
+        assert!(drawable.textures().is_empty());
+
+        // Only parts have an opacity and tint.
+        let puppet = rhino2d_io::testutil::puppet(
+            r#"{ "type": "Drawable", "uuid": 1 }"#,
+            r#"[{
+                "uuid": 10, "name": "Fade",
+                "bindings": [{ "node": 1, "param_name": "opacity", "values": [[1, 0]] }]
+            }]"#,
+        );
+        assert!(PuppetEngine::new(&puppet).is_err());
+    }
+
+    #[test]
+    fn mesh_index_bounds() {
+        let mesh = |indices: &str| {
+            puppet(
+                &format!(
+                    r#"{{ "type": "Part", "uuid": 1, "name": "Face", "mesh": {{
+                        "verts": [0, 0, 1, 0, 0, 1], "uvs": [0, 0, 1, 0, 0, 1],
+                        "indices": {indices}, "origin": [0, 0]
+                    }} }}"#
+                ),
+                "[]",
+            )
+        };
+        assert!(PuppetEngine::new(&mesh("[0, 1, 2]")).is_ok());
+
+        // An index equal to the vertex count is one past the last vertex.
+        let error = PuppetEngine::new(&mesh("[0, 1, 3]")).err().unwrap();
+        assert_eq!(
+            error.to_string(),
+            "invalid model: mesh of 'Face' has index 3, but only 3 vertices"
+        );
+        let error = PuppetEngine::new(&mesh("[0, 1, 2, 0]")).err().unwrap();
+        assert!(
+            error.to_string().contains("'Face' has 4 indices"),
+            "{error}"
+        );
+    }
+
+    #[test]
+    fn disabled_subtree() {
+        let puppet = puppet(
+            r#"{
+                "type": "Node", "uuid": 1,
+                "children": [
+                    {
+                        "type": "Node", "uuid": 2, "enabled": false,
+                        "children": [{ "type": "Part", "uuid": 3 }]
+                    },
+                    { "type": "Part", "uuid": 4 }
+                ]
+            }"#,
+            "[]",
+        );
+        let mut engine = PuppetEngine::new(&puppet).unwrap();
+        let nodes = |engine: &mut PuppetEngine| {
+            let mut nodes = engine
+                .update(Duration::ZERO)
+                .iter()
+                .map(|cmd| cmd.node())
+                .collect::<Vec<_>>();
+            nodes.sort_by_key(|uuid| uuid.raw());
+            nodes
+        };
+
+        assert_eq!(nodes(&mut engine), [uuid(1), uuid(4)]);
+        assert_eq!(engine.transform_index(uuid(3)), None);
+
+        assert!(engine.set_node_enabled(uuid(2), true));
+        assert_eq!(nodes(&mut engine), [uuid(1), uuid(2), uuid(3), uuid(4)]);
+        assert!(engine.set_node_enabled(uuid(1), false));
+        assert!(nodes(&mut engine).is_empty());
+        assert!(!engine.set_node_enabled(uuid(5), false));
+    }
+
+    #[test]
+    fn solo_hides_siblings() {
+        let nodes = |solo| {
+            format!(
+                r#"{{
+                    "type": "Node", "uuid": 1,
+                    "children": [
+                        {{ "type": "Part", "uuid": 2, "solo": {solo} }},
+                        {{ "type": "Part", "uuid": 3 }},
+                        {{ "type": "Node", "uuid": 4, "children": [{{ "type": "Part", "uuid": 5 }}] }}
+                    ]
+                }}"#
+            )
+        };
+        let opacities = |solo, apply| {
+            let puppet = puppet(&nodes(solo), "[]");
+            let mut config = EngineConfig::new();
+            config.set_apply_solo(apply);
+            let mut engine = PuppetEngine::with_config(&puppet, &config).unwrap();
+            let commands = engine.update(Duration::ZERO);
+            [2, 3, 5].map(|node| {
+                commands
+                    .iter()
+                    .find(|cmd| cmd.node() == uuid(node))
+                    .unwrap()
+                    .opacity()
+            })
+        };
+
+        assert_eq!(opacities(false, true), [1.0, 1.0, 1.0]);
+        assert_eq!(opacities(true, true), [1.0, 0.0, 0.0]);
+        // Off by default.
+        assert_eq!(opacities(true, false), [1.0, 1.0, 1.0]);
+    }
+
+    #[test]
+    fn global_tint_and_opacity() {
+        let puppet = puppet(
+            r#"{
+                "type": "Node", "uuid": 1,
+                "children": [
+                    { "type": "Part", "uuid": 2, "tint": [1.0, 0.5, 0.25], "opacity": 0.5 },
+                    { "type": "Drawable", "uuid": 3 }
+                ]
+            }"#,
+            "[]",
+        );
+        let mut engine = PuppetEngine::new(&puppet).unwrap();
+        let colors = |engine: &mut PuppetEngine| {
+            engine
+                .update(Duration::ZERO)
+                .iter()
+                .map(|cmd| (cmd.node().raw(), (cmd.tint(), cmd.opacity())))
+                .collect::<HashMap<_, _>>()
+        };
+
+        let before = colors(&mut engine);
+        assert_eq!(before[&2], ([1.0, 0.5, 0.25], 0.5));
+        assert_eq!(before[&3], ([1.0; 3], 1.0));
+
+        engine.set_global_tint([0.5; 3]);
+        engine.set_global_opacity(0.5);
+        let after = colors(&mut engine);
+        assert_eq!(after.len(), before.len());
+        for (node, (tint, opacity)) in before {
+            assert_eq!(after[&node], (tint.map(|c| c * 0.5), opacity * 0.5));
+        }
+
+        // The global values are applied to the node's values each frame, not accumulated.
+        let again = colors(&mut engine);
+        assert_eq!(again, after);
+    }
+
+    #[test]
+    fn render_at_is_deterministic() {
+        let mut puppet = puppet(
+            r#"{
+                "type": "Node", "uuid": 1,
+                "children": [
+                    { "type": "Node", "uuid": 2 },
+                    { "type": "SimplePhysics", "uuid": 3, "param": 10, "length": 20.0 },
+                    { "type": "Node", "uuid": 4 }
+                ]
+            }"#,
+            r#"[
+                {
+                    "uuid": 10, "name": "Hair", "is_vec2": true,
+                    "min": [-1.0, 0.0], "max": [1.0, 2.0],
+                    "axis_points": [[0.0, 1.0], [0.0, 1.0]],
+                    "bindings": [{
+                        "node": 2, "param_name": "transform.r.z",
+                        "values": [[-1.0, -1.0], [1.0, 1.0]]
+                    }]
+                },
+                {
+                    "uuid": 11, "name": "Sway", "min": [-1.0, 0.0], "max": [1.0, 0.0],
+                    "bindings": [{ "node": 1, "param_name": "transform.t.x", "values": [[-50, 50]] }]
+                },
+                { "uuid": 12, "name": "Offset" , "bindings": [{ "node": 4, "param_name": "transform.t.y", "values": [[0, 10]] }] }
+            ]"#,
+        );
+        // Moves the root node back and forth, making the pendulum swing.
+        puppet.push_automation(
+            serde_json::from_value(serde_json::json!({
+                "type": "sine",
+                "name": "sway",
+                "speed": 3.0,
+                "sine_type": 0,
+                "bindings": [{ "param": "Sway", "axis": 0, "range": [-1.0, 1.0] }],
+            }))
+            .unwrap(),
+        );
+
+        let snapshot = |cmds: &[RenderCommand]| {
+            cmds.iter()
+                .map(|cmd| {
+                    let transform = cmd.transform();
+                    (
+                        cmd.node(),
+                        cmd.zsort(),
+                        transform.as_column_major_data().to_vec(),
+                    )
+                })
+                .collect::<Vec<_>>()
+        };
+        let time = Duration::from_millis(1500);
+        let overrides = [(ParamId::Name("Offset"), [1.0, 0.0])];
+
+        let mut engine = PuppetEngine::new(&puppet).unwrap();
+        let expected = snapshot(engine.render_at(time, &overrides));
+        assert_eq!(snapshot(engine.render_at(time, &overrides)), expected);
+
+        // The physics node is swinging, and the override applies.
+        let transform = |node| &expected.iter().find(|(n, ..)| *n == uuid(node)).unwrap().2;
+        assert_ne!(transform(2)[0], 1.0);
+        assert_eq!(transform(4)[13], 10.0);
+
+        // Reaching the timestamp via other timestamps, or after rewinding, gives the same result.
+        let mut engine = PuppetEngine::new(&puppet).unwrap();
+        for ms in [300, 1100, 200, 1499] {
+            engine.render_at(Duration::from_millis(ms), &overrides);
+        }
+        assert_eq!(snapshot(engine.render_at(time, &overrides)), expected);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn render_command_serde_roundtrip() {
+        let puppet = puppet(
+            r#"{
+                "type": "Part", "uuid": 1, "tint": [1.0, 0.5, 0.25], "opacity": 0.75,
+                "blend_mode": "Screen"
+            }"#,
+            r#"[{
+                "uuid": 10, "name": "Pose", "defaults": [0.5, 0.0],
+                "bindings": [
+                    { "node": 1, "param_name": "deform", "values": [[
+                        [[0, 0], [0, 0], [0, 0]],
+                        [[4, 0], [0, 4], [4, 4]]
+                    ]] },
+                    { "node": 1, "param_name": "transform.t.x", "values": [[0, 8]] }
+                ]
+            }]"#,
+        );
+        let mut engine = PuppetEngine::new(&puppet).unwrap();
+        let cmd = &engine.update(Duration::ZERO)[0];
+
+        let json = serde_json::to_value(cmd).unwrap();
+        assert_eq!(json["transform"].as_array().unwrap().len(), 16);
+        assert_eq!(json["transform"][12], 4.0);
+
+        let roundtrip: RenderCommand = serde_json::from_value(json).unwrap();
+        assert_eq!(roundtrip.node(), cmd.node());
+        assert_eq!(roundtrip.zsort(), cmd.zsort());
+        assert_eq!(
+            roundtrip.transform().as_column_major_data(),
+            cmd.transform().as_column_major_data()
+        );
+        assert_eq!(
+            roundtrip.deform(),
+            Some(&[[2.0, 0.0], [0.0, 2.0], [2.0, 2.0]][..])
+        );
+        assert_eq!(roundtrip.tint(), [1.0, 0.5, 0.25]);
+        assert_eq!(roundtrip.opacity(), 0.75);
+        assert_eq!(roundtrip.blend_mode(), BlendMode::Screen);
+    }
+
+    #[test]
+    fn node_lookup() {
+        let puppet = puppet(
+            r#"{ "type": "Node", "uuid": 1, "children": [
+                {
+                    "type": "Node", "uuid": 2,
+                    "transform": { "trans": [3, 4, 0], "rot": [0, 0, 0], "scale": [1, 1] },
+                    "children": [{
+                        "type": "Part", "uuid": 3, "zsort": 0.5,
+                        "transform": { "trans": [10, 0, 0], "rot": [0, 0, 0], "scale": [1, 1] }
+                    }]
+                }
+            ] }"#,
+            r#"[{
+                "uuid": 10, "name": "Move",
+                "bindings": [{ "node": 2, "param_name": "transform.t.y", "values": [[0, 20]] }]
+            }]"#,
+        );
+        let mut engine = PuppetEngine::new(&puppet).unwrap();
+        assert!(engine.node(uuid(4)).is_none());
+        assert_eq!(engine.node(uuid(1)).unwrap().children().len(), 1);
+
+        engine.set_param("Move", [1.0, 0.0]);
+        engine.update(Duration::from_millis(16));
+        let part = engine.node(uuid(3)).unwrap();
+        assert_eq!(part.uuid(), uuid(3));
+        assert!(matches!(part, Node::Part(_)));
+        assert_eq!(part.zsort(), 0.5);
+        assert_eq!(part.global_transform().origin(), [13.0, 24.0]);
+
+        engine.node_mut(uuid(2)).unwrap().set_enabled(false);
+        assert_eq!(engine.update(Duration::from_millis(16)).len(), 1);
+    }
+
+    #[test]
+    fn iterate_nodes() {
+        let puppet = puppet(
+            r#"{ "type": "Node", "uuid": 1, "children": [
+                { "type": "Node", "uuid": 2, "children": [
+                    { "type": "Part", "uuid": 3 },
+                    { "type": "Node", "uuid": 4, "children": [{ "type": "Part", "uuid": 5 }] }
+                ] },
+                { "type": "Part", "uuid": 6 }
+            ] }"#,
+            "[]",
+        );
+        let mut engine = PuppetEngine::new(&puppet).unwrap();
+        let expected = [(0, 1), (1, 2), (2, 3), (2, 4), (3, 5), (1, 6)].map(|(d, n)| (d, uuid(n)));
+        let visited: Vec<_> = engine
+            .iter_nodes()
+            .map(|(depth, node)| (depth, node.uuid()))
+            .collect();
+        assert_eq!(visited, expected);
+
+        let mut visited = Vec::new();
+        for (depth, mut node) in engine.iter_nodes_mut() {
+            visited.push((depth, node.uuid()));
+            if depth == 2 {
+                node.set_enabled(false);
+            }
+        }
+        assert_eq!(visited, expected);
+        let rendered: Vec<_> = engine
+            .update(Duration::ZERO)
+            .iter()
+            .map(RenderCommand::node)
+            .collect();
+        assert_eq!(rendered.len(), 3);
+        assert!(!rendered.contains(&uuid(3)) && !rendered.contains(&uuid(5)));
+    }
+
+    #[test]
+    fn physics_state() {
+        let puppet = puppet(
+            r#"{
+                "type": "Node", "uuid": 1,
+                "children": [{ "type": "SimplePhysics", "uuid": 2, "param": 10, "length": 50.0 }]
+            }"#,
+            r#"[
+                {
+                    "uuid": 10, "name": "Hair", "is_vec2": true,
+                    "min": [-1.0, 0.0], "max": [1.0, 2.0],
+                    "axis_points": [[0.0, 1.0], [0.0, 1.0]]
+                },
+                {
+                    "uuid": 11, "name": "Move", "min": [-1.0, 0.0], "max": [1.0, 0.0],
+                    "bindings": [{ "node": 1, "param_name": "transform.t.x", "values": [[-20, 20]] }]
+                }
+            ]"#,
+        );
+        let mut engine = PuppetEngine::new(&puppet).unwrap();
+        assert_eq!(engine.physics_state(uuid(1)), None);
+        assert_eq!(engine.physics_state(uuid(3)), None);
+
+        engine.update(Duration::from_millis(16));
+        let state = engine.physics_state(uuid(2)).unwrap();
+        assert_eq!(state.angle(), 0.0);
+        assert_eq!(state.angular_velocity(), 0.0);
+        assert_eq!(state.length(), 50.0);
+        assert_eq!(state.output(), [0.0, 1.0]);
+
+        // Moving the anchor to the left leaves the bob behind, to the right.
+        engine.set_param("Move", [-1.0, 0.0]);
+        for _ in 0..3 {
+            engine.update(Duration::from_millis(16));
+        }
+        let state = engine.physics_state(uuid(2)).unwrap();
+        assert!(state.angle() > 0.1, "{state:?}");
+        assert_ne!(state.angular_velocity(), 0.0);
+        assert!((state.length() - 50.0).abs() < 1e-3, "{state:?}");
+        assert_eq!(
+            engine.params.get(uuid(10).into()).unwrap().value(),
+            state.output()
+        );
+    }
+
+    #[test]
+    fn fixed_physics_timestep() {
+        let puppet = puppet(
+            r#"{
+                "type": "Node", "uuid": 1,
+                "children": [{ "type": "SimplePhysics", "uuid": 2, "param": 10, "length": 50.0 }]
+            }"#,
+            r#"[
+                {
+                    "uuid": 10, "name": "Hair", "is_vec2": true,
+                    "min": [-1.0, 0.0], "max": [1.0, 2.0],
+                    "axis_points": [[0.0, 1.0], [0.0, 1.0]]
+                },
+                {
+                    "uuid": 11, "name": "Move", "min": [-1.0, 0.0], "max": [1.0, 0.0],
+                    "bindings": [{ "node": 1, "param_name": "transform.t.x", "values": [[-20, 20]] }]
+                }
+            ]"#,
+        );
+        let mut engine = PuppetEngine::new(&puppet).unwrap();
+        engine.set_physics_timestep(Duration::from_millis(10));
+        engine.update(Duration::from_millis(10));
+        engine.set_param("Move", [-1.0, 0.0]);
+
+        let mut total = Duration::from_millis(10);
+        let mut outputs = Vec::new();
+        for ms in [3, 17, 5, 40, 1, 0, 9, 25] {
+            engine.update(Duration::from_millis(ms));
+            total += Duration::from_millis(ms);
+            assert_eq!(
+                engine.physics_time(),
+                Duration::from_millis(total.as_millis() as u64 / 10 * 10)
+            );
+            outputs.push(engine.physics_state(uuid(2)).unwrap().output()[0]);
+        }
+        // The output is interpolated between steps, so it changes even on frames without one.
+        assert_ne!(outputs[3], outputs[4]);
+        assert_eq!(outputs[4], outputs[5]);
+
+        // After a long pause, only a bounded number of steps is simulated.
+        engine.update(Duration::from_secs(30));
+        assert_eq!(
+            engine.physics_time(),
+            Duration::from_millis(110) + Duration::from_millis(10) * MAX_PHYSICS_STEPS
+        );
+        let state = engine.physics_state(uuid(2)).unwrap();
+        assert!(state.angle().is_finite() && state.output()[0].is_finite());
+
+        // Timesteps above 100 ms are clamped, so the physics clock matches the simulated time.
+        let mut clamped = PuppetEngine::new(&puppet).unwrap();
+        clamped.set_physics_timestep(Duration::from_millis(250));
+        assert_eq!(clamped.physics_timestep(), Duration::from_millis(100));
+        let mut reference = PuppetEngine::new(&puppet).unwrap();
+        reference.set_physics_timestep(Duration::from_millis(100));
+        for engine in [&mut clamped, &mut reference] {
+            engine.update(Duration::from_millis(100));
+            engine.set_param("Move", [-1.0, 0.0]);
+            engine.update(Duration::from_millis(250));
+        }
+        assert_eq!(clamped.physics_time(), Duration::from_millis(300));
+        let angle = |engine: &PuppetEngine| engine.physics_state(uuid(2)).unwrap().angle();
+        assert_eq!(angle(&clamped), angle(&reference));
+        assert_ne!(angle(&clamped), 0.0);
+    }
+
+    #[test]
+    fn hot_reload_transition() {
+        fn drawable_at(x: f32) -> rhino2d_io::InochiPuppet {
+            puppet(
+                &format!(
+                    r#"{{
+                        "type": "Drawable", "uuid": 1,
+                        "transform": {{ "trans": [{x}, 0, 0], "rot": [0, 0, 0], "scale": [1, 1] }}
+                    }}"#
+                ),
+                "[]",
+            )
+        }
+        fn x(cmds: &[RenderCommand]) -> f32 {
+            cmds[0].transform().as_column_major_data()[12]
+        }
+
+        let mut config = EngineConfig::new();
+        config.set_reload_transition(Duration::from_secs(1));
+        let mut engine = PuppetEngine::with_config(&drawable_at(0.0), &config).unwrap();
+        assert_eq!(x(engine.update(Duration::ZERO)), 0.0);
+
+        engine.hot_reload(&drawable_at(100.0)).unwrap();
+        let first = x(engine.update(Duration::from_millis(250)));
+        assert!(first > 0.0 && first < 50.0, "{first}");
+        let second = x(engine.update(Duration::from_millis(500)));
+        assert!(second > 50.0 && second < 100.0, "{second}");
+        assert_eq!(x(engine.update(Duration::from_millis(250))), 100.0);
+        assert_eq!(x(engine.update(Duration::from_millis(250))), 100.0);
+
+        // Without a transition, the node jumps immediately.
+        let mut engine = PuppetEngine::new(&drawable_at(0.0)).unwrap();
+        engine.update(Duration::ZERO);
+        engine.hot_reload(&drawable_at(100.0)).unwrap();
+        assert_eq!(x(engine.update(Duration::from_millis(10))), 100.0);
+    }
+
+    #[test]
+    fn hot_reload_keeps_param_values() {
+        let params = |uuids: &[u64]| {
+            let params = uuids
+                .iter()
+                .map(|uuid| format!(r#"{{ "uuid": {uuid}, "name": "{uuid}" }}"#))
+                .collect::<Vec<_>>();
+            format!("[{}]", params.join(","))
+        };
+        let model = |uuids: &[u64]| puppet(r#"{ "type": "Node", "uuid": 1 }"#, &params(uuids));
+
+        let mut engine = PuppetEngine::new(&model(&[10, 11])).unwrap();
+        assert!(engine.set_param(uuid(10), [0.25, 0.0]));
+        assert!(engine.set_param(uuid(11), [0.75, 0.0]));
+        engine.hot_reload(&model(&[10, 12])).unwrap();
+        assert_eq!(engine.param(uuid(10)).unwrap().value(), [0.25, 0.0]);
+        assert_eq!(engine.param(uuid(12)).unwrap().value(), [0.0, 0.0]);
+        assert!(engine.param(uuid(11)).is_none());
+    }
+
+    #[cfg(feature = "parallel")]
+    #[test]
+    fn parallel_update() {
+        // Groups of drawables with clashing Z-Sort values, below nested nodes.
+        let groups = (0..4)
+            .map(|g| {
+                let drawables = (0..100)
+                    .map(|i| {
+                        let uuid = 100 * (g + 1) + i;
+                        format!(
+                            r#"{{ "type": "Part", "uuid": {uuid}, "zsort": {} }}"#,
+                            i % 7
+                        )
+                    })
+                    .collect::<Vec<_>>()
+                    .join(",");
+                let uuid = 10 * (g + 1);
+                format!(
+                    r#"{{ "type": "Node", "uuid": {uuid}, "children": [
+                        {{ "type": "Node", "uuid": {}, "children": [{drawables}] }}
+                    ] }}"#,
+                    uuid + 1,
+                )
+            })
+            .collect::<Vec<_>>()
+            .join(",");
+        let puppet = puppet(
+            &format!(r#"{{ "type": "Node", "uuid": 1, "children": [{groups}] }}"#),
+            r#"[{
+                "uuid": 10000, "name": "Move",
+                "bindings": [
+                    { "node": 11, "param_name": "transform.t.x", "values": [[0, 8]] },
+                    { "node": 205, "param_name": "zSort", "values": [[0, -3]] }
+                ]
+            }]"#,
+        );
+
+        let commands = |threshold| {
+            let mut config = EngineConfig::new();
+            config.set_parallel_threshold(threshold);
+            let mut engine = PuppetEngine::with_config(&puppet, &config).unwrap();
+            engine.param("Move").unwrap().set(0.5).unwrap();
+            let commands = engine
+                .update(Duration::from_millis(16))
+                .iter()
+                .map(|cmd| (cmd.node(), cmd.zsort(), cmd.transform_index()))
+                .collect::<Vec<_>>();
+            assert_eq!(commands.len(), 1 + 4 * 102);
+            (commands, engine.transform_buffer().to_vec())
+        };
+        let pool = rayon::ThreadPoolBuilder::new()
+            .num_threads(4)
+            .build()
+            .unwrap();
+        let (sequential, parallel) = pool.install(|| (commands(usize::MAX), commands(0)));
+        assert_eq!(parallel, sequential);
+    }
 }
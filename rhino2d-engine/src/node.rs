@@ -1,26 +1,57 @@
 //! Node representation for the puppeteering engine.
 
+use std::collections::HashMap;
 use std::ops::Deref;
 use std::ops::DerefMut;
 use std::ops::Mul;
+use std::sync::Arc;
 use std::time::Duration;
 
+use nalgebra::Matrix3;
 use nalgebra::Matrix4;
+use nalgebra::Point3;
+use nalgebra::Rotation3;
 use nalgebra::Vector3;
 use rhino2d_io::node as io_node;
+use rhino2d_io::node::BlendMode;
+use rhino2d_io::node::MaskMode;
+use rhino2d_io::ParamMergeMode;
 use rhino2d_io::Uuid;
+use rhino2d_io::Vec2;
+use rhino2d_io::Vec3;
 
 use crate::param::ParamBinding;
+use crate::param::ParamHandle;
 use crate::param::ParamMap;
 use crate::param::ParamTarget;
+use crate::param::ParamWrites;
+use crate::physics::Pendulum;
+use crate::physics::World;
+use crate::EngineConfig;
 use crate::RenderBuffer;
 use crate::RenderCommand;
 use crate::Result;
+use crate::RotationDirection;
 
 pub enum Node {
     /// Hierarchy-only node that isn't visible.
     Node(NodeBase),
     Drawable(Drawable),
+    /// Textured drawable.
+    Part(Part),
+    /// Drawable that is only rendered into the stencil buffer, to mask parts.
+    ///
+    /// See [`RenderCommand::is_mask`].
+    Mask(Drawable),
+    /// Node whose subtree is drawn into an offscreen buffer, which is then drawn onto the pixels
+    /// below it.
+    ///
+    /// See [`RenderCommand::composite_len`].
+    Composite(Composite),
+    /// Invisible node simulating a physical system, driving a parameter.
+    SimplePhysics(SimplePhysics),
+    /// Invisible node with joints that deform the meshes of other nodes.
+    PathDeform(PathDeform),
 }
 
 impl Deref for Node {
@@ -30,6 +61,11 @@ impl Deref for Node {
         match self {
             Node::Node(node) => node,
             Node::Drawable(node) => node,
+            Node::Part(node) => node,
+            Node::Mask(node) => node,
+            Node::Composite(node) => node,
+            Node::SimplePhysics(node) => node,
+            Node::PathDeform(node) => node,
         }
     }
 }
@@ -39,32 +75,411 @@ impl DerefMut for Node {
         match self {
             Node::Node(node) => node,
             Node::Drawable(node) => node,
+            Node::Part(node) => node,
+            Node::Mask(node) => node,
+            Node::Composite(node) => node,
+            Node::SimplePhysics(node) => node,
+            Node::PathDeform(node) => node,
         }
     }
 }
 
 impl Node {
-    pub(crate) fn from_io(params: &mut ParamMap, io: &io_node::Node) -> Result<Self> {
+    pub(crate) fn from_io(
+        params: &mut ParamMap,
+        config: &EngineConfig,
+        io: &io_node::Node,
+    ) -> Result<Self> {
         match io {
-            io_node::Node::Node(node) => Ok(Self::Node(NodeBase::from_io(params, node)?)),
-            io_node::Node::Drawable(node) => Ok(Self::Drawable(Drawable::from_io(params, node)?)),
-            io_node::Node::Part(node) => Ok(Self::Drawable(Drawable::from_io(params, node)?)),
-            _ => Err(crate::Error::unsupported(format!(
-                "node '{}' has unimplemented node type '{:?}'",
-                io.name(),
-                io
-            ))),
+            io_node::Node::Node(node) => Ok(Self::Node(NodeBase::from_io_non_drawable(
+                params, config, node,
+            )?)),
+            io_node::Node::Drawable(node) => {
+                let drawable = Drawable::from_io(params, config, node)?;
+                check_part_bindings(&drawable, node.name())?;
+                Ok(Self::Drawable(drawable))
+            }
+            io_node::Node::Part(node) => Ok(Self::Part(Part::from_io(params, config, node)?)),
+            io_node::Node::Mask(node) => {
+                let drawable = Drawable::from_io(params, config, node)?;
+                check_part_bindings(&drawable, node.name())?;
+                Ok(Self::Mask(drawable))
+            }
+            io_node::Node::SimplePhysics(node) => Ok(Self::SimplePhysics(SimplePhysics::from_io(
+                params, config, node,
+            )?)),
+            io_node::Node::PathDeform(node) => {
+                Ok(Self::PathDeform(PathDeform::from_io(params, config, node)?))
+            }
+            io_node::Node::Composite(node) => {
+                Ok(Self::Composite(Composite::from_io(params, config, node)?))
+            }
+        }
+    }
+
+    /// Advances the simulation of every physics node in the tree by one step of length `delta`.
+    ///
+    /// The output of the simulation is written by [`Node::write_physics`].
+    ///
+    /// `direction` is the [rotation direction][crate::EngineConfig::set_rotation_direction] of the
+    /// engine, which all methods evaluating node transforms take.
+    pub(crate) fn step_physics(
+        &mut self,
+        delta: Duration,
+        world: &World,
+        direction: RotationDirection,
+    ) {
+        self.step_physics_recursive(delta, world, direction, &Transform::identity());
+    }
+
+    fn step_physics_recursive(
+        &mut self,
+        delta: Duration,
+        world: &World,
+        direction: RotationDirection,
+        parent_transform: &Transform,
+    ) {
+        if !self.contains_physics {
+            return;
+        }
+
+        // Physics nodes are anchored at their position in the current frame, which requires
+        // evaluating the transforms of their ancestors.
+        let (_, transform) = self.evaluate(parent_transform, direction);
+        if let Node::SimplePhysics(node) = self {
+            node.step(delta, world, &transform);
+        }
+        for child in &mut self.children {
+            child.step_physics_recursive(delta, world, direction, &transform);
+        }
+    }
+
+    /// Adds the output of every physics node to `writes`, interpolated between the last two
+    /// simulation steps.
+    ///
+    /// `alpha` is the position between the steps, from 0.0 (the second-to-last step) to 1.0 (the
+    /// last step). Physics nodes drive parameters that may affect any other node, so this has to
+    /// be called before [`Node::update`].
+    pub(crate) fn write_physics(&mut self, alpha: f32, writes: &mut ParamWrites) {
+        if !self.contains_physics {
+            return;
         }
+        if let Node::SimplePhysics(node) = self {
+            node.write_output(alpha, writes);
+        }
+        for child in &mut self.children {
+            child.write_physics(alpha, writes);
+        }
+    }
+
+    /// Splits the node into a handle to its own state and its children.
+    fn split_mut(&mut self) -> (NodeMut<'_>, &mut [Node]) {
+        let base = match self {
+            Node::Node(node) => node,
+            Node::Drawable(node) | Node::Mask(node) => &mut node.node,
+            Node::Part(node) => &mut node.drawable.node,
+            Node::Composite(node) => &mut node.node,
+            Node::SimplePhysics(node) => &mut node.node,
+            Node::PathDeform(node) => &mut node.node,
+        };
+        let NodeBase {
+            uuid,
+            children,
+            zsort,
+            global_transform,
+            enabled,
+            ..
+        } = base;
+        let node = NodeMut {
+            uuid: *uuid,
+            zsort: *zsort,
+            global_transform: *global_transform,
+            enabled,
+        };
+        (node, children)
+    }
+
+    /// Maps the UUID of every node in the tree to its path, for [`Node::at_path`].
+    ///
+    /// A path is the list of child indices leading from `self` to the node. If several nodes
+    /// share a UUID, the first one in depth-first order is kept.
+    pub(crate) fn paths(&self) -> HashMap<Uuid, Box<[usize]>> {
+        let mut paths = HashMap::new();
+        self.paths_recursive(&mut Vec::new(), &mut paths);
+        paths
+    }
+
+    fn paths_recursive(&self, path: &mut Vec<usize>, out: &mut HashMap<Uuid, Box<[usize]>>) {
+        out.entry(self.uuid).or_insert_with(|| path[..].into());
+        for (i, child) in self.children.iter().enumerate() {
+            path.push(i);
+            child.paths_recursive(path, out);
+            path.pop();
+        }
+    }
+
+    /// Returns the node at `path` below `self`, as computed by [`Node::paths`].
+    pub(crate) fn at_path(&self, path: &[usize]) -> Option<&Node> {
+        path.iter()
+            .try_fold(self, |node, &index| node.children.get(index))
     }
 
-    pub(crate) fn update(&mut self, delta: Duration, rbuf: &mut RenderBuffer) {
-        let root_transform = Transform::identity();
-        self.update_recursive(delta, rbuf, &root_transform);
+    /// Returns the node at `path` below `self`, for modification.
+    pub(crate) fn at_path_mut(&mut self, path: &[usize]) -> Option<&mut Node> {
+        path.iter()
+            .try_fold(self, |node, &index| node.children.get_mut(index))
+    }
+
+    /// Returns every physics node in the tree to its initial state.
+    pub(crate) fn reset_physics(&mut self) {
+        if !self.contains_physics {
+            return;
+        }
+        if let Node::SimplePhysics(node) = self {
+            node.pendulum.reset();
+            node.output = node.rest_output();
+            node.prev_output = node.output;
+            node.written_output = node.output;
+        }
+        for child in &mut self.children {
+            child.reset_physics();
+        }
+    }
+
+    /// Computes the position every node's origin is transformed to, and stores it in `out`.
+    ///
+    /// Unlike [`Node::update`], this does not modify any state.
+    pub(crate) fn origins(&self, direction: RotationDirection, out: &mut HashMap<Uuid, Vec2>) {
+        self.origins_recursive(direction, &Transform::identity(), out);
+    }
+
+    /// Computes the rectangle spanned by the meshes of all drawables in the tree, without mesh
+    /// deformations applied.
+    ///
+    /// Returns `None` if there are no drawables with vertices. Like [`Node::origins`], this does
+    /// not modify any state.
+    pub(crate) fn bounds(&self, direction: RotationDirection) -> Option<[Vec2; 2]> {
+        let mut bounds = None;
+        self.bounds_recursive(direction, &Transform::identity(), &mut bounds);
+        bounds
+    }
+
+    /// Hides every part that isn't soloed if any part in the tree is soloed.
+    ///
+    /// Hidden parts are drawn fully transparent, so the render commands stay the same. Only called
+    /// if [`EngineConfig::set_apply_solo`] is enabled.
+    pub(crate) fn apply_solo(&mut self) {
+        fn any_solo(node: &Node) -> bool {
+            matches!(node, Node::Part(part) if part.solo) || node.children.iter().any(any_solo)
+        }
+        fn hide(node: &mut Node) {
+            if let Node::Part(part) = node {
+                part.hidden = !part.solo;
+            }
+            for child in &mut node.children {
+                hide(child);
+            }
+        }
+
+        if any_solo(self) {
+            hide(self);
+        }
+    }
+
+    /// Returns the drawable part of this node, if it is drawable.
+    fn drawable(&self) -> Option<&Drawable> {
+        match self {
+            Node::Drawable(drawable) => Some(drawable),
+            Node::Part(part) => Some(part),
+            Node::Mask(mask) => Some(mask),
+            Node::Node(_) | Node::Composite(_) | Node::SimplePhysics(_) | Node::PathDeform(_) => {
+                None
+            }
+        }
+    }
+
+    fn bounds_recursive(
+        &self,
+        direction: RotationDirection,
+        parent_transform: &Transform,
+        bounds: &mut Option<[Vec2; 2]>,
+    ) {
+        let (_, transform) = self.evaluate(parent_transform, direction);
+        if let Some(drawable) = self.drawable() {
+            if let Some([min, max]) = drawable.mesh_bounds {
+                for corner in [min, [max[0], min[1]], [min[0], max[1]], max] {
+                    let [x, y] = transform.transform_point(corner);
+                    let [min, max] = bounds.get_or_insert([[x, y]; 2]);
+                    *min = [min[0].min(x), min[1].min(y)];
+                    *max = [max[0].max(x), max[1].max(y)];
+                }
+            }
+        }
+        for child in &self.children {
+            child.bounds_recursive(direction, &transform, bounds);
+        }
+    }
+
+    fn origins_recursive(
+        &self,
+        direction: RotationDirection,
+        parent_transform: &Transform,
+        out: &mut HashMap<Uuid, Vec2>,
+    ) {
+        let (_, transform) = self.evaluate(parent_transform, direction);
+        out.insert(self.uuid, transform.origin());
+        for child in &self.children {
+            child.origins_recursive(direction, &transform, out);
+        }
+    }
+
+    /// Updates the tree and pushes the render command of every node to `rbuf`.
+    pub(crate) fn update(
+        &mut self,
+        delta: Duration,
+        direction: RotationDirection,
+        rbuf: &mut RenderBuffer,
+    ) {
+        self.update_recursive(delta, direction, rbuf, &Transform::identity());
+    }
+
+    /// Updates `self`'s transform/zsort and all child nodes, recursively.
+    ///
+    /// Disabled nodes and their subtrees are skipped and push no render commands.
+    // `delta` is not needed by any node type yet, but time-dependent nodes will need it.
+    #[allow(clippy::only_used_in_recursion)]
+    fn update_recursive(
+        &mut self,
+        delta: Duration,
+        direction: RotationDirection,
+        rbuf: &mut RenderBuffer,
+        parent_transform: &Transform,
+    ) {
+        let index = rbuf.commands.len();
+        let Some(global_transform) = self.update_own(rbuf, direction, parent_transform) else {
+            return;
+        };
+
+        let node: &mut NodeBase = self;
+        for child in &mut node.children {
+            child.update_recursive(delta, direction, rbuf, &global_transform);
+        }
+        self.close_composite(rbuf, index);
+    }
+
+    /// Like [`Node::update`], but updates large subtrees on multiple threads.
+    ///
+    /// The render commands are pushed in the same order as by [`Node::update`].
+    #[cfg(feature = "parallel")]
+    pub(crate) fn update_parallel(
+        &mut self,
+        delta: Duration,
+        direction: RotationDirection,
+        rbuf: &mut RenderBuffer,
+    ) {
+        self.update_parallel_recursive(delta, direction, rbuf, &Transform::identity());
+    }
+
+    /// Updates `self` and its subtree, splitting the children of nodes with at least
+    /// [`PARALLEL_SPLIT_LEN`] nodes below them across threads.
+    ///
+    /// Every run of children updated on the same thread pushes its commands into a buffer of its
+    /// own, and the buffers are appended to `rbuf` in the order of the children.
+    #[cfg(feature = "parallel")]
+    fn update_parallel_recursive(
+        &mut self,
+        delta: Duration,
+        direction: RotationDirection,
+        rbuf: &mut RenderBuffer,
+        parent_transform: &Transform,
+    ) {
+        use rayon::prelude::*;
+
+        let index = rbuf.commands.len();
+        let Some(global_transform) = self.update_own(rbuf, direction, parent_transform) else {
+            return;
+        };
+
+        let node: &mut NodeBase = self;
+        if node.subtree_len <= PARALLEL_SPLIT_LEN {
+            for child in &mut node.children {
+                child.update_recursive(delta, direction, rbuf, &global_transform);
+            }
+        } else {
+            let buffers: Vec<RenderBuffer> = node
+                .children
+                .par_iter_mut()
+                .fold(RenderBuffer::new, |mut buf, child| {
+                    child.update_parallel_recursive(delta, direction, &mut buf, &global_transform);
+                    buf
+                })
+                .collect();
+            for buf in buffers {
+                rbuf.append(buf.commands);
+            }
+        }
+        self.close_composite(rbuf, index);
+    }
+
+    /// If `self` is a composite node, records the number of commands its subtree pushed after its
+    /// own command at `index`.
+    fn close_composite(&self, rbuf: &mut RenderBuffer, index: usize) {
+        if let Node::Composite(_) = self {
+            let len = rbuf.commands.len() - index - 1;
+            rbuf.commands[index].composite_len = Some(len as u32);
+        }
+    }
+
+    /// Pushes the render command of `self` and returns its global transform, or returns `None`
+    /// without doing anything if `self` is disabled.
+    fn update_own(
+        &mut self,
+        rbuf: &mut RenderBuffer,
+        direction: RotationDirection,
+        parent_transform: &Transform,
+    ) -> Option<Transform> {
+        if !self.enabled {
+            return None;
+        }
+
+        let (deform, appearance) = match self {
+            Node::Node(_) | Node::SimplePhysics(_) | Node::PathDeform(_) => {
+                (None, Appearance::default())
+            }
+            Node::Composite(node) => (None, node.appearance()),
+            Node::Drawable(node) => (
+                node.deform(),
+                Appearance {
+                    mesh: Some(node.mesh.clone()),
+                    ..Appearance::default()
+                },
+            ),
+            Node::Part(node) => (node.deform(), node.appearance()),
+            Node::Mask(node) => (
+                node.deform(),
+                Appearance {
+                    mesh: Some(node.mesh.clone()),
+                    is_mask: true,
+                    ..Appearance::default()
+                },
+            ),
+        };
+
+        let node: &mut NodeBase = self;
+        Some(node.update_self(rbuf, direction, parent_transform, deform, appearance))
     }
 }
 
+/// Number of nodes below a node from which [`Node::update_parallel`] updates its children on
+/// separate threads.
+///
+/// Smaller subtrees are updated faster on a single thread than the threads can be coordinated.
+#[cfg(feature = "parallel")]
+const PARALLEL_SPLIT_LEN: usize = 64;
+
 pub struct NodeBase {
     uuid: Uuid,
+    name: String,
     children: Vec<Node>,
     /// List of parameter bindings that affect this node.
     params: Vec<ParamBinding>,
@@ -72,91 +487,240 @@ pub struct NodeBase {
     /// Transform of this node, as specified by the model.
     ///
     /// Relative to the parent node, without any parameter offsets applied.
-    base_transform: Transform,
+    base_transform: io_node::Transform,
     /// Z-Sort order from the model.
     base_zsort: f32,
 
-    global_transform: Transform,
     zsort: f32,
+    /// Global transform computed by the last update that rendered the node.
+    global_transform: Transform,
+    /// Whether the node and its subtree are rendered.
+    enabled: bool,
     /// Ignores the parent node's transform.
     lock_to_root: bool,
+    /// Whether this node or any node below it is a physics node.
+    contains_physics: bool,
+    /// Number of nodes below this node.
+    #[cfg(feature = "parallel")]
+    subtree_len: usize,
 }
 
 impl NodeBase {
-    fn from_io(params: &mut ParamMap, io: &io_node::NodeBase) -> Result<Self> {
+    fn from_io(
+        params: &mut ParamMap,
+        config: &EngineConfig,
+        io: &io_node::NodeBase,
+    ) -> Result<Self> {
+        let children: Vec<Node> = io
+            .children()
+            .iter()
+            .map(|ch| Node::from_io(params, config, ch))
+            .collect::<Result<_>>()?;
         Ok(Self {
             uuid: io.uuid(),
-            children: io
-                .children()
-                .iter()
-                .map(|ch| Node::from_io(params, ch))
-                .collect::<Result<_>>()?,
+            name: io.name().to_string(),
+            contains_physics: children.iter().any(|ch| ch.contains_physics),
+            #[cfg(feature = "parallel")]
+            subtree_len: children.iter().map(|ch| 1 + ch.subtree_len).sum(),
+            children,
             params: params.take_params_affecting_node(io.uuid()),
-            base_transform: Transform::from_io(io.transform()),
+            base_transform: io.transform().clone(),
             base_zsort: io.zsort(),
+            zsort: io.zsort() + io.transform().translation()[2],
             global_transform: Transform::identity(),
-            zsort: io.zsort(),
+            enabled: io.enabled(),
             lock_to_root: io.lock_to_root(),
         })
     }
 
-    /// Updates `self`'s `global_transform` and `zsort` values based on `parent_transform` and
+    pub fn uuid(&self) -> Uuid {
+        self.uuid
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns the nodes directly below this node.
+    pub fn children(&self) -> &[Node] {
+        &self.children
+    }
+
+    /// Returns the Z-Sort value of the node, including parameter offsets, as computed by the last
+    /// update that rendered the node.
+    ///
+    /// The node's Z translation is added to its Z-Sort value, so that translating a node along the
+    /// Z axis moves it in front of or behind other nodes. Only the node's own translation counts,
+    /// not that of its parents, since Z-Sort values are not inherited either.
+    ///
+    /// Before the first update, this is the Z-Sort value and Z translation specified by the model.
+    pub fn zsort(&self) -> f32 {
+        self.zsort
+    }
+
+    /// Returns the global transform of the node, as computed by the last update that rendered
+    /// the node.
+    ///
+    /// This is the identity transform before the first update. Unlike
+    /// [`RenderCommand::transform`], it does not include the easing of a
+    /// [reload transition][crate::EngineConfig::set_reload_transition].
+    pub fn global_transform(&self) -> Transform {
+        self.global_transform
+    }
+
+    /// Returns whether the node is rendered.
+    ///
+    /// Disabled nodes, including all nodes below them, don't produce render commands. Their
+    /// physics simulation keeps running, since it may drive parameters of other nodes.
+    pub fn enabled(&self) -> bool {
+        self.enabled
+    }
+
+    pub fn set_enabled(&mut self, enabled: bool) {
+        self.enabled = enabled;
+    }
+
+    /// Like [`NodeBase::from_io`], but rejects mesh deformation bindings.
+    fn from_io_non_drawable(
+        params: &mut ParamMap,
+        config: &EngineConfig,
+        io: &io_node::NodeBase,
+    ) -> Result<Self> {
+        let node = Self::from_io(params, config, io)?;
+        if node
+            .params
+            .iter()
+            .any(|p| p.target() == ParamTarget::Deform)
+        {
+            return Err(crate::Error::invalid(format!(
+                "node '{}' has a mesh deformation binding, but is not drawable",
+                io.name()
+            )));
+        }
+        check_part_bindings(&node, io.name())?;
+        Ok(node)
+    }
+
+    /// Computes the Z-Sort value and global transform of `self` from `parent_transform` and the
     /// parameters affecting `self`.
-    fn update_self(&mut self, rbuf: &mut RenderBuffer, parent_transform: &Transform) {
+    fn evaluate(
+        &self,
+        parent_transform: &Transform,
+        direction: RotationDirection,
+    ) -> (f32, Transform) {
         // Parameters need to be applied to the base transform first (eg. rotation applies to the
         // node's origin, not the whole model's origin).
         let mut zsort = self.base_zsort;
         let mut param_tf = rhino2d_io::node::Transform::new();
 
-        for param in &self.params {
+        // Bindings are combined in order, so a forced value overrides earlier bindings, but is
+        // affected by later ones.
+        for param in self.params.iter().filter(|param| !param.is_muted()) {
             let value = param.value();
-            match param.target() {
-                ParamTarget::ZSort => zsort += value,
-                ParamTarget::TranslationX => param_tf.translation_mut()[0] += value,
-                ParamTarget::TranslationY => param_tf.translation_mut()[1] += value,
-                ParamTarget::TranslationZ => param_tf.translation_mut()[2] += value,
-                ParamTarget::RotationX => param_tf.rotation_mut()[0] += value,
-                ParamTarget::RotationY => param_tf.rotation_mut()[1] += value,
-                ParamTarget::RotationZ => param_tf.rotation_mut()[2] += value,
-                ParamTarget::ScaleX => param_tf.scale_mut()[0] += value,
-                ParamTarget::ScaleY => param_tf.scale_mut()[1] += value,
+            // Translation and rotation offsets get added to the base transform below, so `base` is
+            // needed to multiply the resulting value instead of the offset.
+            let base = &self.base_transform;
+            let (property, base) = match param.target() {
+                ParamTarget::ZSort => (&mut zsort, 0.0),
+                ParamTarget::TranslationX => {
+                    (&mut param_tf.translation_mut()[0], base.translation()[0])
+                }
+                ParamTarget::TranslationY => {
+                    (&mut param_tf.translation_mut()[1], base.translation()[1])
+                }
+                ParamTarget::TranslationZ => {
+                    (&mut param_tf.translation_mut()[2], base.translation()[2])
+                }
+                ParamTarget::RotationX => (&mut param_tf.rotation_mut()[0], base.rotation()[0]),
+                ParamTarget::RotationY => (&mut param_tf.rotation_mut()[1], base.rotation()[1]),
+                ParamTarget::RotationZ => (&mut param_tf.rotation_mut()[2], base.rotation()[2]),
+                ParamTarget::ScaleX => (&mut param_tf.scale_mut()[0], 0.0),
+                ParamTarget::ScaleY => (&mut param_tf.scale_mut()[1], 0.0),
+                // Evaluated by `Drawable::deform`.
+                ParamTarget::Deform => continue,
+                // Evaluated by `Part::appearance`.
+                ParamTarget::Opacity
+                | ParamTarget::TintR
+                | ParamTarget::TintG
+                | ParamTarget::TintB => continue,
+            };
+            match param.merge_mode() {
+                ParamMergeMode::Additive | ParamMergeMode::Passthrough => *property += value,
+                ParamMergeMode::Multiplicative => *property = (*property + base) * value - base,
+                ParamMergeMode::Forced => *property = value,
             }
         }
 
-        let self_transform = self.base_transform * Transform::from_io(&param_tf);
+        // Like in Inochi2D, the offsets are applied to the components of the base transform, so
+        // that translation offsets are relative to the parent node rather than rotated and scaled
+        // by the node's own transform.
+        let base = &self.base_transform;
+        for i in 0..3 {
+            param_tf.translation_mut()[i] += base.translation()[i];
+            param_tf.rotation_mut()[i] += base.rotation()[i];
+        }
+        for i in 0..2 {
+            param_tf.scale_mut()[i] *= base.scale()[i];
+        }
 
-        self.zsort = zsort;
+        // Moving a node along the Z axis moves it in front of or behind other nodes, like changing
+        // its Z-Sort value. The translation is kept in the transform as well.
+        let zsort = zsort + param_tf.translation()[2];
+
+        let self_transform = Transform::from_io(&param_tf, direction);
         if self.lock_to_root {
-            self.global_transform = self_transform;
+            (zsort, self_transform)
         } else {
-            self.global_transform = self_transform * *parent_transform;
+            (zsort, *parent_transform * self_transform)
         }
-
-        rbuf.push(RenderCommand {
-            node: self.uuid,
-            transform: self.global_transform,
-            zsort,
-            deform: None,
-        });
     }
 
-    /// Updates `self`'s transform/zsort and all child nodes, recursively.
-    fn update_recursive(
+    /// Updates `self`'s `zsort` value based on `parent_transform` and parameters affecting `self`,
+    /// and pushes its render command.
+    ///
+    /// Returns the global transform of `self`.
+    fn update_self(
         &mut self,
-        delta: Duration,
         rbuf: &mut RenderBuffer,
+        direction: RotationDirection,
         parent_transform: &Transform,
-    ) {
-        self.update_self(rbuf, parent_transform);
+        deform: Option<Vec<Vec2>>,
+        appearance: Appearance,
+    ) -> Transform {
+        let (zsort, global_transform) = self.evaluate(parent_transform, direction);
+        self.zsort = zsort;
+        self.global_transform = global_transform;
 
-        for child in &mut self.children {
-            child.update_recursive(delta, rbuf, &self.global_transform);
-        }
+        rbuf.push(RenderCommand {
+            node: self.uuid,
+            transform: global_transform,
+            transform_index: 0,
+            zsort,
+            deform,
+            tint: appearance.tint,
+            opacity: appearance.opacity,
+            blend_mode: appearance.blend_mode,
+            mask_mode: appearance.mask_mode,
+            masked_by: appearance.masked_by,
+            is_mask: appearance.is_mask,
+            textures: appearance.textures,
+            mask_threshold: appearance.mask_threshold,
+            composite_len: None,
+            mesh: appearance.mesh,
+        });
+        global_transform
     }
 }
 
 pub struct Drawable {
     node: NodeBase,
+    vertex_count: usize,
+    /// The undeformed mesh, shared with the render commands of the node.
+    mesh: Arc<Mesh>,
+    /// Minimum and maximum corner of the undeformed mesh, or `None` if it has no vertices.
+    mesh_bounds: Option<[Vec2; 2]>,
+    /// Mesh deformation bindings, split off from the node's other parameter bindings.
+    deforms: Vec<ParamBinding>,
 }
 
 impl Deref for Drawable {
@@ -174,11 +738,809 @@ impl DerefMut for Drawable {
 }
 
 impl Drawable {
-    fn from_io(params: &mut ParamMap, io: &io_node::Drawable) -> Result<Self> {
+    fn from_io(
+        params: &mut ParamMap,
+        config: &EngineConfig,
+        io: &io_node::Drawable,
+    ) -> Result<Self> {
+        let mut node = NodeBase::from_io(params, config, io)?;
+        let vertex_count = io.mesh_data().vertex_count();
+        let mesh_bounds = (vertex_count > 0).then(|| io.mesh_data().bounds());
+        let (deforms, params): (Vec<_>, Vec<_>) = node
+            .params
+            .drain(..)
+            .partition(|p| p.target() == ParamTarget::Deform);
+        node.params = params;
+
+        if !deforms.iter().all(|p| p.matches_vertex_count(vertex_count)) {
+            return Err(crate::Error::invalid(format!(
+                "mesh deformation of node '{}' does not match its vertex count of {}",
+                io.name(),
+                vertex_count,
+            )));
+        }
+
+        if let Err(errors) = io.check_mesh() {
+            let messages: Vec<_> = errors.iter().map(|error| error.message()).collect();
+            return Err(crate::Error::invalid(messages.join("; ")));
+        }
+
+        let mesh = io.mesh_data();
+
+        Ok(Self {
+            node,
+            vertex_count,
+            mesh: Arc::new(Mesh {
+                vertices: mesh.verts().collect(),
+                uvs: mesh.uvs().map(Iterator::collect).unwrap_or_default(),
+                indices: mesh.indices().to_vec(),
+            }),
+            mesh_bounds,
+            deforms,
+        })
+    }
+
+    /// Computes the current vertex offsets of the mesh, if it has any deformation bindings.
+    fn deform(&self) -> Option<Vec<Vec2>> {
+        deform_offsets(&self.deforms, self.vertex_count)
+    }
+}
+
+/// Sums the offsets of the unmuted mesh deformation bindings `deforms`, which have `count`
+/// offsets each.
+///
+/// Returns `None` if there are no bindings.
+fn deform_offsets(deforms: &[ParamBinding], count: usize) -> Option<Vec<Vec2>> {
+    if deforms.is_empty() {
+        return None;
+    }
+
+    let mut offsets = vec![[0.0; 2]; count];
+    for binding in deforms.iter().filter(|binding| !binding.is_muted()) {
+        binding.deform(&mut offsets);
+    }
+    Some(offsets)
+}
+
+/// Iterator over a node tree in depth-first order, returned by
+/// [`PuppetEngine::iter_nodes`][crate::PuppetEngine::iter_nodes].
+///
+/// Yields every node along with its depth, which is 0 for the root node.
+pub struct NodeIter<'a> {
+    /// Nodes left to visit, the next one on top.
+    stack: Vec<(usize, &'a Node)>,
+}
+
+impl<'a> NodeIter<'a> {
+    pub(crate) fn new(root: &'a Node) -> Self {
+        Self {
+            stack: vec![(0, root)],
+        }
+    }
+}
+
+impl<'a> Iterator for NodeIter<'a> {
+    type Item = (usize, &'a Node);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (depth, node) = self.stack.pop()?;
+        let children = node.children.iter().rev();
+        self.stack.extend(children.map(|child| (depth + 1, child)));
+        Some((depth, node))
+    }
+}
+
+/// Iterator over a node tree in depth-first order, returned by
+/// [`PuppetEngine::iter_nodes_mut`][crate::PuppetEngine::iter_nodes_mut].
+///
+/// Like [`NodeIter`], but yields a [`NodeMut`] for every node.
+pub struct NodeIterMut<'a> {
+    stack: Vec<(usize, &'a mut Node)>,
+}
+
+impl<'a> NodeIterMut<'a> {
+    pub(crate) fn new(root: &'a mut Node) -> Self {
+        Self {
+            stack: vec![(0, root)],
+        }
+    }
+}
+
+impl<'a> Iterator for NodeIterMut<'a> {
+    type Item = (usize, NodeMut<'a>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (depth, node) = self.stack.pop()?;
+        let (node, children) = node.split_mut();
+        let children = children.iter_mut().rev();
+        self.stack.extend(children.map(|child| (depth + 1, child)));
+        Some((depth, node))
+    }
+}
+
+/// Modifies a node yielded by [`NodeIterMut`].
+///
+/// Unlike `&mut Node`, this does not give access to the node's children, which are yielded by
+/// the iterator separately.
+pub struct NodeMut<'a> {
+    uuid: Uuid,
+    zsort: f32,
+    global_transform: Transform,
+    enabled: &'a mut bool,
+}
+
+impl NodeMut<'_> {
+    pub fn uuid(&self) -> Uuid {
+        self.uuid
+    }
+
+    /// See [`NodeBase::zsort`].
+    pub fn zsort(&self) -> f32 {
+        self.zsort
+    }
+
+    /// See [`NodeBase::global_transform`].
+    pub fn global_transform(&self) -> Transform {
+        self.global_transform
+    }
+
+    /// See [`NodeBase::enabled`].
+    pub fn enabled(&self) -> bool {
+        *self.enabled
+    }
+
+    pub fn set_enabled(&mut self, enabled: bool) {
+        *self.enabled = enabled;
+    }
+}
+
+/// The geometry of a drawable node, as specified by the model.
+#[derive(Debug)]
+pub(crate) struct Mesh {
+    vertices: Vec<Vec2>,
+    uvs: Vec<Vec2>,
+    indices: Vec<u16>,
+}
+
+/// The mesh of a rendered node, returned by [`RenderCommand::mesh`].
+///
+/// The undeformed mesh is shared between the node and all of its render commands, and the view
+/// borrows it along with the command's [deformation][RenderCommand::deform]. The deformed vertex
+/// positions are computed when they are iterated.
+#[derive(Debug, Clone, Copy)]
+pub struct MeshView<'a> {
+    pub(crate) mesh: &'a Mesh,
+    pub(crate) deform: Option<&'a [Vec2]>,
+}
+
+impl<'a> MeshView<'a> {
+    /// Returns the positions of the mesh vertices, with mesh deformation applied.
+    ///
+    /// This is [`MeshView::base_vertices`] offset by [`RenderCommand::deform`]. Like the
+    /// vertices in the model, these are relative to the node, and the node's
+    /// [transform][RenderCommand::transform] still has to be applied.
+    pub fn vertices(&self) -> impl ExactSizeIterator<Item = Vec2> + 'a {
+        let vertices = &self.mesh.vertices;
+        let deform = self.deform.unwrap_or_default();
+        (0..vertices.len()).map(move |i| {
+            let [x, y] = vertices[i];
+            let [dx, dy] = deform.get(i).copied().unwrap_or_default();
+            [x + dx, y + dy]
+        })
+    }
+
+    /// Returns the positions of the mesh vertices, as specified by the model.
+    pub fn base_vertices(&self) -> &'a [Vec2] {
+        &self.mesh.vertices
+    }
+
+    /// Returns the texture coordinates of every vertex, or an empty slice if the mesh has none.
+    pub fn uvs(&self) -> &'a [Vec2] {
+        &self.mesh.uvs
+    }
+
+    /// Returns the vertex indices of the mesh's triangles.
+    pub fn indices(&self) -> &'a [u16] {
+        &self.mesh.indices
+    }
+}
+
+/// How a node is drawn, passed to [`NodeBase::update_self`].
+pub(crate) struct Appearance {
+    /// The node's mesh, or `None` if it isn't drawable.
+    mesh: Option<Arc<Mesh>>,
+    tint: Vec3,
+    opacity: f32,
+    blend_mode: BlendMode,
+    mask_mode: Option<MaskMode>,
+    masked_by: Option<Arc<[Uuid]>>,
+    mask_threshold: f32,
+    is_mask: bool,
+    textures: Option<Arc<[u32]>>,
+}
+
+impl Default for Appearance {
+    /// The appearance of nodes that aren't parts.
+    fn default() -> Self {
+        Self {
+            mesh: None,
+            tint: [1.0; 3],
+            opacity: 1.0,
+            blend_mode: BlendMode::Normal,
+            mask_mode: None,
+            masked_by: None,
+            mask_threshold: DEFAULT_MASK_THRESHOLD,
+            is_mask: false,
+            textures: None,
+        }
+    }
+}
+
+/// Mask threshold of nodes that don't specify one, like in Inochi2D.
+pub(crate) const DEFAULT_MASK_THRESHOLD: f32 = 0.5;
+
+/// Rejects opacity and tint bindings on nodes that aren't parts.
+fn check_part_bindings(node: &NodeBase, name: &str) -> Result<()> {
+    if node.params.iter().any(|p| p.target().is_part_property()) {
+        return Err(crate::Error::invalid(format!(
+            "node '{}' has an opacity or tint binding, but is not a part",
+            name
+        )));
+    }
+    Ok(())
+}
+
+pub struct Part {
+    drawable: Drawable,
+    /// Opacity and tint bindings, split off from the node's other parameter bindings.
+    color_params: Vec<ParamBinding>,
+    /// Color multiplied with the texture, as specified by the model.
+    tint: Vec3,
+    /// Opacity, as specified by the model.
+    opacity: f32,
+    blend_mode: BlendMode,
+    mask_mode: Option<MaskMode>,
+    masked_by: Arc<[Uuid]>,
+    mask_threshold: f32,
+    textures: Arc<[u32]>,
+    /// Whether the part is soloed in the editor.
+    solo: bool,
+    /// Hides the part because other parts are soloed.
+    hidden: bool,
+}
+
+impl Deref for Part {
+    type Target = Drawable;
+
+    fn deref(&self) -> &Self::Target {
+        &self.drawable
+    }
+}
+
+impl DerefMut for Part {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.drawable
+    }
+}
+
+impl Part {
+    fn from_io(params: &mut ParamMap, config: &EngineConfig, io: &io_node::Part) -> Result<Self> {
+        let mut drawable = Drawable::from_io(params, config, io)?;
+        let (color_params, params): (Vec<_>, Vec<_>) = drawable
+            .params
+            .drain(..)
+            .partition(|p| p.target().is_part_property());
+        drawable.params = params;
+
         Ok(Self {
-            node: NodeBase::from_io(params, io)?,
+            drawable,
+            color_params,
+            tint: io.tint(),
+            opacity: io.opacity(),
+            blend_mode: io.blend_mode(),
+            mask_mode: io.mask_mode(),
+            masked_by: io.masked_by().into(),
+            mask_threshold: io.mask_threshold(),
+            textures: io.textures().into(),
+            solo: io.solo(),
+            hidden: false,
         })
     }
+
+    /// Computes how the part is drawn with the current parameter values.
+    ///
+    /// Like in Inochi2D, opacity and tint bindings scale the part's opacity and tint, unless they
+    /// use [`ParamMergeMode::Forced`].
+    fn appearance(&self) -> Appearance {
+        let mut tint = self.tint;
+        let mut opacity = self.opacity;
+        for param in self.color_params.iter().filter(|param| !param.is_muted()) {
+            let property = match param.target() {
+                ParamTarget::Opacity => &mut opacity,
+                ParamTarget::TintR => &mut tint[0],
+                ParamTarget::TintG => &mut tint[1],
+                ParamTarget::TintB => &mut tint[2],
+                _ => continue,
+            };
+            match param.merge_mode() {
+                ParamMergeMode::Forced => *property = param.value(),
+                _ => *property *= param.value(),
+            }
+        }
+
+        if self.hidden {
+            opacity = 0.0;
+        }
+
+        Appearance {
+            mesh: Some(self.mesh.clone()),
+            tint,
+            opacity,
+            blend_mode: self.blend_mode,
+            mask_mode: self.mask_mode,
+            masked_by: Some(self.masked_by.clone()),
+            mask_threshold: self.mask_threshold,
+            is_mask: false,
+            textures: Some(self.textures.clone()),
+        }
+    }
+
+    pub fn blend_mode(&self) -> BlendMode {
+        self.blend_mode
+    }
+
+    pub fn mask_mode(&self) -> Option<MaskMode> {
+        self.mask_mode
+    }
+
+    /// Returns the nodes masking this part.
+    pub fn masked_by(&self) -> &[Uuid] {
+        &self.masked_by
+    }
+
+    /// Returns the indices of the textures this part is drawn with.
+    pub fn textures(&self) -> &[u32] {
+        &self.textures
+    }
+
+    /// Returns whether the part is soloed, as described in [`io_node::NodeBase::solo`].
+    pub fn solo(&self) -> bool {
+        self.solo
+    }
+}
+
+/// Node whose subtree is drawn into an offscreen buffer, which is then drawn onto the pixels below
+/// it with the composite's own tint, opacity, and blend mode.
+pub struct Composite {
+    node: NodeBase,
+    tint: Vec3,
+    opacity: f32,
+    blend_mode: BlendMode,
+    mask_threshold: f32,
+}
+
+impl Deref for Composite {
+    type Target = NodeBase;
+
+    fn deref(&self) -> &Self::Target {
+        &self.node
+    }
+}
+
+impl DerefMut for Composite {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.node
+    }
+}
+
+impl Composite {
+    fn from_io(
+        params: &mut ParamMap,
+        config: &EngineConfig,
+        io: &io_node::Composite,
+    ) -> Result<Self> {
+        Ok(Self {
+            node: NodeBase::from_io_non_drawable(params, config, io)?,
+            tint: io.tint(),
+            opacity: io.opacity(),
+            blend_mode: io.blend_mode(),
+            mask_threshold: io.mask_threshold(),
+        })
+    }
+
+    /// Computes how the offscreen buffer is drawn.
+    fn appearance(&self) -> Appearance {
+        Appearance {
+            tint: self.tint,
+            opacity: self.opacity,
+            blend_mode: self.blend_mode,
+            mask_threshold: self.mask_threshold,
+            ..Appearance::default()
+        }
+    }
+
+    /// Returns the color the offscreen buffer is multiplied with when it is drawn.
+    pub fn tint(&self) -> Vec3 {
+        self.tint
+    }
+
+    /// Returns the opacity the offscreen buffer is drawn with.
+    pub fn opacity(&self) -> f32 {
+        self.opacity
+    }
+
+    /// Returns how the offscreen buffer is blended with the pixels below it.
+    pub fn blend_mode(&self) -> BlendMode {
+        self.blend_mode
+    }
+
+    /// Returns the alpha value below which the composite's pixels are discarded when it is drawn
+    /// as a mask.
+    pub fn mask_threshold(&self) -> f32 {
+        self.mask_threshold
+    }
+}
+
+/// Invisible node with joints that deform the meshes of other nodes.
+///
+/// The joints are moved by the node's mesh deformation bindings, which have one offset per joint.
+/// Every vertex bound to joints is displaced by the average offset of its joints, on top of the
+/// deformation of its own mesh. Vertices bound by several [`PathDeform`] nodes add up their
+/// displacements.
+pub struct PathDeform {
+    node: NodeBase,
+    joint_count: usize,
+    /// Mesh deformation bindings, split off from the node's other parameter bindings.
+    deforms: Vec<ParamBinding>,
+    bindings: Vec<JointBinding>,
+}
+
+/// The vertices of a drawable bound to the joints of a [`PathDeform`].
+struct JointBinding {
+    node: Uuid,
+    /// Every bound vertex, and the joints affecting it.
+    vertices: Vec<(usize, Box<[usize]>)>,
+}
+
+impl Deref for PathDeform {
+    type Target = NodeBase;
+
+    fn deref(&self) -> &Self::Target {
+        &self.node
+    }
+}
+
+impl DerefMut for PathDeform {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.node
+    }
+}
+
+impl PathDeform {
+    fn from_io(
+        params: &mut ParamMap,
+        config: &EngineConfig,
+        io: &io_node::PathDeform,
+    ) -> Result<Self> {
+        let mut node = NodeBase::from_io(params, config, io)?;
+        check_part_bindings(&node, io.name())?;
+        let joint_count = io.joint_origins().len();
+        let (deforms, params): (Vec<_>, Vec<_>) = node
+            .params
+            .drain(..)
+            .partition(|p| p.target() == ParamTarget::Deform);
+        node.params = params;
+
+        if !deforms.iter().all(|p| p.matches_vertex_count(joint_count)) {
+            return Err(crate::Error::invalid(format!(
+                "joint deformation of node '{}' does not match its joint count of {}",
+                io.name(),
+                joint_count,
+            )));
+        }
+
+        let mut bindings = Vec::with_capacity(io.bindings().len());
+        for binding in io.bindings() {
+            if binding.bind_data().len() > joint_count {
+                return Err(crate::Error::invalid(format!(
+                    "node '{}' binds {} joints, but only has {}",
+                    io.name(),
+                    binding.bind_data().len(),
+                    joint_count,
+                )));
+            }
+            bindings.push(JointBinding {
+                node: binding.bound_to(),
+                vertices: binding
+                    .vertex_joints()
+                    .into_iter()
+                    .map(|(vertex, joints)| (vertex, joints.into_boxed_slice()))
+                    .collect(),
+            });
+        }
+
+        Ok(Self {
+            node,
+            joint_count,
+            deforms,
+            bindings,
+        })
+    }
+
+    pub fn joint_count(&self) -> usize {
+        self.joint_count
+    }
+}
+
+/// Checks that the joints of every `PathDeform` node below `root` (in the model) are bound to
+/// existing vertices of drawable nodes.
+pub(crate) fn check_joint_bindings(root: &io_node::Node) -> Result<()> {
+    let all = || std::iter::once(root).chain(root.descendants());
+    let mut nodes = HashMap::new();
+    for node in all() {
+        // Like `find_by_uuid`, the first node with a UUID wins.
+        nodes.entry(node.uuid()).or_insert(node);
+    }
+    for node in all() {
+        let io_node::Node::PathDeform(path) = node else {
+            continue;
+        };
+        for binding in path.bindings() {
+            let target = nodes.get(&binding.bound_to());
+            let vertex_count = match target {
+                Some(io_node::Node::Drawable(node)) => node.mesh_data().vertex_count(),
+                Some(io_node::Node::Part(node)) => node.mesh_data().vertex_count(),
+                Some(io_node::Node::Mask(node)) => node.mesh_data().vertex_count(),
+                _ => {
+                    return Err(crate::Error::invalid(format!(
+                        "node '{}' binds joints to node {}, which is not a drawable",
+                        path.name(),
+                        binding.bound_to(),
+                    )))
+                }
+            };
+            if let Some(&vertex) = binding
+                .bind_data()
+                .iter()
+                .flatten()
+                .find(|&&vertex| vertex >= vertex_count)
+            {
+                return Err(crate::Error::invalid(format!(
+                    "node '{}' binds vertex {} of node {}, which only has {} vertices",
+                    path.name(),
+                    vertex,
+                    binding.bound_to(),
+                    vertex_count,
+                )));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Displaces the vertices bound to the joints of the `PathDeform` nodes below `root`.
+///
+/// `commands` are the render commands of the current frame, before sorting, so the position of
+/// every command is its transform index, which `indices` maps node UUIDs to. Nodes that weren't
+/// rendered (because they are disabled) neither deform nor get deformed.
+pub(crate) fn apply_path_deforms(
+    root: &Node,
+    indices: &HashMap<Uuid, u32>,
+    commands: &mut [RenderCommand],
+) {
+    for (_, node) in NodeIter::new(root) {
+        let Node::PathDeform(path) = node else {
+            continue;
+        };
+        let Some(&index) = indices.get(&path.uuid) else {
+            continue;
+        };
+        let index = index as usize;
+        let Some(offsets) = deform_offsets(&path.deforms, path.joint_count) else {
+            continue;
+        };
+        let path_transform = commands[index].transform;
+
+        for binding in &path.bindings {
+            let Some(&index) = indices.get(&binding.node) else {
+                continue;
+            };
+            let cmd = &mut commands[index as usize];
+            let Some(to_local) = cmd.transform.inverse() else {
+                continue;
+            };
+            let Some(mesh) = &cmd.mesh else {
+                continue;
+            };
+            // Joint offsets are relative to the `PathDeform` node, but vertex offsets are relative
+            // to the deformed node.
+            let offsets: Vec<Vec2> = offsets
+                .iter()
+                .map(|&offset| to_local.transform_vector(path_transform.transform_vector(offset)))
+                .collect();
+
+            let vertex_count = mesh.vertices.len();
+            let deform = cmd
+                .deform
+                .get_or_insert_with(|| vec![[0.0; 2]; vertex_count]);
+            for (vertex, joints) in &binding.vertices {
+                let [mut x, mut y] = [0.0; 2];
+                for &joint in joints.iter() {
+                    x += offsets[joint][0];
+                    y += offsets[joint][1];
+                }
+                let count = joints.len() as f32;
+                deform[*vertex][0] += x / count;
+                deform[*vertex][1] += y / count;
+            }
+        }
+    }
+}
+
+pub struct SimplePhysics {
+    node: NodeBase,
+    /// The parameter driven by the simulation, if it is bound to one.
+    param: Option<ParamHandle>,
+    map_mode: io_node::ParamMapMode,
+    output_scale: Vec2,
+    pendulum: Pendulum,
+    /// Anchor point of the pendulum in the last simulation step.
+    anchor: Vec2,
+    /// Parameter value output by the last simulation step, including `output_scale`.
+    output: Vec2,
+    /// Parameter value output by the step before the last one.
+    prev_output: Vec2,
+    /// Parameter value written by the last update, interpolated between the last two steps.
+    written_output: Vec2,
+}
+
+impl Deref for SimplePhysics {
+    type Target = NodeBase;
+
+    fn deref(&self) -> &Self::Target {
+        &self.node
+    }
+}
+
+impl DerefMut for SimplePhysics {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.node
+    }
+}
+
+impl SimplePhysics {
+    fn from_io(
+        params: &mut ParamMap,
+        config: &EngineConfig,
+        io: &io_node::SimplePhysics,
+    ) -> Result<Self> {
+        let mut node = NodeBase::from_io_non_drawable(params, config, io)?;
+        node.contains_physics = true;
+        let mut physics = Self {
+            node,
+            param: params.handle(io.param()),
+            map_mode: io.map_mode(),
+            output_scale: io.output_scale(),
+            pendulum: match io.model_type() {
+                io_node::PhysicsModel::Pendulum => {
+                    Pendulum::new(io.length(), io.gravity(), io.angle_damping())
+                }
+                io_node::PhysicsModel::SpringPendulum => Pendulum::spring(
+                    io.length(),
+                    io.gravity(),
+                    io.angle_damping(),
+                    io.frequency(),
+                    io.length_damping(),
+                ),
+            },
+            anchor: [0.0; 2],
+            output: [0.0; 2],
+            prev_output: [0.0; 2],
+            written_output: [0.0; 2],
+        };
+        physics.output = physics.rest_output();
+        physics.prev_output = physics.output;
+        physics.written_output = physics.output;
+        Ok(physics)
+    }
+
+    /// Returns the output of the simulation while the pendulum hangs at rest.
+    fn rest_output(&self) -> Vec2 {
+        let [x, y] = self.pendulum.output(self.anchor, self.map_mode);
+        [x * self.output_scale[0], y * self.output_scale[1]]
+    }
+
+    /// Advances the simulation by `delta`, with the pendulum anchored at the origin of `transform`.
+    fn step(&mut self, delta: Duration, world: &World, transform: &Transform) {
+        // The output of the simulation would be ignored, so don't bother running it.
+        if self.is_skipped() {
+            return;
+        }
+
+        let anchor = transform.origin();
+        self.pendulum.step(world, anchor, delta);
+
+        let [x, y] = self.pendulum.output(anchor, self.map_mode);
+        self.anchor = anchor;
+        self.prev_output = self.output;
+        self.output = [x * self.output_scale[0], y * self.output_scale[1]];
+    }
+
+    /// Adds the output for the bound parameter to `writes`, interpolated between the last two
+    /// steps by `alpha`.
+    fn write_output(&mut self, alpha: f32, writes: &mut ParamWrites) {
+        if self.is_skipped() {
+            return;
+        }
+        let [prev, cur] = [self.prev_output, self.output];
+        let lerp = |i: usize| prev[i] + (cur[i] - prev[i]) * alpha;
+        self.written_output = [lerp(0), lerp(1)];
+        if let Some(param) = &self.param {
+            writes.add(param, self.written_output);
+        }
+    }
+
+    fn is_skipped(&self) -> bool {
+        self.param.as_ref().is_some_and(|param| param.is_skipped())
+    }
+
+    /// Returns the state of the simulation after the last update.
+    pub(crate) fn state(&self) -> PhysicsState {
+        let (angle, length) = self.pendulum.rod(self.anchor);
+        PhysicsState {
+            angle,
+            angular_velocity: self.pendulum.velocity(),
+            length,
+            length_velocity: self.pendulum.length_velocity(),
+            output: self.written_output,
+        }
+    }
+}
+
+/// A snapshot of the simulation of a [`SimplePhysics`] node.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PhysicsState {
+    angle: f32,
+    angular_velocity: f32,
+    length: f32,
+    length_velocity: f32,
+    output: Vec2,
+}
+
+impl PhysicsState {
+    /// Returns the angle of the pendulum in radians.
+    ///
+    /// The angle is 0 when the pendulum hangs straight down, and positive when it swings to the
+    /// right of its anchor.
+    pub fn angle(&self) -> f32 {
+        self.angle
+    }
+
+    /// Returns the angular velocity of the pendulum, in radians per second.
+    pub fn angular_velocity(&self) -> f32 {
+        self.angular_velocity
+    }
+
+    /// Returns the distance between the pendulum's anchor and its bob, in pixels.
+    pub fn length(&self) -> f32 {
+        self.length
+    }
+
+    /// Returns the rate at which the pendulum stretches, in pixels per second.
+    ///
+    /// This is always 0 unless the node uses the spring pendulum model.
+    pub fn length_velocity(&self) -> f32 {
+        self.length_velocity
+    }
+
+    /// Returns the value written to the driven parameter, after scaling.
+    ///
+    /// This is interpolated between the last two steps of the simulation, so it can lag behind
+    /// the pendulum's angle and length by up to one
+    /// [physics timestep][crate::PuppetEngine::set_physics_timestep].
+    pub fn output(&self) -> Vec2 {
+        self.output
+    }
 }
 
 /// An affine transformation, represented as a 4x4 matrix of `f32` values.
@@ -195,14 +1557,109 @@ impl Transform {
     }
 
     /// Converts an `inochi_io` transform to an `inochi_engine` transform.
-    pub(crate) fn from_io(t: &rhino2d_io::node::Transform) -> Self {
-        let rot = t.rotation();
+    ///
+    /// The result scales, then rotates, then translates. The rotation angles of `t` are negated if
+    /// `direction` is [`RotationDirection::CounterClockwise`].
+    pub(crate) fn from_io(t: &rhino2d_io::node::Transform, direction: RotationDirection) -> Self {
+        let sign = match direction {
+            RotationDirection::Clockwise => 1.0,
+            RotationDirection::CounterClockwise => -1.0,
+        };
+        let rot = t.rotation().map(|angle| angle * sign);
         let scale = t.scale();
         let trans = t.translation();
         Self {
-            mat: Matrix4::new_nonuniform_scaling(&Vector3::new(scale[0], scale[1], 1.0))
+            mat: Matrix4::new_translation(&Vector3::new(trans[0], trans[1], trans[2]))
                 * Matrix4::from_euler_angles(rot[0], rot[1], rot[2])
-                * Matrix4::new_translation(&Vector3::new(trans[0], trans[1], trans[2])),
+                * Matrix4::new_nonuniform_scaling(&Vector3::new(scale[0], scale[1], 1.0)),
+        }
+    }
+
+    /// Linearly interpolates every matrix element between `self` and `other`.
+    ///
+    /// This is only an approximation for transforms with differing rotations, but it is exact at
+    /// `t = 0` and `t = 1`.
+    pub(crate) fn lerp(&self, other: &Self, t: f32) -> Self {
+        Self {
+            mat: self.mat * (1.0 - t) + other.mat * t,
+        }
+    }
+
+    /// Returns the X and Y coordinates the point `[x, y, 0]` is transformed to.
+    pub(crate) fn transform_point(&self, [x, y]: Vec2) -> Vec2 {
+        let p = self.mat.transform_point(&Point3::new(x, y, 0.0));
+        [p.x, p.y]
+    }
+
+    /// Returns the X and Y components the vector `[x, y, 0]` is transformed to, ignoring the
+    /// translation.
+    pub(crate) fn transform_vector(&self, [x, y]: Vec2) -> Vec2 {
+        let v = self.mat.transform_vector(&Vector3::new(x, y, 0.0));
+        [v.x, v.y]
+    }
+
+    /// Returns the inverse transform, or `None` if the transform is not invertible (eg. because
+    /// it scales an axis to 0).
+    pub(crate) fn inverse(&self) -> Option<Self> {
+        self.mat.try_inverse().map(|mat| Self { mat })
+    }
+
+    /// Returns the X and Y coordinates the origin is transformed to.
+    pub(crate) fn origin(&self) -> Vec2 {
+        [self.mat[(0, 3)], self.mat[(1, 3)]]
+    }
+
+    /// Returns the translation of the transform.
+    pub fn translation(&self) -> Vec3 {
+        [self.mat[(0, 3)], self.mat[(1, 3)], self.mat[(2, 3)]]
+    }
+
+    /// Returns the rotation of the transform as Euler angles in radians, in the order the model's
+    /// transforms specify them.
+    ///
+    /// Like [`Transform::scale`], this assumes the transform scales, then rotates, then translates,
+    /// like the transforms of the nodes of a model. The angles are those of the matrix, so they
+    /// are negated compared to the model's if the engine uses
+    /// [`RotationDirection::CounterClockwise`]. They can also differ from the model's by full
+    /// turns, or be an equivalent combination of angles.
+    ///
+    /// The rotation is unspecified if the transform scales an axis to 0, and only approximate if
+    /// it shears (eg. a rotated node below a non-uniformly scaled parent).
+    pub fn rotation_euler(&self) -> Vec3 {
+        let [scale_x, scale_y] = self.scale();
+        let axes = self.mat.fixed_slice::<3, 3>(0, 0);
+        let unit = |axis: usize, scale: f32| {
+            if scale != 0.0 {
+                axes.column(axis) / scale
+            } else {
+                Matrix3::identity().column(axis).into_owned()
+            }
+        };
+        let mat = Matrix3::from_columns(&[unit(0, scale_x), unit(1, scale_y), unit(2, 1.0)]);
+        let (x, y, z) = Rotation3::from_matrix_unchecked(mat).euler_angles();
+        [x, y, z]
+    }
+
+    /// Returns the X and Y scale of the transform.
+    ///
+    /// A transform that mirrors is reported with a negative Y scale, since a negative X scale is
+    /// the same as a negative Y scale combined with a half turn. Shear can not be represented and
+    /// is lost.
+    pub fn scale(&self) -> Vec2 {
+        let axes = self.mat.fixed_slice::<3, 3>(0, 0);
+        let sign = if axes.determinant() < 0.0 { -1.0 } else { 1.0 };
+        [axes.column(0).norm(), sign * axes.column(1).norm()]
+    }
+
+    /// Returns the matrix in column-major order.
+    pub(crate) fn to_array(self) -> [f32; 16] {
+        self.mat.as_slice().try_into().unwrap()
+    }
+
+    /// Creates a transform from the 16 elements of its matrix, in column-major order.
+    pub fn from_column_major_data(data: &[f32; 16]) -> Self {
+        Self {
+            mat: Matrix4::from_column_slice(data),
         }
     }
 
@@ -212,6 +1669,23 @@ impl Transform {
     }
 }
 
+/// Serialized as the 16 elements of the matrix, in column-major order.
+#[cfg(feature = "serde")]
+impl serde::Serialize for Transform {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let data: &[f32; 16] = self.mat.as_slice().try_into().unwrap();
+        data.serialize(serializer)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for Transform {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let data = <[f32; 16]>::deserialize(deserializer)?;
+        Ok(Self::from_column_major_data(&data))
+    }
+}
+
 impl Mul for Transform {
     type Output = Self;
 
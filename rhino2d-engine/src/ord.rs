@@ -16,7 +16,7 @@ impl Eq for TotalF32 {}
 
 impl PartialOrd for TotalF32 {
     fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
-        self.0.partial_cmp(&other.0)
+        Some(self.cmp(other))
     }
 }
 
@@ -1,31 +1,89 @@
 use std::{
     cmp,
-    collections::HashMap,
+    collections::BTreeMap,
+    f32::consts::TAU,
     str::FromStr,
-    sync::{atomic::Ordering, Arc},
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        Arc,
+    },
+    time::Duration,
 };
 
-use rhino2d_io::{InterpolateMode, Uuid};
+use half::f16;
+use rhino2d_io::{EdgeBehavior, InterpolateMode, ParamMergeMode, ParamValue, Uuid, Vec2};
 
 use crate::{
     atomic::{AtomicF32, AtomicF32x2},
     ord::{is_sorted, TotalF32},
-    Error, Result,
+    DeformPrecision, EngineConfig, Error, Result,
 };
 
+/// Identifies a parameter of a puppet, either by its name or by its UUID.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ParamId<'a> {
+    Name(&'a str),
+    Uuid(Uuid),
+}
+
+impl<'a> From<&'a str> for ParamId<'a> {
+    fn from(name: &'a str) -> Self {
+        Self::Name(name)
+    }
+}
+
+impl ParamId<'_> {
+    fn matches(&self, param: &ParamInfo) -> bool {
+        match *self {
+            ParamId::Name(name) => param.name == name,
+            ParamId::Uuid(uuid) => param.uuid == uuid,
+        }
+    }
+}
+
+impl From<Uuid> for ParamId<'_> {
+    fn from(uuid: Uuid) -> Self {
+        Self::Uuid(uuid)
+    }
+}
+
 pub struct ParamMap {
-    map: HashMap<Uuid, Vec<ParamBinding>>,
+    /// All parameters of the model, sorted by UUID.
+    params: Vec<ParamInfo>,
+    map: BTreeMap<Uuid, Vec<ParamBinding>>,
+    /// The level of detail. Parameters with a higher LOD level are skipped.
+    lod: u32,
+    /// Incremented whenever any parameter is set, muted, or skipped. See [`ParamMap::generation`].
+    generation: Arc<AtomicU64>,
+}
+
+/// Per-parameter data that outlives node construction.
+struct ParamInfo {
+    uuid: Uuid,
+    name: String,
+    handle: ParamHandle,
+    /// The nodes affected by this parameter, and which of their properties it affects.
+    targets: Vec<(Uuid, ParamTarget)>,
+    lod_level: u32,
 }
 
 impl ParamMap {
-    pub(crate) fn lower(io: &[rhino2d_io::Param]) -> Result<Self> {
-        let mut map: HashMap<_, Vec<_>> = HashMap::new();
+    pub(crate) fn lower(io: &[rhino2d_io::Param], config: &EngineConfig) -> Result<Self> {
+        let precision = config.deform_precision();
+        let mut params = Vec::with_capacity(io.len());
+        let mut map: BTreeMap<_, Vec<_>> = BTreeMap::new();
+        let generation = Arc::new(AtomicU64::new(0));
         for param in io {
+            check_axis_count(param)?;
             let handle = if param.is_vec2() {
                 ParamHandle::Param2D(ParamHandle2D {
                     rc: Arc::new(Param2D {
                         axes: [ParamAxis::lower(param, 0)?, ParamAxis::lower(param, 1)?],
                         value: AtomicF32x2::new(param.defaults()[0], param.defaults()[1]),
+                        default: param.defaults(),
+                        skipped: AtomicBool::new(false),
+                        muted: AtomicBool::new(false),
+                        generation: generation.clone(),
                     }),
                 })
             } else {
@@ -33,63 +91,632 @@ impl ParamMap {
                     rc: Arc::new(Param1D {
                         axes: [ParamAxis::lower(param, 0)?],
                         value: AtomicF32::new(param.defaults()[0]),
+                        default: param.defaults()[0],
+                        skipped: AtomicBool::new(false),
+                        muted: AtomicBool::new(false),
+                        generation: generation.clone(),
                     }),
                 })
             };
 
+            let mut targets = Vec::with_capacity(param.bindings().len());
             for binding in param.bindings() {
-                if binding.interpolate_mode() != InterpolateMode::Linear {
-                    return Err(Error::unsupported(format!(
-                        "parameter binding interpolation mode '{:?}'",
-                        binding.interpolate_mode()
-                    )));
+                let modes = [
+                    binding.interpolate_mode(),
+                    binding
+                        .interpolate_mode_y()
+                        .unwrap_or(binding.interpolate_mode()),
+                ];
+                for mode in modes {
+                    match mode {
+                        InterpolateMode::Nearest
+                        | InterpolateMode::Linear
+                        | InterpolateMode::Stepped
+                        | InterpolateMode::Cubic
+                        | InterpolateMode::Bezier => {}
+                        mode => {
+                            return Err(Error::unsupported(format!(
+                                "parameter binding interpolation mode '{:?}'",
+                                mode
+                            )));
+                        }
+                    }
                 }
 
-                map.entry(binding.node()).or_default().push(ParamBinding {
+                let target = ParamTarget::from_str(binding.param_name())?;
+                let values = if target == ParamTarget::Deform {
+                    let grid = lower_grid(param, binding, |value| match value {
+                        ParamValue::Deformation(offsets) => Some(offsets.clone()),
+                        ParamValue::Scalar(_) => None,
+                    })?;
+                    BindingValues::Deform(
+                        grid.iter()
+                            .map(|row| {
+                                row.iter()
+                                    .map(|offsets| Deformation::new(offsets, precision))
+                                    .collect()
+                            })
+                            .collect(),
+                    )
+                } else {
+                    BindingValues::Scalar(lower_grid(param, binding, |value| match value {
+                        ParamValue::Scalar(f) => Some(*f),
+                        ParamValue::Deformation(_) => None,
+                    })?)
+                };
+
+                let mut lowered = ParamBinding {
                     param: handle.clone(),
-                    target: ParamTarget::from_str(binding.param_name())?,
-                    values: binding
-                        .values()
-                        .iter()
-                        .map(|val| {
-                            val.iter()
-                                .map(|value| match value {
-                                    rhino2d_io::ParamValue::Scalar(f) => Ok(*f),
-                                    rhino2d_io::ParamValue::Deformation(_) => {
-                                        Err(Error::unsupported("mesh deformation"))
-                                    }
-                                })
-                                .collect::<Result<Vec<_>>>()
-                        })
-                        .collect::<Result<Vec<_>>>()?,
-                });
+                    target,
+                    modes,
+                    wrap_angles: config.shortest_arc_rotations() && target.is_rotation(),
+                    merge_mode: param.merge_mode().unwrap_or_default(),
+                    values,
+                    rest_value: 0.0,
+                    rest_deform: None,
+                };
+                // The parameter is still at its default value, so this computes the rest values.
+                lowered.rest_value = lowered.value();
+                if let BindingValues::Deform(grid) = &lowered.values {
+                    let vertex_count = grid
+                        .first()
+                        .and_then(|row| row.first())
+                        .map_or(0, |d| d.len());
+                    let mut rest = vec![[0.0; 2]; vertex_count];
+                    lowered.deform(&mut rest);
+                    if rest.iter().any(|offset| *offset != [0.0; 2]) {
+                        lowered.rest_deform = Some(rest.into());
+                    }
+                }
+
+                targets.push((binding.node(), target));
+                map.entry(binding.node()).or_default().push(lowered);
             }
+
+            params.push(ParamInfo {
+                uuid: param.uuid(),
+                name: param.name().to_string(),
+                handle,
+                targets,
+                lod_level: 0,
+            });
         }
 
-        Ok(Self { map })
+        params.sort_by_key(|param| param.uuid);
+        Ok(Self {
+            params,
+            map,
+            lod: u32::MAX,
+            generation,
+        })
     }
 
     pub(crate) fn take_params_affecting_node(&mut self, node: Uuid) -> Vec<ParamBinding> {
         self.map.remove(&node).unwrap_or_default()
     }
+
+    fn find(&self, id: ParamId<'_>) -> Option<&ParamInfo> {
+        self.params.iter().find(|param| id.matches(param))
+    }
+
+    /// Returns a handle to the parameter with the given UUID, if there is one.
+    pub(crate) fn handle(&self, uuid: Uuid) -> Option<ParamHandle> {
+        self.find(ParamId::Uuid(uuid))
+            .map(|param| param.handle.clone())
+    }
+
+    /// Returns a handle to the parameter `id`, if it exists.
+    pub(crate) fn get(&self, id: ParamId<'_>) -> Option<&ParamHandle> {
+        self.find(id).map(|param| &param.handle)
+    }
+
+    /// Sets the value of the parameter `id`.
+    ///
+    /// Returns `false` if no such parameter exists.
+    pub(crate) fn set(&self, id: ParamId<'_>, value: [f32; 2]) -> bool {
+        self.find(id).map(|param| param.handle.set(value)).is_some()
+    }
+
+    /// Mutes or unmutes the parameter `id`.
+    ///
+    /// Returns `false` if no such parameter exists.
+    pub(crate) fn set_muted(&self, id: ParamId<'_>, muted: bool) -> bool {
+        self.find(id)
+            .map(|param| param.handle.set_muted(muted))
+            .is_some()
+    }
+
+    /// Resets the parameter `id` to its default value.
+    ///
+    /// Returns `false` if no such parameter exists.
+    pub(crate) fn reset(&self, id: ParamId<'_>) -> bool {
+        self.find(id).map(|param| param.handle.reset()).is_some()
+    }
+
+    /// Resets every parameter to its default value.
+    pub(crate) fn reset_all(&self) {
+        for param in &self.params {
+            param.handle.reset();
+        }
+    }
+
+    pub(crate) fn lod(&self) -> u32 {
+        self.lod
+    }
+
+    /// Returns a counter that changes whenever any parameter is set (even to its current value),
+    /// muted, or skipped, through any handle.
+    pub(crate) fn generation(&self) -> u64 {
+        self.generation.load(Ordering::Relaxed)
+    }
+
+    /// Sets the level of detail, skipping all parameters with a higher LOD level.
+    pub(crate) fn set_lod(&mut self, lod: u32) {
+        self.lod = lod;
+        for param in &self.params {
+            param.handle.set_skipped(param.lod_level > lod);
+        }
+    }
+
+    /// Sets the LOD level of the parameter `id`.
+    ///
+    /// Returns `false` if no such parameter exists.
+    pub(crate) fn set_lod_level(&mut self, id: ParamId<'_>, level: u32) -> bool {
+        let lod = self.lod;
+        let Some(param) = self.params.iter_mut().find(|param| id.matches(param)) else {
+            return false;
+        };
+        param.lod_level = level;
+        param.handle.set_skipped(level > lod);
+        true
+    }
+
+    /// Returns the UUID and handle of every parameter, sorted by UUID.
+    pub(crate) fn handles(&self) -> impl Iterator<Item = (Uuid, &ParamHandle)> + '_ {
+        self.params.iter().map(|param| (param.uuid, &param.handle))
+    }
+
+    /// Returns the UUID and name of every parameter, sorted by UUID.
+    pub(crate) fn iter(&self) -> impl Iterator<Item = (Uuid, &str)> + '_ {
+        self.params.iter().map(|param| (param.uuid, &*param.name))
+    }
+
+    /// Returns the nodes affected by the parameter `id`, and which of their properties it affects.
+    ///
+    /// Returns an empty list if no such parameter exists.
+    pub(crate) fn targets(&self, id: ParamId<'_>) -> &[(Uuid, ParamTarget)] {
+        self.find(id).map_or(&[], |param| &param.targets)
+    }
+}
+
+/// Checks that the number of axis point lists of `param` matches its dimensionality.
+///
+/// 2D parameters need two lists of axis points. 1D parameters store a placeholder list containing
+/// only `0.0` as the second list.
+fn check_axis_count(param: &rhino2d_io::Param) -> Result<()> {
+    let axis_points = param.axis_points();
+    if axis_points.len() != 2 {
+        return Err(Error::invalid(format!(
+            "parameter '{}' is invalid: expected 2 lists of axis points, got {}",
+            param.name(),
+            axis_points.len(),
+        )));
+    }
+    let placeholder = axis_points[1] == [0.0];
+    if param.is_vec2() && placeholder {
+        return Err(Error::invalid(format!(
+            "parameter '{}' is invalid: 2D parameter has no axis points for its second axis",
+            param.name(),
+        )));
+    }
+    if !param.is_vec2() && !placeholder {
+        return Err(Error::invalid(format!(
+            "parameter '{}' is invalid: 1D parameter has axis points for a second axis ({:?})",
+            param.name(),
+            axis_points[1],
+        )));
+    }
+    Ok(())
+}
+
+/// Lowers the value grid of a binding, failing if `lower` rejects any of the values.
+///
+/// The values that aren't set are filled in.
+fn lower_grid<T: Blend>(
+    param: &rhino2d_io::Param,
+    binding: &rhino2d_io::ParamBinding,
+    lower: impl Fn(&ParamValue) -> Option<T>,
+) -> Result<Vec<Vec<T>>> {
+    let mut grid = binding
+        .values()
+        .iter()
+        .map(|row| {
+            row.iter()
+                .map(|value| {
+                    lower(value).ok_or_else(|| {
+                        Error::invalid(format!(
+                            "parameter '{}' has a binding mixing scalar and deformation values",
+                            param.name()
+                        ))
+                    })
+                })
+                .collect()
+        })
+        .collect::<Result<Vec<Vec<T>>>>()?;
+    fill_unset(&mut grid, binding.is_set(), param.axis_points());
+    Ok(grid)
+}
+
+/// Keyframe values that can be blended to fill in values that aren't set.
+trait Blend: Clone {
+    /// Interpolates linearly between `self` (at `t = 0`) and `other` (at `t = 1`).
+    fn blend(&self, other: &Self, t: f32) -> Self;
+}
+
+impl Blend for f32 {
+    fn blend(&self, other: &Self, t: f32) -> Self {
+        self + (other - self) * t
+    }
+}
+
+impl Blend for Vec<Vec2> {
+    fn blend(&self, other: &Self, t: f32) -> Self {
+        self.iter()
+            .zip(other)
+            .map(|(a, b)| [a[0].blend(&b[0], t), a[1].blend(&b[1], t)])
+            .collect()
+    }
+}
+
+/// Replaces the values of `grid` whose `is_set` flag is `false` with values interpolated from the
+/// surrounding set values.
+///
+/// Inochi2D stores a value for every axis point, but only those the rigger keyed are meaningful.
+/// Unset values are interpolated linearly from the nearest set values in the same row, or copied
+/// from the nearest one if there is only one side. Rows without any set values are then filled in
+/// from the surrounding rows in the same way. Values are used as-is if nothing is set, or if the
+/// `is_set` flags are missing.
+///
+/// This matches how Inochi2D fills in unset keyframes. The flags apply to whole keyframes, so for
+/// deformations every vertex of an unset keyframe follows the neighboring keyframes: a vertex only
+/// stays at rest there if it is at rest in those keyframes, and the values stored in the unset
+/// keyframe itself are never used.
+fn fill_unset<T: Blend>(grid: &mut [Vec<T>], is_set: &[Vec<bool>], axis_points: &[Vec<f32>]) {
+    let is_set = |row: usize, col: usize| {
+        is_set
+            .get(row)
+            .and_then(|row| row.get(col))
+            .copied()
+            .unwrap_or(true)
+    };
+    let points = |axis: usize| axis_points.get(axis).map(Vec::as_slice).unwrap_or(&[]);
+
+    let mut set_rows = Vec::with_capacity(grid.len());
+    for (y, row) in grid.iter_mut().enumerate() {
+        let set = (0..row.len()).filter(|&x| is_set(y, x)).collect::<Vec<_>>();
+        if set.len() == row.len() {
+            set_rows.push(y);
+            continue;
+        }
+        if set.is_empty() {
+            continue;
+        }
+        for x in 0..row.len() {
+            if !is_set(y, x) {
+                let value = interpolate(row, &set, points(0), x);
+                row[x] = value;
+            }
+        }
+        set_rows.push(y);
+    }
+
+    if set_rows.is_empty() || set_rows.len() == grid.len() {
+        return;
+    }
+    let rows = grid.to_vec();
+    for (y, row) in grid.iter_mut().enumerate() {
+        if set_rows.contains(&y) {
+            continue;
+        }
+        for (x, value) in row.iter_mut().enumerate() {
+            let column = rows
+                .iter()
+                .map(|row| row.get(x).unwrap_or(&*value).clone())
+                .collect::<Vec<_>>();
+            *value = interpolate(&column, &set_rows, points(1), y);
+        }
+    }
+}
+
+/// Interpolates the value at `index` from the values in `values` at the indices in `set`.
+///
+/// `positions` contains the axis point of every index. `set` must not be empty.
+fn interpolate<T: Blend>(values: &[T], set: &[usize], positions: &[f32], index: usize) -> T {
+    let after = set.partition_point(|&i| i < index);
+    match (after.checked_sub(1).map(|i| set[i]), set.get(after)) {
+        (Some(prev), Some(&next)) => {
+            let position = |i: usize| positions.get(i).copied().unwrap_or(i as f32);
+            let span = position(next) - position(prev);
+            let t = if span > 0.0 {
+                (position(index) - position(prev)) / span
+            } else {
+                0.0
+            };
+            values[prev].blend(&values[next], t)
+        }
+        (Some(prev), None) => values[prev].clone(),
+        (None, Some(&next)) => values[next].clone(),
+        (None, None) => unreachable!("no set values to interpolate from"),
+    }
 }
 
 #[derive(Debug, Clone)]
-enum ParamHandle {
+pub(crate) enum ParamHandle {
     Param1D(ParamHandle1D),
     Param2D(ParamHandle2D),
 }
 
+impl ParamHandle {
+    /// Sets the value of the parameter. The Y component is ignored for 1D parameters.
+    pub(crate) fn set(&self, [x, y]: [f32; 2]) {
+        match self {
+            ParamHandle::Param1D(p) => p.set(x),
+            ParamHandle::Param2D(p) => p.set(x, y),
+        }
+    }
+
+    pub(crate) fn reset(&self) {
+        match self {
+            ParamHandle::Param1D(p) => p.reset(),
+            ParamHandle::Param2D(p) => p.reset(),
+        }
+    }
+
+    /// Returns the current value of the parameter. The Y component is 0 for 1D parameters.
+    pub(crate) fn value(&self) -> [f32; 2] {
+        match self {
+            ParamHandle::Param1D(p) => [p.rc.value.load(Ordering::Relaxed), 0.0],
+            ParamHandle::Param2D(p) => p.rc.value.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Returns the default value of the parameter. The Y component is 0 for 1D parameters.
+    pub(crate) fn default_value(&self) -> [f32; 2] {
+        match self {
+            ParamHandle::Param1D(p) => [p.rc.default, 0.0],
+            ParamHandle::Param2D(p) => p.rc.default,
+        }
+    }
+
+    /// Returns the minimum and maximum value of the parameter.
+    pub(crate) fn range(&self) -> [[f32; 2]; 2] {
+        match self {
+            ParamHandle::Param1D(p) => [[p.rc.axes[0].min, 0.0], [p.rc.axes[0].max, 0.0]],
+            ParamHandle::Param2D(p) => {
+                let [x, y] = &p.rc.axes;
+                [[x.min, y.min], [x.max, y.max]]
+            }
+        }
+    }
+
+    fn bump_generation(&self) {
+        let generation = match self {
+            ParamHandle::Param1D(p) => &p.rc.generation,
+            ParamHandle::Param2D(p) => &p.rc.generation,
+        };
+        generation.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Returns whether `self` and `other` refer to the same parameter.
+    fn is_same(&self, other: &ParamHandle) -> bool {
+        match (self, other) {
+            (ParamHandle::Param1D(a), ParamHandle::Param1D(b)) => Arc::ptr_eq(&a.rc, &b.rc),
+            (ParamHandle::Param2D(a), ParamHandle::Param2D(b)) => Arc::ptr_eq(&a.rc, &b.rc),
+            _ => false,
+        }
+    }
+
+    /// Returns whether the parameter is skipped due to the engine's level of detail.
+    pub(crate) fn is_skipped(&self) -> bool {
+        let skipped = match self {
+            ParamHandle::Param1D(p) => &p.rc.skipped,
+            ParamHandle::Param2D(p) => &p.rc.skipped,
+        };
+        skipped.load(Ordering::Relaxed)
+    }
+
+    /// Returns whether the parameter's bindings are disabled.
+    pub(crate) fn is_muted(&self) -> bool {
+        let muted = match self {
+            ParamHandle::Param1D(p) => &p.rc.muted,
+            ParamHandle::Param2D(p) => &p.rc.muted,
+        };
+        muted.load(Ordering::Relaxed)
+    }
+
+    pub(crate) fn set_muted(&self, muted: bool) {
+        let flag = match self {
+            ParamHandle::Param1D(p) => &p.rc.muted,
+            ParamHandle::Param2D(p) => &p.rc.muted,
+        };
+        flag.store(muted, Ordering::Relaxed);
+        self.bump_generation();
+    }
+
+    fn set_skipped(&self, skipped: bool) {
+        let flag = match self {
+            ParamHandle::Param1D(p) => &p.rc.skipped,
+            ParamHandle::Param2D(p) => &p.rc.skipped,
+        };
+        flag.store(skipped, Ordering::Relaxed);
+        self.bump_generation();
+    }
+}
+
+/// Collects the parameter values computed by automations and physics during a frame.
+///
+/// When several sources drive the same parameter axis, their contributions are summed instead of
+/// the last one overwriting the others. Axes no source contributes to keep their current value.
+///
+/// Contributions can be weighted to fade a source in or out. An axis whose contributions have a
+/// total weight below 1 keeps the remaining fraction of its base value: the value it had before
+/// the first frame with partially weighted contributions. Blending with the base value instead of
+/// the previous frame's (already blended) value makes a linear ramp of the weight fade the value
+/// linearly, too.
+#[derive(Debug)]
+pub(crate) struct ParamWrites {
+    writes: Vec<(ParamHandle, [Option<AxisWrite>; 2])>,
+    /// Weight of the contributions that are added next.
+    weight: f32,
+    /// Base values of the axes whose contributions were partially weighted in the last frame.
+    bases: Vec<(ParamHandle, [Option<f32>; 2])>,
+}
+
+/// The contributions to an axis in a [`ParamWrites`].
+#[derive(Debug, Default, Clone, Copy)]
+struct AxisWrite {
+    /// The weighted sum of the contributions.
+    sum: f32,
+    /// The sum of the contributions' weights.
+    weight: f32,
+}
+
+impl Default for ParamWrites {
+    fn default() -> Self {
+        Self {
+            writes: Vec::new(),
+            weight: 1.0,
+            bases: Vec::new(),
+        }
+    }
+}
+
+impl ParamWrites {
+    /// Sets the weight of the contributions that are added next, from 0.0 to 1.0.
+    pub(crate) fn set_weight(&mut self, weight: f32) {
+        self.weight = weight;
+    }
+
+    /// Adds `value` to the contribution to both axes of `param`.
+    pub(crate) fn add(&mut self, param: &ParamHandle, [x, y]: [f32; 2]) {
+        self.add_axis(param, 0, x);
+        self.add_axis(param, 1, y);
+    }
+
+    /// Adds `value` to the contribution to axis `axis` (0 = X, 1 = Y) of `param`.
+    pub(crate) fn add_axis(&mut self, param: &ParamHandle, axis: usize, value: f32) {
+        let index = match self.writes.iter().position(|(p, _)| p.is_same(param)) {
+            Some(index) => index,
+            None => {
+                self.writes.push((param.clone(), [None; 2]));
+                self.writes.len() - 1
+            }
+        };
+        let write = self.writes[index].1[axis].get_or_insert_with(AxisWrite::default);
+        write.sum += value * self.weight;
+        write.weight += self.weight;
+    }
+
+    /// Writes the accumulated contributions to their parameters and clears them.
+    ///
+    /// Parameters whose value doesn't change are not written, so that they don't invalidate the
+    /// render commands of the previous frame.
+    pub(crate) fn apply(&mut self) {
+        let mut bases = Vec::new();
+        for (param, axes) in self.writes.drain(..) {
+            let cur = param.value();
+            let old_base = self
+                .bases
+                .iter()
+                .find(|(p, _)| p.is_same(&param))
+                .map_or([None; 2], |(_, base)| *base);
+            let mut base = [None; 2];
+            let value = [0, 1].map(|i| match axes[i] {
+                Some(write) if write.weight < 1.0 => {
+                    let b = *base[i].insert(old_base[i].unwrap_or(cur[i]));
+                    write.sum + (1.0 - write.weight) * b
+                }
+                Some(write) => write.sum,
+                None => cur[i],
+            });
+            if base != [None; 2] {
+                bases.push((param.clone(), base));
+            }
+            if value != cur {
+                param.set(value);
+            }
+        }
+        self.bases = bases;
+        self.weight = 1.0;
+    }
+}
+
+/// Fades the contribution of a system driving parameters in and out when it is toggled.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Ramp {
+    enabled: bool,
+    /// Current weight of the system's contribution, from 0.0 to 1.0.
+    weight: f32,
+}
+
+impl Ramp {
+    /// Creates an enabled ramp at full weight.
+    pub(crate) fn new() -> Self {
+        Self {
+            enabled: true,
+            weight: 1.0,
+        }
+    }
+
+    pub(crate) fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    pub(crate) fn set_enabled(&mut self, enabled: bool) {
+        self.enabled = enabled;
+    }
+
+    pub(crate) fn weight(&self) -> f32 {
+        self.weight
+    }
+
+    /// Moves the weight towards 1.0 if enabled or 0.0 if disabled, at a rate that covers the full
+    /// range in `duration`, and returns the new weight.
+    pub(crate) fn advance(&mut self, delta: Duration, duration: Duration) -> f32 {
+        let target = if self.enabled { 1.0 } else { 0.0 };
+        if duration.is_zero() {
+            self.weight = target;
+        } else {
+            let step = delta.as_secs_f32() / duration.as_secs_f32();
+            self.weight = if self.enabled {
+                (self.weight + step).min(target)
+            } else {
+                (self.weight - step).max(target)
+            };
+        }
+        self.weight
+    }
+}
+
 #[derive(Debug)]
 struct Param1D {
     axes: [ParamAxis; 1],
     value: AtomicF32,
+    default: f32,
+    skipped: AtomicBool,
+    muted: AtomicBool,
+    /// Shared by all parameters of a [`ParamMap`]. See [`ParamMap::generation`].
+    generation: Arc<AtomicU64>,
 }
 
 #[derive(Debug)]
 struct Param2D {
     axes: [ParamAxis; 2],
     value: AtomicF32x2,
+    default: [f32; 2],
+    skipped: AtomicBool,
+    muted: AtomicBool,
+    generation: Arc<AtomicU64>,
 }
 
 /// Configuration of a single axis of a parameter.
@@ -98,6 +725,7 @@ pub struct ParamAxis {
     min: f32,
     max: f32,
     axis_points: Vec<f32>,
+    edge_behavior: EdgeBehavior,
 }
 
 impl ParamAxis {
@@ -137,23 +765,37 @@ impl ParamAxis {
             min,
             max,
             axis_points,
+            edge_behavior: param.edge_behavior().unwrap_or_default(),
         })
     }
 
     fn interp(&self, value: f32) -> Interp {
-        // clamp and map input value to 0..1, since that's where axis points are defined in
-        let value = (value.min(self.max).max(self.min) - self.min) / (self.max - self.min);
+        // map input value to 0..1, since that's where axis points are defined in
+        let value = match self.edge_behavior {
+            EdgeBehavior::Clamp => value.min(self.max).max(self.min),
+            EdgeBehavior::Extrapolate => value,
+        };
+        let value = (value - self.min) / (self.max - self.min);
 
-        let larger_idx = self
+        let mut larger_idx = self
             .axis_points
             .iter()
             .position(|p| p > &value)
             .unwrap_or(self.axis_points.len() - 1);
+        if self.edge_behavior == EdgeBehavior::Extrapolate {
+            // below the first point, extend the first segment instead of holding its start
+            larger_idx = larger_idx.max(1).min(self.axis_points.len() - 1);
+        }
         let smaller_idx = larger_idx.saturating_sub(1);
 
         let larger_val = self.axis_points[larger_idx];
         let smaller_val = self.axis_points[smaller_idx];
-        let interp = (value - smaller_val) / (larger_val - smaller_val);
+        // an axis with a single point has nothing to interpolate between
+        let interp = if larger_idx == smaller_idx {
+            0.0
+        } else {
+            (value - smaller_val) / (larger_val - smaller_val)
+        };
 
         Interp {
             start_index: smaller_idx,
@@ -166,18 +808,94 @@ impl ParamAxis {
 struct Interp {
     start_index: usize,
     /// Value from 0 to 1, 0 means == start, 1 means == start+1
+    ///
+    /// Values outside of 0..1 extrapolate past the first or last axis point.
     dist: f32,
 }
 
 impl Interp {
+    #[cfg(test)]
     fn lookup(&self, values: &[f32]) -> f32 {
-        let start = values[self.start_index];
-        if self.dist > 0.0 {
-            let end = values[self.start_index + 1];
-            start * (1.0 - self.dist) + end * self.dist
-        } else {
-            start
+        self.weights(InterpolateMode::Linear, values.len())
+            .iter()
+            .map(|(index, weight)| values[index] * weight)
+            .sum()
+    }
+
+    /// Computes the weights of the axis points contributing to the interpolated value.
+    ///
+    /// `len` is the number of values along the axis.
+    fn weights(&self, mode: InterpolateMode, len: usize) -> Weights {
+        let mut weights = Weights::default();
+        match mode {
+            InterpolateMode::Nearest => {
+                let index = self.start_index + usize::from(self.dist >= 0.5);
+                weights.push(cmp::min(index, len - 1), 1.0);
+            }
+            InterpolateMode::Stepped => {
+                let index = self.start_index + usize::from(self.dist >= 1.0);
+                weights.push(cmp::min(index, len - 1), 1.0);
+            }
+            // Past the edges, extrapolating the spline would curve away, so cubic and bezier
+            // interpolation extrapolate linearly like the other modes.
+            InterpolateMode::Cubic if (0.0..=1.0).contains(&self.dist) => {
+                // Uniform Catmull-Rom spline through the 4 surrounding points. Indices past either
+                // end of the axis are clamped, which duplicates the outermost value.
+                let t = self.dist;
+                let (t2, t3) = (t * t, t * t * t);
+                let coeffs = [
+                    0.5 * (-t + 2.0 * t2 - t3),
+                    0.5 * (2.0 - 5.0 * t2 + 3.0 * t3),
+                    0.5 * (t + 4.0 * t2 - 3.0 * t3),
+                    0.5 * (-t2 + t3),
+                ];
+                for (offset, coeff) in coeffs.into_iter().enumerate() {
+                    let index = (self.start_index + offset).saturating_sub(1);
+                    weights.push(index.min(len - 1), coeff);
+                }
+            }
+            InterpolateMode::Bezier if (0.0..=1.0).contains(&self.dist) => {
+                // Cubic bezier curve whose control points repeat the values it starts and ends
+                // at, so it eases out of and into every axis point.
+                let t = self.dist;
+                let eased = t * t * (3.0 - 2.0 * t);
+                if self.start_index + 1 < len {
+                    weights.push(self.start_index, 1.0 - eased);
+                    weights.push(self.start_index + 1, eased);
+                } else {
+                    weights.push(cmp::min(self.start_index, len - 1), 1.0);
+                }
+            }
+            _ => {
+                // At the last point there is no next value to blend towards, so the edge value
+                // is used as-is instead of being blended with itself.
+                if self.dist != 0.0 && self.start_index + 1 < len {
+                    weights.push(self.start_index, 1.0 - self.dist);
+                    weights.push(self.start_index + 1, self.dist);
+                } else {
+                    weights.push(cmp::min(self.start_index, len - 1), 1.0);
+                }
+            }
         }
+        weights
+    }
+}
+
+/// List of `(index, weight)` pairs along one parameter axis.
+#[derive(Debug, Default, Clone, Copy)]
+struct Weights {
+    entries: [(usize, f32); 4],
+    len: usize,
+}
+
+impl Weights {
+    fn push(&mut self, index: usize, weight: f32) {
+        self.entries[self.len] = (index, weight);
+        self.len += 1;
+    }
+
+    fn iter(&self) -> impl Iterator<Item = (usize, f32)> + '_ {
+        self.entries[..self.len].iter().copied()
     }
 }
 
@@ -189,6 +907,12 @@ pub struct ParamHandle1D {
 impl ParamHandle1D {
     pub fn set(&self, value: f32) {
         self.rc.value.store(value, Ordering::Relaxed);
+        self.rc.generation.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Resets the parameter to its default value, as specified by the model.
+    pub fn reset(&self) {
+        self.set(self.rc.default);
     }
 }
 
@@ -200,20 +924,168 @@ pub struct ParamHandle2D {
 impl ParamHandle2D {
     pub fn set(&self, x: f32, y: f32) {
         self.rc.value.store(x, y, Ordering::Relaxed);
+        self.rc.generation.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Resets the parameter to its default value, as specified by the model.
+    pub fn reset(&self) {
+        let [x, y] = self.rc.default;
+        self.set(x, y);
+    }
+}
+
+/// A reference to a parameter of a [`PuppetEngine`], returned by [`PuppetEngine::param`].
+///
+/// Setting a value through the reference has the same effect as [`PuppetEngine::set_param`], but
+/// doesn't look up the parameter again. References stay valid while the engine exists, but are
+/// detached from it by [`PuppetEngine::hot_reload`].
+///
+/// [`PuppetEngine`]: crate::PuppetEngine
+/// [`PuppetEngine::param`]: crate::PuppetEngine::param
+/// [`PuppetEngine::set_param`]: crate::PuppetEngine::set_param
+/// [`PuppetEngine::hot_reload`]: crate::PuppetEngine::hot_reload
+#[derive(Debug, Clone)]
+pub enum ParamRef {
+    Param1D(ParamHandle1D),
+    Param2D(ParamHandle2D),
+}
+
+impl ParamRef {
+    pub(crate) fn new(handle: &ParamHandle) -> Self {
+        match handle {
+            ParamHandle::Param1D(p) => Self::Param1D(p.clone()),
+            ParamHandle::Param2D(p) => Self::Param2D(p.clone()),
+        }
+    }
+
+    /// Returns whether the parameter has two axes.
+    pub fn is_2d(&self) -> bool {
+        matches!(self, Self::Param2D(_))
+    }
+
+    /// Sets the value of a 1D parameter.
+    ///
+    /// Returns an error without changing the value if the parameter is 2D.
+    pub fn set(&self, value: f32) -> Result<()> {
+        match self {
+            Self::Param1D(p) => p.set(value),
+            Self::Param2D(_) => {
+                return Err(Error::usage("cannot set a 2D parameter to a single value"))
+            }
+        }
+        Ok(())
+    }
+
+    /// Sets the value of a 2D parameter.
+    ///
+    /// Returns an error without changing the value if the parameter is 1D.
+    pub fn set_xy(&self, x: f32, y: f32) -> Result<()> {
+        match self {
+            Self::Param1D(_) => return Err(Error::usage("cannot set both axes of a 1D parameter")),
+            Self::Param2D(p) => p.set(x, y),
+        }
+        Ok(())
+    }
+
+    /// Returns the current value of the parameter. The Y component is 0 for 1D parameters.
+    pub fn value(&self) -> Vec2 {
+        match self {
+            Self::Param1D(p) => [p.rc.value.load(Ordering::Relaxed), 0.0],
+            Self::Param2D(p) => p.rc.value.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Resets the parameter to its default value, as specified by the model.
+    pub fn reset(&self) {
+        match self {
+            Self::Param1D(p) => p.reset(),
+            Self::Param2D(p) => p.reset(),
+        }
     }
 }
 
+/// Wraps an angle difference, in radians, to the range `-π..=π`.
+fn wrap_angle(delta: f32) -> f32 {
+    delta - TAU * (delta / TAU).round()
+}
+
 /// Describes to a node how a parameter affects one of its properties.
 #[derive(Debug, Clone)]
 pub struct ParamBinding {
     param: ParamHandle,
     target: ParamTarget,
-    values: Vec<Vec<f32>>,
+    /// Interpolation modes along the X and Y axis.
+    modes: [InterpolateMode; 2],
+    /// Whether the values are angles that are blended along the shortest arc between them.
+    wrap_angles: bool,
+    merge_mode: ParamMergeMode,
+    values: BindingValues,
+    /// The value of the binding at the parameter's default value, used while it is skipped.
+    rest_value: f32,
+    /// The vertex offsets at the parameter's default value, used while it is skipped.
+    ///
+    /// `None` if there are none.
+    rest_deform: Option<Box<[Vec2]>>,
+}
+
+/// The values a binding assigns to its target at each axis point.
+///
+/// Indexed by the axis point in Y direction first, then in X direction.
+#[derive(Debug, Clone)]
+enum BindingValues {
+    Scalar(Vec<Vec<f32>>),
+    Deform(Vec<Vec<Deformation>>),
+}
+
+/// Per-vertex offsets of a mesh deformation keyframe.
+#[derive(Debug, Clone)]
+enum Deformation {
+    F32(Vec<Vec2>),
+    F16(Vec<[f16; 2]>),
+}
+
+impl Deformation {
+    fn new(offsets: &[Vec2], precision: DeformPrecision) -> Self {
+        match precision {
+            DeformPrecision::F32 => Self::F32(offsets.to_vec()),
+            DeformPrecision::F16 => Self::F16(
+                offsets
+                    .iter()
+                    .map(|[x, y]| [f16::from_f32(*x), f16::from_f32(*y)])
+                    .collect(),
+            ),
+        }
+    }
+
+    fn len(&self) -> usize {
+        match self {
+            Self::F32(offsets) => offsets.len(),
+            Self::F16(offsets) => offsets.len(),
+        }
+    }
+
+    /// Adds this deformation's offsets, scaled by `weight`, to `out`.
+    fn accumulate(&self, weight: f32, out: &mut [Vec2]) {
+        match self {
+            Self::F32(offsets) => {
+                for (out, [x, y]) in out.iter_mut().zip(offsets) {
+                    out[0] += x * weight;
+                    out[1] += y * weight;
+                }
+            }
+            Self::F16(offsets) => {
+                for (out, [x, y]) in out.iter_mut().zip(offsets) {
+                    out[0] += x.to_f32() * weight;
+                    out[1] += y.to_f32() * weight;
+                }
+            }
+        }
+    }
 }
 
 impl ParamBinding {
-    pub fn value(&self) -> f32 {
-        let [x, y] = match &self.param {
+    fn interps(&self) -> [Interp; 2] {
+        match &self.param {
             ParamHandle::Param1D(p) => {
                 let x = p.rc.value.load(Ordering::Relaxed);
                 [
@@ -228,24 +1100,95 @@ impl ParamBinding {
                 let [x, y] = p.rc.value.load(Ordering::Relaxed);
                 [p.rc.axes[0].interp(x), p.rc.axes[1].interp(y)]
             }
-        };
+        }
+    }
+
+    /// Invokes `f` with every value in `grid` that contributes to the binding's current value,
+    /// along with its weight.
+    fn for_each_weight<T>(&self, grid: &[Vec<T>], mut f: impl FnMut(&T, f32)) {
+        let [x, y] = self.interps();
+        let [mode_x, mode_y] = self.modes;
+        for (row, wy) in y.weights(mode_y, grid.len()).iter() {
+            let row = &grid[row];
+            for (col, wx) in x.weights(mode_x, row.len()).iter() {
+                f(&row[col], wx * wy);
+            }
+        }
+    }
 
-        // TODO `InterpolateMode::Nearest`
+    /// Computes the current value of a scalar binding.
+    ///
+    /// Returns 0.0 for [`ParamTarget::Deform`] bindings, use [`ParamBinding::deform`] instead.
+    pub fn value(&self) -> f32 {
+        if self.param.is_skipped() {
+            return self.rest_value;
+        }
 
-        let start_row = &self.values[y.start_index];
-        let start = x.lookup(start_row);
-        if y.dist > 0.0 {
-            let end_row = &self.values[cmp::min(y.start_index + 1, self.values.len() - 1)];
-            let end = x.lookup(end_row);
-            start * (1.0 - y.dist) + end * y.dist
-        } else {
-            start
+        let mut value = 0.0;
+        if let BindingValues::Scalar(grid) = &self.values {
+            // When blending angles, every value is moved by whole turns to lie within half a turn
+            // of the first one, so that they are blended along the shortest arc.
+            let mut reference = None;
+            self.for_each_weight(grid, |&v, weight| {
+                let v = if self.wrap_angles {
+                    let reference = *reference.get_or_insert(v);
+                    reference + wrap_angle(v - reference)
+                } else {
+                    v
+                };
+                value += v * weight;
+            });
+        }
+        value
+    }
+
+    /// Adds the current vertex offsets of a [`ParamTarget::Deform`] binding to `out`.
+    ///
+    /// Does nothing for bindings of other targets.
+    pub fn deform(&self, out: &mut [Vec2]) {
+        if self.param.is_skipped() {
+            for (out, [x, y]) in out.iter_mut().zip(self.rest_deform.iter().flatten()) {
+                out[0] += x;
+                out[1] += y;
+            }
+            return;
+        }
+
+        if let BindingValues::Deform(grid) = &self.values {
+            self.for_each_weight(grid, |deformation, weight| {
+                deformation.accumulate(weight, out)
+            });
+        }
+    }
+
+    /// Returns whether every deformation keyframe of this binding has exactly `vertex_count`
+    /// offsets.
+    ///
+    /// Always `true` for scalar bindings.
+    pub(crate) fn matches_vertex_count(&self, vertex_count: usize) -> bool {
+        match &self.values {
+            BindingValues::Scalar(_) => true,
+            BindingValues::Deform(grid) => grid
+                .iter()
+                .flatten()
+                .all(|deformation| deformation.len() == vertex_count),
         }
     }
 
     pub fn target(&self) -> ParamTarget {
         self.target
     }
+
+    /// Returns whether the binding's parameter is [muted][crate::PuppetEngine::set_param_muted],
+    /// in which case the binding must not affect its target.
+    pub(crate) fn is_muted(&self) -> bool {
+        self.param.is_muted()
+    }
+
+    /// Returns how the binding's value is combined with the value of its target.
+    pub fn merge_mode(&self) -> ParamMergeMode {
+        self.merge_mode
+    }
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -253,12 +1196,39 @@ pub enum ParamTarget {
     ZSort,
     TranslationX,
     TranslationY,
+    /// Z translation, which also offsets the node's Z-Sort value. See
+    /// [`NodeBase::zsort`](crate::node::NodeBase::zsort).
     TranslationZ,
     RotationX,
     RotationY,
     RotationZ,
     ScaleX,
     ScaleY,
+    /// Mesh deformation, only valid on drawable nodes.
+    Deform,
+    /// Opacity factor, only valid on parts.
+    Opacity,
+    /// Factor of the red channel of the tint, only valid on parts.
+    TintR,
+    /// Factor of the green channel of the tint, only valid on parts.
+    TintG,
+    /// Factor of the blue channel of the tint, only valid on parts.
+    TintB,
+}
+
+impl ParamTarget {
+    /// Returns whether the target is a property only parts have.
+    pub fn is_part_property(&self) -> bool {
+        matches!(
+            self,
+            Self::Opacity | Self::TintR | Self::TintG | Self::TintB
+        )
+    }
+
+    /// Returns whether the target is a rotation angle.
+    pub fn is_rotation(&self) -> bool {
+        matches!(self, Self::RotationX | Self::RotationY | Self::RotationZ)
+    }
 }
 
 impl FromStr for ParamTarget {
@@ -275,6 +1245,11 @@ impl FromStr for ParamTarget {
             "transform.r.z" => Self::RotationZ,
             "transform.s.x" => Self::ScaleX,
             "transform.s.y" => Self::ScaleY,
+            "deform" => Self::Deform,
+            "opacity" => Self::Opacity,
+            "tint.r" => Self::TintR,
+            "tint.g" => Self::TintG,
+            "tint.b" => Self::TintB,
             _ => {
                 return Err(Error::unsupported(format!("parameter target '{}'", s)));
             }
@@ -286,6 +1261,57 @@ impl FromStr for ParamTarget {
 mod tests {
     use super::*;
 
+    fn axis(axis_points: &[f32]) -> ParamAxis {
+        ParamAxis {
+            min: 0.0,
+            max: 1.0,
+            axis_points: axis_points.to_vec(),
+            edge_behavior: EdgeBehavior::Clamp,
+        }
+    }
+
+    /// Creates a 1D parameter ranging from 0 to 1, with the given axis points.
+    fn param_1d(axis_points: &[f32]) -> ParamHandle1D {
+        ParamHandle1D {
+            rc: Arc::new(Param1D {
+                axes: [axis(axis_points)],
+                value: AtomicF32::new(0.0),
+                default: 0.0,
+                skipped: AtomicBool::new(false),
+                muted: AtomicBool::new(false),
+                generation: Arc::default(),
+            }),
+        }
+    }
+
+    /// Creates a 2D parameter ranging from 0 to 1 on both axes, with the given axis points.
+    fn param_2d(x_points: &[f32], y_points: &[f32]) -> ParamHandle2D {
+        ParamHandle2D {
+            rc: Arc::new(Param2D {
+                axes: [axis(x_points), axis(y_points)],
+                value: AtomicF32x2::new(0.0, 0.0),
+                default: [0.0, 0.0],
+                skipped: AtomicBool::new(false),
+                muted: AtomicBool::new(false),
+                generation: Arc::default(),
+            }),
+        }
+    }
+
+    /// Creates an additive, linearly interpolated binding of `param` to `target`.
+    fn binding(param: ParamHandle, target: ParamTarget, values: Vec<Vec<f32>>) -> ParamBinding {
+        ParamBinding {
+            param,
+            target,
+            modes: [InterpolateMode::Linear; 2],
+            wrap_angles: false,
+            merge_mode: ParamMergeMode::Additive,
+            values: BindingValues::Scalar(values),
+            rest_value: 0.0,
+            rest_deform: None,
+        }
+    }
+
     #[test]
     fn test_axis_interp() {
         // Axis points at -1.0, 0.0, and 1.0
@@ -293,6 +1319,7 @@ mod tests {
             min: -1.0,
             max: 1.0,
             axis_points: vec![0.0, 0.5, 1.0],
+            edge_behavior: EdgeBehavior::Clamp,
         };
         assert_eq!(
             axis.interp(-100.0),
@@ -359,6 +1386,199 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_cubic_binding() {
+        let handle = param_1d(&[0.0, 0.5, 1.0]);
+        let mut binding = binding(
+            ParamHandle::Param1D(handle.clone()),
+            ParamTarget::TranslationX,
+            vec![vec![0.0, 1.0, 0.0]],
+        );
+        binding.modes = [InterpolateMode::Cubic; 2];
+
+        // The spline passes through every keyframe.
+        for (x, expected) in [(0.0, 0.0), (0.5, 1.0), (1.0, 0.0)] {
+            handle.set(x);
+            assert_eq!(binding.value(), expected);
+        }
+
+        // Between keyframes it overshoots the straight line towards the peak.
+        handle.set(0.25);
+        assert_eq!(binding.value(), 0.5625);
+        handle.set(0.75);
+        assert_eq!(binding.value(), 0.5625);
+    }
+
+    #[test]
+    fn test_stepped_and_bezier_bindings() {
+        let handle = param_1d(&[0.0, 0.5, 1.0]);
+        let [stepped, bezier] = [InterpolateMode::Stepped, InterpolateMode::Bezier].map(|mode| {
+            let mut binding = binding(
+                ParamHandle::Param1D(handle.clone()),
+                ParamTarget::TranslationX,
+                vec![vec![0.0, 4.0, 8.0]],
+            );
+            binding.modes = [mode; 2];
+            binding
+        });
+
+        // Stepped bindings hold the value of the preceding keyframe, bezier bindings ease between
+        // keyframes and hit them exactly.
+        for (x, step, ease) in [
+            (0.0, 0.0, 0.0),
+            (0.125, 0.0, 0.625),
+            (0.25, 0.0, 2.0),
+            (0.49, 0.0, 3.9952),
+            (0.5, 4.0, 4.0),
+            (0.875, 4.0, 7.375),
+            (1.0, 8.0, 8.0),
+        ] {
+            handle.set(x);
+            assert_eq!(stepped.value(), step, "{x}");
+            assert!(
+                (bezier.value() - ease).abs() < 1e-3,
+                "{x}: {}",
+                bezier.value()
+            );
+        }
+    }
+
+    #[test]
+    fn test_edge_continuity() {
+        let handle = param_2d(&[0.0, 0.5, 1.0], &[0.0, 1.0]);
+        let binding = binding(
+            ParamHandle::Param2D(handle.clone()),
+            ParamTarget::TranslationX,
+            vec![vec![0.0, 1.0, 2.0], vec![4.0, 5.0, 6.0]],
+        );
+
+        handle.set(1.0, 1.0);
+        assert_eq!(binding.value(), 6.0);
+        handle.set(100.0, 100.0);
+        assert_eq!(binding.value(), 6.0);
+
+        handle.set(0.99999, 0.99999);
+        assert!((binding.value() - 6.0).abs() < 1e-3, "{}", binding.value());
+        handle.set(1.0, 0.99999);
+        assert!((binding.value() - 6.0).abs() < 1e-3, "{}", binding.value());
+    }
+
+    #[test]
+    fn test_per_axis_modes() {
+        let handle = param_2d(&[0.0, 1.0], &[0.0, 1.0]);
+        let mut binding = binding(
+            ParamHandle::Param2D(handle.clone()),
+            ParamTarget::TranslationX,
+            vec![vec![0.0, 10.0], vec![100.0, 110.0]],
+        );
+        binding.modes = [InterpolateMode::Nearest, InterpolateMode::Linear];
+
+        // X snaps to the closest column, Y blends between the rows.
+        handle.set(0.25, 0.5);
+        assert_eq!(binding.value(), 50.0);
+        handle.set(0.75, 0.5);
+        assert_eq!(binding.value(), 60.0);
+        handle.set(0.5, 0.25);
+        assert_eq!(binding.value(), 35.0);
+        handle.set(1.0, 1.0);
+        assert_eq!(binding.value(), 110.0);
+    }
+
+    #[test]
+    fn test_shortest_arc_rotation() {
+        let handle = param_1d(&[0.0, 1.0]);
+        let (from, to) = (170f32.to_radians(), -170f32.to_radians());
+        let mut binding = binding(
+            ParamHandle::Param1D(handle.clone()),
+            ParamTarget::RotationZ,
+            vec![vec![from, to]],
+        );
+        binding.wrap_angles = true;
+
+        // Passes through 180° instead of 0°.
+        let degrees = |binding: &ParamBinding| binding.value().to_degrees();
+        handle.set(0.25);
+        assert!((degrees(&binding) - 175.0).abs() < 1e-3);
+        handle.set(0.5);
+        assert!((degrees(&binding).abs() - 180.0).abs() < 1e-3);
+        handle.set(0.75);
+        assert!((degrees(&binding) - 185.0).abs() < 1e-3);
+        handle.set(1.0);
+        assert!((wrap_angle(binding.value() - to)).abs() < 1e-5);
+
+        binding.wrap_angles = false;
+        handle.set(0.5);
+        assert!(binding.value().abs() < 1e-5);
+    }
+
+    #[test]
+    fn test_axis_count() {
+        let lower = |is_vec2: bool, axis_points: serde_json::Value| {
+            let param: rhino2d_io::Param = serde_json::from_value(serde_json::json!({
+                "uuid": 1, "name": "p", "is_vec2": is_vec2,
+                "min": [0.0, 0.0], "max": [1.0, 1.0], "defaults": [0.0, 0.0],
+                "axis_points": axis_points, "bindings": [],
+            }))
+            .unwrap();
+            ParamMap::lower(&[param], &EngineConfig::default()).map(drop)
+        };
+
+        lower(false, serde_json::json!([[0.0, 1.0], [0.0]])).unwrap();
+        lower(true, serde_json::json!([[0.0, 1.0], [0.0, 0.5, 1.0]])).unwrap();
+
+        // 2D parameters missing their second axis.
+        lower(true, serde_json::json!([[0.0, 1.0]])).unwrap_err();
+        lower(true, serde_json::json!([[0.0, 1.0], [0.0]])).unwrap_err();
+        // 1D parameters with a second axis.
+        lower(false, serde_json::json!([[0.0, 1.0], [0.0, 1.0]])).unwrap_err();
+        lower(false, serde_json::json!([[0.0, 1.0]])).unwrap_err();
+        lower(false, serde_json::json!([[0.0, 1.0], [0.0], [0.0]])).unwrap_err();
+    }
+
+    #[test]
+    fn test_fill_unset() {
+        let axis_points = [vec![0.0, 0.25, 1.0], vec![0.0, 0.5, 1.0]];
+        let mut grid = vec![
+            vec![0.0, -1.0, 4.0],
+            vec![-1.0, -1.0, -1.0],
+            vec![-1.0, 2.0, -1.0],
+        ];
+        let is_set = [
+            vec![true, false, true],
+            vec![false, false, false],
+            vec![false, true, false],
+        ];
+        fill_unset(&mut grid, &is_set, &axis_points);
+        assert_eq!(
+            grid,
+            [
+                vec![0.0, 1.0, 4.0],
+                vec![1.0, 1.5, 3.0],
+                vec![2.0, 2.0, 2.0],
+            ]
+        );
+
+        // Nothing set, or no flags at all.
+        let mut grid = vec![vec![1.0, 2.0]];
+        fill_unset(&mut grid, &[vec![false, false]], &axis_points);
+        fill_unset(&mut grid, &[], &axis_points);
+        assert_eq!(grid, [vec![1.0, 2.0]]);
+    }
+
+    #[test]
+    fn test_single_point_axis() {
+        let axis = axis(&[0.0]);
+        for value in [0.0, 0.5, 1.0] {
+            assert_eq!(
+                axis.interp(value),
+                Interp {
+                    start_index: 0,
+                    dist: 0.0
+                }
+            );
+        }
+    }
+
     #[test]
     fn test_interp_lookup() {
         assert_eq!(
@@ -417,5 +1637,14 @@ mod tests {
             .lookup(&[0.0, 1.0, 2.0]),
             1.75
         );
+        // `start_index` at the last value never blends with it
+        assert_eq!(
+            Interp {
+                start_index: 1,
+                dist: 0.5
+            }
+            .lookup(&[0.0, 1.0]),
+            1.0
+        );
     }
 }
@@ -0,0 +1,320 @@
+//! Physics simulation.
+
+use std::f32::consts::PI;
+use std::time::Duration;
+
+use rhino2d_io::node::ParamMapMode;
+use rhino2d_io::Vec2;
+
+/// Longest time step the simulation is advanced by at once, in seconds.
+///
+/// Longer updates are split into multiple steps to keep the simulation stable.
+const MAX_STEP: f32 = 1.0 / 120.0;
+
+/// Longest step the simulation can be advanced by.
+///
+/// [`PuppetEngine::set_physics_timestep`][crate::PuppetEngine::set_physics_timestep] clamps the
+/// timestep to this, so every step is simulated in full. Long pauses (eg. after the application
+/// was suspended) are skipped by the engine instead.
+pub(crate) const MAX_TIMESTEP: Duration = Duration::from_millis(100);
+
+/// Model-wide physics properties.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct World {
+    /// Gravity, in pixels per second squared.
+    gravity: f32,
+}
+
+impl World {
+    pub(crate) fn from_io(io: &rhino2d_io::Physics) -> Self {
+        Self {
+            gravity: io.gravity() * io.pixels_per_meter(),
+        }
+    }
+}
+
+/// Shortest length a spring pendulum's rod is compressed to, in pixels.
+///
+/// Keeps the bob from passing through the anchor, where its angle is undefined.
+const MIN_SPRING_LENGTH: f32 = 0.01;
+
+/// A pendulum with a massless rod, swinging from an anchor point.
+///
+/// When the anchor moves, the bob at the end of the rod stays in place, which makes the pendulum
+/// swing.
+#[derive(Debug)]
+pub(crate) struct Pendulum {
+    /// Length of the rod at rest, in pixels.
+    length: f32,
+    /// Gravity, relative to the gravity of the [`World`].
+    gravity: f32,
+    /// Damping of the swing, relative to critical damping.
+    damping: f32,
+    /// Makes the rod stretchable, or `None` if it is rigid.
+    spring: Option<Spring>,
+
+    /// Position of the bob, or `None` if the simulation hasn't run yet.
+    bob: Option<Vec2>,
+    /// Angular velocity of the rod, in radians per second.
+    velocity: f32,
+    /// Rate at which the rod stretches, in pixels per second.
+    length_velocity: f32,
+}
+
+/// The spring a spring pendulum's rod is made of.
+#[derive(Debug, Clone, Copy)]
+struct Spring {
+    /// Natural frequency of the spring's oscillation, in Hz.
+    frequency: f32,
+    /// Damping of the oscillation, relative to critical damping.
+    damping: f32,
+}
+
+impl Pendulum {
+    pub(crate) fn new(length: f32, gravity: f32, damping: f32) -> Self {
+        Self {
+            length,
+            gravity,
+            damping,
+            spring: None,
+            bob: None,
+            velocity: 0.0,
+            length_velocity: 0.0,
+        }
+    }
+
+    /// Creates a pendulum whose rod is a spring that oscillates with `frequency` (in Hz).
+    ///
+    /// The spring is stretched to `length` when the pendulum hangs at rest.
+    pub(crate) fn spring(
+        length: f32,
+        gravity: f32,
+        damping: f32,
+        frequency: f32,
+        length_damping: f32,
+    ) -> Self {
+        Self {
+            spring: Some(Spring {
+                frequency,
+                damping: length_damping,
+            }),
+            ..Self::new(length, gravity, damping)
+        }
+    }
+
+    /// Advances the simulation by `delta`, with the pendulum hanging from `anchor`.
+    pub(crate) fn step(&mut self, world: &World, anchor: Vec2, delta: Duration) {
+        let length = self.length;
+        let bob = self.bob.get_or_insert([anchor[0], anchor[1] + length]);
+        if delta.is_zero() {
+            return;
+        }
+
+        // The angle of the rod, 0 when hanging straight down (Y points down).
+        let [x, y] = [bob[0] - anchor[0], bob[1] - anchor[1]];
+        let mut angle = x.atan2(y);
+        let mut rod = match self.spring {
+            Some(_) => x.hypot(y),
+            None => length,
+        };
+
+        let gravity = self.gravity * world.gravity;
+        let ratio = finite_or_zero(gravity / length).max(0.0);
+        let critical_damping = 2.0 * ratio.sqrt();
+
+        let delta = delta.as_secs_f32();
+        let steps = (delta / MAX_STEP).ceil().max(1.0);
+        let h = delta / steps;
+
+        // Angular frequency and damping coefficient of the spring. Explicit integration of a stiff
+        // spring diverges, so both are limited to what the step size can integrate stably.
+        let (omega, spring_damping) = match self.spring {
+            Some(spring) => {
+                let omega = finite_or_zero(2.0 * PI * spring.frequency).clamp(0.0, 1.0 / h);
+                let damping = (2.0 * omega * spring.damping.max(0.0)).min(1.0 / h);
+                (omega, damping)
+            }
+            None => (0.0, 0.0),
+        };
+
+        for _ in 0..steps as u32 {
+            // Semi-implicit Euler.
+            if self.spring.is_some() {
+                // The spring supports the weight of the bob at rest, so gravity only stretches it
+                // beyond `length` when the pendulum swings.
+                let accel = rod * self.velocity * self.velocity
+                    + finite_or_zero(gravity) * (angle.cos() - 1.0)
+                    - omega * omega * (rod - length)
+                    - spring_damping * self.length_velocity;
+                self.length_velocity += accel * h;
+                rod = (rod + self.length_velocity * h).max(MIN_SPRING_LENGTH);
+
+                // The Coriolis term conserves angular momentum as the rod stretches.
+                let accel = -finite_or_zero(gravity / rod) * angle.sin()
+                    - 2.0 * self.length_velocity * self.velocity / rod
+                    - self.velocity * self.damping * critical_damping;
+                self.velocity += accel * h;
+            } else {
+                let accel = -ratio * angle.sin() - self.velocity * self.damping * critical_damping;
+                self.velocity += accel * h;
+            }
+            angle += self.velocity * h;
+        }
+
+        *bob = [anchor[0] + rod * angle.sin(), anchor[1] + rod * angle.cos()];
+    }
+
+    /// Returns the angle of the rod in radians, and its length, when hanging from `anchor`.
+    ///
+    /// The angle is 0 when the pendulum hangs straight down, and positive when the bob is to the
+    /// right of the anchor.
+    pub(crate) fn rod(&self, anchor: Vec2) -> (f32, f32) {
+        match self.bob {
+            Some(bob) => {
+                let [x, y] = [bob[0] - anchor[0], bob[1] - anchor[1]];
+                (x.atan2(y), x.hypot(y))
+            }
+            None => (0.0, self.length),
+        }
+    }
+
+    /// Returns the angular velocity of the rod, in radians per second.
+    pub(crate) fn velocity(&self) -> f32 {
+        self.velocity
+    }
+
+    /// Returns the rate at which the rod stretches, in pixels per second.
+    ///
+    /// This is always 0 for rigid pendulums.
+    pub(crate) fn length_velocity(&self) -> f32 {
+        self.length_velocity
+    }
+
+    /// Returns the pendulum to its initial state, hanging straight down at rest.
+    pub(crate) fn reset(&mut self) {
+        self.bob = None;
+        self.velocity = 0.0;
+        self.length_velocity = 0.0;
+    }
+
+    /// Computes the parameter value the pendulum hanging from `anchor` outputs.
+    ///
+    /// At rest, [`ParamMapMode::AngleLength`] outputs `[0.0, 1.0]` and [`ParamMapMode::XY`] outputs
+    /// `[0.0, 0.0]`.
+    pub(crate) fn output(&self, anchor: Vec2, mode: ParamMapMode) -> Vec2 {
+        // Position of the bob relative to the anchor, in units of the rod length.
+        let [x, y] = match self.bob {
+            Some(bob) if self.length > 0.0 => [
+                (bob[0] - anchor[0]) / self.length,
+                (bob[1] - anchor[1]) / self.length,
+            ],
+            _ => [0.0, 1.0],
+        };
+
+        match mode {
+            ParamMapMode::AngleLength => [(-x).atan2(y) / PI, x.hypot(y)],
+            // Y points up in parameter space.
+            ParamMapMode::XY => [x, 1.0 - y],
+        }
+    }
+}
+
+fn finite_or_zero(value: f32) -> f32 {
+    if value.is_finite() {
+        value
+    } else {
+        0.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FRAME: Duration = Duration::from_millis(16);
+
+    #[test]
+    fn pendulum_swings_back_to_rest() {
+        let world = World {
+            gravity: 9.8 * 100.0,
+        };
+        let mut pendulum = Pendulum::new(50.0, 1.0, 0.5);
+
+        pendulum.step(&world, [0.0, 0.0], FRAME);
+        assert_eq!(
+            pendulum.output([0.0, 0.0], ParamMapMode::AngleLength),
+            [0.0, 1.0]
+        );
+        assert_eq!(pendulum.output([0.0, 0.0], ParamMapMode::XY), [0.0, 0.0]);
+
+        // Moving the anchor to the left leaves the bob behind, to the right.
+        let anchor = [-20.0, 0.0];
+        pendulum.step(&world, anchor, FRAME);
+        let [angle, length] = pendulum.output(anchor, ParamMapMode::AngleLength);
+        assert!(angle < -0.1, "{angle}");
+        assert!((length - 1.0).abs() < 1e-5, "{length}");
+
+        let mut crossed = false;
+        for _ in 0..600 {
+            pendulum.step(&world, anchor, FRAME);
+            crossed |= pendulum.output(anchor, ParamMapMode::AngleLength)[0] > 0.0;
+        }
+        assert!(
+            crossed,
+            "underdamped pendulum should swing past its rest position"
+        );
+        let [x, y] = pendulum.output(anchor, ParamMapMode::XY);
+        assert!(x.abs() < 1e-3 && y.abs() < 1e-3, "{x} {y}");
+    }
+
+    #[test]
+    fn spring_returns_to_rest_length() {
+        let world = World {
+            gravity: 9.8 * 100.0,
+        };
+        let mut pendulum = Pendulum::spring(50.0, 1.0, 0.5, 2.0, 0.3);
+
+        pendulum.step(&world, [0.0, 0.0], FRAME);
+        assert_eq!(
+            pendulum.output([0.0, 0.0], ParamMapMode::AngleLength),
+            [0.0, 1.0]
+        );
+
+        // Jerking the anchor up stretches the spring, which then bounces.
+        let anchor = [0.0, -20.0];
+        pendulum.step(&world, anchor, FRAME);
+        let (_, length) = pendulum.rod(anchor);
+        assert!(length > 60.0, "{length}");
+
+        let mut compressed = false;
+        for _ in 0..600 {
+            pendulum.step(&world, anchor, FRAME);
+            compressed |= pendulum.rod(anchor).1 < 50.0;
+        }
+        assert!(
+            compressed,
+            "underdamped spring should bounce past its rest length"
+        );
+        let (angle, length) = pendulum.rod(anchor);
+        assert!(angle.abs() < 1e-3, "{angle}");
+        assert!((length - 50.0).abs() < 1e-2, "{length}");
+        assert!(pendulum.length_velocity().abs() < 1e-2);
+    }
+
+    #[test]
+    fn stiff_spring_stays_stable() {
+        let world = World {
+            gravity: 9.8 * 100.0,
+        };
+        let mut pendulum = Pendulum::spring(50.0, 1.0, 0.5, 10_000.0, 0.0);
+
+        pendulum.step(&world, [0.0, 0.0], FRAME);
+        let anchor = [30.0, -30.0];
+        for _ in 0..600 {
+            pendulum.step(&world, anchor, Duration::from_millis(100));
+            let (angle, length) = pendulum.rod(anchor);
+            assert!(angle.is_finite(), "{angle}");
+            assert!(length.is_finite() && length < 200.0, "{length}");
+        }
+    }
+}
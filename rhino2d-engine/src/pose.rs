@@ -0,0 +1,28 @@
+//! JSON export of node transforms.
+
+use rhino2d_io::Uuid;
+use serde_json::{json, Map, Value};
+
+use crate::RenderCommand;
+
+/// Exports the transforms of `commands`, along with the node names returned by `name`.
+pub(crate) fn to_json<'a>(commands: &[RenderCommand], name: impl Fn(Uuid) -> &'a str) -> String {
+    let mut commands = commands.iter().collect::<Vec<_>>();
+    commands.sort_by_key(|cmd| cmd.node);
+
+    // Non-finite values are converted to `null` by `serde_json`.
+    let pose = commands
+        .into_iter()
+        .map(|cmd| {
+            let [x, y, _] = cmd.transform.translation();
+            let node = json!({
+                "name": name(cmd.node),
+                "position": [x, y],
+                "rotation": cmd.transform.rotation_euler()[2],
+                "scale": cmd.transform.scale(),
+            });
+            (cmd.node.to_string(), node)
+        })
+        .collect::<Map<String, Value>>();
+    Value::Object(pose).to_string()
+}
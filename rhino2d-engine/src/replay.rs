@@ -0,0 +1,188 @@
+//! Recording and replaying of render commands.
+//!
+//! A replay file starts with a short header, followed by one MessagePack-encoded frame for every
+//! call to [`RenderRecorder::record`]. Frames are self-delimiting, so a recording can be streamed
+//! and replayed while it is still being written.
+
+use std::io::{self, BufRead, Write};
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use crate::RenderCommand;
+
+/// Identifies replay files, and the version of their format.
+const MAGIC: &[u8; 8] = b"RHINORP1";
+
+/// Writes the render commands of successive frames to a replay file.
+///
+/// The commands can be played back with [`RenderReplay`], without a [`PuppetEngine`]. Since
+/// [`RenderCommand::mesh`] isn't recorded, the renderer needs access to the puppet's meshes.
+///
+/// [`PuppetEngine`]: crate::PuppetEngine
+pub struct RenderRecorder<W: Write> {
+    writer: W,
+}
+
+impl<W: Write> RenderRecorder<W> {
+    /// Creates a recorder that writes to `writer`, and writes the file header.
+    pub fn new(mut writer: W) -> io::Result<Self> {
+        writer.write_all(MAGIC)?;
+        Ok(Self { writer })
+    }
+
+    /// Appends a frame with the given `commands`, rendered at `time` since the start of the
+    /// recording.
+    pub fn record(&mut self, time: Duration, commands: &[RenderCommand]) -> io::Result<()> {
+        rmp_serde::encode::write(&mut self.writer, &FrameData { time, commands })
+            .map_err(io::Error::other)
+    }
+
+    /// Flushes the underlying writer.
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+
+    /// Returns the underlying writer.
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
+
+#[derive(Serialize)]
+struct FrameData<'a> {
+    time: Duration,
+    commands: &'a [RenderCommand],
+}
+
+/// A recorded frame, yielded by [`RenderReplay`].
+#[derive(Clone, Deserialize)]
+pub struct ReplayFrame {
+    time: Duration,
+    commands: Vec<RenderCommand>,
+}
+
+impl ReplayFrame {
+    /// Returns the time the frame was rendered at, since the start of the recording.
+    pub fn time(&self) -> Duration {
+        self.time
+    }
+
+    /// Returns the render commands of the frame, in the order they were recorded in.
+    pub fn commands(&self) -> &[RenderCommand] {
+        &self.commands
+    }
+
+    pub fn into_commands(self) -> Vec<RenderCommand> {
+        self.commands
+    }
+}
+
+/// Reads the frames of a replay file written by [`RenderRecorder`].
+///
+/// Iterating yields the frames in the order they were recorded in. Reading stops after the first
+/// error.
+pub struct RenderReplay<R: BufRead> {
+    reader: R,
+    failed: bool,
+}
+
+impl<R: BufRead> RenderReplay<R> {
+    /// Creates a replay that reads from `reader`, and checks the file header.
+    pub fn new(mut reader: R) -> io::Result<Self> {
+        let mut magic = [0; MAGIC.len()];
+        reader.read_exact(&mut magic)?;
+        if &magic != MAGIC {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "not a render replay file",
+            ));
+        }
+        Ok(Self {
+            reader,
+            failed: false,
+        })
+    }
+
+    fn read_frame(&mut self) -> io::Result<Option<ReplayFrame>> {
+        if self.reader.fill_buf()?.is_empty() {
+            return Ok(None);
+        }
+        rmp_serde::from_read(&mut self.reader)
+            .map(Some)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+}
+
+impl<R: BufRead> Iterator for RenderReplay<R> {
+    type Item = io::Result<ReplayFrame>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.failed {
+            return None;
+        }
+        let result = self.read_frame();
+        self.failed = result.is_err();
+        result.transpose()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::PuppetEngine;
+    use rhino2d_io::testutil::puppet;
+
+    use super::*;
+
+    #[test]
+    fn record_and_replay() {
+        let puppet = puppet(
+            r#"{ "type": "Node", "uuid": 1, "children": [
+                { "type": "Part", "uuid": 2, "tint": [1.0, 0.5, 0.25] },
+                { "type": "Part", "uuid": 3, "zsort": 1.0 }
+            ] }"#,
+            r#"[{
+                "uuid": 10, "name": "Sway",
+                "bindings": [
+                    { "node": 2, "param_name": "deform", "values": [[
+                        [[0, 0], [0, 0], [0, 0]],
+                        [[4, 0], [0, 4], [4, 4]]
+                    ]] },
+                    { "node": 3, "param_name": "transform.t.x", "values": [[0, 8]] }
+                ]
+            }]"#,
+        );
+        let mut engine = PuppetEngine::new(&puppet).unwrap();
+
+        let mut recorder = RenderRecorder::new(Vec::new()).unwrap();
+        let mut recorded = Vec::new();
+        for frame in 0..3 {
+            let time = Duration::from_millis(16 * frame);
+            engine
+                .param("Sway")
+                .unwrap()
+                .set(frame as f32 / 2.0)
+                .unwrap();
+            let commands = engine.update(Duration::from_millis(16));
+            recorder.record(time, commands).unwrap();
+            recorded.push((time, serde_json::to_value(commands).unwrap()));
+        }
+        let file = recorder.into_inner();
+
+        let frames = RenderReplay::new(&file[..])
+            .unwrap()
+            .collect::<io::Result<Vec<_>>>()
+            .unwrap();
+        assert_eq!(frames.len(), 3);
+        for (frame, (time, commands)) in frames.iter().zip(&recorded) {
+            assert_eq!(frame.time(), *time);
+            assert_eq!(frame.commands().len(), 3);
+            assert_eq!(serde_json::to_value(frame.commands()).unwrap(), *commands);
+        }
+
+        assert!(RenderReplay::new(&b"RHINORP0"[..]).is_err());
+        let mut truncated = RenderReplay::new(&file[..file.len() - 1]).unwrap();
+        assert!(truncated.nth(2).unwrap().is_err());
+        assert!(truncated.next().is_none());
+    }
+}
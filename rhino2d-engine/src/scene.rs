@@ -0,0 +1,238 @@
+//! Composition of multiple puppets.
+
+use std::time::Duration;
+
+use crate::{node::Transform, ord::TotalF32, sort_key, PuppetEngine, RenderCommand};
+
+/// A group of puppets rendered together, eg. several characters sharing a stage.
+///
+/// Every puppet keeps its own [`PuppetEngine`], and is placed in the scene with a world transform.
+/// [`Scene::update`] updates all puppets and merges their render commands into a single list,
+/// sorted by Z-Sort across puppets, so that parts of different puppets can be interleaved.
+pub struct Scene {
+    puppets: Vec<ScenePuppet>,
+    /// Sort key, puppet index, and range of the top-level commands of every puppet. The commands
+    /// inside a composite stay with the composite.
+    runs: Vec<(TotalF32, u32, u32, u32)>,
+    /// Index of the first entry of every puppet in `transforms`.
+    offsets: Vec<u32>,
+    transforms: Vec<[f32; 16]>,
+}
+
+struct ScenePuppet {
+    engine: PuppetEngine,
+    transform: Transform,
+}
+
+/// A render command of a [`Scene`], as returned by [`Scene::update`].
+#[derive(Clone, Copy)]
+pub struct SceneCommand<'a> {
+    puppet: usize,
+    command: &'a RenderCommand,
+    transform_index: usize,
+}
+
+impl<'a> SceneCommand<'a> {
+    /// Returns the index of the puppet the command belongs to.
+    ///
+    /// Node UUIDs are only unique within a puppet.
+    pub fn puppet(&self) -> usize {
+        self.puppet
+    }
+
+    /// Returns the command, as produced by the [`PuppetEngine`] of its puppet.
+    ///
+    /// The transform of the command does *not* include the world transform of the puppet, see
+    /// [`SceneCommand::transform_index`] for that.
+    pub fn command(&self) -> &'a RenderCommand {
+        self.command
+    }
+
+    /// Returns the index of the command's global transform in [`Scene::transform_buffer`].
+    pub fn transform_index(&self) -> usize {
+        self.transform_index
+    }
+}
+
+impl Scene {
+    pub fn new() -> Self {
+        Self {
+            puppets: Vec::new(),
+            runs: Vec::new(),
+            offsets: Vec::new(),
+            transforms: Vec::new(),
+        }
+    }
+
+    /// Adds a puppet to the scene, placed with the world transform `transform`.
+    ///
+    /// Returns the index of the puppet in the scene.
+    pub fn add(&mut self, engine: PuppetEngine, transform: Transform) -> usize {
+        self.puppets.push(ScenePuppet { engine, transform });
+        self.puppets.len() - 1
+    }
+
+    /// Returns the number of puppets in the scene.
+    pub fn len(&self) -> usize {
+        self.puppets.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.puppets.is_empty()
+    }
+
+    /// Returns the engine of the puppet at `index`.
+    pub fn puppet(&self, index: usize) -> Option<&PuppetEngine> {
+        self.puppets.get(index).map(|puppet| &puppet.engine)
+    }
+
+    /// Returns the engine of the puppet at `index`, eg. to set its parameters.
+    pub fn puppet_mut(&mut self, index: usize) -> Option<&mut PuppetEngine> {
+        self.puppets.get_mut(index).map(|puppet| &mut puppet.engine)
+    }
+
+    /// Returns the world transform of the puppet at `index`.
+    pub fn transform(&self, index: usize) -> Option<Transform> {
+        self.puppets.get(index).map(|puppet| puppet.transform)
+    }
+
+    /// Sets the world transform of the puppet at `index`.
+    ///
+    /// Returns `false` if there is no puppet at `index`.
+    pub fn set_transform(&mut self, index: usize, transform: Transform) -> bool {
+        match self.puppets.get_mut(index) {
+            Some(puppet) => {
+                puppet.transform = transform;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Updates every puppet, and sorts the render commands of the whole scene.
+    ///
+    /// Use [`Scene::commands`] to get the sorted commands afterwards.
+    pub fn update(&mut self, delta: Duration) {
+        self.runs.clear();
+        self.offsets.clear();
+        self.transforms.clear();
+        for (index, puppet) in self.puppets.iter_mut().enumerate() {
+            let offset = self.transforms.len();
+            self.offsets.push(offset as u32);
+            let commands = puppet.engine.update(delta);
+            self.transforms.resize(offset + commands.len(), [0.0; 16]);
+            let mut i = 0;
+            while i < commands.len() {
+                let len = 1 + commands[i].composite_len().unwrap_or(0);
+                let key = sort_key(commands[i].zsort());
+                self.runs.push((key, index as u32, i as u32, len as u32));
+                i += len;
+            }
+            for cmd in commands {
+                self.transforms[offset + cmd.transform_index()] =
+                    (puppet.transform * cmd.transform()).to_array();
+            }
+        }
+        // Sort keys can repeat, but every `(sort_key, puppet, start, len)` tuple is unique, since
+        // no two runs share a puppet and start index. So an unstable sort still yields a
+        // deterministic order: ties within a puppet keep the puppet's own order, and ties between
+        // puppets are broken by puppet index.
+        self.runs.sort_unstable();
+    }
+
+    /// Returns the render commands of the whole scene, as of the last [`Scene::update`].
+    ///
+    /// The commands are sorted by Z-Sort value like the ones of a single [`PuppetEngine`].
+    /// Commands with the same Z-Sort value are ordered by the index of their puppet. The
+    /// commands are borrowed from the puppets' engines; their global transforms, including the
+    /// world transform of their puppet, are in [`Scene::transform_buffer`].
+    pub fn commands(&self) -> impl Iterator<Item = SceneCommand<'_>> + '_ {
+        let puppets = &self.puppets;
+        let offsets = &self.offsets;
+        self.runs.iter().flat_map(move |&(_, puppet, start, len)| {
+            let puppet = puppet as usize;
+            let commands = &puppets[puppet].engine.render_buffer.commands;
+            let offset = offsets[puppet] as usize;
+            commands[start as usize..(start + len) as usize]
+                .iter()
+                .map(move |command| SceneCommand {
+                    puppet,
+                    command,
+                    transform_index: offset + command.transform_index(),
+                })
+        })
+    }
+
+    /// Returns the global transforms of the commands returned by the last [`Scene::update`],
+    /// indexed by [`SceneCommand::transform_index`].
+    pub fn transform_buffer(&self) -> &[[f32; 16]] {
+        &self.transforms
+    }
+}
+
+impl Default for Scene {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rhino2d_io::testutil::puppet;
+
+    use super::*;
+
+    #[test]
+    fn interleaved_zsort() {
+        let front = puppet(
+            r#"{
+                "type": "Node", "uuid": 1, "zsort": 0.0,
+                "children": [{ "type": "Part", "uuid": 2, "zsort": 2.0 }]
+            }"#,
+            "[]",
+        );
+        let back = puppet(
+            r#"{
+                "type": "Node", "uuid": 1, "zsort": 1.0,
+                "children": [{ "type": "Part", "uuid": 2, "zsort": -1.0 }]
+            }"#,
+            "[]",
+        );
+
+        let mut scene = Scene::new();
+        let mut offset = [0.0; 16];
+        for i in [0, 5, 10, 15] {
+            offset[i] = 1.0;
+        }
+        offset[12] = 100.0;
+        scene.add(PuppetEngine::new(&front).unwrap(), Transform::identity());
+        let back_index = scene.add(
+            PuppetEngine::new(&back).unwrap(),
+            Transform::from_column_major_data(&offset),
+        );
+        assert_eq!(back_index, 1);
+        assert_eq!(scene.len(), 2);
+
+        scene.update(Duration::ZERO);
+        let commands = scene.commands().collect::<Vec<_>>();
+        let order = commands
+            .iter()
+            .map(|cmd| {
+                let inner = cmd.command();
+                (cmd.puppet(), inner.node().raw(), inner.zsort())
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(order, [(0, 2, 2.0), (1, 1, 1.0), (0, 1, 0.0), (1, 2, -1.0)]);
+
+        // Global transforms include the world transform of their puppet.
+        for cmd in &commands {
+            let x = scene.transform_buffer()[cmd.transform_index()][12];
+            let expected = if cmd.puppet() == back_index {
+                100.0
+            } else {
+                0.0
+            };
+            assert_eq!(x, expected);
+        }
+    }
+}
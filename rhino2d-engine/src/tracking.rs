@@ -0,0 +1,247 @@
+//! Mapping of face tracking data to conventionally named parameters.
+
+use crate::param::{ParamId, ParamMap};
+
+/// Face and body tracking data, as provided by VTuber tracking software.
+///
+/// Every value is optional, and only tracked values are applied to a puppet by
+/// [`PuppetEngine::apply_tracking`]. Rotations, gaze directions and eyebrow heights are normalized
+/// to the range -1.0 to 1.0, and all other values to the range 0.0 to 1.0.
+///
+/// The values drive the parameters that Inochi2D models conventionally use for them:
+///
+/// | Values                     | Parameter             |
+/// |----------------------------|-----------------------|
+/// | head yaw (X), pitch (Y)    | `Head:: Yaw-Pitch`    |
+/// | head roll                  | `Head:: Roll`         |
+/// | body yaw (X), pitch (Y)    | `Body:: Yaw-Pitch`    |
+/// | body roll                  | `Body:: Roll`         |
+/// | left eye openness          | `Eye:: Left:: Blink`  |
+/// | right eye openness         | `Eye:: Right:: Blink` |
+/// | left eye gaze X, Y         | `Eye:: Left:: X-Y`    |
+/// | right eye gaze X, Y        | `Eye:: Right:: X-Y`   |
+/// | left eyebrow height        | `Eyebrow:: Left:: Y`  |
+/// | right eyebrow height       | `Eyebrow:: Right:: Y` |
+/// | mouth openness             | `Mouth:: Open`        |
+/// | smile                      | `Mouth:: Smile`       |
+/// | breathing                  | `Breath`              |
+///
+/// Blink parameters are 1.0 when the eye is closed, so they are set to 1.0 minus the openness.
+///
+/// This covers the values that Inochi2D models conventionally have parameters for. Trackers
+/// that report the larger blendshape sets (eg. the 52 ARKit blendshapes, or 64 values including
+/// head position) also provide values like cheek puff, tongue, jaw and nose movement, which
+/// don't have a conventional parameter name. They are left out, since there is nothing to route
+/// them to; set them by the model's own parameter names with [`PuppetEngine::set_param`]
+/// instead. Head position is left out for the same reason.
+///
+/// [`PuppetEngine::set_param`]: crate::PuppetEngine::set_param
+///
+/// [`PuppetEngine::apply_tracking`]: crate::PuppetEngine::apply_tracking
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TrackingInput {
+    head_yaw: Option<f32>,
+    head_pitch: Option<f32>,
+    head_roll: Option<f32>,
+    body_yaw: Option<f32>,
+    body_pitch: Option<f32>,
+    body_roll: Option<f32>,
+    left_eye_open: Option<f32>,
+    right_eye_open: Option<f32>,
+    left_eye_x: Option<f32>,
+    left_eye_y: Option<f32>,
+    right_eye_x: Option<f32>,
+    right_eye_y: Option<f32>,
+    left_brow: Option<f32>,
+    right_brow: Option<f32>,
+    mouth_open: Option<f32>,
+    smile: Option<f32>,
+    breath: Option<f32>,
+}
+
+impl TrackingInput {
+    /// Creates an input without any tracked values.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn head_yaw(&self) -> Option<f32> {
+        self.head_yaw
+    }
+
+    /// Sets the head rotation around the vertical axis. Positive values turn to the right.
+    pub fn set_head_yaw(&mut self, yaw: f32) {
+        self.head_yaw = Some(yaw);
+    }
+
+    pub fn head_pitch(&self) -> Option<f32> {
+        self.head_pitch
+    }
+
+    /// Sets the head rotation around the horizontal axis. Positive values look up.
+    pub fn set_head_pitch(&mut self, pitch: f32) {
+        self.head_pitch = Some(pitch);
+    }
+
+    pub fn head_roll(&self) -> Option<f32> {
+        self.head_roll
+    }
+
+    /// Sets the tilt of the head to the side.
+    pub fn set_head_roll(&mut self, roll: f32) {
+        self.head_roll = Some(roll);
+    }
+
+    pub fn body_yaw(&self) -> Option<f32> {
+        self.body_yaw
+    }
+
+    pub fn set_body_yaw(&mut self, yaw: f32) {
+        self.body_yaw = Some(yaw);
+    }
+
+    pub fn body_pitch(&self) -> Option<f32> {
+        self.body_pitch
+    }
+
+    pub fn set_body_pitch(&mut self, pitch: f32) {
+        self.body_pitch = Some(pitch);
+    }
+
+    pub fn body_roll(&self) -> Option<f32> {
+        self.body_roll
+    }
+
+    pub fn set_body_roll(&mut self, roll: f32) {
+        self.body_roll = Some(roll);
+    }
+
+    pub fn left_eye_open(&self) -> Option<f32> {
+        self.left_eye_open
+    }
+
+    /// Sets how far the left eye is open, from 0.0 (closed) to 1.0 (open).
+    pub fn set_left_eye_open(&mut self, open: f32) {
+        self.left_eye_open = Some(open);
+    }
+
+    pub fn right_eye_open(&self) -> Option<f32> {
+        self.right_eye_open
+    }
+
+    /// Sets how far the right eye is open, from 0.0 (closed) to 1.0 (open).
+    pub fn set_right_eye_open(&mut self, open: f32) {
+        self.right_eye_open = Some(open);
+    }
+
+    pub fn left_eye_x(&self) -> Option<f32> {
+        self.left_eye_x
+    }
+
+    /// Sets the horizontal gaze direction of the left eye. Positive values look to the right.
+    pub fn set_left_eye_x(&mut self, x: f32) {
+        self.left_eye_x = Some(x);
+    }
+
+    pub fn left_eye_y(&self) -> Option<f32> {
+        self.left_eye_y
+    }
+
+    /// Sets the vertical gaze direction of the left eye. Positive values look up.
+    pub fn set_left_eye_y(&mut self, y: f32) {
+        self.left_eye_y = Some(y);
+    }
+
+    pub fn right_eye_x(&self) -> Option<f32> {
+        self.right_eye_x
+    }
+
+    /// Sets the horizontal gaze direction of the right eye. Positive values look to the right.
+    pub fn set_right_eye_x(&mut self, x: f32) {
+        self.right_eye_x = Some(x);
+    }
+
+    pub fn right_eye_y(&self) -> Option<f32> {
+        self.right_eye_y
+    }
+
+    /// Sets the vertical gaze direction of the right eye. Positive values look up.
+    pub fn set_right_eye_y(&mut self, y: f32) {
+        self.right_eye_y = Some(y);
+    }
+
+    pub fn left_brow(&self) -> Option<f32> {
+        self.left_brow
+    }
+
+    /// Sets the height of the left eyebrow. Positive values raise it.
+    pub fn set_left_brow(&mut self, height: f32) {
+        self.left_brow = Some(height);
+    }
+
+    pub fn right_brow(&self) -> Option<f32> {
+        self.right_brow
+    }
+
+    /// Sets the height of the right eyebrow. Positive values raise it.
+    pub fn set_right_brow(&mut self, height: f32) {
+        self.right_brow = Some(height);
+    }
+
+    pub fn mouth_open(&self) -> Option<f32> {
+        self.mouth_open
+    }
+
+    /// Sets how far the mouth is open, from 0.0 (closed) to 1.0 (open).
+    pub fn set_mouth_open(&mut self, open: f32) {
+        self.mouth_open = Some(open);
+    }
+
+    pub fn smile(&self) -> Option<f32> {
+        self.smile
+    }
+
+    /// Sets how much the mouth smiles, from 0.0 (neutral) to 1.0 (smiling).
+    pub fn set_smile(&mut self, smile: f32) {
+        self.smile = Some(smile);
+    }
+
+    pub fn breath(&self) -> Option<f32> {
+        self.breath
+    }
+
+    /// Sets the phase of breathing, from 0.0 (exhaled) to 1.0 (inhaled).
+    pub fn set_breath(&mut self, breath: f32) {
+        self.breath = Some(breath);
+    }
+}
+
+pub(crate) fn apply(params: &ParamMap, input: &TrackingInput) {
+    let blink = |open: Option<f32>| open.map(|open| 1.0 - open);
+    let mappings = [
+        ("Head:: Yaw-Pitch", input.head_yaw, input.head_pitch),
+        ("Head:: Roll", input.head_roll, None),
+        ("Body:: Yaw-Pitch", input.body_yaw, input.body_pitch),
+        ("Body:: Roll", input.body_roll, None),
+        ("Eye:: Left:: Blink", blink(input.left_eye_open), None),
+        ("Eye:: Right:: Blink", blink(input.right_eye_open), None),
+        ("Eye:: Left:: X-Y", input.left_eye_x, input.left_eye_y),
+        ("Eye:: Right:: X-Y", input.right_eye_x, input.right_eye_y),
+        ("Eyebrow:: Left:: Y", input.left_brow, None),
+        ("Eyebrow:: Right:: Y", input.right_brow, None),
+        ("Mouth:: Open", input.mouth_open, None),
+        ("Mouth:: Smile", input.smile, None),
+        ("Breath", input.breath, None),
+    ];
+
+    for (name, x, y) in mappings {
+        if x.is_none() && y.is_none() {
+            continue;
+        }
+        if let Some(handle) = params.get(ParamId::Name(name)) {
+            // Untracked axes keep their current value.
+            let [cur_x, cur_y] = handle.value();
+            handle.set([x.unwrap_or(cur_x), y.unwrap_or(cur_y)]);
+        }
+    }
+}
@@ -9,7 +9,7 @@ fn main() -> io::Result<()> {
         .parse_default_env()
         .init();
 
-    let path = match std::env::args_os().skip(1).next() {
+    let path = match std::env::args_os().nth(1) {
         Some(arg) => arg,
         None => {
             eprintln!("usage: info <path-to-model>");
@@ -0,0 +1,8 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use rhino2d_io::InochiPuppet;
+
+fuzz_target!(|data: &[u8]| {
+    let _ = InochiPuppet::from_read(&mut &*data);
+});
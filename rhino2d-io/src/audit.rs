@@ -0,0 +1,141 @@
+//! Detection of unused model content.
+
+use std::collections::HashSet;
+
+use crate::node::Node;
+use crate::{InochiPuppet, Uuid};
+
+/// Lists the content of a model that has no effect, for cleaning up models.
+///
+/// Returned by [`InochiPuppet::audit`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct AuditReport {
+    unused_textures: Vec<usize>,
+    unbound_params: Vec<Uuid>,
+    orphaned_nodes: Vec<Uuid>,
+}
+
+impl AuditReport {
+    /// Returns the indices of the textures that are neither used by a part nor the thumbnail.
+    pub fn unused_textures(&self) -> &[usize] {
+        &self.unused_textures
+    }
+
+    /// Returns the UUIDs of the parameters without any bindings.
+    pub fn unbound_params(&self) -> &[Uuid] {
+        &self.unbound_params
+    }
+
+    /// Returns the UUIDs of the nodes that have no effect on how the model is rendered.
+    ///
+    /// A node is orphaned if neither it nor any node below it is rendered or simulates physics,
+    /// and it isn't referenced by a parameter binding or mask. Disabled nodes are never rendered.
+    /// The nodes below an orphaned node are orphaned as well, and are listed after it. The root
+    /// node is never listed.
+    pub fn orphaned_nodes(&self) -> &[Uuid] {
+        &self.orphaned_nodes
+    }
+
+    /// Returns whether nothing unused was found.
+    pub fn is_empty(&self) -> bool {
+        self.unused_textures.is_empty()
+            && self.unbound_params.is_empty()
+            && self.orphaned_nodes.is_empty()
+    }
+}
+
+pub(crate) fn audit(puppet: &InochiPuppet) -> AuditReport {
+    let mut used_textures = HashSet::new();
+    let mut referenced = HashSet::new();
+    used_textures.extend(puppet.metadata().thumbnail_id());
+    let root = puppet.root_node();
+    for node in std::iter::once(root).chain(root.descendants()) {
+        if let Node::Part(part) = node {
+            used_textures.extend(part.textures().iter().copied());
+            referenced.extend(part.masked_by().iter().copied());
+        }
+    }
+    for param in puppet.params() {
+        referenced.extend(param.bindings().iter().map(|binding| binding.node()));
+    }
+
+    let mut orphaned_nodes = Vec::new();
+    for child in puppet.root_node().children() {
+        find_orphans(child, &referenced, &mut orphaned_nodes);
+    }
+
+    AuditReport {
+        unused_textures: (0..puppet.textures().len())
+            .filter(|&index| !used_textures.contains(&(index as u32)))
+            .collect(),
+        unbound_params: puppet
+            .params()
+            .iter()
+            .filter(|param| param.bindings().is_empty())
+            .map(|param| param.uuid())
+            .collect(),
+        orphaned_nodes,
+    }
+}
+
+/// Adds the orphaned nodes in the subtree of `node` to `out`, and returns whether `node` itself
+/// is orphaned.
+fn find_orphans(node: &Node, referenced: &HashSet<Uuid>, out: &mut Vec<Uuid>) -> bool {
+    let index = out.len();
+    let mut orphaned = true;
+    for child in node.children() {
+        orphaned &= find_orphans(child, referenced, out);
+    }
+
+    let effective = match node {
+        Node::Node(_) | Node::PathDeform(_) => false,
+        Node::Drawable(_) | Node::Part(_) | Node::Mask(_) | Node::Composite(_) => node.enabled(),
+        Node::SimplePhysics(_) => true,
+    };
+    orphaned &= !effective && !referenced.contains(&node.uuid());
+    if orphaned {
+        // List the node before its descendants.
+        out.insert(index, node.uuid());
+    }
+    orphaned
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::testutil::puppet;
+    use crate::{Texture, TextureEncoding};
+
+    #[test]
+    fn unused_content() {
+        let mut puppet = puppet(
+            r#"{ "type": "Node", "uuid": 1, "children": [
+                { "type": "Part", "uuid": 2, "textures": [1], "masked_by": [3] },
+                { "type": "Node", "uuid": 3 },
+                { "type": "Node", "uuid": 4, "children": [
+                    { "type": "Part", "uuid": 5, "enabled": false, "textures": [1] }
+                ] },
+                { "type": "Node", "uuid": 6, "children": [{ "type": "Part", "uuid": 7, "textures": [1] }] },
+                { "type": "Node", "uuid": 8 }
+            ] }"#,
+            r#"[
+                { "uuid": 10, "name": "Unbound" },
+                {
+                    "uuid": 11, "name": "Bound",
+                    "bindings": [{ "node": 8, "param_name": "transform.t.x", "values": [[0, 1]] }]
+                }
+            ]"#,
+        );
+        for _ in 0..2 {
+            puppet.push_texture(Texture::new(TextureEncoding::Png, vec![1, 2, 3]));
+        }
+
+        let report = puppet.audit();
+        assert_eq!(report.unused_textures(), [0]);
+        assert_eq!(report.unbound_params(), [crate::Uuid { raw: 10 }]);
+        assert_eq!(
+            report.orphaned_nodes(),
+            [crate::Uuid { raw: 4 }, crate::Uuid { raw: 5 }]
+        );
+        assert!(!report.is_empty());
+    }
+}
@@ -0,0 +1,86 @@
+//! Decoding of texture images.
+
+use std::io;
+
+use image::ImageFormat;
+
+use crate::{Texture, TextureEncoding};
+
+/// A texture decoded to 8-bit RGBA pixels, returned by [`Texture::decode`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DecodedTexture {
+    width: u32,
+    height: u32,
+    pixels: Vec<u8>,
+}
+
+impl DecodedTexture {
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    /// Returns the pixels, row by row from the top, with 4 bytes (red, green, blue, alpha) per
+    /// pixel.
+    ///
+    /// Colors are in the sRGB color space, and alpha is not premultiplied.
+    pub fn pixels(&self) -> &[u8] {
+        &self.pixels
+    }
+
+    pub fn into_pixels(self) -> Vec<u8> {
+        self.pixels
+    }
+}
+
+pub(crate) fn decode(texture: &Texture) -> io::Result<DecodedTexture> {
+    let format = match texture.encoding() {
+        TextureEncoding::Png => ImageFormat::Png,
+        TextureEncoding::Tga => ImageFormat::Tga,
+        unk => {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("{unk:?} textures can not be decoded yet"),
+            ))
+        }
+    };
+    let image = image::load_from_memory_with_format(texture.data(), format)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?
+        .into_rgba8();
+    Ok(DecodedTexture {
+        width: image.width(),
+        height: image.height(),
+        pixels: image.into_raw(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A 2x1 PNG with a red and a translucent blue pixel.
+    const PNG: &[u8] = &[
+        137, 80, 78, 71, 13, 10, 26, 10, 0, 0, 0, 13, 73, 72, 68, 82, 0, 0, 0, 2, 0, 0, 0, 1, 8, 6,
+        0, 0, 0, 244, 34, 127, 138, 0, 0, 0, 14, 73, 68, 65, 84, 120, 156, 99, 248, 207, 192, 0,
+        66, 13, 0, 15, 122, 3, 126, 119, 233, 127, 151, 0, 0, 0, 0, 73, 69, 78, 68, 174, 66, 96,
+        130,
+    ];
+
+    #[test]
+    fn decode_png() {
+        let decoded = Texture::new(TextureEncoding::Png, PNG.to_vec())
+            .decode()
+            .unwrap();
+        assert_eq!([decoded.width(), decoded.height()], [2, 1]);
+        assert_eq!(decoded.pixels(), [255, 0, 0, 255, 0, 0, 255, 128]);
+
+        // The data does not match the encoding.
+        assert!(Texture::new(TextureEncoding::Tga, PNG.to_vec())
+            .decode()
+            .is_err());
+        assert!(Texture::bc7(4, 4, vec![0; 16]).decode().is_err());
+    }
+}
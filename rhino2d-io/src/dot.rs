@@ -0,0 +1,91 @@
+//! Graphviz export of the node hierarchy.
+
+use std::collections::HashSet;
+use std::fmt::Write;
+
+use crate::node::Node;
+use crate::{InochiPuppet, Uuid};
+
+pub(crate) fn to_dot(puppet: &InochiPuppet) -> String {
+    let mut out = String::new();
+    out.push_str("digraph puppet {\n");
+    out.push_str("    node [shape=box];\n");
+    let params = puppet.params().iter().map(|param| param.uuid()).collect();
+    write_node(&mut out, puppet.root_node(), &params);
+
+    out.push_str("    subgraph cluster_params {\n");
+    out.push_str("        label=\"Parameters\";\n");
+    out.push_str("        node [shape=ellipse];\n");
+    for param in puppet.params() {
+        writeln!(
+            out,
+            "        p{} [label=\"{}\"];",
+            param.uuid(),
+            escape(param.name())
+        )
+        .unwrap();
+    }
+    out.push_str("    }\n");
+    for param in puppet.params() {
+        for binding in param.bindings() {
+            writeln!(
+                out,
+                "    p{} -> n{} [label=\"{}\"];",
+                param.uuid(),
+                binding.node(),
+                escape(binding.param_name())
+            )
+            .unwrap();
+        }
+    }
+
+    out.push_str("}\n");
+    out
+}
+
+fn write_node(out: &mut String, node: &Node, params: &HashSet<Uuid>) {
+    writeln!(
+        out,
+        "    n{} [label=\"{}\\n{}\"];",
+        node.uuid(),
+        escape(node.name()),
+        node.type_name()
+    )
+    .unwrap();
+
+    match node {
+        Node::Part(part) => {
+            for mask in part.masked_by() {
+                writeln!(
+                    out,
+                    "    n{} -> n{} [style=dashed, label=\"masks\"];",
+                    mask,
+                    node.uuid()
+                )
+                .unwrap();
+            }
+        }
+        // Physics nodes that are unbound, or bound to a parameter that does not exist, drive
+        // nothing.
+        Node::SimplePhysics(physics) if params.contains(&physics.param()) => {
+            writeln!(
+                out,
+                "    n{} -> p{} [style=dashed, label=\"drives\"];",
+                node.uuid(),
+                physics.param()
+            )
+            .unwrap();
+        }
+        _ => {}
+    }
+
+    for child in node.children() {
+        writeln!(out, "    n{} -> n{};", node.uuid(), child.uuid()).unwrap();
+        write_node(out, child, params);
+    }
+}
+
+/// Escapes `s` for use in a quoted DOT string.
+fn escape(s: &str) -> String {
+    s.replace('\\', "\\\\").replace('"', "\\\"")
+}
@@ -0,0 +1,125 @@
+//! Extraction of node subtrees into standalone puppets.
+
+use std::collections::{HashMap, HashSet};
+
+use serde::{de::DeserializeOwned, Serialize};
+
+use crate::{node::Node, InochiPuppet, JsonData, Texture, Uuid};
+
+pub(crate) fn extract_subtree(puppet: &InochiPuppet, root: Uuid) -> Option<InochiPuppet> {
+    let root_node = puppet.root_node();
+    let subtree = if root_node.uuid() == root {
+        root_node
+    } else {
+        root_node.find_by_uuid(root)?
+    };
+    let mut nodes: Node = deep_copy(subtree);
+
+    let mut uuids = HashSet::new();
+    let mut physics_params = HashSet::new();
+    nodes.visit_mut(&mut |node| {
+        uuids.insert(node.uuid());
+        if let Node::SimplePhysics(physics) = node {
+            physics_params.insert(physics.param());
+        }
+    });
+
+    // Keep the parameters that are bound to or driven by the subtree, with only the bindings of
+    // subtree nodes.
+    let mut params = Vec::new();
+    for param in puppet.params() {
+        let binds_subtree = param
+            .bindings()
+            .iter()
+            .any(|binding| uuids.contains(&binding.node()));
+        if !binds_subtree && !physics_params.contains(&param.uuid()) {
+            continue;
+        }
+
+        let bindings = param
+            .bindings()
+            .iter()
+            .filter(|binding| uuids.contains(&binding.node()))
+            .map(deep_copy)
+            .collect();
+        let mut param = deep_copy(param);
+        param.set_bindings(bindings);
+        params.push(param);
+    }
+
+    // Automations are kept if every parameter they animate was kept.
+    let param_names = params
+        .iter()
+        .map(|param| param.name())
+        .collect::<HashSet<_>>();
+    let automation = puppet
+        .automations()
+        .iter()
+        .filter(|automation| {
+            automation
+                .bindings()
+                .iter()
+                .all(|binding| param_names.contains(binding.param()))
+        })
+        .map(deep_copy)
+        .collect::<Vec<_>>();
+
+    // Copy the used textures, in the order of their first use, and drop masks outside the subtree.
+    // Invalid texture indices are dropped as well.
+    let mut remap = HashMap::new();
+    let mut textures = Vec::new();
+    nodes.visit_mut(&mut |node| {
+        if let Node::Part(part) = node {
+            let indices = part
+                .textures()
+                .iter()
+                .filter_map(|&index| {
+                    let tex = puppet.textures().get(index as usize)?;
+                    Some(*remap.entry(index).or_insert_with(|| {
+                        let mut copy = Texture::new(tex.encoding(), tex.data().to_vec());
+                        copy.set_dimensions(tex.dimensions());
+                        copy.set_filter(tex.filter());
+                        copy.set_wrap(tex.wrap());
+                        textures.push(copy);
+                        (textures.len() - 1) as u32
+                    }))
+                })
+                .collect();
+            part.set_textures(indices);
+
+            if !part.masked_by().is_empty() {
+                let masked_by = part
+                    .masked_by()
+                    .iter()
+                    .copied()
+                    .filter(|mask| uuids.contains(mask))
+                    .collect();
+                part.set_masked_by(Some(masked_by));
+            }
+        }
+    });
+
+    let mut meta = deep_copy(puppet.metadata());
+    let thumbnail = meta
+        .thumbnail_id()
+        .and_then(|index| remap.get(&index).copied());
+    meta.set_thumbnail_id(thumbnail);
+
+    Some(InochiPuppet {
+        data: JsonData {
+            meta,
+            physics: deep_copy(puppet.physics()),
+            nodes,
+            param: params,
+            automation: (!automation.is_empty()).then_some(automation),
+            extra: serde_json::Map::new(),
+        },
+        textures,
+        vendor_data: Vec::new(),
+    })
+}
+
+/// Copies a model entity by round-tripping it through its JSON representation.
+fn deep_copy<T: Serialize + DeserializeOwned>(value: &T) -> T {
+    serde_json::from_value(serde_json::to_value(value).unwrap()).unwrap()
+}
@@ -0,0 +1,120 @@
+//! Content hashing of models.
+
+use serde_json::{Number, Value};
+
+use crate::InochiPuppet;
+
+/// 64-bit FNV-1a, used because its output is fully specified and never changes between builds.
+pub(crate) struct Fnv(u64);
+
+impl Fnv {
+    pub(crate) fn new() -> Self {
+        Self(0xcbf29ce484222325)
+    }
+
+    pub(crate) fn write(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            self.0 ^= u64::from(byte);
+            self.0 = self.0.wrapping_mul(0x100000001b3);
+        }
+    }
+
+    fn write_u64(&mut self, value: u64) {
+        self.write(&value.to_le_bytes());
+    }
+
+    pub(crate) fn finish(&self) -> u64 {
+        self.0
+    }
+
+    /// Hashes a length-prefixed byte string, so that adjacent strings can't be confused.
+    fn write_bytes(&mut self, bytes: &[u8]) {
+        self.write_u64(bytes.len() as u64);
+        self.write(bytes);
+    }
+}
+
+pub(crate) fn fingerprint(puppet: &InochiPuppet) -> u64 {
+    let mut hasher = Fnv::new();
+
+    // Going through `serde_json::Value` hashes exactly the data that is saved, without depending
+    // on the formatting or key order of the JSON text.
+    let json = serde_json::to_value(puppet.json_data()).expect("model data is always valid JSON");
+    hash_json(&mut hasher, &json);
+
+    hasher.write_u64(puppet.textures.len() as u64);
+    for texture in &puppet.textures {
+        hasher.write(&[texture.encoding() as u8]);
+        if let Some([width, height]) = texture.dimensions() {
+            hasher.write_u64(u64::from(width) << 32 | u64::from(height));
+        }
+        hasher.write_bytes(texture.data());
+    }
+
+    hasher.write_u64(puppet.vendor_data.len() as u64);
+    for data in &puppet.vendor_data {
+        hasher.write_bytes(data.name().as_bytes());
+        hasher.write_bytes(data.payload());
+    }
+
+    hasher.finish()
+}
+
+/// Returns the exact value of `n` if it is a whole number.
+///
+/// Integers are kept exact, since UUIDs above 2^53 don't survive a conversion to `f64`. Whole
+/// floats are treated like the integer they are equal to, because `1` and `1.0` (and `-0.0` and
+/// `0`) denote the same value.
+pub(crate) fn integer(n: &Number) -> Option<i128> {
+    if let Some(n) = n.as_i64() {
+        return Some(n.into());
+    }
+    if let Some(n) = n.as_u64() {
+        return Some(n.into());
+    }
+    let n = n.as_f64()?;
+    (n.fract() == 0.0 && n >= i64::MIN as f64 && n <= u64::MAX as f64).then_some(n as i128)
+}
+
+fn hash_json(hasher: &mut Fnv, value: &Value) {
+    match value {
+        Value::Null => hasher.write(&[0]),
+        Value::Bool(b) => hasher.write(&[1, u8::from(*b)]),
+        Value::Number(n) => match integer(n) {
+            Some(n) => {
+                hasher.write(&[2]);
+                hasher.write(&n.to_le_bytes());
+            }
+            None => {
+                hasher.write(&[6]);
+                hasher.write_u64(n.as_f64().unwrap_or(f64::NAN).to_bits());
+            }
+        },
+        Value::String(s) => {
+            hasher.write(&[3]);
+            hasher.write_bytes(s.as_bytes());
+        }
+        Value::Array(values) => {
+            hasher.write(&[4]);
+            hasher.write_u64(values.len() as u64);
+            for value in values {
+                hash_json(hasher, value);
+            }
+        }
+        Value::Object(map) => {
+            // Omitted optional entries are equivalent to `null` ones.
+            let mut entries = map
+                .iter()
+                .filter(|(_, value)| !value.is_null())
+                .collect::<Vec<_>>();
+            entries.sort_unstable_by_key(|(key, _)| *key);
+
+            hasher.write(&[5]);
+            hasher.write_u64(entries.len() as u64);
+            for (key, value) in entries {
+                hasher.write_bytes(key.as_bytes());
+                hash_json(hasher, value);
+            }
+        }
+    }
+}
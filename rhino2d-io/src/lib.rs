@@ -1,29 +1,75 @@
+mod audit;
 pub mod automation;
+#[cfg(feature = "image")]
+mod decode;
+mod dot;
+mod extract;
+mod fingerprint;
+mod limits;
 mod metadata;
 pub mod node;
 mod param;
 mod physics;
+mod roundtrip;
+#[cfg(any(test, feature = "testutil"))]
+#[doc(hidden)]
+pub mod testutil;
+mod validate;
 
+pub use audit::AuditReport;
 use automation::Automation;
+#[cfg(feature = "image")]
+pub use decode::DecodedTexture;
+use limits::Budget;
+pub use limits::*;
 pub use metadata::*;
 use node::Node;
 pub use param::*;
 pub use physics::*;
+pub use roundtrip::RoundtripDiff;
+pub use validate::ValidationError;
 
 use std::{
+    collections::{hash_map::RandomState, HashMap, HashSet},
     fmt,
     fs::File,
-    io::{self, BufReader, BufWriter, Read, Write},
+    hash::{BuildHasher, Hasher},
+    io::{self, BufRead, BufReader, BufWriter, Read, Write},
     path::Path,
+    sync::atomic::{AtomicU32, Ordering},
+    sync::OnceLock,
 };
 
 use byteorder::{ReadBytesExt, WriteBytesExt, BE};
+use flate2::read::{GzDecoder, ZlibDecoder};
+use flate2::write::ZlibEncoder;
+use flate2::Compression;
 use serde::{Deserialize, Serialize};
 
 const MAGIC: [u8; 8] = *b"TRNSRTS\0";
 const MAGIC_TEX: [u8; 8] = *b"TEX_SECT";
+/// Magic bytes of the extended texture section, in which BC7 textures store their dimensions.
+///
+/// This is an extension of the Inochi2D format. It is only written if the model contains BC7
+/// textures, so models with other textures stay readable by Inochi2D.
+const MAGIC_TEX_V2: [u8; 8] = *b"TEX_SEC2";
 const MAGIC_EXT: [u8; 8] = *b"EXT_SECT";
 
+/// Top-level JSON key of the texture sampling extension.
+///
+/// It holds a list with the sampling settings of every texture, in texture order. This is an
+/// extension of the Inochi2D format, and is only written if any texture has non-default settings.
+const TEXTURE_SAMPLING_KEY: &str = "textureSampling";
+
+/// Flag in the length of the JSON section marking it as zlib-compressed.
+///
+/// This is an extension of the Inochi2D format, which Inochi2D can not read. The remaining bits
+/// hold the compressed length.
+const JSON_COMPRESSED: u32 = 1 << 31;
+
+/// Magic bytes at the start of a gzip stream.
+const MAGIC_GZIP: [u8; 2] = [0x1f, 0x8b];
+
 /// An Inochi2D puppet.
 #[derive(Debug)]
 pub struct InochiPuppet {
@@ -32,16 +78,210 @@ pub struct InochiPuppet {
     vendor_data: Vec<VendorData>,
 }
 
+/// Maximum number of list entries preallocated based on a count read from a model file.
+///
+/// Counts are not trusted to be valid, so larger lists grow as their entries are read.
+const MAX_PREALLOC: u32 = 256;
+
+/// Reads exactly `len` bytes from `read`.
+///
+/// Memory is allocated as data is read, so a corrupted `len` can not cause a huge allocation.
+fn read_bytes(read: &mut dyn Read, len: u32) -> io::Result<Vec<u8>> {
+    let mut buf = Vec::new();
+    read.take(len.into()).read_to_end(&mut buf)?;
+    if buf.len() != len as usize {
+        return Err(io::Error::new(
+            io::ErrorKind::UnexpectedEof,
+            format!(
+                "expected {len} bytes of data, but the file ends after {}",
+                buf.len()
+            ),
+        ));
+    }
+    Ok(buf)
+}
+
+type TextureResolver<'a> = dyn FnMut(&str) -> io::Result<Texture> + 'a;
+
+/// Reads the magic bytes at the start of a section.
+///
+/// Returns `None` if the end of the file was reached instead.
+fn read_magic(read: &mut dyn Read) -> io::Result<Option<[u8; 8]>> {
+    let mut magic = [0; 8];
+    let mut len = 0;
+    while len < magic.len() {
+        match read.read(&mut magic[len..]) {
+            Ok(0) => break,
+            Ok(n) => len += n,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+            Err(e) => return Err(e),
+        }
+    }
+    match len {
+        0 => Ok(None),
+        8 => Ok(Some(magic)),
+        _ => Err(io::Error::new(
+            io::ErrorKind::UnexpectedEof,
+            format!("file ends in the middle of a section magic after {len} bytes"),
+        )),
+    }
+}
+
+/// Decompresses a [compressed JSON section][JSON_COMPRESSED].
+///
+/// The decompressed data counts towards the JSON size limit of `budget`, and decompression stops
+/// as soon as it exceeds it.
+fn inflate_json(compressed: &[u8], budget: &mut Budget<'_>) -> io::Result<Vec<u8>> {
+    let limit = u64::from(budget.max_json_size()) + 1;
+    let mut json = Vec::new();
+    ZlibDecoder::new(compressed)
+        .take(limit)
+        .read_to_end(&mut json)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+    budget.json(json.len().try_into().unwrap_or(u32::MAX))?;
+    Ok(json)
+}
+
+/// Reads the contents of the texture section, after its magic bytes.
+///
+/// `extended` is `true` for the [extended texture section][MAGIC_TEX_V2].
+fn read_textures(
+    read: &mut dyn Read,
+    extended: bool,
+    mut resolver: Option<&mut TextureResolver<'_>>,
+    budget: &mut Budget<'_>,
+) -> io::Result<Vec<Texture>> {
+    let texture_count = read.read_u32::<BE>()?;
+    let mut textures = Vec::with_capacity(texture_count.min(MAX_PREALLOC) as usize);
+
+    for _ in 0..texture_count {
+        let payload_len = read.read_u32::<BE>()?;
+        let encoding = read.read_u8()?;
+        let encoding = match encoding {
+            0 => TextureEncoding::Png,
+            1 => TextureEncoding::Tga,
+            2 => TextureEncoding::Bc7,
+            255 => TextureEncoding::External,
+            _ => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("invalid texture encoding value {encoding}"),
+                ))
+            }
+        };
+
+        let dimensions = match encoding {
+            TextureEncoding::Bc7 if extended => {
+                Some([read.read_u32::<BE>()?, read.read_u32::<BE>()?])
+            }
+            TextureEncoding::Bc7 => {
+                log::warn!("BC7 texture without dimensions in legacy texture section");
+                None
+            }
+            _ => None,
+        };
+
+        budget.payload("texture size", payload_len)?;
+        let data = read_bytes(read, payload_len)?;
+
+        let mut texture = Texture::new(encoding, data);
+        texture.dimensions = dimensions;
+        if let (Some(reference), Some(resolver)) = (
+            texture.external_reference()?.map(str::to_string),
+            resolver.as_mut(),
+        ) {
+            texture = resolver(&reference)?;
+            if texture.encoding() == TextureEncoding::External {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("external texture '{reference}' resolved to another reference"),
+                ));
+            }
+        }
+        textures.push(texture);
+    }
+    Ok(textures)
+}
+
+/// Reads the contents of the EXT vendor data section, after its magic bytes.
+fn read_vendor_data(read: &mut dyn Read, budget: &mut Budget<'_>) -> io::Result<Vec<VendorData>> {
+    let payload_count = read.read_u32::<BE>()?;
+    let mut vendor_payloads = Vec::with_capacity(payload_count.min(MAX_PREALLOC) as usize);
+
+    for _ in 0..payload_count {
+        let name_len = read.read_u32::<BE>()?;
+        budget.payload("vendor data name length", name_len)?;
+        let name = String::from_utf8(read_bytes(read, name_len)?)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+
+        let payload_len = read.read_u32::<BE>()?;
+        budget.payload("vendor data size", payload_len)?;
+        let data = read_bytes(read, payload_len)?;
+        vendor_payloads.push(VendorData {
+            name,
+            payload: data,
+        });
+    }
+    Ok(vendor_payloads)
+}
+
 impl InochiPuppet {
+    /// Reads a model from the file at `path`.
+    ///
+    /// The file may also be a gzip-compressed model file (eg. `model.inp.gz`), which is detected
+    /// by its contents and decompressed while reading.
     pub fn from_path<P: AsRef<Path>>(path: P) -> io::Result<Self> {
-        Self::from_read(&mut BufReader::new(File::open(path.as_ref())?))
+        let mut read = BufReader::new(File::open(path.as_ref())?);
+        if read.fill_buf()?.starts_with(&MAGIC_GZIP) {
+            Self::from_read(&mut GzDecoder::new(read))
+        } else {
+            Self::from_read(&mut read)
+        }
     }
 
+    /// Reads a model from `read`.
+    ///
+    /// Models written with [compressed JSON data][InochiPuppet::write_compressed] are detected and
+    /// decompressed while reading.
+    ///
+    /// This is safe to call on untrusted data: malformed input results in an error instead of a
+    /// panic, and length fields are never trusted to preallocate memory, so memory use is bounded
+    /// by the amount of data actually present in the stream.
+    ///
+    /// However, no limits are imposed on the size of the model. Use
+    /// [`InochiPuppet::from_read_with_limits`] to load untrusted models.
     pub fn from_read<R: Read>(read: &mut R) -> io::Result<Self> {
-        Self::from_read_impl(read)
+        Self::from_read_impl(read, None, &LoadLimits::unlimited())
+    }
+
+    /// Reads a model from `read`, rejecting it if it exceeds `limits`.
+    pub fn from_read_with_limits<R: Read>(read: &mut R, limits: &LoadLimits) -> io::Result<Self> {
+        Self::from_read_impl(read, None, limits)
+    }
+
+    /// Reads a model, loading [external textures] via `resolver`.
+    ///
+    /// `resolver` is invoked with the reference stored in each external texture, and returns the
+    /// texture data it refers to. The returned [`Texture`] replaces the external one in the loaded
+    /// model, so it must not be external itself.
+    ///
+    /// [`InochiPuppet::from_read`] keeps external textures unresolved instead.
+    ///
+    /// [external textures]: TextureEncoding::External
+    pub fn from_read_with_resolver<R, F>(read: &mut R, mut resolver: F) -> io::Result<Self>
+    where
+        R: Read,
+        F: FnMut(&str) -> io::Result<Texture>,
+    {
+        Self::from_read_impl(read, Some(&mut resolver), &LoadLimits::unlimited())
     }
 
-    fn from_read_impl(read: &mut dyn Read) -> io::Result<Self> {
+    fn from_read_impl(
+        read: &mut dyn Read,
+        mut resolver: Option<&mut TextureResolver<'_>>,
+        limits: &LoadLimits,
+    ) -> io::Result<Self> {
+        let mut budget = Budget::new(limits);
         let mut magic = [0; 8];
         read.read_exact(&mut magic)?;
         if magic != MAGIC {
@@ -56,121 +296,197 @@ impl InochiPuppet {
         }
 
         let json_len = read.read_u32::<BE>()?;
-        let mut buf = vec![0; json_len as usize];
-        read.read_exact(&mut buf)?;
+        let compressed = json_len & JSON_COMPRESSED != 0;
+        let json_len = json_len & !JSON_COMPRESSED;
+        budget.json(json_len)?;
+        let mut buf = read_bytes(read, json_len)?;
+        if compressed {
+            buf = inflate_json(&buf, &mut budget)?;
+        }
+        budget.counts(&buf)?;
         let mut de = serde_json::Deserializer::from_slice(&buf);
-        let json: JsonData = serde_ignored::deserialize(&mut de, |unused| {
+        let mut json: JsonData = serde_ignored::deserialize(&mut de, |unused| {
             log::warn!("deserializer ignoring `{}`", unused);
         })
-        .map_err(|e| {
+        .inspect_err(|_| {
             log::error!(
                 "failed to deserialize; model JSON dump:\n{}",
                 String::from_utf8_lossy(&buf),
             );
-            e
         })?;
+        let sampling = json.prepare()?;
 
-        let mut magic = [0; 8];
-        read.read_exact(&mut magic)?;
-        if magic != MAGIC_TEX {
-            return Err(io::Error::new(
-                io::ErrorKind::InvalidData,
-                format!(
-                    "invalid magic bytes for texture section, expected '{}', got '{}'",
-                    MAGIC_TEX.escape_ascii(),
-                    magic.escape_ascii()
-                ),
-            ));
-        }
-
-        let texture_count = read.read_u32::<BE>()?;
-        let mut textures = Vec::with_capacity(texture_count as usize);
-
-        for _ in 0..texture_count {
-            let payload_len = read.read_u32::<BE>()?;
-            let encoding = read.read_u8()?;
-            let encoding = match encoding {
-                0 => TextureEncoding::Png,
-                1 => TextureEncoding::Tga,
-                2 => TextureEncoding::Bc7,
+        // The texture and EXT sections are optional, and may appear in any order.
+        let mut textures = None;
+        let mut vendor_payloads = None;
+        while let Some(magic) = read_magic(read)? {
+            let (duplicate, name) = match magic {
+                MAGIC_TEX | MAGIC_TEX_V2 => (textures.is_some(), "texture"),
+                MAGIC_EXT => (vendor_payloads.is_some(), "EXT"),
                 _ => {
-                    return Err(io::Error::new(
-                        io::ErrorKind::InvalidData,
-                        format!("invalid texture encoding value {encoding}"),
-                    ))
-                }
-            };
-
-            let mut data = vec![0; payload_len as usize];
-            read.read_exact(&mut data)?;
-
-            textures.push(Texture {
-                enc: encoding,
-                data,
-            });
-        }
-
-        // Optional EXT Vendor Data section.
-        let mut vendor_payloads = Vec::new();
-        let mut magic = [0; 8];
-        match read.read_exact(&mut magic) {
-            Ok(_) => {
-                if magic != MAGIC_EXT {
                     return Err(io::Error::new(
                         io::ErrorKind::InvalidData,
                         format!(
-                            "invalid magic bytes for EXT section, expected '{}', got '{}'",
+                            "invalid magic bytes for section, expected '{}' or '{}', got '{}'",
+                            MAGIC_TEX.escape_ascii(),
                             MAGIC_EXT.escape_ascii(),
                             magic.escape_ascii()
                         ),
-                    ));
+                    ))
                 }
+            };
+            if duplicate {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("duplicate {name} section"),
+                ));
+            }
 
-                let payload_count = read.read_u32::<BE>()?;
-                vendor_payloads = Vec::with_capacity(payload_count as usize);
-
-                for _ in 0..payload_count {
-                    let name_len = read.read_u32::<BE>()?;
-                    let mut name = String::with_capacity(name_len as usize);
-                    read.take(name_len.into()).read_to_string(&mut name)?;
-
-                    let payload_len = read.read_u32::<BE>()?;
-                    let mut data = vec![0; payload_len as usize];
-                    read.read_exact(&mut data)?;
-                    vendor_payloads.push(VendorData {
-                        name,
-                        payload: data,
-                    });
-                }
+            if magic == MAGIC_TEX || magic == MAGIC_TEX_V2 {
+                let extended = magic == MAGIC_TEX_V2;
+                textures = Some(read_textures(
+                    read,
+                    extended,
+                    resolver.as_deref_mut(),
+                    &mut budget,
+                )?);
+            } else {
+                vendor_payloads = Some(read_vendor_data(read, &mut budget)?);
             }
-            Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => {}
-            Err(e) => return Err(e),
         }
 
+        let mut textures = textures.unwrap_or_default();
+        apply_sampling(&mut textures, sampling);
+
         Ok(Self {
             data: json,
             textures,
-            vendor_data: vendor_payloads,
+            vendor_data: vendor_payloads.unwrap_or_default(),
+        })
+    }
+
+    /// Creates a model from its JSON data, as returned by [`InochiPuppet::to_json`].
+    ///
+    /// The model has no textures or vendor data. Texture sampling settings in the JSON data
+    /// belong to the textures, so they are dropped as well.
+    ///
+    /// Like [`InochiPuppet::from_read`], this imposes no limits on the size of the model. Use
+    /// [`InochiPuppet::from_json_with_limits`] for untrusted data.
+    pub fn from_json(value: serde_json::Value) -> io::Result<Self> {
+        Self::from_json_with_limits(value, &LoadLimits::unlimited())
+    }
+
+    /// Creates a model from its JSON data, rejecting it if it exceeds the node or parameter count
+    /// of `limits`.
+    ///
+    /// The other limits concern the model file, and don't apply to JSON data that was already
+    /// parsed.
+    pub fn from_json_with_limits(
+        value: serde_json::Value,
+        limits: &LoadLimits,
+    ) -> io::Result<Self> {
+        Budget::new(limits).value_counts(&value)?;
+        let mut json: JsonData = serde_ignored::deserialize(value, |unused| {
+            log::warn!("deserializer ignoring `{}`", unused);
+        })?;
+        // The sampling settings are dropped along with the textures they belong to.
+        json.prepare()?;
+        Ok(Self {
+            data: json,
+            textures: Vec::new(),
+            vendor_data: Vec::new(),
         })
     }
 
+    /// Returns the model's JSON data, as it is stored in the model file.
+    ///
+    /// This includes the node tree, parameters, automations, physics settings, and metadata, but
+    /// not the textures and vendor data, which are stored outside of the JSON data. It is meant
+    /// for debugging, diffing models, and hand-editing them, and can be turned back into a model
+    /// with [`InochiPuppet::from_json`].
+    pub fn to_json(&self) -> serde_json::Value {
+        serde_json::to_value(self.json_data()).expect("model JSON data is always serializable")
+    }
+
+    /// Writes the model's JSON data, as returned by [`InochiPuppet::to_json`], to `w`.
+    ///
+    /// The JSON is pretty-printed, to make it easy to read and diff.
+    pub fn write_json<W: Write>(&self, w: W) -> io::Result<()> {
+        serde_json::to_writer_pretty(w, &self.json_data())?;
+        Ok(())
+    }
+
     /// Writes this model to a file at `path`.
     pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
         self.write(BufWriter::new(File::create(path.as_ref())?))
     }
 
+    /// Writes this model to a file at `path`, with its JSON data compressed.
+    ///
+    /// See [`InochiPuppet::write_compressed`].
+    pub fn save_compressed<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        self.write_compressed(BufWriter::new(File::create(path.as_ref())?))
+    }
+
     /// Serializes this model into a type that implements [`Write`].
-    pub fn write<W: Write>(&self, mut w: W) -> io::Result<()> {
+    ///
+    /// Returns an error if the model contains a BC7 texture without
+    /// [dimensions][Texture::dimensions].
+    pub fn write<W: Write>(&self, w: W) -> io::Result<()> {
+        self.write_impl(w, false)
+    }
+
+    /// Like [`InochiPuppet::write`], but compresses the model's JSON data.
+    ///
+    /// The JSON data of large models is often several megabytes in size, and compresses well.
+    /// Textures are already compressed, so they are written as-is. Models written this way can be
+    /// read by this crate, but not by Inochi2D.
+    pub fn write_compressed<W: Write>(&self, w: W) -> io::Result<()> {
+        self.write_impl(w, true)
+    }
+
+    fn write_impl<W: Write>(&self, mut w: W, compress: bool) -> io::Result<()> {
+        let mut extended = false;
+        for tex in self.textures() {
+            if tex.encoding() == TextureEncoding::Bc7 {
+                if tex.dimensions().is_none() {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidInput,
+                        "BC7 texture has no dimensions",
+                    ));
+                }
+                extended = true;
+            }
+        }
+
         w.write_all(&MAGIC)?;
-        let json = serde_json::to_vec(&self.data)?;
-        w.write_u32::<BE>(json.len().try_into().unwrap())?;
+        let mut json = serde_json::to_vec(&self.json_data())?;
+        let mut flags = 0;
+        if compress {
+            let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+            encoder.write_all(&json)?;
+            json = encoder.finish()?;
+            flags = JSON_COMPRESSED;
+        }
+        let json_len = u32::try_from(json.len())
+            .ok()
+            .filter(|len| len & JSON_COMPRESSED == 0)
+            .ok_or_else(|| {
+                io::Error::new(io::ErrorKind::InvalidInput, "model JSON data is too large")
+            })?;
+        w.write_u32::<BE>(json_len | flags)?;
         w.write_all(&json)?;
 
-        w.write_all(&MAGIC_TEX)?;
+        w.write_all(if extended { &MAGIC_TEX_V2 } else { &MAGIC_TEX })?;
         w.write_u32::<BE>(self.textures().len().try_into().unwrap())?;
         for tex in self.textures() {
             w.write_u32::<BE>(tex.data().len().try_into().unwrap())?;
             w.write_u8(tex.encoding() as u8)?;
+            if let (TextureEncoding::Bc7, Some([width, height])) = (tex.encoding(), tex.dimensions)
+            {
+                w.write_u32::<BE>(width)?;
+                w.write_u32::<BE>(height)?;
+            }
             w.write_all(tex.data())?;
         }
 
@@ -186,6 +502,24 @@ impl InochiPuppet {
         Ok(())
     }
 
+    /// Returns the root JSON object, as it is saved.
+    fn json_data(&self) -> JsonDataOut<'_> {
+        let default = |tex: &Texture| tex.filter.is_none() && tex.wrap == TextureWrap::default();
+        let texture_sampling = (!self.textures.iter().all(default)).then(|| {
+            self.textures
+                .iter()
+                .map(|tex| TextureSampling {
+                    filter: tex.filter,
+                    wrap: tex.wrap,
+                })
+                .collect()
+        });
+        JsonDataOut {
+            data: &self.data,
+            texture_sampling,
+        }
+    }
+
     /// Returns a reference to the model metadata, containing author, license, and version
     /// information.
     pub fn metadata(&self) -> &Metadata {
@@ -251,6 +585,17 @@ impl InochiPuppet {
             .push(automation);
     }
 
+    /// Returns the top-level JSON entries that aren't part of the known model sections.
+    ///
+    /// They are preserved as-is when writing the model back out.
+    pub fn extra_fields(&self) -> &serde_json::Map<String, serde_json::Value> {
+        &self.data.extra
+    }
+
+    pub fn extra_fields_mut(&mut self) -> &mut serde_json::Map<String, serde_json::Value> {
+        &mut self.data.extra
+    }
+
     pub fn textures(&self) -> &[Texture] {
         &self.textures
     }
@@ -272,12 +617,194 @@ impl InochiPuppet {
     pub fn push_vendor_data(&mut self, data: VendorData) {
         self.vendor_data.push(data);
     }
+
+    /// Generates an ID that no node or parameter of the model uses yet, for adding entities to
+    /// it.
+    ///
+    /// IDs are drawn from a sequence that is randomized for every process, and never repeats
+    /// within one, skipping the IDs already used by the model. Like the IDs assigned by Inochi2D,
+    /// they fit into 32 bits, and are never [`Uuid::INVALID`].
+    pub fn new_uuid(&self) -> Uuid {
+        self.first_unused(std::iter::repeat_with(Uuid::next_random))
+    }
+
+    /// Returns the first of `candidates` that no node or parameter uses.
+    fn first_unused(&self, candidates: impl IntoIterator<Item = Uuid>) -> Uuid {
+        let root = self.root_node();
+        candidates
+            .into_iter()
+            .find(|&uuid| {
+                !std::iter::once(root)
+                    .chain(root.descendants())
+                    .any(|node| node.uuid() == uuid)
+                    && !self.params().iter().any(|param| param.uuid() == uuid)
+            })
+            .expect("ran out of unused IDs")
+    }
+
+    /// Describes the node hierarchy as a [Graphviz] DOT graph, for debugging.
+    ///
+    /// Nodes are linked to their children, and with dashed edges to the parts they mask and the
+    /// parameters their physics simulation drives. Parameters are grouped in a separate cluster,
+    /// and linked to the nodes they are bound to.
+    ///
+    /// [Graphviz]: https://graphviz.org/
+    pub fn to_dot(&self) -> String {
+        dot::to_dot(self)
+    }
+
+    /// Computes a hash of the model's content, for detecting whether a model has changed.
+    ///
+    /// The hash covers the node tree, parameters, physics settings, metadata, textures, and vendor
+    /// data. It does not depend on how the model's JSON data is formatted, so a model re-saved by
+    /// another application has the same fingerprint as long as its content is the same. The hash is
+    /// stable across platforms and versions of this crate.
+    pub fn fingerprint(&self) -> u64 {
+        fingerprint::fingerprint(self)
+    }
+
+    /// Checks that saving this model and loading it again reproduces it exactly.
+    ///
+    /// The model is written to a buffer and read back. Its JSON data, textures, and vendor data
+    /// are then compared with the original, like [`InochiPuppet::fingerprint`] does. On mismatch,
+    /// the returned [`RoundtripDiff`] describes what changed, eg. values that were dropped or
+    /// modified, or sections that ended up in a different order.
+    pub fn verify_roundtrip(&self) -> Result<(), RoundtripDiff> {
+        roundtrip::verify_roundtrip(self)
+    }
+
+    /// Checks the model for internal inconsistencies, and returns all of them.
+    ///
+    /// Loading a model only checks that its structure is valid. This additionally checks that
+    /// UUIDs of nodes and parameters are unique, meshes only index existing vertices and have a
+    /// UV for every vertex, parts only use existing textures and are masked by existing nodes,
+    /// and parameter bindings and physics nodes refer to existing nodes and parameters.
+    pub fn validate(&self) -> Result<(), Vec<ValidationError>> {
+        validate::validate(self)
+    }
+
+    /// Removes group nodes that have no effect, and returns the number of removed nodes.
+    ///
+    /// A node is removed if it is of type [`Node::Node`], is enabled, is not locked to the root,
+    /// and is not referenced by any parameter binding or mask. Its children are moved to its
+    /// parent, with the group's transform applied to them, so that they stay in place. Groups
+    /// whose transform can not be applied to their children exactly (eg. because that would
+    /// require a shear) are kept. The root node is never removed.
+    ///
+    /// Applying a rotated group's transform to the position of its children depends on the
+    /// direction of rotation. This uses Inochi2D's convention, where positive angles turn the X
+    /// axis towards the Y axis (clockwise on screen). When such a group has translated children,
+    /// the result only renders the same with that convention, not with
+    /// `RotationDirection::CounterClockwise` in `rhino2d-engine`.
+    pub fn prune_empty(&mut self) -> usize {
+        let mut referenced: HashSet<Uuid> = self
+            .data
+            .param
+            .iter()
+            .flat_map(|param| param.bindings().iter().map(|binding| binding.node()))
+            .collect();
+        self.data.nodes.visit_mut(&mut |node| {
+            if let Node::Part(part) = node {
+                referenced.extend(part.masked_by());
+            }
+        });
+
+        self.data.nodes.prune_empty(&referenced)
+    }
+
+    /// Finds content of the model that has no effect: textures that aren't used, parameters
+    /// without bindings, and nodes that are neither rendered nor referenced.
+    ///
+    /// See [`AuditReport`] for details. This does not modify the model, but the report can be
+    /// used to clean it up, eg. together with [`InochiPuppet::prune_empty`].
+    pub fn audit(&self) -> AuditReport {
+        audit::audit(self)
+    }
+
+    /// Copies the subtree rooted at the node `root` into a new, standalone puppet.
+    ///
+    /// The new puppet's root node is a copy of `root`, keeping its transform. It contains the
+    /// parameters that are bound to a node of the subtree or driven by one of its physics nodes,
+    /// with only the bindings of subtree nodes, and the automations that only animate those
+    /// parameters. Only the textures used by the subtree are copied, and texture indices are
+    /// rewritten to match. Mask references to nodes outside the subtree are removed.
+    ///
+    /// Metadata and physics settings are copied as well. Vendor data and
+    /// [extra fields][InochiPuppet::extra_fields] can refer to anything in the model, so they are
+    /// not.
+    ///
+    /// Returns `None` if there is no node with the UUID `root`.
+    pub fn extract_subtree(&self, root: Uuid) -> Option<InochiPuppet> {
+        extract::extract_subtree(self, root)
+    }
+
+    /// Removes duplicate textures, and updates all references to them.
+    ///
+    /// Two textures are considered duplicates if they use the same [`TextureEncoding`] and sampling
+    /// settings, and their encoded data is byte-for-byte identical. The same image encoded
+    /// differently (eg. as PNG and TGA, or with different compression settings) is *not* merged.
+    ///
+    /// The first occurrence of every texture is kept, and texture references in [`Part`]s and the
+    /// model's thumbnail are rewritten to point to it. Returns the number of removed textures.
+    ///
+    /// [`Part`]: node::Part
+    pub fn dedupe_textures(&mut self) -> usize {
+        // Index of the first texture with the same contents as each texture.
+        let mut first_index = HashMap::new();
+        let canonical = self
+            .textures
+            .iter()
+            .enumerate()
+            .map(|(i, tex)| {
+                let key = (tex.enc, tex.dimensions, tex.filter, tex.wrap, &*tex.data);
+                *first_index.entry(key).or_insert(i)
+            })
+            .collect::<Vec<_>>();
+
+        let mut remap: Vec<u32> = Vec::with_capacity(canonical.len());
+        let mut kept = 0;
+        for (i, &canonical) in canonical.iter().enumerate() {
+            if canonical == i {
+                remap.push(kept);
+                kept += 1;
+            } else {
+                remap.push(remap[canonical]);
+            }
+        }
+
+        let removed = canonical.len() - kept as usize;
+        if removed == 0 {
+            return 0;
+        }
+
+        let mut i = 0;
+        self.textures.retain(|_| {
+            i += 1;
+            canonical[i - 1] == i - 1
+        });
+
+        let remap = |index: u32| remap.get(index as usize).copied().unwrap_or(index);
+        self.data.nodes.visit_mut(&mut |node| {
+            if let Node::Part(part) = node {
+                let textures = part.textures().iter().map(|&i| remap(i)).collect();
+                part.set_textures(textures);
+            }
+        });
+        if let Some(thumbnail) = self.data.meta.thumbnail_id() {
+            self.data.meta.set_thumbnail_id(Some(remap(thumbnail)));
+        }
+
+        removed
+    }
 }
 
 /// A texture image.
 pub struct Texture {
     enc: TextureEncoding,
     data: Vec<u8>,
+    dimensions: Option<[u32; 2]>,
+    filter: Option<TextureFilter>,
+    wrap: TextureWrap,
 }
 
 impl Texture {
@@ -285,9 +812,38 @@ impl Texture {
         Self {
             enc: encoding,
             data,
+            dimensions: None,
+            filter: None,
+            wrap: TextureWrap::default(),
         }
     }
 
+    /// Creates a [BC7 compressed texture][TextureEncoding::Bc7] of the given size.
+    pub fn bc7(width: u32, height: u32, data: Vec<u8>) -> Self {
+        Self {
+            dimensions: Some([width, height]),
+            ..Self::new(TextureEncoding::Bc7, data)
+        }
+    }
+
+    /// Creates an [external texture][TextureEncoding::External] referring to `reference`.
+    pub fn external(reference: String) -> Self {
+        Self::new(TextureEncoding::External, reference.into_bytes())
+    }
+
+    /// Returns the reference stored in an [external texture][TextureEncoding::External].
+    ///
+    /// Returns `Ok(None)` if this texture is not external, and an error if the reference is not
+    /// valid UTF-8.
+    pub fn external_reference(&self) -> io::Result<Option<&str>> {
+        if self.enc != TextureEncoding::External {
+            return Ok(None);
+        }
+        std::str::from_utf8(&self.data)
+            .map(Some)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+
     pub fn encoding(&self) -> TextureEncoding {
         self.enc
     }
@@ -295,6 +851,48 @@ impl Texture {
     pub fn data(&self) -> &[u8] {
         &self.data
     }
+
+    /// Returns the width and height of the texture, in pixels.
+    ///
+    /// This is only stored for BC7 textures, whose dimensions can not be determined from their
+    /// data. It is `None` for all other encodings, and for BC7 textures loaded from models that
+    /// predate the extended texture section this crate writes them in, which can not be decoded.
+    pub fn dimensions(&self) -> Option<[u32; 2]> {
+        self.dimensions
+    }
+
+    pub fn set_dimensions(&mut self, dimensions: Option<[u32; 2]>) {
+        self.dimensions = dimensions;
+    }
+
+    /// Decodes the texture to 8-bit RGBA pixels.
+    ///
+    /// PNG and TGA textures are supported. Returns an error for other encodings, and if the data
+    /// can not be decoded.
+    #[cfg(feature = "image")]
+    pub fn decode(&self) -> io::Result<DecodedTexture> {
+        decode::decode(self)
+    }
+
+    /// Returns the filter to sample this texture with.
+    ///
+    /// `None` means that the model-wide [`Metadata::preserve_pixels`] setting decides.
+    pub fn filter(&self) -> Option<TextureFilter> {
+        self.filter
+    }
+
+    pub fn set_filter(&mut self, filter: Option<TextureFilter>) {
+        self.filter = filter;
+    }
+
+    /// Returns how this texture is sampled outside of its bounds.
+    pub fn wrap(&self) -> TextureWrap {
+        self.wrap
+    }
+
+    pub fn set_wrap(&mut self, wrap: TextureWrap) {
+        self.wrap = wrap;
+    }
 }
 
 impl fmt::Debug for Texture {
@@ -302,20 +900,67 @@ impl fmt::Debug for Texture {
         f.debug_struct("Texture")
             .field("encoding", &self.enc)
             .field("data_len", &self.data.len())
+            .field("dimensions", &self.dimensions)
+            .field("filter", &self.filter)
+            .field("wrap", &self.wrap)
             .finish()
     }
 }
 
 /// List of supported formats for [`Texture`]s.
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 #[non_exhaustive]
 pub enum TextureEncoding {
     /// Texture is PNG encoded (lossless).
     Png = 0,
     /// Texture is in Truevision TGA / TARGA format (lossless).
     Tga = 1,
-    /// [Not yet implemented] Texture is BC7 compressed (lossy).
+    /// Texture is BC7 compressed (lossy).
+    ///
+    /// Inochi2D stores no dimensions for BC7 textures, so they are stored in an extension of the
+    /// format. See [`Texture::dimensions`].
     Bc7 = 2,
+    /// Texture data is stored outside of the model file.
+    ///
+    /// The texture data is a UTF-8 encoded reference (eg. a path or URL) to the actual texture,
+    /// which can be loaded with [`InochiPuppet::from_read_with_resolver`].
+    ///
+    /// This is an extension of the Inochi2D format, and not supported by Inochi2D itself.
+    External = 255,
+}
+
+/// Filter used when sampling a [`Texture`].
+///
+/// This is an extension of the Inochi2D format.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum TextureFilter {
+    /// Texels are not interpolated, which keeps pixel art sharp.
+    Nearest,
+    /// Texels are linearly interpolated.
+    Linear,
+}
+
+/// Addressing mode used when sampling a [`Texture`] outside of its bounds.
+///
+/// This is an extension of the Inochi2D format.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum TextureWrap {
+    /// Texture coordinates are clamped to the edge of the texture.
+    #[default]
+    Clamp,
+    /// The texture repeats, for tiling patterns.
+    Repeat,
+    /// The texture repeats, mirrored on every repetition.
+    MirroredRepeat,
+}
+
+/// Entry of the texture sampling extension list.
+#[derive(Debug, Serialize, Deserialize)]
+struct TextureSampling {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    filter: Option<TextureFilter>,
+    #[serde(default)]
+    wrap: TextureWrap,
 }
 
 /// Vendor-specific extension data attached to a model.
@@ -360,21 +1005,177 @@ struct JsonData {
     nodes: Node, // really the root node
     param: Vec<Param>,
     automation: Option<Vec<Automation>>,
+    /// Unknown top-level entries, kept so that they survive a round-trip.
+    #[serde(flatten)]
+    extra: serde_json::Map<String, serde_json::Value>,
+}
+
+impl JsonData {
+    /// Extracts the extensions of the JSON data that are stored on other model entities, and
+    /// fixes up common mistakes.
+    ///
+    /// Returns the sampling settings of the model's textures.
+    fn prepare(&mut self) -> io::Result<Vec<TextureSampling>> {
+        let sampling = match self.extra.remove(TEXTURE_SAMPLING_KEY) {
+            Some(value) => serde_json::from_value(value)?,
+            None => Vec::new(),
+        };
+
+        // A part masked by itself is a common mistake after copying parts. It can not be rendered
+        // in a meaningful way, so the mask is dropped.
+        self.nodes.visit_mut(&mut |node| {
+            let uuid = node.uuid();
+            if let Node::Part(part) = node {
+                if part.masked_by().contains(&uuid) {
+                    log::warn!(
+                        "part '{}' ({}) is masked by itself, ignoring the self-mask",
+                        part.name(),
+                        uuid
+                    );
+                    let masked_by = part.masked_by().iter().copied();
+                    let masked_by = masked_by.filter(|&mask| mask != uuid).collect();
+                    part.set_masked_by(Some(masked_by));
+                }
+            }
+        });
+
+        Ok(sampling)
+    }
+}
+
+/// Applies the sampling settings read from a model's JSON data to its textures.
+fn apply_sampling(textures: &mut [Texture], sampling: Vec<TextureSampling>) {
+    if sampling.len() > textures.len() {
+        log::warn!(
+            "model has sampling settings for {} textures, but only {} textures",
+            sampling.len(),
+            textures.len()
+        );
+    }
+    for (texture, sampling) in textures.iter_mut().zip(sampling) {
+        texture.filter = sampling.filter;
+        texture.wrap = sampling.wrap;
+    }
+}
+
+/// Root JSON object, with the extensions that are stored on other model entities.
+#[derive(Serialize)]
+struct JsonDataOut<'a> {
+    #[serde(flatten)]
+    data: &'a JsonData,
+    #[serde(rename = "textureSampling", skip_serializing_if = "Option::is_none")]
+    texture_sampling: Option<Vec<TextureSampling>>,
 }
 
 /// A unique ID attached to some model entity.
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+///
+/// Inochi2D stores IDs as JSON numbers, which is also how they are written. For compatibility with
+/// other tools, IDs stored as strings are accepted as well: a string containing a decimal number
+/// denotes that number, and a string in the standard UUID format (eg.
+/// `"123e4567-e89b-12d3-a456-426614174000"`) is hashed to a number, which is the same every time
+/// the same UUID is loaded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize)]
 #[serde(transparent)]
 pub struct Uuid {
     raw: u64,
 }
 
 impl Uuid {
+    /// The ID Inochi2D uses for references to nothing, eg. by an unbound
+    /// [`SimplePhysics`][node::SimplePhysics] node.
+    ///
+    /// Inochi2D stores IDs as 32-bit integers, and reserves the largest one for this.
+    pub const INVALID: Self = Self {
+        raw: u32::MAX as u64,
+    };
+
+    pub const fn from_raw(raw: u64) -> Self {
+        Self { raw }
+    }
+
+    /// Returns the next ID of a sequence that is randomized for every process, and never repeats
+    /// within one. See [`InochiPuppet::new_uuid`].
+    fn next_random() -> Self {
+        static KEY: OnceLock<u64> = OnceLock::new();
+        static COUNTER: AtomicU32 = AtomicU32::new(0);
+
+        let key = *KEY.get_or_init(|| RandomState::new().build_hasher().finish());
+        loop {
+            // Every step of this is a bijection on `u32`, so distinct counter values give
+            // distinct IDs.
+            let mut x = COUNTER.fetch_add(1, Ordering::Relaxed) ^ key as u32;
+            x ^= x >> 16;
+            x = x.wrapping_mul(0x7feb_352d);
+            x ^= x >> 15;
+            x = x.wrapping_mul(0x846c_a68b);
+            x ^= x >> 16;
+            x ^= (key >> 32) as u32;
+            if x != u32::MAX {
+                return Self { raw: x.into() };
+            }
+        }
+    }
+
     pub fn raw(&self) -> u64 {
         self.raw
     }
 }
 
+impl<'de> Deserialize<'de> for Uuid {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct Visitor;
+
+        impl serde::de::Visitor<'_> for Visitor {
+            type Value = Uuid;
+
+            fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.write_str("an unsigned integer, or a string containing one or a UUID")
+            }
+
+            fn visit_u64<E: serde::de::Error>(self, raw: u64) -> Result<Uuid, E> {
+                Ok(Uuid { raw })
+            }
+
+            fn visit_str<E: serde::de::Error>(self, s: &str) -> Result<Uuid, E> {
+                if let Ok(raw) = s.parse() {
+                    return Ok(Uuid { raw });
+                }
+                let uuid = parse_uuid(s)
+                    .ok_or_else(|| E::invalid_value(serde::de::Unexpected::Str(s), &self))?;
+                let mut hasher = fingerprint::Fnv::new();
+                hasher.write(&uuid.to_be_bytes());
+                Ok(Uuid {
+                    raw: hasher.finish(),
+                })
+            }
+        }
+
+        deserializer.deserialize_any(Visitor)
+    }
+}
+
+/// Parses a UUID in the hyphenated format of RFC 4122 (`xxxxxxxx-xxxx-xxxx-xxxx-xxxxxxxxxxxx`).
+///
+/// Hex digits may be upper or lower case.
+fn parse_uuid(s: &str) -> Option<u128> {
+    let bytes = s.as_bytes();
+    if bytes.len() != 36 {
+        return None;
+    }
+    let mut value = 0u128;
+    for (i, &byte) in bytes.iter().enumerate() {
+        if matches!(i, 8 | 13 | 18 | 23) {
+            if byte != b'-' {
+                return None;
+            }
+            continue;
+        }
+        let digit = char::from(byte).to_digit(16)?;
+        value = value << 4 | u128::from(digit);
+    }
+    Some(value)
+}
+
 impl fmt::Display for Uuid {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         self.raw.fmt(f)
@@ -386,3 +1187,726 @@ pub type Vec2 = [f32; 2];
 
 /// A vector or point in 3D space.
 pub type Vec3 = [f32; 3];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testutil::puppet;
+
+    fn part_textures(puppet: &InochiPuppet) -> Vec<Vec<u32>> {
+        puppet
+            .root_node()
+            .children()
+            .iter()
+            .map(|node| match node {
+                Node::Part(part) => part.textures().to_vec(),
+                _ => unreachable!(),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn dedupe_textures() {
+        let mut puppet = puppet(
+            r#"{
+                "type": "Node", "uuid": 1,
+                "children": [
+                    { "type": "Part", "uuid": 2, "textures": [0] },
+                    { "type": "Part", "uuid": 3, "textures": [1] },
+                    { "type": "Part", "uuid": 4, "textures": [2] },
+                    { "type": "Part", "uuid": 5, "textures": [3] }
+                ]
+            }"#,
+            "[]",
+        );
+        puppet.push_texture(Texture::new(TextureEncoding::Png, vec![1, 2, 3]));
+        puppet.push_texture(Texture::new(TextureEncoding::Png, vec![4, 5, 6]));
+        puppet.push_texture(Texture::new(TextureEncoding::Png, vec![1, 2, 3]));
+        puppet.push_texture(Texture::new(TextureEncoding::Tga, vec![1, 2, 3]));
+        puppet.metadata_mut().set_thumbnail_id(Some(2));
+
+        assert_eq!(puppet.dedupe_textures(), 1);
+        assert_eq!(puppet.textures().len(), 3);
+        assert_eq!(puppet.textures()[1].data(), [4, 5, 6]);
+        assert_eq!(puppet.textures()[2].encoding(), TextureEncoding::Tga);
+        assert_eq!(part_textures(&puppet), [[0], [1], [0], [2]]);
+        assert_eq!(puppet.metadata().thumbnail_id(), Some(0));
+
+        assert_eq!(puppet.dedupe_textures(), 0);
+    }
+
+    #[test]
+    fn extra_fields_roundtrip() {
+        let mut json =
+            serde_json::to_value(&puppet(r#"{ "type": "Node", "uuid": 1 }"#, "[]").data).unwrap();
+        json["puppet"] = serde_json::json!({ "editor": { "version": 3 } });
+
+        let data: JsonData = serde_json::from_value(json.clone()).unwrap();
+        assert_eq!(data.extra.len(), 1);
+        assert_eq!(data.extra["puppet"]["editor"]["version"], 3);
+        assert_eq!(serde_json::to_value(&data).unwrap(), json);
+    }
+
+    #[test]
+    fn to_dot() {
+        let puppet = puppet(
+            r#"{
+                "type": "Node", "uuid": 1, "name": "root",
+                "children": [
+                    { "type": "Part", "uuid": 2, "name": "face \"front\"", "masked_by": [3] },
+                    { "type": "Mask", "uuid": 3, "name": "mask" },
+                    { "type": "SimplePhysics", "uuid": 4, "name": "hair", "param": 10 },
+                    { "type": "SimplePhysics", "uuid": 5, "name": "unbound", "param": 4294967295 }
+                ]
+            }"#,
+            r#"[{
+                "uuid": 10, "name": "Hair Swing",
+                "bindings": [{ "node": 2, "param_name": "transform.t.x", "values": [[0, 1]] }]
+            }]"#,
+        );
+        let dot = puppet.to_dot();
+        assert!(dot.starts_with("digraph puppet {\n"), "{dot}");
+        for line in [
+            r#"n1 [label="root\nNode"];"#,
+            r#"n2 [label="face \"front\"\nPart"];"#,
+            r#"n4 [label="hair\nSimplePhysics"];"#,
+            "n1 -> n2;",
+            "n1 -> n3;",
+            r#"n3 -> n2 [style=dashed, label="masks"];"#,
+            r#"n4 -> p10 [style=dashed, label="drives"];"#,
+            r#"p10 [label="Hair Swing"];"#,
+            r#"p10 -> n2 [label="transform.t.x"];"#,
+        ] {
+            assert!(dot.contains(line), "missing `{line}` in:\n{dot}");
+        }
+        assert!(!dot.contains("n5 -> p"), "{dot}");
+    }
+
+    /// Returns the bytes of a valid model up to the texture count, followed by `tail`.
+    fn model_bytes(tail: &[u8]) -> Vec<u8> {
+        let puppet = puppet(r#"{ "type": "Node", "uuid": 1 }"#, "[]");
+        let mut bytes = Vec::new();
+        puppet.write(&mut bytes).unwrap();
+        // Strip the empty texture section (texture count and EXT section header).
+        let tex = bytes.windows(8).position(|w| w == MAGIC_TEX).unwrap();
+        bytes.truncate(tex + 8);
+        bytes.extend_from_slice(tail);
+        bytes
+    }
+
+    fn read_err(bytes: &[u8]) -> io::ErrorKind {
+        InochiPuppet::from_read(&mut &*bytes).unwrap_err().kind()
+    }
+
+    #[test]
+    fn malformed_input() {
+        assert!(InochiPuppet::from_read(&mut &*model_bytes(&0u32.to_be_bytes())).is_ok());
+
+        // Huge JSON length.
+        let mut bytes = MAGIC.to_vec();
+        bytes.extend_from_slice(&u32::MAX.to_be_bytes());
+        bytes.extend_from_slice(b"{}");
+        assert_eq!(read_err(&bytes), io::ErrorKind::UnexpectedEof);
+
+        // Malformed JSON.
+        let mut bytes = MAGIC.to_vec();
+        bytes.extend_from_slice(&4u32.to_be_bytes());
+        bytes.extend_from_slice(b"{]}[");
+        assert_eq!(read_err(&bytes), io::ErrorKind::InvalidData);
+
+        // Huge texture count.
+        let bytes = model_bytes(&u32::MAX.to_be_bytes());
+        assert_eq!(read_err(&bytes), io::ErrorKind::UnexpectedEof);
+
+        // Huge texture payload length.
+        let mut tail = 1u32.to_be_bytes().to_vec();
+        tail.extend_from_slice(&u32::MAX.to_be_bytes());
+        tail.push(0);
+        tail.extend_from_slice(b"\x89PNG");
+        assert_eq!(read_err(&model_bytes(&tail)), io::ErrorKind::UnexpectedEof);
+
+        // Huge vendor data count, and truncated vendor data name.
+        for (count, name_len) in [(u32::MAX, 3), (1, u32::MAX)] {
+            let mut tail = 0u32.to_be_bytes().to_vec();
+            tail.extend_from_slice(&MAGIC_EXT);
+            tail.extend_from_slice(&count.to_be_bytes());
+            tail.extend_from_slice(&name_len.to_be_bytes());
+            tail.extend_from_slice(b"abc");
+            tail.extend_from_slice(&0u32.to_be_bytes());
+            assert_eq!(read_err(&model_bytes(&tail)), io::ErrorKind::UnexpectedEof);
+        }
+    }
+
+    #[test]
+    fn optional_sections() {
+        let mut puppet = puppet(r#"{ "type": "Node", "uuid": 1 }"#, "[]");
+        puppet.push_texture(Texture::new(TextureEncoding::Png, vec![1, 2, 3]));
+        puppet.push_vendor_data(VendorData::new("app".into(), vec![4, 5]));
+        let mut bytes = Vec::new();
+        puppet.write(&mut bytes).unwrap();
+        let tex = bytes.windows(8).position(|w| w == MAGIC_TEX).unwrap();
+        let ext = bytes.windows(8).position(|w| w == MAGIC_EXT).unwrap();
+        let (json, tex_section, ext_section) = (&bytes[..tex], &bytes[tex..ext], &bytes[ext..]);
+
+        // No texture or EXT section at all.
+        let puppet = InochiPuppet::from_read(&mut &*json).unwrap();
+        assert!(puppet.textures().is_empty());
+        assert!(puppet.vendor_data().is_empty());
+
+        // Only an EXT section.
+        let puppet = InochiPuppet::from_read(&mut &*[json, ext_section].concat()).unwrap();
+        assert!(puppet.textures().is_empty());
+        assert_eq!(puppet.vendor_data()[0].payload(), [4, 5]);
+
+        // EXT section before the texture section.
+        let bytes = [json, ext_section, tex_section].concat();
+        let puppet = InochiPuppet::from_read(&mut &*bytes).unwrap();
+        assert_eq!(puppet.textures()[0].data(), [1, 2, 3]);
+        assert_eq!(puppet.vendor_data()[0].payload(), [4, 5]);
+
+        // Unknown and duplicate sections, and truncated magic bytes.
+        let bytes = [json, b"BAD_SECT", &0u32.to_be_bytes()].concat();
+        assert_eq!(read_err(&bytes), io::ErrorKind::InvalidData);
+        let bytes = [json, tex_section, ext_section, tex_section].concat();
+        assert_eq!(read_err(&bytes), io::ErrorKind::InvalidData);
+        let bytes = [json, tex_section, &MAGIC_EXT[..3]].concat();
+        assert_eq!(read_err(&bytes), io::ErrorKind::UnexpectedEof);
+    }
+
+    #[test]
+    fn texture_sampling_roundtrip() {
+        let mut puppet = puppet(r#"{ "type": "Node", "uuid": 1 }"#, "[]");
+        puppet.push_texture(Texture::new(TextureEncoding::Png, vec![1, 2, 3]));
+        let mut bytes = Vec::new();
+        puppet.write(&mut bytes).unwrap();
+        assert!(!String::from_utf8_lossy(&bytes).contains(TEXTURE_SAMPLING_KEY));
+
+        let mut tiled = Texture::new(TextureEncoding::Png, vec![1, 2, 3]);
+        tiled.set_wrap(TextureWrap::Repeat);
+        tiled.set_filter(Some(TextureFilter::Nearest));
+        puppet.push_texture(tiled);
+        assert_eq!(puppet.dedupe_textures(), 0);
+
+        let mut bytes = Vec::new();
+        puppet.write(&mut bytes).unwrap();
+        let puppet = InochiPuppet::from_read(&mut &*bytes).unwrap();
+        assert!(puppet.extra_fields().is_empty());
+        assert_eq!(puppet.textures()[0].wrap(), TextureWrap::Clamp);
+        assert_eq!(puppet.textures()[0].filter(), None);
+        assert_eq!(puppet.textures()[1].wrap(), TextureWrap::Repeat);
+        assert_eq!(puppet.textures()[1].filter(), Some(TextureFilter::Nearest));
+    }
+
+    #[test]
+    fn solo_roundtrip() {
+        let puppet = puppet(
+            r#"{ "type": "Node", "uuid": 1, "children": [
+                { "type": "Part", "uuid": 2, "solo": true },
+                { "type": "Part", "uuid": 3 }
+            ] }"#,
+            "[]",
+        );
+        let mut bytes = Vec::new();
+        puppet.write(&mut bytes).unwrap();
+        let puppet = InochiPuppet::from_read(&mut &*bytes).unwrap();
+        let children = puppet.root_node().children();
+        assert!(children[0].solo());
+        assert!(!children[1].solo());
+
+        // Nodes without the flag are written without it.
+        let json = serde_json::to_value(&children[1]).unwrap();
+        assert!(json.get("solo").is_none());
+    }
+
+    #[test]
+    fn load_limits() {
+        let mut puppet = puppet(
+            r#"{ "type": "Node", "uuid": 1, "children": [{ "type": "Node", "uuid": 2 }] }"#,
+            "[]",
+        );
+        puppet.push_texture(Texture::new(TextureEncoding::Png, vec![0; 100]));
+        let mut bytes = Vec::new();
+        puppet.write(&mut bytes).unwrap();
+        let load = |limits: &LoadLimits| InochiPuppet::from_read_with_limits(&mut &*bytes, limits);
+
+        assert!(load(&LoadLimits::new()).is_ok());
+
+        let mut limits = LoadLimits::new();
+        limits.set_max_texture_size(99);
+        let err = load(&limits).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        assert!(err.to_string().contains("texture size of 100"), "{err}");
+
+        let mut limits = LoadLimits::new();
+        limits.set_max_node_count(1);
+        let err = load(&limits).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        assert!(err.to_string().contains("node count of 2"), "{err}");
+        limits.set_max_node_count(2);
+        assert!(load(&limits).is_ok());
+
+        let with_params = crate::testutil::puppet(
+            r#"{ "type": "Node", "uuid": 1 }"#,
+            r#"[{ "uuid": 10, "name": "a" }, { "uuid": 11, "name": "b" }]"#,
+        );
+        let mut with_params_bytes = Vec::new();
+        with_params.write(&mut with_params_bytes).unwrap();
+        let mut limits = LoadLimits::new();
+        limits.set_max_param_count(1);
+        let err =
+            InochiPuppet::from_read_with_limits(&mut &*with_params_bytes, &limits).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        assert!(err.to_string().contains("parameter count of 2"), "{err}");
+        let err = InochiPuppet::from_json_with_limits(with_params.to_json(), &limits).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        assert!(err.to_string().contains("parameter count of 2"), "{err}");
+        limits.set_max_param_count(2);
+        assert!(InochiPuppet::from_json_with_limits(with_params.to_json(), &limits).is_ok());
+
+        let mut limits = LoadLimits::new();
+        limits.set_max_total_memory(bytes.len() as u64 / 2);
+        assert_eq!(
+            load(&limits).unwrap_err().kind(),
+            io::ErrorKind::InvalidData
+        );
+
+        // The declared JSON length is rejected before its data is read.
+        let mut bytes = MAGIC.to_vec();
+        bytes.extend_from_slice(&(65u32 << 20).to_be_bytes());
+        let err =
+            InochiPuppet::from_read_with_limits(&mut &*bytes, &LoadLimits::new()).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn fingerprint() {
+        let mut puppet = puppet(
+            r#"{
+                "type": "Node", "uuid": 1,
+                "children": [{ "type": "Part", "uuid": 2, "transform": { "trans": [-0.0, 1.5, 0.0], "rot": [0.0, 0.0, 0.0], "scale": [1.0, 1.0] } }]
+            }"#,
+            r#"[{ "uuid": 10, "name": "Param" }]"#,
+        );
+        puppet.push_texture(Texture::new(TextureEncoding::Png, vec![1, 2, 3]));
+        puppet.push_vendor_data(VendorData::new("app".into(), vec![4, 5]));
+        let fingerprint = puppet.fingerprint();
+
+        let mut bytes = Vec::new();
+        puppet.write(&mut bytes).unwrap();
+        let resaved = InochiPuppet::from_read(&mut &*bytes).unwrap();
+        assert_eq!(resaved.fingerprint(), fingerprint);
+
+        // Rewrite the JSON data with different formatting, key order, and number representation,
+        // as another writer might.
+        let json =
+            serde_json::to_string_pretty(&serde_json::to_value(&puppet.data).unwrap()).unwrap();
+        assert!(json.contains("-0.0") && json.contains("1.5"));
+        let json = json.replace("-0.0", "0").replace("1.5", "1.50");
+        let mut bytes = MAGIC.to_vec();
+        bytes.extend_from_slice(&u32::try_from(json.len()).unwrap().to_be_bytes());
+        bytes.extend_from_slice(json.as_bytes());
+        let mut rest = Vec::new();
+        puppet.write(&mut rest).unwrap();
+        let tex = rest.windows(8).position(|w| w == MAGIC_TEX).unwrap();
+        bytes.extend_from_slice(&rest[tex..]);
+        let rewritten = InochiPuppet::from_read(&mut &*bytes).unwrap();
+        assert_eq!(rewritten.fingerprint(), fingerprint);
+
+        puppet.params_mut()[0].set_name("Renamed".into());
+        assert_ne!(puppet.fingerprint(), fingerprint);
+        puppet.params_mut()[0].set_name("Param".into());
+        assert_eq!(puppet.fingerprint(), fingerprint);
+        puppet.push_texture(Texture::new(TextureEncoding::Png, vec![1, 2, 3]));
+        assert_ne!(puppet.fingerprint(), fingerprint);
+
+        // UUIDs that only differ beyond the precision of `f64` are told apart.
+        let a = crate::testutil::puppet(r#"{ "type": "Node", "uuid": 9007199254740993 }"#, "[]");
+        let b = crate::testutil::puppet(r#"{ "type": "Node", "uuid": 9007199254740992 }"#, "[]");
+        assert_ne!(a.fingerprint(), b.fingerprint());
+    }
+
+    /// Transforms `point` from the local space of node `target` to model space.
+    fn model_point(node: &Node, target: Uuid, point: Vec2) -> Option<Vec2> {
+        let [x, y] = if node.uuid() == target {
+            point
+        } else {
+            node.children()
+                .iter()
+                .find_map(|child| model_point(child, target, point))?
+        };
+        let transform = node.transform();
+        let [sx, sy] = transform.scale();
+        let (sin, cos) = transform.rotation()[2].sin_cos();
+        let [x, y] = [x * sx, y * sy];
+        let [tx, ty, _] = transform.translation();
+        Some([tx + cos * x - sin * y, ty + sin * x + cos * y])
+    }
+
+    #[test]
+    fn prune_empty() {
+        let mut puppet = puppet(
+            r#"{
+                "type": "Node", "uuid": 1,
+                "children": [
+                    {
+                        "type": "Node", "uuid": 2,
+                        "transform": { "trans": [10.0, 5.0, 0.0], "rot": [0.0, 0.0, 1.0], "scale": [2.0, 2.0] },
+                        "children": [{
+                            "type": "Part", "uuid": 3,
+                            "transform": { "trans": [1.0, 0.0, 0.0], "rot": [0.0, 0.0, 0.25], "scale": [1.0, 3.0] }
+                        }]
+                    },
+                    { "type": "Node", "uuid": 4 },
+                    { "type": "Node", "uuid": 5 },
+                    {
+                        "type": "Node", "uuid": 6,
+                        "transform": { "trans": [0.0, 0.0, 0.0], "rot": [0.0, 0.0, 0.0], "scale": [1.0, 2.0] },
+                        "children": [{
+                            "type": "Part", "uuid": 7,
+                            "transform": { "trans": [0.0, 0.0, 0.0], "rot": [0.0, 0.0, 0.5], "scale": [1.0, 1.0] }
+                        }]
+                    },
+                    { "type": "Node", "uuid": 8, "children": [{ "type": "Node", "uuid": 9 }] }
+                ]
+            }"#,
+            r#"[{
+                "uuid": 10, "name": "Param",
+                "bindings": [{ "node": 5, "param_name": "transform.t.x", "values": [[0, 1]] }]
+            }]"#,
+        );
+        let points = [[0.0, 0.0], [1.0, 1.0], [-2.0, 0.5]];
+        let before = points.map(|p| model_point(puppet.root_node(), Uuid { raw: 3 }, p).unwrap());
+
+        // The empty group 8 is removed after its empty child 9.
+        assert_eq!(puppet.prune_empty(), 4);
+        let root = puppet.root_node();
+        let children = root
+            .children()
+            .iter()
+            .map(|ch| ch.uuid().raw())
+            .collect::<Vec<_>>();
+        // Group 6 would need a shear to be applied to its rotated child.
+        assert_eq!(children, [3, 5, 6]);
+
+        let after = points.map(|p| model_point(root, Uuid { raw: 3 }, p).unwrap());
+        for (before, after) in before.iter().zip(after) {
+            assert!(
+                (before[0] - after[0]).abs() < 1e-4 && (before[1] - after[1]).abs() < 1e-4,
+                "{before:?} {after:?}"
+            );
+        }
+
+        assert_eq!(puppet.prune_empty(), 0);
+    }
+
+    #[test]
+    fn extract_subtree() {
+        let mut puppet = puppet(
+            r#"{
+                "type": "Node", "uuid": 1,
+                "children": [
+                    {
+                        "type": "Node", "uuid": 2,
+                        "children": [
+                            { "type": "Part", "uuid": 3, "textures": [2], "masked_by": [4, 5] },
+                            { "type": "Part", "uuid": 4, "textures": [0] },
+                            { "type": "SimplePhysics", "uuid": 6, "param": 12 }
+                        ]
+                    },
+                    { "type": "Part", "uuid": 5, "textures": [1] }
+                ]
+            }"#,
+            r#"[
+                {
+                    "uuid": 10, "name": "Arm",
+                    "bindings": [
+                        { "node": 3, "param_name": "transform.t.x", "values": [[0, 1]] },
+                        { "node": 5, "param_name": "transform.t.x", "values": [[0, 1]] }
+                    ]
+                },
+                {
+                    "uuid": 11, "name": "Head",
+                    "bindings": [{ "node": 5, "param_name": "transform.t.y", "values": [[0, 1]] }]
+                },
+                { "uuid": 12, "name": "Sway" }
+            ]"#,
+        );
+        for i in 0..3 {
+            puppet.push_texture(Texture::new(TextureEncoding::Png, vec![i]));
+        }
+
+        let sub = puppet.extract_subtree(Uuid { raw: 2 }).unwrap();
+        assert_eq!(sub.root_node().uuid().raw(), 2);
+        assert_eq!(sub.root_node().children().len(), 3);
+
+        let params = sub
+            .params()
+            .iter()
+            .map(|param| {
+                let nodes = param.bindings().iter().map(|b| b.node().raw());
+                (param.uuid().raw(), nodes.collect::<Vec<_>>())
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(params, [(10, vec![3]), (12, vec![])]);
+
+        let data = sub
+            .textures()
+            .iter()
+            .map(|tex| tex.data()[0])
+            .collect::<Vec<_>>();
+        assert_eq!(data, [2, 0]);
+        let children = sub.root_node().children();
+        let (Node::Part(arm), Node::Part(hand)) = (&children[0], &children[1]) else {
+            unreachable!()
+        };
+        assert_eq!(arm.textures(), [0]);
+        assert_eq!(arm.masked_by(), [Uuid { raw: 4 }]);
+        assert_eq!(hand.textures(), [1]);
+
+        assert!(puppet.extract_subtree(Uuid { raw: 99 }).is_none());
+    }
+
+    #[test]
+    fn gzip_from_path() {
+        use flate2::{write::GzEncoder, Compression};
+
+        let mut puppet = puppet(r#"{ "type": "Node", "uuid": 1 }"#, "[]");
+        puppet.push_texture(Texture::new(TextureEncoding::Png, vec![1, 2, 3]));
+        let mut bytes = Vec::new();
+        puppet.write(&mut bytes).unwrap();
+        let mut gz = GzEncoder::new(Vec::new(), Compression::default());
+        gz.write_all(&bytes).unwrap();
+        let gz = gz.finish().unwrap();
+
+        let dir = std::env::temp_dir();
+        let plain_path = dir.join(format!("rhino2d-test-{}.inp", std::process::id()));
+        let gz_path = dir.join(format!("rhino2d-test-{}.inp.gz", std::process::id()));
+        std::fs::write(&plain_path, &bytes).unwrap();
+        std::fs::write(&gz_path, &gz).unwrap();
+        let plain = InochiPuppet::from_path(&plain_path);
+        let unzipped = InochiPuppet::from_path(&gz_path);
+        std::fs::remove_file(&plain_path).unwrap();
+        std::fs::remove_file(&gz_path).unwrap();
+
+        assert_eq!(plain.unwrap().fingerprint(), puppet.fingerprint());
+        assert_eq!(unzipped.unwrap().fingerprint(), puppet.fingerprint());
+    }
+
+    #[test]
+    fn compressed_json() {
+        let mut puppet = puppet(
+            r#"{ "type": "Node", "uuid": 1, "children": [
+                { "type": "Part", "uuid": 2 }, { "type": "Part", "uuid": 3 },
+                { "type": "Part", "uuid": 4 }, { "type": "Part", "uuid": 5 }
+            ] }"#,
+            r#"[{ "uuid": 10, "name": "Param" }]"#,
+        );
+        puppet.push_texture(Texture::new(TextureEncoding::Png, vec![1, 2, 3]));
+        let mut plain = Vec::new();
+        puppet.write(&mut plain).unwrap();
+        let mut compressed = Vec::new();
+        puppet.write_compressed(&mut compressed).unwrap();
+        assert!(compressed.len() < plain.len());
+        assert_eq!(compressed[8] & 0x80, 0x80);
+
+        let reloaded = InochiPuppet::from_read(&mut &*compressed).unwrap();
+        assert_eq!(reloaded.fingerprint(), puppet.fingerprint());
+        assert_eq!(reloaded.to_json(), puppet.to_json());
+
+        // The size limit applies to the decompressed data.
+        let json_len = serde_json::to_vec(&puppet.json_data()).unwrap().len();
+        let mut limits = LoadLimits::new();
+        limits.set_max_json_size(json_len as u32);
+        assert!(InochiPuppet::from_read_with_limits(&mut &*compressed, &limits).is_ok());
+        limits.set_max_json_size(json_len as u32 - 1);
+        let err = InochiPuppet::from_read_with_limits(&mut &*compressed, &limits).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+
+        // Corrupted compressed data is rejected.
+        compressed[20] ^= 0xff;
+        assert!(InochiPuppet::from_read(&mut &*compressed).is_err());
+    }
+
+    #[test]
+    fn unique_uuids() {
+        let model = puppet(
+            r#"{ "type": "Node", "uuid": 1, "children": [{ "type": "Node", "uuid": 2 }] }"#,
+            r#"[{ "uuid": 3, "name": "Param" }]"#,
+        );
+        let a = model.new_uuid();
+        let b = model.new_uuid();
+        assert_ne!(a, b);
+        for uuid in [a, b] {
+            assert!(uuid.raw() < u64::from(u32::MAX));
+        }
+
+        // IDs used by nodes or parameters are skipped.
+        let candidates = [1, 2, 3, 4].map(Uuid::from_raw);
+        assert_eq!(model.first_unused(candidates), Uuid::from_raw(4));
+        assert_eq!(Uuid::from_raw(42).raw(), 42);
+        assert_eq!(Uuid::INVALID.raw(), 4294967295);
+    }
+
+    #[test]
+    fn json_export() {
+        let mut puppet = puppet(
+            r#"{ "type": "Node", "uuid": 1, "children": [
+                { "type": "Part", "uuid": 2, "children": [{ "type": "Node", "uuid": 3 }] },
+                { "type": "SimplePhysics", "uuid": 4, "param": 10 }
+            ] }"#,
+            r#"[{
+                "uuid": 10, "name": "Move",
+                "bindings": [{ "node": 3, "param_name": "transform.t.x", "values": [[0, 1]] }]
+            }]"#,
+        );
+        puppet.push_automation(
+            serde_json::from_value(serde_json::json!({
+                "type": "sine", "name": "idle", "speed": 1.0, "sine_type": 0,
+                "bindings": [{ "param": "Move", "axis": 0, "range": [0.0, 1.0] }],
+            }))
+            .unwrap(),
+        );
+        puppet.push_texture(Texture::new(TextureEncoding::Png, vec![1, 2, 3]));
+
+        let json = puppet.to_json();
+        assert_eq!(json["nodes"]["children"][0]["uuid"], 2);
+        let reparsed = InochiPuppet::from_json(json.clone()).unwrap();
+        assert_eq!(reparsed.root_node().descendants().count(), 3);
+        assert_eq!(reparsed.params().len(), 1);
+        assert_eq!(reparsed.params()[0].bindings().len(), 1);
+        assert_eq!(reparsed.automations().len(), 1);
+        assert!(reparsed.textures().is_empty());
+        assert_eq!(reparsed.to_json(), json);
+
+        let mut written = Vec::new();
+        puppet.write_json(&mut written).unwrap();
+        // Floats are written in their shortest form, so compare them at their original precision.
+        let written = serde_json::from_slice(&written).unwrap();
+        assert_eq!(InochiPuppet::from_json(written).unwrap().to_json(), json);
+
+        assert!(InochiPuppet::from_json(serde_json::json!({ "nodes": 1 })).is_err());
+    }
+
+    #[test]
+    fn uuid_forms() {
+        let parse = |json: &str| serde_json::from_str::<Uuid>(json).map(|uuid| uuid.raw);
+        assert_eq!(parse("42").unwrap(), 42);
+        assert_eq!(parse(&u64::MAX.to_string()).unwrap(), u64::MAX);
+        assert_eq!(parse(r#""42""#).unwrap(), 42);
+
+        let hashed = parse(r#""123e4567-e89b-12d3-a456-426614174000""#).unwrap();
+        assert_eq!(hashed, 0x7f0c_9ad0_1399_33e0);
+        assert_eq!(
+            parse(r#""123E4567-E89B-12D3-A456-426614174000""#).unwrap(),
+            hashed
+        );
+        assert_ne!(
+            parse(r#""123e4567-e89b-12d3-a456-426614174001""#).unwrap(),
+            hashed
+        );
+
+        for invalid in [
+            "-1",
+            "1.5",
+            r#""""#,
+            r#""abc""#,
+            r#""123e4567e89b12d3a456426614174000""#,
+            r#""123e4567-e89b-12d3-a456-42661417400g""#,
+        ] {
+            assert!(parse(invalid).is_err(), "{invalid}");
+        }
+
+        // IDs are always written as numbers.
+        let uuid: Uuid = serde_json::from_str(r#""7""#).unwrap();
+        assert_eq!(serde_json::to_string(&uuid).unwrap(), "7");
+    }
+
+    #[test]
+    fn self_masking_part() {
+        let puppet = puppet(
+            r#"{
+                "type": "Node", "uuid": 1,
+                "children": [
+                    { "type": "Part", "uuid": 2, "masked_by": [3, 2, 2] },
+                    { "type": "Part", "uuid": 3, "masked_by": [3] }
+                ]
+            }"#,
+            "[]",
+        );
+        let mut bytes = Vec::new();
+        puppet.write(&mut bytes).unwrap();
+
+        let puppet = InochiPuppet::from_read(&mut &*bytes).unwrap();
+        let masks = puppet
+            .root_node()
+            .children()
+            .iter()
+            .map(|node| match node {
+                Node::Part(part) => part.masked_by().to_vec(),
+                _ => unreachable!(),
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(masks, [vec![Uuid { raw: 3 }], vec![]]);
+    }
+
+    #[test]
+    fn bc7_dimensions_roundtrip() {
+        let mut puppet = puppet(r#"{ "type": "Node", "uuid": 1 }"#, "[]");
+        puppet.push_texture(Texture::new(TextureEncoding::Png, vec![1, 2, 3]));
+        let mut png_bytes = Vec::new();
+        puppet.write(&mut png_bytes).unwrap();
+        let section = png_bytes
+            .windows(8)
+            .position(|w| w == MAGIC_TEX)
+            .expect("models without BC7 textures use the Inochi2D texture section");
+
+        puppet.push_texture(Texture::bc7(8, 4, vec![0; 32]));
+        let mut bytes = Vec::new();
+        puppet.write(&mut bytes).unwrap();
+        let puppet = InochiPuppet::from_read(&mut &*bytes).unwrap();
+        assert_eq!(puppet.textures()[0].dimensions(), None);
+        assert_eq!(puppet.textures()[1].encoding(), TextureEncoding::Bc7);
+        assert_eq!(puppet.textures()[1].dimensions(), Some([8, 4]));
+        assert_eq!(puppet.textures()[1].data(), [0; 32]);
+
+        // BC7 textures in the Inochi2D texture section have no dimensions, and can't be saved.
+        png_bytes[section + 8 + 4 + 4] = TextureEncoding::Bc7 as u8;
+        let legacy = InochiPuppet::from_read(&mut &*png_bytes).unwrap();
+        assert_eq!(legacy.textures()[0].encoding(), TextureEncoding::Bc7);
+        assert_eq!(legacy.textures()[0].dimensions(), None);
+        let err = legacy.write(&mut Vec::new()).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+    }
+
+    #[test]
+    fn resolve_external_textures() {
+        let mut puppet = puppet(r#"{ "type": "Node", "uuid": 1 }"#, "[]");
+        puppet.push_texture(Texture::new(TextureEncoding::Png, vec![1, 2, 3]));
+        puppet.push_texture(Texture::external("shared/face.tga".into()));
+        let mut bytes = Vec::new();
+        puppet.write(&mut bytes).unwrap();
+
+        let unresolved = InochiPuppet::from_read(&mut &*bytes).unwrap();
+        assert_eq!(
+            unresolved.textures()[1].external_reference().unwrap(),
+            Some("shared/face.tga")
+        );
+
+        let mut requested = Vec::new();
+        let resolved = InochiPuppet::from_read_with_resolver(&mut &*bytes, |reference| {
+            requested.push(reference.to_string());
+            Ok(Texture::new(TextureEncoding::Tga, vec![4, 5]))
+        })
+        .unwrap();
+        assert_eq!(requested, ["shared/face.tga"]);
+        assert_eq!(resolved.textures()[0].data(), [1, 2, 3]);
+        assert_eq!(resolved.textures()[1].encoding(), TextureEncoding::Tga);
+        assert_eq!(resolved.textures()[1].data(), [4, 5]);
+
+        let err = InochiPuppet::from_read_with_resolver(&mut &*bytes, |_| {
+            Err(io::Error::new(io::ErrorKind::NotFound, "missing"))
+        })
+        .unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::NotFound);
+    }
+}
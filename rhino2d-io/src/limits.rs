@@ -0,0 +1,314 @@
+use std::fmt;
+use std::io;
+
+use serde::de::{self, DeserializeSeed, IgnoredAny, MapAccess, SeqAccess, Visitor};
+use serde::Deserializer;
+
+/// Limits on the size of models loaded via [`crate::InochiPuppet::from_read_with_limits`] or
+/// [`crate::InochiPuppet::from_json_with_limits`].
+///
+/// Loading a model file that exceeds any of the limits fails with an
+/// [`io::ErrorKind::InvalidData`] error, before the memory for the offending data is allocated.
+#[derive(Debug, Clone)]
+pub struct LoadLimits {
+    max_json_size: u32,
+    max_texture_size: u32,
+    max_node_count: usize,
+    max_param_count: usize,
+    max_total_memory: u64,
+}
+
+impl LoadLimits {
+    /// Creates limits suitable for loading untrusted models.
+    ///
+    /// They are generous enough for typical models: JSON data and textures of up to 64 MiB each,
+    /// up to 100000 nodes, up to 10000 parameters, and up to 512 MiB of data in total.
+    pub fn new() -> Self {
+        Self {
+            max_json_size: 64 << 20,
+            max_texture_size: 64 << 20,
+            max_node_count: 100_000,
+            max_param_count: 10_000,
+            max_total_memory: 512 << 20,
+        }
+    }
+
+    /// Creates limits that allow loading any model.
+    pub fn unlimited() -> Self {
+        Self {
+            max_json_size: u32::MAX,
+            max_texture_size: u32::MAX,
+            max_node_count: usize::MAX,
+            max_param_count: usize::MAX,
+            max_total_memory: u64::MAX,
+        }
+    }
+
+    pub fn max_json_size(&self) -> u32 {
+        self.max_json_size
+    }
+
+    /// Sets the maximum size of the model's JSON data, in bytes.
+    pub fn set_max_json_size(&mut self, max_json_size: u32) {
+        self.max_json_size = max_json_size;
+    }
+
+    pub fn max_texture_size(&self) -> u32 {
+        self.max_texture_size
+    }
+
+    /// Sets the maximum size of each (encoded) texture and vendor data payload, in bytes.
+    pub fn set_max_texture_size(&mut self, max_texture_size: u32) {
+        self.max_texture_size = max_texture_size;
+    }
+
+    pub fn max_node_count(&self) -> usize {
+        self.max_node_count
+    }
+
+    /// Sets the maximum number of nodes in the model, including the root node.
+    pub fn set_max_node_count(&mut self, max_node_count: usize) {
+        self.max_node_count = max_node_count;
+    }
+
+    pub fn max_param_count(&self) -> usize {
+        self.max_param_count
+    }
+
+    /// Sets the maximum number of parameters in the model.
+    pub fn set_max_param_count(&mut self, max_param_count: usize) {
+        self.max_param_count = max_param_count;
+    }
+
+    pub fn max_total_memory(&self) -> u64 {
+        self.max_total_memory
+    }
+
+    /// Sets the maximum combined size of the JSON data, textures, and vendor data, in bytes.
+    pub fn set_max_total_memory(&mut self, max_total_memory: u64) {
+        self.max_total_memory = max_total_memory;
+    }
+}
+
+impl Default for LoadLimits {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Tracks the resources used by a model while it is being loaded.
+pub(crate) struct Budget<'a> {
+    limits: &'a LoadLimits,
+    total_memory: u64,
+}
+
+impl<'a> Budget<'a> {
+    pub(crate) fn new(limits: &'a LoadLimits) -> Self {
+        Self {
+            limits,
+            total_memory: 0,
+        }
+    }
+
+    pub(crate) fn json(&mut self, len: u32) -> io::Result<()> {
+        check(
+            "JSON data size",
+            len.into(),
+            self.limits.max_json_size.into(),
+        )?;
+        self.allocate(len)
+    }
+
+    pub(crate) fn max_json_size(&self) -> u32 {
+        self.limits.max_json_size
+    }
+
+    /// Accounts for a texture or vendor data payload, described by `what` in errors.
+    pub(crate) fn payload(&mut self, what: &str, len: u32) -> io::Result<()> {
+        check(what, len.into(), self.limits.max_texture_size.into())?;
+        self.allocate(len)
+    }
+
+    /// Checks the number of nodes and parameters in the model JSON data `json`.
+    ///
+    /// The data is scanned without building the node tree, so that a model with too many nodes
+    /// is rejected before the memory for them is allocated. Malformed data is not rejected here.
+    pub(crate) fn counts(&self, json: &[u8]) -> io::Result<()> {
+        self.count_with(&mut serde_json::Deserializer::from_slice(json))
+    }
+
+    /// Checks the number of nodes and parameters in the already parsed model JSON data `json`.
+    pub(crate) fn value_counts(&self, json: &serde_json::Value) -> io::Result<()> {
+        self.count_with(json)
+    }
+
+    fn count_with<'de, D: Deserializer<'de>>(&self, de: D) -> io::Result<()> {
+        let mut nodes = Counter::new(self.limits.max_node_count);
+        let mut params = Counter::new(self.limits.max_param_count);
+        let _ = de.deserialize_any(CountVisitor {
+            nodes: &mut nodes,
+            params: &mut params,
+        });
+        nodes.check("node count")?;
+        params.check("parameter count")
+    }
+
+    fn allocate(&mut self, len: u32) -> io::Result<()> {
+        self.total_memory += u64::from(len);
+        check(
+            "total size",
+            self.total_memory,
+            self.limits.max_total_memory,
+        )
+    }
+}
+
+fn check(what: &str, value: u64, limit: u64) -> io::Result<()> {
+    if value > limit {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("model {what} of {value} exceeds the limit of {limit}"),
+        ));
+    }
+    Ok(())
+}
+
+/// Counts entities while scanning model JSON data, stopping the scan once there are too many.
+struct Counter {
+    count: usize,
+    limit: usize,
+}
+
+impl Counter {
+    fn new(limit: usize) -> Self {
+        Self { count: 0, limit }
+    }
+
+    fn add<E: de::Error>(&mut self) -> Result<(), E> {
+        self.count += 1;
+        if self.count > self.limit {
+            return Err(E::custom("limit exceeded"));
+        }
+        Ok(())
+    }
+
+    fn check(&self, what: &str) -> io::Result<()> {
+        check(
+            what,
+            self.count as u64,
+            self.limit.try_into().unwrap_or(u64::MAX),
+        )
+    }
+}
+
+/// Visits the top-level object of the model JSON data, counting its nodes and parameters.
+struct CountVisitor<'a> {
+    nodes: &'a mut Counter,
+    params: &'a mut Counter,
+}
+
+impl<'de> Visitor<'de> for CountVisitor<'_> {
+    type Value = ();
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("model JSON data")
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<(), A::Error> {
+        while let Some(key) = map.next_key::<&str>()? {
+            match key {
+                "nodes" => map.next_value_seed(NodeSeed(&mut *self.nodes))?,
+                "param" => map.next_value_seed(ParamSeed(&mut *self.params))?,
+                _ => {
+                    map.next_value::<IgnoredAny>()?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Counts a node and its descendants.
+struct NodeSeed<'a>(&'a mut Counter);
+
+impl<'de> DeserializeSeed<'de> for NodeSeed<'_> {
+    type Value = ();
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<(), D::Error> {
+        deserializer.deserialize_any(self)
+    }
+}
+
+impl<'de> Visitor<'de> for NodeSeed<'_> {
+    type Value = ();
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("a node")
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<(), A::Error> {
+        self.0.add()?;
+        while let Some(key) = map.next_key::<&str>()? {
+            if key == "children" {
+                map.next_value_seed(ChildrenSeed(&mut *self.0))?;
+            } else {
+                map.next_value::<IgnoredAny>()?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Counts the nodes in a list of children.
+struct ChildrenSeed<'a>(&'a mut Counter);
+
+impl<'de> DeserializeSeed<'de> for ChildrenSeed<'_> {
+    type Value = ();
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<(), D::Error> {
+        deserializer.deserialize_any(self)
+    }
+}
+
+impl<'de> Visitor<'de> for ChildrenSeed<'_> {
+    type Value = ();
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("a list of nodes")
+    }
+
+    fn visit_unit<E: de::Error>(self) -> Result<(), E> {
+        Ok(())
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<(), A::Error> {
+        while seq.next_element_seed(NodeSeed(&mut *self.0))?.is_some() {}
+        Ok(())
+    }
+}
+
+/// Counts the entries of the parameter list.
+struct ParamSeed<'a>(&'a mut Counter);
+
+impl<'de> DeserializeSeed<'de> for ParamSeed<'_> {
+    type Value = ();
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<(), D::Error> {
+        deserializer.deserialize_any(self)
+    }
+}
+
+impl<'de> Visitor<'de> for ParamSeed<'_> {
+    type Value = ();
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("a list of parameters")
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<(), A::Error> {
+        while seq.next_element::<IgnoredAny>()?.is_some() {
+            self.0.add()?;
+        }
+        Ok(())
+    }
+}
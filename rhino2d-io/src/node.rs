@@ -9,6 +9,8 @@
 //!
 //! [`InochiPuppet::root_node`]: crate::InochiPuppet::root_node
 
+use std::collections::{BTreeMap, HashSet};
+use std::io;
 use std::ops::{Deref, DerefMut};
 
 use serde::{Deserialize, Serialize};
@@ -30,6 +32,14 @@ pub enum Node {
 }
 
 impl Node {
+    /// Calls `f` with `self` and every node below it, in depth-first order.
+    pub(crate) fn visit_mut(&mut self, f: &mut dyn FnMut(&mut Node)) {
+        f(self);
+        for child in self.children_mut() {
+            child.visit_mut(f);
+        }
+    }
+
     pub fn type_name(&self) -> &str {
         match self {
             Node::Node(_) => "Node",
@@ -82,6 +92,9 @@ pub struct NodeBase {
     uuid: Uuid,
     name: String,
     enabled: bool,
+    /// Editor flag that shows only soloed nodes. Omitted by files that don't use it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    solo: Option<bool>,
     zsort: f32,
     transform: Transform,
     lock_to_root: bool,
@@ -94,6 +107,7 @@ impl NodeBase {
             uuid,
             name,
             enabled: true,
+            solo: None,
             zsort: 0.0,
             transform: Transform::new(),
             lock_to_root: false,
@@ -125,6 +139,19 @@ impl NodeBase {
         self.enabled = enabled;
     }
 
+    /// Returns whether the node is soloed.
+    ///
+    /// Editors use this to inspect individual parts: while any part of a puppet is soloed, only
+    /// the soloed parts are shown. Unlike [`NodeBase::enabled`], this is view state of the editor
+    /// rather than part of the rig.
+    pub fn solo(&self) -> bool {
+        self.solo.unwrap_or(false)
+    }
+
+    pub fn set_solo(&mut self, solo: bool) {
+        self.solo = Some(solo);
+    }
+
     /// Returns the node's Z-Sort value.
     ///
     /// The Z-Axis points into the scene, so nodes with a *lower* Z-Sort value are in front of
@@ -162,9 +189,171 @@ impl NodeBase {
         self.children.as_deref_mut().unwrap_or(&mut [])
     }
 
+    /// Returns an iterator over every node below `self`, in depth-first order.
+    ///
+    /// Every node is followed by its own descendants, before its next sibling. `self` is not
+    /// included.
+    pub fn descendants(&self) -> impl Iterator<Item = &Node> + '_ {
+        let mut stack = vec![self.children().iter()];
+        std::iter::from_fn(move || loop {
+            match stack.last_mut()?.next() {
+                Some(node) => {
+                    stack.push(node.children().iter());
+                    return Some(node);
+                }
+                None => {
+                    stack.pop();
+                }
+            }
+        })
+    }
+
+    /// Calls `f` with every node below `self`, in the same order as [`NodeBase::descendants`].
+    ///
+    /// This takes a closure instead of returning an iterator, since an iterator can't hand out
+    /// mutable references to a node and to its children at the same time.
+    pub fn descendants_mut(&mut self, mut f: impl FnMut(&mut Node)) {
+        for child in self.children_mut() {
+            child.visit_mut(&mut f);
+        }
+    }
+
+    /// Returns the node below `self` with the given UUID.
+    ///
+    /// `self` is not considered. If several nodes share the UUID, the first one in the order of
+    /// [`NodeBase::descendants`] is returned.
+    pub fn find_by_uuid(&self, uuid: Uuid) -> Option<&Node> {
+        self.descendants().find(|node| node.uuid() == uuid)
+    }
+
+    /// Returns the node below `self` with the given name.
+    ///
+    /// Node names are not guaranteed to be unique, so this returns the first match in the order of
+    /// [`NodeBase::descendants`]. `self` is not considered.
+    pub fn find_by_name(&self, name: &str) -> Option<&Node> {
+        self.descendants().find(|node| node.name() == name)
+    }
+
+    /// Like [`NodeBase::find_by_name`], but returns a mutable reference.
+    pub fn find_by_name_mut(&mut self, name: &str) -> Option<&mut Node> {
+        for child in self.children_mut() {
+            if child.name() == name {
+                return Some(child);
+            }
+            if let Some(node) = child.find_by_name_mut(name) {
+                return Some(node);
+            }
+        }
+        None
+    }
+
     pub fn push_child(&mut self, node: Node) {
         self.children.get_or_insert(Vec::new()).push(node);
     }
+
+    /// Removes group nodes below `self` that have no effect, as described in
+    /// [`InochiPuppet::prune_empty`][crate::InochiPuppet::prune_empty].
+    ///
+    /// Returns the number of removed nodes.
+    pub(crate) fn prune_empty(&mut self, referenced: &HashSet<Uuid>) -> usize {
+        let Some(children) = self.children.take() else {
+            return 0;
+        };
+
+        let mut pruned = 0;
+        let mut kept = Vec::with_capacity(children.len());
+        for mut child in children {
+            pruned += child.prune_empty(referenced);
+            match child {
+                Node::Node(group)
+                    if group.enabled
+                        && !group.lock_to_root
+                        && !referenced.contains(&group.uuid) =>
+                {
+                    match group.dissolve() {
+                        Ok(grandchildren) => {
+                            kept.extend(grandchildren);
+                            pruned += 1;
+                        }
+                        Err(group) => kept.push(Node::Node(group)),
+                    }
+                }
+                child => kept.push(child),
+            }
+        }
+        self.children = Some(kept);
+        pruned
+    }
+
+    /// Returns the children of `self`, with `self`'s transform applied to them, so that they can
+    /// take its place in the tree.
+    ///
+    /// If the combined transform of any child can not be represented as a [`Transform`], `self` is
+    /// returned unchanged instead.
+    fn dissolve(mut self) -> Result<Vec<Node>, Self> {
+        let mut children = self.children.take().unwrap_or_default();
+        let transforms: Option<Vec<Transform>> = children
+            .iter()
+            .map(|child| {
+                if child.lock_to_root {
+                    Some(child.transform.clone())
+                } else {
+                    self.transform.compose(&child.transform)
+                }
+            })
+            .collect();
+
+        match transforms {
+            Some(transforms) => {
+                for (child, transform) in children.iter_mut().zip(transforms) {
+                    child.transform = transform;
+                }
+                Ok(children)
+            }
+            None => {
+                self.children = Some(children);
+                Err(self)
+            }
+        }
+    }
+
+    /// Moves the child at index `from` to index `to`, shifting the children in between.
+    ///
+    /// Children are drawn in order when their Z-Sort values are equal, so this changes the draw
+    /// order of such children.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `from` or `to` is out of bounds.
+    pub fn move_child(&mut self, from: usize, to: usize) {
+        let children = self.children_mut();
+        check_child_index(from, children.len());
+        check_child_index(to, children.len());
+        if from < to {
+            children[from..=to].rotate_left(1);
+        } else {
+            children[to..=from].rotate_right(1);
+        }
+    }
+
+    /// Swaps the children at index `a` and `b`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `a` or `b` is out of bounds.
+    pub fn swap_children(&mut self, a: usize, b: usize) {
+        let children = self.children_mut();
+        check_child_index(a, children.len());
+        check_child_index(b, children.len());
+        children.swap(a, b);
+    }
+}
+
+fn check_child_index(index: usize, len: usize) {
+    assert!(
+        index < len,
+        "child index {index} out of bounds for node with {len} children"
+    );
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -315,10 +504,27 @@ impl JointBindingData {
     /// Every entry in the returned slice corresponds to one joint. Every entry in the contained
     /// `Vec<usize>` is a vertex index that should be affected by the joint.
     ///
-    /// FIXME: provide a better interface, this is really hard to understand
+    /// [`JointBindingData::vertex_joints`] returns the same data by vertex instead.
     pub fn bind_data(&self) -> &[Vec<usize>] {
         &self.bind_data
     }
+
+    /// Returns the joints affecting every bound vertex of the attached [`Drawable`].
+    ///
+    /// The map is keyed by vertex index, and lists the indices of the joints affecting the vertex
+    /// in ascending order. Vertices that aren't affected by any joint are not included.
+    pub fn vertex_joints(&self) -> BTreeMap<usize, Vec<usize>> {
+        let mut vertices: BTreeMap<usize, Vec<usize>> = BTreeMap::new();
+        for (joint, bound) in self.bind_data.iter().enumerate() {
+            for &vertex in bound {
+                let joints = vertices.entry(vertex).or_default();
+                if joints.last() != Some(&joint) {
+                    joints.push(joint);
+                }
+            }
+        }
+        vertices
+    }
 }
 
 /// A node with associated mesh data.
@@ -494,6 +700,91 @@ pub struct MeshData {
 }
 
 impl MeshData {
+    /// Creates a mesh from its vertex positions, optional texture coordinates, and triangle
+    /// indices.
+    ///
+    /// Every 3 consecutive `indices` form a triangle. `origin` is the point the mesh is positioned
+    /// relative to.
+    ///
+    /// Returns an error if `uvs` is present and doesn't have one entry per vertex. The indices are
+    /// not checked, see [`InochiPuppet::validate`] for that.
+    ///
+    /// [`InochiPuppet::validate`]: crate::InochiPuppet::validate
+    pub fn new(
+        verts: Vec<Vec2>,
+        uvs: Option<Vec<Vec2>>,
+        indices: Vec<u16>,
+        origin: Vec2,
+    ) -> io::Result<Self> {
+        if let Some(uvs) = &uvs {
+            if uvs.len() != verts.len() {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("mesh has {} vertices, but {} UVs", verts.len(), uvs.len()),
+                ));
+            }
+        }
+        Ok(Self {
+            verts: verts.into_iter().flatten().collect(),
+            uvs: uvs.map(|uvs| uvs.into_iter().flatten().collect()),
+            indices,
+            origin,
+        })
+    }
+
+    /// Creates a regular grid of `cols` by `rows` cells, `size` pixels wide and high in total and
+    /// centered on the origin.
+    ///
+    /// The UVs span the texture from `[0.0, 0.0]` in the top left corner to `[1.0, 1.0]` in the
+    /// bottom right one. Vertices are laid out row by row, starting at the top left corner, and
+    /// every cell is split into two triangles with the same winding.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the grid has more vertices than can be indexed with a `u16`.
+    pub fn grid(cols: usize, rows: usize, size: Vec2) -> Self {
+        let stride = cols + 1;
+        let vertex_count = stride * (rows + 1);
+        assert!(
+            vertex_count <= usize::from(u16::MAX) + 1,
+            "{cols}x{rows} grid has too many vertices",
+        );
+
+        let mut verts = Vec::with_capacity(vertex_count);
+        let mut uvs = Vec::with_capacity(vertex_count);
+        let [cols_f, rows_f] = [cols.max(1) as f32, rows.max(1) as f32];
+        for row in 0..=rows {
+            for col in 0..=cols {
+                let [col, row] = [col as f32, row as f32];
+                verts.push([
+                    col * size[0] / cols_f - size[0] / 2.0,
+                    row * size[1] / rows_f - size[1] / 2.0,
+                ]);
+                uvs.push([col / cols_f, row / rows_f]);
+            }
+        }
+
+        let mut indices = Vec::with_capacity(cols * rows * 6);
+        for row in 0..rows {
+            for col in 0..cols {
+                let top_left = (row * stride + col) as u16;
+                let top_right = top_left + 1;
+                let bottom_left = top_left + stride as u16;
+                let bottom_right = bottom_left + 1;
+                indices.extend_from_slice(&[
+                    top_left,
+                    top_right,
+                    bottom_left,
+                    bottom_left,
+                    top_right,
+                    bottom_right,
+                ]);
+            }
+        }
+
+        Self::new(verts, Some(uvs), indices, [0.0; 2]).unwrap()
+    }
+
     pub fn verts(&self) -> impl Iterator<Item = Vec2> + '_ {
         self.verts.chunks(2).map(|chunk| [chunk[0], chunk[1]])
     }
@@ -507,10 +798,95 @@ impl MeshData {
         )
     }
 
+    /// Returns the minimum and maximum corner of the rectangle spanned by the mesh's vertices.
+    ///
+    /// Like the vertices, the corners are relative to the node the mesh belongs to. A mesh without
+    /// vertices returns an empty rectangle at `[0.0, 0.0]`.
+    pub fn bounds(&self) -> [Vec2; 2] {
+        let mut verts = self.verts();
+        let Some(first) = verts.next() else {
+            return [[0.0; 2]; 2];
+        };
+        verts.fold([first, first], |[min, max], [x, y]| {
+            [
+                [min[0].min(x), min[1].min(y)],
+                [max[0].max(x), max[1].max(y)],
+            ]
+        })
+    }
+
+    /// Returns the minimum and maximum corner of the rectangle spanned by the mesh's UVs.
+    ///
+    /// Returns `None` if the mesh has no UVs.
+    pub fn uv_bounds(&self) -> Option<[Vec2; 2]> {
+        let mut uvs = self.uvs()?;
+        let first = uvs.next()?;
+        Some(uvs.fold([first, first], |[min, max], uv| {
+            [
+                [min[0].min(uv[0]), min[1].min(uv[1])],
+                [max[0].max(uv[0]), max[1].max(uv[1])],
+            ]
+        }))
+    }
+
     pub fn indices(&self) -> &[u16] {
         &self.indices
     }
 
+    /// Computes a tangent for every vertex, pointing in the direction the U texture coordinate
+    /// increases in.
+    ///
+    /// Together with the mesh normal (pointing out of the screen, along -Z), the tangents define
+    /// the tangent space normal and bump maps are expressed in. Tangents are normalized, and
+    /// averaged over all triangles sharing a vertex. Vertices that are not part of any
+    /// non-degenerate triangle get a tangent of `[1.0, 0.0, 0.0]`.
+    ///
+    /// Returns `None` if the mesh has no UVs.
+    pub fn compute_tangents(&self) -> Option<Vec<Vec3>> {
+        let verts: Vec<Vec2> = self.verts().collect();
+        let uvs: Vec<Vec2> = self.uvs()?.collect();
+        let mut tangents = vec![[0.0; 2]; verts.len()];
+
+        for tri in self.indices.chunks_exact(3) {
+            let [a, b, c] = [tri[0], tri[1], tri[2]].map(usize::from);
+            if a.max(b).max(c) >= verts.len().min(uvs.len()) {
+                continue;
+            }
+
+            let e1 = [verts[b][0] - verts[a][0], verts[b][1] - verts[a][1]];
+            let e2 = [verts[c][0] - verts[a][0], verts[c][1] - verts[a][1]];
+            let [du1, dv1] = [uvs[b][0] - uvs[a][0], uvs[b][1] - uvs[a][1]];
+            let [du2, dv2] = [uvs[c][0] - uvs[a][0], uvs[c][1] - uvs[a][1]];
+            let det = du1 * dv2 - du2 * dv1;
+            if det == 0.0 || !det.is_finite() {
+                continue;
+            }
+
+            let tangent = [
+                (e1[0] * dv2 - e2[0] * dv1) / det,
+                (e1[1] * dv2 - e2[1] * dv1) / det,
+            ];
+            for i in [a, b, c] {
+                tangents[i][0] += tangent[0];
+                tangents[i][1] += tangent[1];
+            }
+        }
+
+        Some(
+            tangents
+                .into_iter()
+                .map(|[x, y]| {
+                    let len = x.hypot(y);
+                    if len > 0.0 && len.is_finite() {
+                        [x / len, y / len, 0.0]
+                    } else {
+                        [1.0, 0.0, 0.0]
+                    }
+                })
+                .collect(),
+        )
+    }
+
     pub fn vertex_count(&self) -> usize {
         self.verts.len() / 2
     }
@@ -529,13 +905,75 @@ impl MeshData {
 /// Scale is applied first, then rotation, then translation.
 ///
 /// Y points down, X to the right, Z points into the scene.
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(from = "TransformRepr")]
 pub struct Transform {
     trans: Vec3,
     rot: Vec3,
     scale: Vec2,
 }
 
+/// The representations of [`Transform`] written by different exporters.
+///
+/// Transforms are either objects or `[trans, rot, scale]` arrays, and their vectors are either
+/// arrays or objects with `x`, `y` (and `z`) fields.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum TransformRepr {
+    Object {
+        trans: Vec3Repr,
+        rot: Vec3Repr,
+        scale: Vec2Repr,
+    },
+    Array(Vec3Repr, Vec3Repr, Vec2Repr),
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum Vec3Repr {
+    Array(Vec3),
+    Object { x: f32, y: f32, z: f32 },
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum Vec2Repr {
+    Array(Vec2),
+    Object { x: f32, y: f32 },
+}
+
+impl From<TransformRepr> for Transform {
+    fn from(repr: TransformRepr) -> Self {
+        let (trans, rot, scale) = match repr {
+            TransformRepr::Object { trans, rot, scale } => (trans, rot, scale),
+            TransformRepr::Array(trans, rot, scale) => (trans, rot, scale),
+        };
+        Self {
+            trans: trans.into(),
+            rot: rot.into(),
+            scale: scale.into(),
+        }
+    }
+}
+
+impl From<Vec3Repr> for Vec3 {
+    fn from(repr: Vec3Repr) -> Self {
+        match repr {
+            Vec3Repr::Array(v) => v,
+            Vec3Repr::Object { x, y, z } => [x, y, z],
+        }
+    }
+}
+
+impl From<Vec2Repr> for Vec2 {
+    fn from(repr: Vec2Repr) -> Self {
+        match repr {
+            Vec2Repr::Array(v) => v,
+            Vec2Repr::Object { x, y } => [x, y],
+        }
+    }
+}
+
 impl Transform {
     /// Creates an identity transform.
     pub fn new() -> Self {
@@ -546,6 +984,40 @@ impl Transform {
         }
     }
 
+    /// Computes the transform that applies `child` first, then `self`.
+    ///
+    /// Returns `None` if the result can not be represented as a [`Transform`]. That is the case
+    /// when either transform rotates around the X or Y axis, or when `self` scales non-uniformly
+    /// and `child` rotates, which would result in a shear.
+    ///
+    /// Positive angles are assumed to turn the X axis towards the Y axis, like in Inochi2D.
+    fn compose(&self, child: &Transform) -> Option<Transform> {
+        if self.rot[..2] != [0.0; 2] || child.rot[..2] != [0.0; 2] {
+            return None;
+        }
+        if self.scale[0] != self.scale[1] && child.rot[2] != 0.0 {
+            return None;
+        }
+
+        let (sin, cos) = self.rot[2].sin_cos();
+        let [x, y] = [
+            child.trans[0] * self.scale[0],
+            child.trans[1] * self.scale[1],
+        ];
+        Some(Transform {
+            trans: [
+                self.trans[0] + cos * x - sin * y,
+                self.trans[1] + sin * x + cos * y,
+                self.trans[2] + child.trans[2],
+            ],
+            rot: [0.0, 0.0, self.rot[2] + child.rot[2]],
+            scale: [
+                self.scale[0] * child.scale[0],
+                self.scale[1] * child.scale[1],
+            ],
+        })
+    }
+
     pub fn translation(&self) -> Vec3 {
         self.trans
     }
@@ -556,10 +1028,9 @@ impl Transform {
 
     /// Sets the transform's translation.
     ///
-    /// Note that translation allows for 3 dimentions, but translation in Z direction does *not*
-    /// affect the Z-Sort order.
-    ///
-    /// FIXME: what does Z translation *do*?
+    /// Inochi2D does not specify what translation in Z direction does. `rhino2d-engine` adds it to
+    /// the node's Z-Sort value, so that moving a node along the Z axis moves it in front of or
+    /// behind other nodes.
     pub fn set_translation(&mut self, translation: Vec3) {
         self.trans = translation;
     }
@@ -613,7 +1084,7 @@ pub struct SimplePhysics {
 impl SimplePhysics {
     /// Returns the parameter ID this physics object is bound to.
     ///
-    /// If not bound, an ID of `-1` is used.
+    /// If not bound, this is [`Uuid::INVALID`].
     pub fn param(&self) -> Uuid {
         self.param
     }
@@ -712,3 +1183,231 @@ pub enum ParamMapMode {
     AngleLength,
     XY,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn uv_bounds() {
+        let mut mesh = MeshData {
+            verts: vec![0.0, 0.0, 1.0, 0.0, 0.0, 1.0, 1.0, 1.0],
+            uvs: Some(vec![0.25, 0.5, 0.75, 0.5, 0.25, 0.625, 0.5, 0.875]),
+            indices: vec![0, 1, 2, 2, 1, 3],
+            origin: [0.0, 0.0],
+        };
+        assert_eq!(mesh.uv_bounds(), Some([[0.25, 0.5], [0.75, 0.875]]));
+
+        mesh.uvs = None;
+        assert_eq!(mesh.uv_bounds(), None);
+        mesh.uvs = Some(Vec::new());
+        assert_eq!(mesh.uv_bounds(), None);
+    }
+
+    fn child_uuids(node: &NodeBase) -> Vec<u64> {
+        node.children().iter().map(|ch| ch.uuid().raw()).collect()
+    }
+
+    #[test]
+    fn reorder_children() {
+        let mut root = NodeBase::new(Uuid { raw: 1 }, "root".into());
+        for uuid in 2..=5 {
+            root.push_child(Node::Node(NodeBase::new(
+                Uuid { raw: uuid },
+                format!("child {uuid}"),
+            )));
+        }
+
+        root.move_child(0, 2);
+        assert_eq!(child_uuids(&root), [3, 4, 2, 5]);
+        root.move_child(3, 0);
+        assert_eq!(child_uuids(&root), [5, 3, 4, 2]);
+        root.move_child(1, 1);
+        assert_eq!(child_uuids(&root), [5, 3, 4, 2]);
+        root.swap_children(0, 3);
+        assert_eq!(child_uuids(&root), [2, 3, 4, 5]);
+
+        // Nodes keep their identity, so references by UUID (eg. masks) remain valid.
+        for (i, child) in root.children().iter().enumerate() {
+            assert_eq!(child.name(), format!("child {}", i + 2));
+        }
+    }
+
+    #[test]
+    fn descendants() {
+        let mut root = NodeBase::new(Uuid { raw: 1 }, "root".into());
+        for uuid in [2, 5] {
+            let mut child = NodeBase::new(Uuid { raw: uuid }, format!("child {uuid}"));
+            let mut grandchild = NodeBase::new(Uuid { raw: uuid + 1 }, "grandchild".into());
+            grandchild.push_child(Node::Node(NodeBase::new(
+                Uuid { raw: uuid + 2 },
+                "great-grandchild".into(),
+            )));
+            child.push_child(Node::Node(grandchild));
+            root.push_child(Node::Node(child));
+        }
+
+        let order = |root: &NodeBase| {
+            root.descendants()
+                .map(|node| node.uuid().raw())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(order(&root), [2, 3, 4, 5, 6, 7]);
+        assert_eq!(
+            root.find_by_uuid(Uuid { raw: 5 }).unwrap().name(),
+            "child 5"
+        );
+        assert_eq!(root.find_by_uuid(Uuid { raw: 7 }).unwrap().uuid().raw(), 7);
+        assert!(root.find_by_uuid(Uuid { raw: 1 }).is_none());
+
+        let mut visited = Vec::new();
+        root.descendants_mut(|node| {
+            visited.push(node.uuid().raw());
+            node.set_enabled(false);
+        });
+        assert_eq!(visited, order(&root));
+        assert!(root.enabled());
+        assert!(root.descendants().all(|node| !node.enabled()));
+    }
+
+    #[test]
+    fn find_by_name() {
+        let mut root = NodeBase::new(Uuid { raw: 1 }, "root".into());
+        let mut arm = NodeBase::new(Uuid { raw: 2 }, "Arm".into());
+        arm.push_child(Node::Node(NodeBase::new(Uuid { raw: 3 }, "Hand".into())));
+        root.push_child(Node::Node(arm));
+        root.push_child(Node::Node(NodeBase::new(Uuid { raw: 4 }, "Hand".into())));
+
+        // The nested node comes first in depth-first order.
+        assert_eq!(root.find_by_name("Hand").unwrap().uuid().raw(), 3);
+        assert_eq!(root.find_by_name("Arm").unwrap().uuid().raw(), 2);
+        assert!(root.find_by_name("root").is_none());
+        assert!(root.find_by_name("Leg").is_none());
+
+        root.find_by_name_mut("Hand")
+            .unwrap()
+            .set_name("Left Hand".into());
+        assert_eq!(root.find_by_name("Hand").unwrap().uuid().raw(), 4);
+        assert_eq!(root.find_by_name_mut("Left Hand").unwrap().uuid().raw(), 3);
+    }
+
+    #[test]
+    #[should_panic = "child index 0 out of bounds"]
+    fn reorder_without_children() {
+        let mut root = NodeBase::new(Uuid { raw: 1 }, "root".into());
+        root.move_child(0, 0);
+    }
+
+    #[test]
+    fn compute_tangents() {
+        let mut mesh = MeshData {
+            verts: vec![0.0, 0.0, 2.0, 0.0, 0.0, 2.0, 2.0, 2.0, 5.0, 5.0],
+            uvs: Some(vec![0.0, 0.0, 1.0, 0.0, 0.0, 1.0, 1.0, 1.0, 0.5, 0.5]),
+            indices: vec![0, 1, 2, 2, 1, 3],
+            origin: [0.0, 0.0],
+        };
+        // The last vertex is not part of any triangle.
+        assert_eq!(mesh.compute_tangents().unwrap(), [[1.0, 0.0, 0.0]; 5]);
+
+        // Rotate the texture by 90°, so that U increases downwards.
+        mesh.uvs = Some(vec![0.0, 1.0, 0.0, 0.0, 1.0, 1.0, 1.0, 0.0, 0.5, 0.5]);
+        let tangents = mesh.compute_tangents().unwrap();
+        assert_eq!(tangents[..4], [[0.0, 1.0, 0.0]; 4]);
+
+        mesh.uvs = None;
+        assert_eq!(mesh.compute_tangents(), None);
+    }
+
+    #[test]
+    fn new_mesh() {
+        let verts = vec![[0.0, 0.0], [2.0, 0.0], [0.0, 1.0], [2.0, 1.0]];
+        let uvs = vec![[0.0, 0.0], [1.0, 0.0], [0.0, 1.0], [1.0, 1.0]];
+        let mesh = MeshData::new(
+            verts.clone(),
+            Some(uvs.clone()),
+            vec![0, 1, 2, 2, 1, 3],
+            [1.0, 0.5],
+        )
+        .unwrap();
+        assert_eq!(mesh.vertex_count(), 4);
+        assert_eq!(mesh.verts().collect::<Vec<_>>(), verts);
+        assert_eq!(mesh.uvs().unwrap().collect::<Vec<_>>(), uvs);
+        assert_eq!(mesh.indices(), [0, 1, 2, 2, 1, 3]);
+        assert_eq!(mesh.origin(), [1.0, 0.5]);
+
+        let mesh = MeshData::new(verts.clone(), None, Vec::new(), [0.0; 2]).unwrap();
+        assert!(mesh.uvs().is_none());
+
+        let err = MeshData::new(verts, Some(uvs[..3].to_vec()), Vec::new(), [0.0; 2]).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+    }
+
+    #[test]
+    fn mesh_bounds() {
+        let mesh = |verts| MeshData::new(verts, None, Vec::new(), [5.0, 5.0]).unwrap();
+        assert_eq!(mesh(Vec::new()).bounds(), [[0.0, 0.0], [0.0, 0.0]]);
+        assert_eq!(mesh(vec![[3.0, -2.0]]).bounds(), [[3.0, -2.0], [3.0, -2.0]]);
+        assert_eq!(
+            mesh(vec![[3.0, -2.0], [-1.0, 4.0], [0.0, 0.0]]).bounds(),
+            [[-1.0, -2.0], [3.0, 4.0]]
+        );
+    }
+
+    #[test]
+    fn vertex_joints() {
+        let binding: JointBindingData =
+            serde_json::from_str(r#"{ "bound_to": 3, "bind_data": [[0, 2], [1, 2, 2], [], [4]] }"#)
+                .unwrap();
+        let vertices = binding.vertex_joints();
+        assert_eq!(
+            vertices.into_iter().collect::<Vec<_>>(),
+            [(0, vec![0]), (1, vec![1]), (2, vec![0, 1]), (4, vec![3])]
+        );
+    }
+
+    #[test]
+    fn grid_mesh() {
+        let mesh = MeshData::grid(3, 2, [60.0, 40.0]);
+        assert_eq!(mesh.vertex_count(), 4 * 3);
+        assert_eq!(mesh.indices().len(), 3 * 2 * 6);
+        assert_eq!(mesh.uv_bounds(), Some([[0.0, 0.0], [1.0, 1.0]]));
+
+        let verts: Vec<Vec2> = mesh.verts().collect();
+        assert_eq!(verts[0], [-30.0, -20.0]);
+        assert_eq!(verts[1], [-10.0, -20.0]);
+        assert_eq!(verts[11], [30.0, 20.0]);
+        for tri in mesh.indices().chunks(3) {
+            let [a, b, c] = [tri[0], tri[1], tri[2]].map(|i| verts[usize::from(i)]);
+            let cross = (b[0] - a[0]) * (c[1] - a[1]) - (b[1] - a[1]) * (c[0] - a[0]);
+            assert_eq!(cross, 400.0, "{tri:?}");
+        }
+
+        assert_eq!(MeshData::grid(0, 0, [1.0, 1.0]).vertex_count(), 1);
+        assert_eq!(MeshData::grid(255, 255, [1.0, 1.0]).vertex_count(), 65536);
+    }
+
+    #[test]
+    fn transform_representations() {
+        let canonical = r#"{"trans":[1.0,2.0,3.0],"rot":[0.0,0.0,0.5],"scale":[2.0,1.0]}"#;
+        let expected: Transform = serde_json::from_str(canonical).unwrap();
+        assert_eq!(expected.translation(), [1.0, 2.0, 3.0]);
+        assert_eq!(expected.rotation(), [0.0, 0.0, 0.5]);
+        assert_eq!(expected.scale(), [2.0, 1.0]);
+
+        for json in [
+            r#"[[1, 2, 3], [0, 0, 0.5], [2, 1]]"#,
+            r#"{
+                "trans": { "x": 1, "y": 2, "z": 3 },
+                "rot": { "x": 0, "y": 0, "z": 0.5 },
+                "scale": { "x": 2, "y": 1 }
+            }"#,
+            r#"[{ "x": 1, "y": 2, "z": 3 }, [0, 0, 0.5], { "x": 2, "y": 1 }]"#,
+        ] {
+            let transform: Transform = serde_json::from_str(json).unwrap();
+            assert_eq!(transform, expected, "{json}");
+            assert_eq!(serde_json::to_string(&transform).unwrap(), canonical);
+        }
+
+        assert!(serde_json::from_str::<Transform>(r#"[[1, 2, 3], [0, 0, 0]]"#).is_err());
+    }
+}
@@ -2,7 +2,7 @@ use std::fmt;
 
 use serde::{Deserialize, Serialize};
 
-use crate::{Uuid, Vec2};
+use crate::{Uuid, Vec2, Vec3};
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Param {
@@ -14,6 +14,14 @@ pub struct Param {
     defaults: Vec2,
     axis_points: Vec<Vec<f32>>,
     bindings: Vec<ParamBinding>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    merge_mode: Option<ParamMergeMode>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    edge_behavior: Option<EdgeBehavior>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    ui_position: Option<Vec2>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    ui_color: Option<Vec3>,
 }
 
 impl Param {
@@ -98,6 +106,50 @@ impl Param {
     pub fn clear_bindings(&mut self) {
         self.bindings.clear();
     }
+
+    /// Returns how the values of this parameter's bindings are combined with other bindings of
+    /// the same node property.
+    ///
+    /// If this is `None`, [`ParamMergeMode::Additive`] is used.
+    pub fn merge_mode(&self) -> Option<ParamMergeMode> {
+        self.merge_mode
+    }
+
+    pub fn set_merge_mode(&mut self, merge_mode: Option<ParamMergeMode>) {
+        self.merge_mode = merge_mode;
+    }
+
+    /// Returns how the parameter's bindings behave when the parameter is driven past its range.
+    ///
+    /// If this is `None`, [`EdgeBehavior::Clamp`] is used.
+    pub fn edge_behavior(&self) -> Option<EdgeBehavior> {
+        self.edge_behavior
+    }
+
+    pub fn set_edge_behavior(&mut self, edge_behavior: Option<EdgeBehavior>) {
+        self.edge_behavior = edge_behavior;
+    }
+
+    /// Returns the position of the parameter's control in an editor's parameter panel.
+    ///
+    /// This, and [`Param::ui_color`], are layout hints for editors and don't affect how the model
+    /// is rendered. Both are `None` for models that don't store them.
+    pub fn ui_position(&self) -> Option<Vec2> {
+        self.ui_position
+    }
+
+    pub fn set_ui_position(&mut self, ui_position: Option<Vec2>) {
+        self.ui_position = ui_position;
+    }
+
+    /// Returns the color an editor displays the parameter's control in, as linear RGB.
+    pub fn ui_color(&self) -> Option<Vec3> {
+        self.ui_color
+    }
+
+    pub fn set_ui_color(&mut self, ui_color: Option<Vec3>) {
+        self.ui_color = ui_color;
+    }
 }
 
 /// Describes a model property affected by a [`Param`]s value.
@@ -124,6 +176,8 @@ pub struct ParamBinding {
     #[serde(rename = "isSet")]
     is_set: Vec<Vec<bool>>,
     interpolate_mode: InterpolateMode,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    interpolate_mode_y: Option<InterpolateMode>,
 }
 
 impl ParamBinding {
@@ -153,6 +207,20 @@ impl ParamBinding {
         self.interpolate_mode = mode;
     }
 
+    /// Returns the interpolation mode along the Y axis of a 2D parameter, if it differs from
+    /// [`ParamBinding::interpolate_mode`].
+    ///
+    /// This allows combining discrete and continuous axes, eg. choosing the nearest mouth shape on
+    /// one axis while linearly interpolating how far it is opened on the other. This is an
+    /// extension of the Inochi2D format, and not supported by Inochi2D itself.
+    pub fn interpolate_mode_y(&self) -> Option<InterpolateMode> {
+        self.interpolate_mode_y
+    }
+
+    pub fn set_interpolate_mode_y(&mut self, mode: Option<InterpolateMode>) {
+        self.interpolate_mode_y = mode;
+    }
+
     // FIXME: offer a better API for these
 
     /// Returns the values of the bound node property for each axis point.
@@ -197,4 +265,93 @@ pub enum InterpolateMode {
     Nearest,
     /// Linearly interpolate between the nearest parameter values.
     Linear,
+    /// Hold the value of the preceding axis point until the next one is reached.
+    Stepped,
+    /// Smoothly interpolate using a cubic spline through the surrounding parameter values.
+    Cubic,
+    /// Interpolate along a bezier curve, easing in and out of each parameter value.
+    Bezier,
+}
+
+/// Describes how the value of a [`ParamBinding`] is combined with the node property it affects.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ParamMergeMode {
+    /// The value is added to the property.
+    #[default]
+    Additive,
+    /// The property is multiplied with the value.
+    Multiplicative,
+    /// The value replaces the property.
+    Forced,
+    /// The value is passed through to the property unmodified, like [`ParamMergeMode::Additive`].
+    Passthrough,
+}
+
+/// Describes how the bindings of a [`Param`] behave outside of the parameter's range.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+pub enum EdgeBehavior {
+    /// The value is clamped to the range, so the bindings hold their value at the edge.
+    #[default]
+    Clamp,
+    /// The bindings continue the slope of the outermost segment past the edge.
+    Extrapolate,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn interpolate_mode_roundtrip() {
+        for (mode, json) in [
+            (InterpolateMode::Nearest, "\"Nearest\""),
+            (InterpolateMode::Linear, "\"Linear\""),
+            (InterpolateMode::Stepped, "\"Stepped\""),
+            (InterpolateMode::Cubic, "\"Cubic\""),
+            (InterpolateMode::Bezier, "\"Bezier\""),
+        ] {
+            assert_eq!(serde_json::to_string(&mode).unwrap(), json);
+            assert_eq!(serde_json::from_str::<InterpolateMode>(json).unwrap(), mode);
+        }
+    }
+
+    #[test]
+    fn ui_layout_roundtrip() {
+        let json = r#"{"uuid":1,"name":"p","is_vec2":false,"min":[0.0,0.0],"max":[1.0,1.0],"defaults":[0.0,0.0],"axis_points":[[0.0,1.0],[0.0]],"bindings":[],"ui_position":[120.0,-40.5],"ui_color":[1.0,0.5,0.0]}"#;
+        let mut param: Param = serde_json::from_str(json).unwrap();
+        assert_eq!(param.ui_position(), Some([120.0, -40.5]));
+        assert_eq!(param.ui_color(), Some([1.0, 0.5, 0.0]));
+        assert_eq!(serde_json::to_string(&param).unwrap(), json);
+
+        param.set_ui_position(None);
+        param.set_ui_color(None);
+        assert!(!serde_json::to_string(&param).unwrap().contains("ui_"));
+    }
+
+    #[test]
+    fn interpolate_mode_y_roundtrip() {
+        let json = r#"{"node":1,"param_name":"transform.t.x","values":[[0.0]],"isSet":[[true]],"interpolate_mode":"Nearest"}"#;
+        let mut binding: ParamBinding = serde_json::from_str(json).unwrap();
+        assert_eq!(binding.interpolate_mode_y(), None);
+        assert_eq!(serde_json::to_string(&binding).unwrap(), json);
+
+        binding.set_interpolate_mode_y(Some(InterpolateMode::Linear));
+        let json = json.replace("}", r#","interpolate_mode_y":"Linear"}"#);
+        assert_eq!(serde_json::to_string(&binding).unwrap(), json);
+        let binding: ParamBinding = serde_json::from_str(&json).unwrap();
+        assert_eq!(binding.interpolate_mode_y(), Some(InterpolateMode::Linear));
+    }
+
+    #[test]
+    fn merge_mode_roundtrip() {
+        let json = r#"{"uuid":1,"name":"p","is_vec2":false,"min":[0.0,0.0],"max":[1.0,1.0],"defaults":[0.0,0.0],"axis_points":[[0.0,1.0],[0.0]],"bindings":[]}"#;
+        let param: Param = serde_json::from_str(json).unwrap();
+        assert_eq!(param.merge_mode(), None);
+        assert_eq!(serde_json::to_string(&param).unwrap(), json);
+
+        let json = json.replace("[]}", r#"[],"merge_mode":"Multiplicative"}"#);
+        let param: Param = serde_json::from_str(&json).unwrap();
+        assert_eq!(param.merge_mode(), Some(ParamMergeMode::Multiplicative));
+        assert_eq!(serde_json::to_string(&param).unwrap(), json);
+    }
 }
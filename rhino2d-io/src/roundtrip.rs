@@ -0,0 +1,245 @@
+//! Verification of lossless saving and loading.
+
+use std::{error::Error, fmt, io};
+
+use serde_json::Value;
+
+use crate::{fingerprint::integer, InochiPuppet, LoadLimits};
+
+/// Describes how a model changed when it was saved and loaded again.
+///
+/// Returned by [`InochiPuppet::verify_roundtrip`].
+#[derive(Debug)]
+pub struct RoundtripDiff {
+    error: Option<io::Error>,
+    json: Vec<String>,
+    textures: Vec<usize>,
+    vendor_data: Vec<usize>,
+}
+
+impl RoundtripDiff {
+    /// Returns the error that occurred while saving or reloading the model, if any.
+    ///
+    /// If this is `Some`, the model could not be reloaded, and nothing else was compared.
+    pub fn error(&self) -> Option<&io::Error> {
+        self.error.as_ref()
+    }
+
+    /// Returns the locations in the model's JSON data whose values changed, as JSON pointers.
+    ///
+    /// Values that were added or dropped are included. Changes inside a location are not listed
+    /// separately, eg. a list whose length changed is only reported once.
+    pub fn json(&self) -> &[String] {
+        &self.json
+    }
+
+    /// Returns the indices of the textures that changed, were added, or were dropped.
+    pub fn textures(&self) -> &[usize] {
+        &self.textures
+    }
+
+    /// Returns the indices of the vendor data payloads that changed, were added, or were dropped.
+    pub fn vendor_data(&self) -> &[usize] {
+        &self.vendor_data
+    }
+}
+
+impl fmt::Display for RoundtripDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(error) = &self.error {
+            return write!(f, "model could not be reloaded: {error}");
+        }
+        f.write_str("model changed when reloaded:")?;
+        for path in &self.json {
+            write!(f, " JSON value at '{path}';")?;
+        }
+        for index in &self.textures {
+            write!(f, " texture {index};")?;
+        }
+        for index in &self.vendor_data {
+            write!(f, " vendor data {index};")?;
+        }
+        Ok(())
+    }
+}
+
+impl Error for RoundtripDiff {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        self.error.as_ref().map(|e| e as _)
+    }
+}
+
+pub(crate) fn verify_roundtrip(puppet: &InochiPuppet) -> Result<(), RoundtripDiff> {
+    let mut diff = RoundtripDiff {
+        error: None,
+        json: Vec::new(),
+        textures: Vec::new(),
+        vendor_data: Vec::new(),
+    };
+
+    let mut bytes = Vec::new();
+    let reloaded = puppet
+        .write(&mut bytes)
+        .and_then(|()| InochiPuppet::from_read_with_limits(&mut &*bytes, &LoadLimits::unlimited()));
+    let reloaded = match reloaded {
+        Ok(reloaded) => reloaded,
+        Err(e) => {
+            diff.error = Some(e);
+            return Err(diff);
+        }
+    };
+
+    let json = |puppet: &InochiPuppet| {
+        serde_json::to_value(puppet.json_data()).expect("model data is always valid JSON")
+    };
+    diff_json(
+        &json(puppet),
+        &json(&reloaded),
+        &mut String::new(),
+        &mut diff.json,
+    );
+
+    diff.textures = diff_lists(&puppet.textures, &reloaded.textures, |a, b| {
+        a.enc == b.enc
+            && a.data == b.data
+            && a.dimensions == b.dimensions
+            && a.filter == b.filter
+            && a.wrap == b.wrap
+    });
+    diff.vendor_data = diff_lists(&puppet.vendor_data, &reloaded.vendor_data, |a, b| {
+        a.name() == b.name() && a.payload() == b.payload()
+    });
+
+    if diff.json.is_empty() && diff.textures.is_empty() && diff.vendor_data.is_empty() {
+        Ok(())
+    } else {
+        Err(diff)
+    }
+}
+
+/// Returns the indices at which `a` and `b` differ, including the entries only one of them has.
+fn diff_lists<T>(a: &[T], b: &[T], eq: impl Fn(&T, &T) -> bool) -> Vec<usize> {
+    (0..a.len().max(b.len()))
+        .filter(|&i| match (a.get(i), b.get(i)) {
+            (Some(a), Some(b)) => !eq(a, b),
+            _ => true,
+        })
+        .collect()
+}
+
+/// Appends the JSON pointers of the values that differ between `a` and `b` to `out`.
+///
+/// Like [`InochiPuppet::fingerprint`], this compares whole numbers exactly and regardless of whether
+/// they are written as integers or floats, and treats omitted object entries as `null`.
+fn diff_json(a: &Value, b: &Value, path: &mut String, out: &mut Vec<String>) {
+    match (a, b) {
+        (Value::Number(a), Value::Number(b)) => {
+            let equal = match (integer(a), integer(b)) {
+                (Some(a), Some(b)) => a == b,
+                _ => a.as_f64() == b.as_f64(),
+            };
+            if !equal {
+                out.push(path.clone());
+            }
+        }
+        (Value::Array(a), Value::Array(b)) if a.len() == b.len() => {
+            for (i, (a, b)) in a.iter().zip(b).enumerate() {
+                let len = path.len();
+                path.push_str(&format!("/{i}"));
+                diff_json(a, b, path, out);
+                path.truncate(len);
+            }
+        }
+        (Value::Object(a), Value::Object(b)) => {
+            let mut keys = a.keys().chain(b.keys()).collect::<Vec<_>>();
+            keys.sort_unstable();
+            keys.dedup();
+            for key in keys {
+                let len = path.len();
+                path.push('/');
+                path.push_str(&key.replace('~', "~0").replace('/', "~1"));
+                let get = |map: &serde_json::Map<String, Value>| {
+                    map.get(key).cloned().unwrap_or(Value::Null)
+                };
+                diff_json(&get(a), &get(b), path, out);
+                path.truncate(len);
+            }
+        }
+        (a, b) => {
+            if a != b {
+                out.push(path.clone());
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::testutil::puppet;
+    use crate::{Texture, TextureEncoding, TextureWrap, VendorData};
+
+    use super::*;
+
+    fn model() -> InochiPuppet {
+        let mut puppet = puppet(
+            r#"{
+                "type": "Node", "uuid": 1,
+                "children": [
+                    { "type": "Part", "uuid": 2, "opacity": 0.1, "tint": [0.3, 0.7, 1e-7] },
+                    { "type": "SimplePhysics", "uuid": 3, "param": 10 }
+                ]
+            }"#,
+            r#"[{
+                "uuid": 10, "name": "Sway", "is_vec2": true,
+                "axis_points": [[0.0, 0.33333334, 1.0], [0.0, 1.0]],
+                "bindings": [{ "node": 2, "param_name": "transform.t.x", "values": [
+                    [0.1, 0.2, 0.3], [-0.1, -0.2, -0.3]
+                ] }]
+            }]"#,
+        );
+        puppet
+            .extra_fields_mut()
+            .insert("editor".into(), serde_json::json!({ "zoom": 1.5 }));
+        let mut texture = Texture::new(TextureEncoding::Png, vec![1, 2, 3]);
+        texture.set_wrap(TextureWrap::Repeat);
+        puppet.push_texture(texture);
+        puppet.push_texture(Texture::new(TextureEncoding::Tga, vec![4, 5]));
+        puppet.push_vendor_data(VendorData::new("app".into(), vec![6, 7]));
+        puppet
+    }
+
+    #[test]
+    fn lossless() {
+        model().verify_roundtrip().unwrap();
+    }
+
+    #[test]
+    fn lossy() {
+        // The texture sampling extension claims this entry when loading, so it gets dropped.
+        let mut puppet = puppet(r#"{ "type": "Node", "uuid": 1 }"#, "[]");
+        puppet
+            .extra_fields_mut()
+            .insert("textureSampling".into(), serde_json::json!([]));
+        let diff = puppet.verify_roundtrip().unwrap_err();
+        assert!(diff.error().is_none());
+        assert_eq!(diff.json(), ["/textureSampling"]);
+        assert!(diff.textures().is_empty());
+        assert!(diff.vendor_data().is_empty());
+    }
+
+    #[test]
+    fn diff_json_paths() {
+        let a = serde_json::json!({ "a": [1, 2.0, { "b/c": true }], "d": null, "e": "x" });
+        let b = serde_json::json!({ "a": [1.0, 2.5, { "b/c": false }], "e": "x", "f": 0 });
+        let mut out = Vec::new();
+        diff_json(&a, &b, &mut String::new(), &mut out);
+        assert_eq!(out, ["/a/1", "/a/2/b~1c", "/f"]);
+
+        // UUIDs above 2^53 that are equal as `f64` still differ.
+        let a = serde_json::json!({ "uuid": (1u64 << 53) + 1, "zero": -0.0 });
+        let b = serde_json::json!({ "uuid": 1u64 << 53, "zero": 0 });
+        let mut out = Vec::new();
+        diff_json(&a, &b, &mut String::new(), &mut out);
+        assert_eq!(out, ["/uuid"]);
+    }
+}
@@ -0,0 +1,123 @@
+//! Helpers for building puppets in tests.
+//!
+//! This is shared by the tests of all rhino2d crates, which enable it via the `testutil` feature.
+//! It is not part of the public API.
+
+use serde_json::{json, Value};
+
+use crate::{InochiPuppet, Uuid};
+
+pub fn uuid(raw: u64) -> Uuid {
+    Uuid::from_raw(raw)
+}
+
+/// Builds a puppet from the JSON of its root node and parameter list.
+///
+/// Fields that are irrelevant to most tests can be omitted and will be filled in with defaults.
+/// The puppet has no textures.
+pub fn puppet(nodes: &str, params: &str) -> InochiPuppet {
+    let mut nodes: Value = serde_json::from_str(nodes).unwrap();
+    let mut params: Value = serde_json::from_str(params).unwrap();
+    fill_node(&mut nodes);
+    for param in params.as_array_mut().unwrap() {
+        fill_param(param);
+    }
+
+    InochiPuppet::from_json(json!({
+        "meta": { "version": "test", "preservePixels": false },
+        "physics": { "pixelsPerMeter": 1000.0, "gravity": 9.8 },
+        "nodes": nodes,
+        "param": params,
+    }))
+    .unwrap()
+}
+
+fn set_default(obj: &mut Value, key: &str, value: Value) {
+    let obj = obj.as_object_mut().unwrap();
+    if !obj.contains_key(key) {
+        obj.insert(key.to_string(), value);
+    }
+}
+
+fn fill_node(node: &mut Value) {
+    set_default(node, "name", json!("node"));
+    set_default(node, "enabled", json!(true));
+    set_default(node, "zsort", json!(0.0));
+    set_default(
+        node,
+        "transform",
+        json!({ "trans": [0.0, 0.0, 0.0], "rot": [0.0, 0.0, 0.0], "scale": [1.0, 1.0] }),
+    );
+    set_default(node, "lockToRoot", json!(false));
+
+    let ty = node["type"].as_str().unwrap().to_string();
+    if matches!(&*ty, "Drawable" | "Part" | "Mask") {
+        set_default(
+            node,
+            "mesh",
+            json!({
+                "verts": [0.0, 0.0, 1.0, 0.0, 0.0, 1.0],
+                "uvs": [0.0, 0.0, 1.0, 0.0, 0.0, 1.0],
+                "indices": [0, 1, 2],
+                "origin": [0.0, 0.0],
+            }),
+        );
+    }
+    if ty == "Part" {
+        set_default(node, "textures", json!([0]));
+        set_default(node, "opacity", json!(1.0));
+        set_default(node, "mask_threshold", json!(0.5));
+        set_default(node, "tint", json!([1.0, 1.0, 1.0]));
+        set_default(node, "blend_mode", json!("Normal"));
+    }
+
+    if ty == "Composite" {
+        set_default(node, "opacity", json!(1.0));
+        set_default(node, "mask_threshold", json!(0.5));
+        set_default(node, "tint", json!([1.0, 1.0, 1.0]));
+        set_default(node, "blend_mode", json!("Normal"));
+    }
+
+    if ty == "PathDeform" {
+        set_default(node, "joints", json!([]));
+        set_default(node, "bindings", json!([]));
+    }
+
+    if ty == "SimplePhysics" {
+        set_default(node, "param", json!(0));
+        set_default(node, "model_type", json!("Pendulum"));
+        set_default(node, "map_mode", json!("AngleLength"));
+        set_default(node, "gravity", json!(1.0));
+        set_default(node, "length", json!(100.0));
+        set_default(node, "frequency", json!(1.0));
+        set_default(node, "angle_damping", json!(0.5));
+        set_default(node, "length_damping", json!(0.5));
+        set_default(node, "output_scale", json!([1.0, 1.0]));
+    }
+
+    if let Some(children) = node.get_mut("children") {
+        for child in children.as_array_mut().unwrap() {
+            fill_node(child);
+        }
+    }
+}
+
+fn fill_param(param: &mut Value) {
+    set_default(param, "is_vec2", json!(false));
+    set_default(param, "min", json!([0.0, 0.0]));
+    set_default(param, "max", json!([1.0, 1.0]));
+    set_default(param, "defaults", json!([0.0, 0.0]));
+    set_default(param, "axis_points", json!([[0.0, 1.0], [0.0]]));
+    set_default(param, "bindings", json!([]));
+
+    for binding in param["bindings"].as_array_mut().unwrap() {
+        let is_set = binding["values"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|row| vec![true; row.as_array().unwrap().len()])
+            .collect::<Vec<_>>();
+        set_default(binding, "isSet", json!(is_set));
+        set_default(binding, "interpolate_mode", json!("Linear"));
+    }
+}
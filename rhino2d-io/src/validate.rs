@@ -0,0 +1,259 @@
+//! Consistency checks of model content.
+
+use std::collections::HashSet;
+use std::{error::Error, fmt};
+
+use crate::node::{Drawable, Node};
+use crate::{InochiPuppet, Uuid};
+
+/// An internal inconsistency of a model, found by [`InochiPuppet::validate`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ValidationError {
+    uuid: Uuid,
+    message: String,
+}
+
+impl ValidationError {
+    fn new(uuid: Uuid, message: String) -> Self {
+        Self { uuid, message }
+    }
+
+    /// Returns the UUID of the node or parameter the problem was found in.
+    pub fn uuid(&self) -> Uuid {
+        self.uuid
+    }
+
+    /// Returns a description of the problem.
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+}
+
+impl fmt::Display for ValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.uuid, self.message)
+    }
+}
+
+impl Error for ValidationError {}
+
+pub(crate) fn validate(puppet: &InochiPuppet) -> Result<(), Vec<ValidationError>> {
+    let mut errors = Vec::new();
+
+    let root = puppet.root_node();
+    let nodes: Vec<&Node> = std::iter::once(root).chain(root.descendants()).collect();
+    let mut node_uuids = HashSet::new();
+    for node in &nodes {
+        if !node_uuids.insert(node.uuid()) {
+            errors.push(ValidationError::new(
+                node.uuid(),
+                format!("UUID of node '{}' is used by another node", node.name()),
+            ));
+        }
+    }
+    let mut param_uuids = HashSet::new();
+    for param in puppet.params() {
+        if !param_uuids.insert(param.uuid()) {
+            errors.push(ValidationError::new(
+                param.uuid(),
+                format!(
+                    "UUID of parameter '{}' is used by another parameter",
+                    param.name()
+                ),
+            ));
+        }
+    }
+
+    for node in &nodes {
+        let uuid = node.uuid();
+        let name = node.name();
+        let drawable: Option<&Drawable> = match node {
+            Node::Drawable(drawable) => Some(drawable),
+            Node::Part(part) => Some(part),
+            Node::Mask(mask) => Some(mask),
+            _ => None,
+        };
+        if let Some(drawable) = drawable {
+            check_mesh(drawable, &mut errors);
+        }
+
+        match node {
+            Node::Part(part) => {
+                for &texture in part.textures() {
+                    if texture as usize >= puppet.textures().len() {
+                        errors.push(ValidationError::new(
+                            uuid,
+                            format!(
+                                "part '{name}' uses texture {texture}, but the model has only {} \
+                                 textures",
+                                puppet.textures().len(),
+                            ),
+                        ));
+                    }
+                }
+                for &mask in part.masked_by() {
+                    if !node_uuids.contains(&mask) {
+                        errors.push(ValidationError::new(
+                            uuid,
+                            format!("part '{name}' is masked by nonexistent node {mask}"),
+                        ));
+                    }
+                }
+            }
+            Node::SimplePhysics(physics) => {
+                let param = physics.param();
+                if param != Uuid::INVALID && !param_uuids.contains(&param) {
+                    errors.push(ValidationError::new(
+                        uuid,
+                        format!("physics node '{name}' drives nonexistent parameter {param}"),
+                    ));
+                }
+            }
+            _ => {}
+        }
+    }
+
+    for param in puppet.params() {
+        for binding in param.bindings() {
+            if !node_uuids.contains(&binding.node()) {
+                errors.push(ValidationError::new(
+                    param.uuid(),
+                    format!(
+                        "parameter '{}' is bound to nonexistent node {}",
+                        param.name(),
+                        binding.node(),
+                    ),
+                ));
+            }
+        }
+    }
+
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(errors)
+    }
+}
+
+impl Drawable {
+    /// Checks that the mesh of the drawable is consistent: every index refers to a vertex, the
+    /// indices form whole triangles, and there is one UV per vertex, if the mesh has UVs.
+    ///
+    /// This is the part of [`InochiPuppet::validate`] that concerns a single drawable.
+    pub fn check_mesh(&self) -> Result<(), Vec<ValidationError>> {
+        let mut errors = Vec::new();
+        check_mesh(self, &mut errors);
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+}
+
+fn check_mesh(drawable: &Drawable, errors: &mut Vec<ValidationError>) {
+    let mesh = drawable.mesh_data();
+    let (uuid, name) = (drawable.uuid(), drawable.name());
+    let vertex_count = mesh.vertex_count();
+    if let Some(index) = mesh
+        .indices()
+        .iter()
+        .find(|&&index| usize::from(index) >= vertex_count)
+    {
+        errors.push(ValidationError::new(
+            uuid,
+            format!("mesh of '{name}' has index {index}, but only {vertex_count} vertices"),
+        ));
+    }
+    if !mesh.indices().len().is_multiple_of(3) {
+        errors.push(ValidationError::new(
+            uuid,
+            format!(
+                "mesh of '{name}' has {} indices, which is not a multiple of 3",
+                mesh.indices().len(),
+            ),
+        ));
+    }
+    if let Some(uvs) = mesh.uvs() {
+        let uv_count = uvs.count();
+        if uv_count != vertex_count {
+            errors.push(ValidationError::new(
+                uuid,
+                format!("mesh of '{name}' has {vertex_count} vertices, but {uv_count} UVs"),
+            ));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::testutil::puppet;
+    use crate::{Texture, TextureEncoding, Uuid};
+
+    #[test]
+    fn valid_model() {
+        let mut puppet = puppet(
+            r#"{ "type": "Node", "uuid": 1, "children": [
+                { "type": "Part", "uuid": 2, "masked_by": [3] },
+                { "type": "Mask", "uuid": 3 }
+            ] }"#,
+            r#"[{
+                "uuid": 10, "name": "Move",
+                "bindings": [{ "node": 2, "param_name": "transform.t.x", "values": [[0, 1]] }]
+            }]"#,
+        );
+        puppet.push_texture(Texture::new(TextureEncoding::Png, vec![1, 2, 3]));
+        assert_eq!(puppet.validate(), Ok(()));
+    }
+
+    #[test]
+    fn inconsistent_model() {
+        let puppet = puppet(
+            r#"{ "type": "Node", "uuid": 1, "children": [
+                {
+                    "type": "Part", "uuid": 2, "name": "Body", "masked_by": [7],
+                    "mesh": {
+                        "verts": [0, 0, 1, 0, 0, 1], "uvs": [0, 0, 1, 0, 0, 1],
+                        "indices": [0, 1, 3], "origin": [0, 0]
+                    }
+                },
+                { "type": "Node", "uuid": 2, "name": "Group" }
+            ] }"#,
+            r#"[{
+                "uuid": 10, "name": "Move",
+                "bindings": [{ "node": 8, "param_name": "transform.t.x", "values": [[0, 1]] }]
+            }]"#,
+        );
+        let errors = puppet.validate().unwrap_err();
+        let errors: Vec<_> = errors
+            .iter()
+            .map(|error| (error.uuid(), error.to_string()))
+            .collect();
+        assert_eq!(
+            errors,
+            [
+                (
+                    Uuid { raw: 2 },
+                    "2: UUID of node 'Group' is used by another node"
+                ),
+                (
+                    Uuid { raw: 2 },
+                    "2: mesh of 'Body' has index 3, but only 3 vertices"
+                ),
+                (
+                    Uuid { raw: 2 },
+                    "2: part 'Body' uses texture 0, but the model has only 0 textures"
+                ),
+                (
+                    Uuid { raw: 2 },
+                    "2: part 'Body' is masked by nonexistent node 7"
+                ),
+                (
+                    Uuid { raw: 10 },
+                    "10: parameter 'Move' is bound to nonexistent node 8"
+                ),
+            ]
+            .map(|(uuid, message)| (uuid, message.to_string()))
+        );
+    }
+}
@@ -0,0 +1,381 @@
+//! Software rasterizer for Inochi2D models.
+//!
+//! This renders models on the CPU, so it works without a GPU. It is meant for thumbnails and
+//! previews rather than real-time rendering: textures are sampled with nearest-neighbor filtering,
+//! and masks and blend modes are ignored. The contents of composites are drawn directly, without
+//! the composite's tint and opacity.
+
+use std::{collections::HashMap, io, io::Read};
+
+use image::{GrayImage, Luma, Rgba, RgbaImage};
+use rhino2d_engine::{RenderCommand, StaticPuppet};
+use rhino2d_io::{node::Node, InochiPuppet, Uuid, Vec2, Vec3};
+
+/// Loads a model from `read` and renders its rest pose into an image of the given size.
+///
+/// The model is scaled to fit the image, keeping its aspect ratio, and centered. If the model has
+/// nothing to draw, a fully transparent image is returned.
+pub fn thumbnail<R: Read>(read: &mut R, width: u32, height: u32) -> io::Result<RgbaImage> {
+    let puppet = InochiPuppet::from_read(read)?;
+    let rest_pose = StaticPuppet::new(&puppet).map_err(invalid_data)?;
+    let rasterizer = Rasterizer::new(&puppet)?;
+    Ok(rasterizer.render(rest_pose.commands(), width, height))
+}
+
+/// Renders the [`RenderCommand`]s of a puppet into RGBA images.
+pub struct Rasterizer {
+    textures: Vec<RgbaImage>,
+    parts: HashMap<Uuid, Part>,
+}
+
+/// The data of a [`rhino2d_io::node::Part`] needed to draw it.
+struct Part {
+    verts: Vec<Vec2>,
+    uvs: Vec<Vec2>,
+    indices: Vec<u16>,
+    texture: usize,
+}
+
+impl Rasterizer {
+    /// Decodes the textures of `puppet` and collects the meshes of its parts.
+    pub fn new(puppet: &InochiPuppet) -> io::Result<Self> {
+        let textures = puppet
+            .textures()
+            .iter()
+            .map(|texture| {
+                let decoded = texture.decode()?;
+                let (width, height) = (decoded.width(), decoded.height());
+                Ok(RgbaImage::from_raw(width, height, decoded.into_pixels())
+                    .expect("decoded texture has a pixel for every texel"))
+            })
+            .collect::<io::Result<Vec<_>>>()?;
+
+        let mut parts = HashMap::new();
+        collect_parts(puppet.root_node(), textures.len(), &mut parts)?;
+        Ok(Self { textures, parts })
+    }
+
+    /// Renders `commands` in order, scaling and centering the result to fit a `width` by `height`
+    /// image.
+    ///
+    /// Commands for nodes that are not textured parts are skipped.
+    pub fn render(&self, commands: &[RenderCommand], width: u32, height: u32) -> RgbaImage {
+        let mut image = RgbaImage::new(width, height);
+        for (part, screen, tint, opacity) in self.layout(commands, width, height) {
+            let texture = &self.textures[part.texture];
+            part.rasterize(&screen, width, height, |x, y, uv| {
+                let texel = sample(texture, uv);
+                blend(image.get_pixel_mut(x, y), texel, tint, opacity);
+            });
+        }
+        image
+    }
+
+    /// Computes the combined alpha coverage of `commands`, eg. to draw an outline around the
+    /// silhouette of a puppet, or to quickly reject hit tests.
+    ///
+    /// The alpha of every part, including its texture and opacity, is composited like by
+    /// [`Rasterizer::render`], and the result has the same size and placement as the image
+    /// rendered from the same arguments. A 1-bit mask can be obtained by thresholding the result.
+    pub fn coverage(&self, commands: &[RenderCommand], width: u32, height: u32) -> GrayImage {
+        let mut mask = GrayImage::new(width, height);
+        for (part, screen, _, opacity) in self.layout(commands, width, height) {
+            let texture = &self.textures[part.texture];
+            part.rasterize(&screen, width, height, |x, y, uv| {
+                let src = f32::from(sample(texture, uv)[3]) / 255.0 * opacity.clamp(0.0, 1.0);
+                let Luma([dest]) = mask.get_pixel_mut(x, y);
+                let alpha = src + f32::from(*dest) / 255.0 * (1.0 - src);
+                *dest = (alpha * 255.0).round() as u8;
+            });
+        }
+        mask
+    }
+
+    /// Computes the screen-space vertex positions, tint, and opacity of every part drawn by
+    /// `commands`, scaled and centered to fit a `width` by `height` image.
+    fn layout(
+        &self,
+        commands: &[RenderCommand],
+        width: u32,
+        height: u32,
+    ) -> Vec<(&Part, Vec<Vec2>, Vec3, f32)> {
+        // Transform every part into model space first, to determine how to fit the model into the
+        // image.
+        let mut drawn = Vec::new();
+        let mut min = [f32::INFINITY; 2];
+        let mut max = [f32::NEG_INFINITY; 2];
+        for cmd in commands {
+            let Some(part) = self.parts.get(&cmd.node()) else {
+                continue;
+            };
+            let transform = cmd.transform();
+            let m = transform.as_column_major_data();
+            let deform = cmd.deform().unwrap_or(&[]);
+            let positions: Vec<Vec2> = part
+                .verts
+                .iter()
+                .enumerate()
+                .map(|(i, &[x, y])| {
+                    let [dx, dy] = deform.get(i).copied().unwrap_or([0.0; 2]);
+                    let (x, y) = (x + dx, y + dy);
+                    [m[0] * x + m[4] * y + m[12], m[1] * x + m[5] * y + m[13]]
+                })
+                .collect();
+            for &[x, y] in &positions {
+                min = [min[0].min(x), min[1].min(y)];
+                max = [max[0].max(x), max[1].max(y)];
+            }
+            drawn.push((part, positions, cmd.tint(), cmd.opacity()));
+        }
+
+        let size = [max[0] - min[0], max[1] - min[1]];
+        if drawn.is_empty() || !(size[0] > 0.0 || size[1] > 0.0) {
+            return Vec::new();
+        }
+        let scale = (width as f32 / size[0]).min(height as f32 / size[1]);
+        let offset = [
+            (width as f32 - size[0] * scale) * 0.5 - min[0] * scale,
+            (height as f32 - size[1] * scale) * 0.5 - min[1] * scale,
+        ];
+
+        drawn
+            .into_iter()
+            .map(|(part, positions, tint, opacity)| {
+                let screen = positions
+                    .iter()
+                    .map(|&[x, y]| [x * scale + offset[0], y * scale + offset[1]])
+                    .collect();
+                (part, screen, tint, opacity)
+            })
+            .collect()
+    }
+}
+
+impl Part {
+    /// Calls `plot` with the coordinates and UV coordinates of every pixel of a `width` by
+    /// `height` image covered by the part, with its vertices at the positions in `screen`.
+    fn rasterize(
+        &self,
+        screen: &[Vec2],
+        width: u32,
+        height: u32,
+        mut plot: impl FnMut(u32, u32, Vec2),
+    ) {
+        for tri in self.indices.chunks_exact(3) {
+            let [a, b, c] = [tri[0], tri[1], tri[2]].map(usize::from);
+            if a.max(b).max(c) >= screen.len() {
+                continue;
+            }
+            rasterize_triangle(
+                [screen[a], screen[b], screen[c]],
+                [self.uvs[a], self.uvs[b], self.uvs[c]],
+                width,
+                height,
+                &mut plot,
+            );
+        }
+    }
+}
+
+fn collect_parts(
+    node: &Node,
+    texture_count: usize,
+    out: &mut HashMap<Uuid, Part>,
+) -> io::Result<()> {
+    if let Node::Part(part) = node {
+        let mesh = part.mesh_data();
+        if let (Some(&texture), Some(uvs)) = (part.textures().first(), mesh.uvs()) {
+            let texture = texture as usize;
+            if texture >= texture_count {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!(
+                        "part '{}' uses texture {texture}, but the model only has {texture_count}",
+                        part.name()
+                    ),
+                ));
+            }
+            out.insert(
+                part.uuid(),
+                Part {
+                    verts: mesh.verts().collect(),
+                    uvs: uvs.collect(),
+                    indices: mesh.indices().to_vec(),
+                    texture,
+                },
+            );
+        }
+    }
+
+    for child in node.children() {
+        collect_parts(child, texture_count, out)?;
+    }
+    Ok(())
+}
+
+/// Calls `plot` with the coordinates and interpolated UV coordinates of every pixel of a `width` by
+/// `height` image whose center lies inside a triangle.
+fn rasterize_triangle(
+    [p0, p1, p2]: [Vec2; 3],
+    [uv0, uv1, uv2]: [Vec2; 3],
+    width: u32,
+    height: u32,
+    plot: &mut impl FnMut(u32, u32, Vec2),
+) {
+    let edge =
+        |a: Vec2, b: Vec2, p: Vec2| (b[0] - a[0]) * (p[1] - a[1]) - (b[1] - a[1]) * (p[0] - a[0]);
+    let area = edge(p0, p1, p2);
+    if area == 0.0 || !area.is_finite() {
+        return;
+    }
+
+    let x_range = pixel_range(
+        p0[0].min(p1[0]).min(p2[0]),
+        p0[0].max(p1[0]).max(p2[0]),
+        width,
+    );
+    let y_range = pixel_range(
+        p0[1].min(p1[1]).min(p2[1]),
+        p0[1].max(p1[1]).max(p2[1]),
+        height,
+    );
+    for y in y_range {
+        for x in x_range.clone() {
+            let p = [x as f32 + 0.5, y as f32 + 0.5];
+            // Barycentric weights, which are all non-negative inside the triangle regardless of its
+            // winding order.
+            let w0 = edge(p1, p2, p) / area;
+            let w1 = edge(p2, p0, p) / area;
+            let w2 = edge(p0, p1, p) / area;
+            if w0 < 0.0 || w1 < 0.0 || w2 < 0.0 {
+                continue;
+            }
+
+            let u = w0 * uv0[0] + w1 * uv1[0] + w2 * uv2[0];
+            let v = w0 * uv0[1] + w1 * uv1[1] + w2 * uv2[1];
+            plot(x, y, [u, v]);
+        }
+    }
+}
+
+/// Returns the range of pixels whose centers may lie between `min` and `max`.
+fn pixel_range(min: f32, max: f32, len: u32) -> std::ops::Range<u32> {
+    let start = min.floor().max(0.0) as u32;
+    let end = (max.ceil().max(0.0) as u32).min(len);
+    start..end.max(start)
+}
+
+/// Samples `texture` at the nearest texel to `uv`, with coordinates clamped to the edges.
+fn sample(texture: &RgbaImage, [u, v]: Vec2) -> Rgba<u8> {
+    let (width, height) = texture.dimensions();
+    if width == 0 || height == 0 {
+        return Rgba([0; 4]);
+    }
+    let x = ((u * width as f32) as u32).min(width - 1);
+    let y = ((v * height as f32) as u32).min(height - 1);
+    *texture.get_pixel(x, y)
+}
+
+/// Blends `src` over `dest`, with its color multiplied by `tint` and its alpha by `opacity`.
+fn blend(dest: &mut Rgba<u8>, src: Rgba<u8>, tint: Vec3, opacity: f32) {
+    let src_alpha = f32::from(src[3]) / 255.0 * opacity.clamp(0.0, 1.0);
+    let dest_alpha = f32::from(dest[3]) / 255.0;
+    let alpha = src_alpha + dest_alpha * (1.0 - src_alpha);
+    if alpha <= 0.0 {
+        return;
+    }
+
+    for i in 0..3 {
+        let src_color = f32::from(src[i]) * tint[i].clamp(0.0, 1.0);
+        let color =
+            (src_color * src_alpha + f32::from(dest[i]) * dest_alpha * (1.0 - src_alpha)) / alpha;
+        dest[i] = color.round() as u8;
+    }
+    dest[3] = (alpha * 255.0).round() as u8;
+}
+
+fn invalid_data<E: Into<Box<dyn std::error::Error + Send + Sync>>>(e: E) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, e)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use image::ImageOutputFormat;
+    use rhino2d_io::{testutil, Texture, TextureEncoding};
+    use serde_json::{json, Value};
+
+    use super::*;
+
+    fn node(ty: &str, uuid: u64, children: Vec<Value>) -> Value {
+        json!({ "type": ty, "uuid": uuid, "children": children })
+    }
+
+    /// Encodes a model with the given root node and textures as an INP file.
+    fn model(root: Value, textures: &[RgbaImage]) -> Vec<u8> {
+        let mut puppet = testutil::puppet(&root.to_string(), "[]");
+        for texture in textures {
+            let mut png = Cursor::new(Vec::new());
+            texture.write_to(&mut png, ImageOutputFormat::Png).unwrap();
+            puppet.push_texture(Texture::new(TextureEncoding::Png, png.into_inner()));
+        }
+        let mut bytes = Vec::new();
+        puppet.write(&mut bytes).unwrap();
+        bytes
+    }
+
+    fn square_part(uuid: u64) -> Value {
+        json!({
+            "type": "Part",
+            "uuid": uuid,
+            "mesh": {
+                "verts": [0.0, 0.0, 10.0, 0.0, 0.0, 10.0, 10.0, 10.0],
+                "uvs": [0.0, 0.0, 1.0, 0.0, 0.0, 1.0, 1.0, 1.0],
+                "indices": [0, 1, 2, 2, 1, 3],
+                "origin": [0.0, 0.0],
+            },
+        })
+    }
+
+    #[test]
+    fn thumbnail_of_one_part() {
+        let red = RgbaImage::from_pixel(2, 2, Rgba([255, 0, 0, 255]));
+        let bytes = model(node("Node", 1, vec![square_part(2)]), &[red]);
+
+        // The square part is scaled to 8x8 pixels and centered horizontally.
+        let image = thumbnail(&mut &*bytes, 16, 8).unwrap();
+        assert_eq!(image.dimensions(), (16, 8));
+        for (x, y, pixel) in image.enumerate_pixels() {
+            let expected = if (4..12).contains(&x) {
+                Rgba([255, 0, 0, 255])
+            } else {
+                Rgba([0; 4])
+            };
+            assert_eq!(*pixel, expected, "pixel at {x},{y}");
+        }
+    }
+
+    #[test]
+    fn coverage_of_one_part() {
+        let red = RgbaImage::from_pixel(2, 2, Rgba([255, 0, 0, 255]));
+        let bytes = model(node("Node", 1, vec![square_part(2)]), &[red]);
+        let puppet = InochiPuppet::from_read(&mut &*bytes).unwrap();
+        let rest_pose = StaticPuppet::new(&puppet).unwrap();
+        let rasterizer = Rasterizer::new(&puppet).unwrap();
+
+        let mask = rasterizer.coverage(rest_pose.commands(), 16, 8);
+        assert_eq!(mask.dimensions(), (16, 8));
+        for (x, y, pixel) in mask.enumerate_pixels() {
+            let expected = if (4..12).contains(&x) { 255 } else { 0 };
+            assert_eq!(pixel[0], expected, "pixel at {x},{y}");
+        }
+    }
+
+    #[test]
+    fn thumbnail_without_drawables() {
+        let bytes = model(node("Node", 1, vec![node("Node", 2, Vec::new())]), &[]);
+        let image = thumbnail(&mut &*bytes, 4, 4).unwrap();
+        assert!(image.pixels().all(|pixel| *pixel == Rgba([0; 4])));
+    }
+}
@@ -1,17 +1,13 @@
-mod node;
+mod mesh;
 
-use std::{
-    borrow::Cow,
-    fs::File,
-    io::{self, BufReader, Read},
-    path::Path,
-};
+use std::{borrow::Cow, collections::HashMap, io, num::NonZeroU32};
 
-use image::ImageFormat;
-use rhino2d_io::TextureEncoding;
+use mesh::MeshBuffers;
+use rhino2d_io::{TextureEncoding, TextureFilter, TextureWrap, Uuid};
 use wgpu::{
-    util::DeviceExt, BindGroup, Device, Extent3d, Queue, Texture, TextureDescriptor,
-    TextureDimension, TextureFormat, TextureUsages,
+    AddressMode, Device, Extent3d, FilterMode, ImageCopyTexture, ImageDataLayout, Origin3d, Queue,
+    Sampler, SamplerDescriptor, Texture, TextureAspect, TextureDescriptor, TextureDimension,
+    TextureFormat, TextureUsages,
 };
 
 pub struct Gpu {
@@ -19,35 +15,344 @@ pub struct Gpu {
     pub queue: Queue,
 }
 
+// Rendering is not implemented yet, so nothing reads the uploaded resources.
+#[allow(dead_code)]
 pub struct Renderer {
     gpu: Gpu,
     textures: Vec<Texture>,
+    /// For every texture of the puppet, where it is stored on the GPU and its size.
+    texture_slots: Vec<(TextureLocation, Extent3d, TextureFormat)>,
+    /// Sampler for every texture of the puppet.
+    samplers: Vec<Sampler>,
+    /// Vertex and index buffers of every distinct mesh of the puppet.
+    meshes: Vec<MeshBuffers>,
+    /// Index into `meshes` for every drawable node.
+    node_meshes: HashMap<Uuid, usize>,
+}
+
+/// Location of one of the puppet's textures on the GPU.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TextureLocation {
+    texture: usize,
+    layer: u32,
+}
+
+impl TextureLocation {
+    /// Returns the index of the GPU texture holding the puppet texture.
+    pub fn texture(&self) -> usize {
+        self.texture
+    }
+
+    /// Returns the array layer of the GPU texture holding the puppet texture.
+    ///
+    /// This is always 0 for textures that aren't part of a texture array.
+    pub fn layer(&self) -> u32 {
+        self.layer
+    }
 }
 
 impl Renderer {
+    /// Creates a renderer that uploads every texture of `puppet` as a separate GPU texture.
     pub fn new(gpu: Gpu, puppet: &rhino2d_io::InochiPuppet) -> io::Result<Self> {
-        let mut textures = Vec::with_capacity(puppet.textures().len());
-        for texture in puppet.textures() {
-            let info = TextureInfo::new(texture)?;
-
-            let texture = gpu.device.create_texture_with_data(
-                &gpu.queue,
-                &TextureDescriptor {
-                    label: None,
-                    size: info.extent,
-                    mip_level_count: 1,
-                    sample_count: 1,
-                    dimension: TextureDimension::D2,
-                    format: info.texture_format,
-                    usage: TextureUsages::TEXTURE_BINDING,
+        Self::with_textures(gpu, puppet, false)
+    }
+
+    /// Creates a renderer that uploads textures of the same size as layers of a texture array.
+    ///
+    /// Every group of equally sized textures is stored in a single 2D array texture, which lets
+    /// parts using any of them share one bind group. Textures whose size is unique are uploaded
+    /// individually, like [`Renderer::new`] does. Use [`Renderer::texture_location`] to find the
+    /// layer a texture was placed in.
+    pub fn new_array(gpu: Gpu, puppet: &rhino2d_io::InochiPuppet) -> io::Result<Self> {
+        Self::with_textures(gpu, puppet, true)
+    }
+
+    fn with_textures(
+        gpu: Gpu,
+        puppet: &rhino2d_io::InochiPuppet,
+        arrays: bool,
+    ) -> io::Result<Self> {
+        let infos = puppet
+            .textures()
+            .iter()
+            .map(TextureInfo::new)
+            .collect::<io::Result<Vec<_>>>()?;
+        let keys = infos
+            .iter()
+            .map(|info| (info.extent, info.texture_format))
+            .collect::<Vec<_>>();
+
+        let groups = group_textures(&keys, arrays);
+        let mut textures = Vec::with_capacity(groups.len());
+        let mut texture_slots = vec![None; infos.len()];
+        for group in groups {
+            let first = &infos[group[0]];
+            let texture = gpu.device.create_texture(&TextureDescriptor {
+                label: None,
+                size: Extent3d {
+                    depth_or_array_layers: group.len() as u32,
+                    ..first.extent
                 },
-                &info.data,
-            );
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: TextureDimension::D2,
+                format: first.texture_format,
+                usage: TextureUsages::TEXTURE_BINDING | TextureUsages::COPY_DST,
+            });
+
+            for (layer, index) in group.into_iter().enumerate() {
+                let info = &infos[index];
+                let location = TextureLocation {
+                    texture: textures.len(),
+                    layer: layer as u32,
+                };
+                write_layer(&gpu.queue, &texture, location.layer, info);
+                texture_slots[index] = Some((location, info.extent, info.texture_format));
+            }
             textures.push(texture);
         }
 
-        Ok(Self { gpu, textures })
+        let preserve_pixels = puppet.metadata().preserve_pixels();
+        let samplers = puppet
+            .textures()
+            .iter()
+            .map(|texture| {
+                gpu.device
+                    .create_sampler(&sampler_descriptor(texture, preserve_pixels))
+            })
+            .collect();
+
+        let (meshes, node_meshes) = mesh::collect_meshes(puppet.root_node());
+        let meshes = meshes.iter().map(|mesh| mesh.upload(&gpu.device)).collect();
+
+        Ok(Self {
+            gpu,
+            textures,
+            texture_slots: texture_slots.into_iter().map(Option::unwrap).collect(),
+            samplers,
+            meshes,
+            node_meshes,
+        })
+    }
+
+    /// Returns where the puppet's texture at `index` is stored on the GPU.
+    ///
+    /// Returns `None` if the puppet has no texture at `index`.
+    pub fn texture_location(&self, index: usize) -> Option<TextureLocation> {
+        self.texture_slots
+            .get(index)
+            .map(|(location, ..)| *location)
+    }
+
+    /// Returns the number of mesh buffers the renderer has uploaded.
+    ///
+    /// Drawables with identical meshes (same vertices, UVs, and indices) share a single set of
+    /// buffers, so this can be lower than the number of drawables in the puppet.
+    pub fn mesh_count(&self) -> usize {
+        self.meshes.len()
+    }
+
+    /// Returns the index of the mesh buffers used by the drawable node `node`.
+    ///
+    /// Returns `None` if `node` is not a drawable node of the puppet.
+    pub fn mesh_index(&self, node: Uuid) -> Option<usize> {
+        self.node_meshes.get(&node).copied()
+    }
+
+    /// Overwrites a rectangular region of the texture at `index`.
+    ///
+    /// `data` contains the new texels of the region in the texture's format, row by row: RGBA8
+    /// texels for PNG and TGA textures, and rows of 4x4 texel blocks for BC7 textures. This is
+    /// cheaper than recreating the renderer when only a part of a texture changes, eg. for parts
+    /// showing live content.
+    ///
+    /// Returns an error if there is no texture at `index`, if the region does not lie within the
+    /// texture, if it does not start and end on block boundaries (or the texture's edge), or if the
+    /// length of `data` does not match the size of the region.
+    pub fn update_texture_region(
+        &mut self,
+        index: usize,
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+        data: &[u8],
+    ) -> io::Result<()> {
+        let (location, size, format) = match self.texture_slots.get(index) {
+            Some(slot) => *slot,
+            None => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("texture index {index} out of range"),
+                ))
+            }
+        };
+        check_region(size, format, x, y, width, height, data.len())?;
+        if width == 0 || height == 0 {
+            return Ok(());
+        }
+
+        self.gpu.queue.write_texture(
+            ImageCopyTexture {
+                texture: &self.textures[location.texture],
+                mip_level: 0,
+                origin: Origin3d {
+                    x,
+                    y,
+                    z: location.layer,
+                },
+                aspect: TextureAspect::All,
+            },
+            data,
+            data_layout(format, width, height),
+            Extent3d {
+                width,
+                height,
+                depth_or_array_layers: 1,
+            },
+        );
+        Ok(())
+    }
+}
+
+/// Groups textures into the GPU textures that store them.
+///
+/// `keys` contains the size and format of every texture. Each returned group lists the indices of
+/// the textures stored as layers of one GPU texture. If `arrays` is `false`, or no other texture
+/// matches, a texture is stored on its own.
+fn group_textures(keys: &[(Extent3d, TextureFormat)], arrays: bool) -> Vec<Vec<usize>> {
+    let mut groups: Vec<Vec<usize>> = Vec::new();
+    for (index, key) in keys.iter().enumerate() {
+        let group = groups
+            .iter_mut()
+            .find(|group| arrays && keys[group[0]] == *key);
+        match group {
+            Some(group) => group.push(index),
+            None => groups.push(vec![index]),
+        }
+    }
+    groups
+}
+
+/// Returns the sampler settings for `texture`.
+///
+/// Textures without a filter of their own use nearest filtering if the model sets
+/// `preserve_pixels`, and linear filtering otherwise.
+fn sampler_descriptor(
+    texture: &rhino2d_io::Texture,
+    preserve_pixels: bool,
+) -> SamplerDescriptor<'static> {
+    let filter = match texture.filter() {
+        Some(TextureFilter::Nearest) => FilterMode::Nearest,
+        Some(TextureFilter::Linear) => FilterMode::Linear,
+        None if preserve_pixels => FilterMode::Nearest,
+        None => FilterMode::Linear,
+    };
+    let address_mode = match texture.wrap() {
+        TextureWrap::Clamp => AddressMode::ClampToEdge,
+        TextureWrap::Repeat => AddressMode::Repeat,
+        TextureWrap::MirroredRepeat => AddressMode::MirrorRepeat,
+    };
+    SamplerDescriptor {
+        address_mode_u: address_mode,
+        address_mode_v: address_mode,
+        mag_filter: filter,
+        min_filter: filter,
+        ..Default::default()
+    }
+}
+
+fn write_layer(queue: &Queue, texture: &Texture, layer: u32, info: &TextureInfo<'_>) {
+    queue.write_texture(
+        ImageCopyTexture {
+            texture,
+            mip_level: 0,
+            origin: Origin3d {
+                x: 0,
+                y: 0,
+                z: layer,
+            },
+            aspect: TextureAspect::All,
+        },
+        &info.data,
+        data_layout(info.texture_format, info.extent.width, info.extent.height),
+        info.extent,
+    );
+}
+
+/// Returns the layout of tightly packed texel data of `format` covering `width` x `height`
+/// texels.
+fn data_layout(format: TextureFormat, width: u32, height: u32) -> ImageDataLayout {
+    let (bytes_per_row, rows) = block_layout(format, width, height);
+    ImageDataLayout {
+        offset: 0,
+        bytes_per_row: NonZeroU32::new(bytes_per_row),
+        rows_per_image: NonZeroU32::new(rows),
+    }
+}
+
+/// Returns the size of a row of texel blocks of `format` covering `width` texels, in bytes, and
+/// the number of block rows covering `height` texels.
+///
+/// The blocks of uncompressed formats are single texels, while block-compressed formats like BC7
+/// store 4x4 texels in each block.
+fn block_layout(format: TextureFormat, width: u32, height: u32) -> (u32, u32) {
+    let info = format.describe();
+    let (block_width, block_height) = info.block_dimensions;
+    (
+        width.div_ceil(block_width.into()) * u32::from(info.block_size),
+        height.div_ceil(block_height.into()),
+    )
+}
+
+/// Checks that a region lies within a texture of the given size and format, and that `data_len`
+/// bytes of texel data cover it exactly.
+fn check_region(
+    size: Extent3d,
+    format: TextureFormat,
+    x: u32,
+    y: u32,
+    width: u32,
+    height: u32,
+    data_len: usize,
+) -> io::Result<()> {
+    let in_bounds =
+        |start: u32, len: u32, max: u32| start.checked_add(len).is_some_and(|end| end <= max);
+    if !in_bounds(x, width, size.width) || !in_bounds(y, height, size.height) {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!(
+                "region of size {width}x{height} at ({x}, {y}) exceeds texture size {}x{}",
+                size.width, size.height
+            ),
+        ));
+    }
+
+    let (block_width, block_height) = format.describe().block_dimensions;
+    let aligned = |start: u32, len: u32, block: u8, max: u32| {
+        start.is_multiple_of(block.into())
+            && (len.is_multiple_of(block.into()) || start + len == max)
+    };
+    if !aligned(x, width, block_width, size.width) || !aligned(y, height, block_height, size.height)
+    {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!(
+                "region of size {width}x{height} at ({x}, {y}) is not aligned to the \
+                 {block_width}x{block_height} texel blocks of the texture"
+            ),
+        ));
+    }
+
+    let (bytes_per_row, rows) = block_layout(format, width, height);
+    let expected = u64::from(bytes_per_row) * u64::from(rows);
+    if data_len as u64 != expected {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("expected {expected} bytes of texel data for the region, got {data_len}"),
+        ));
     }
+    Ok(())
 }
 
 struct TextureInfo<'a> {
@@ -62,25 +367,16 @@ impl<'a> TextureInfo<'a> {
         let height;
         let mut tex_fmt = TextureFormat::Rgba8UnormSrgb;
         let data: Cow<[u8]> = match texture.encoding() {
-            TextureEncoding::Png => {
-                let image = image::load_from_memory_with_format(texture.data(), ImageFormat::Png)
-                    .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?
-                    .to_rgba8();
+            TextureEncoding::Png | TextureEncoding::Tga => {
+                let image = texture.decode()?;
                 width = image.width();
                 height = image.height();
-                image.into_vec().into()
-            }
-            TextureEncoding::Tga => {
-                let image = image::load_from_memory_with_format(texture.data(), ImageFormat::Tga)
-                    .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?
-                    .to_rgba8();
-                width = image.width();
-                height = image.height();
-                image.into_vec().into()
+                image.into_pixels().into()
             }
             TextureEncoding::Bc7 => {
-                // Inochi2D does not yet support this. The file format is missing required metadata
-                // to load this type of texture (height and width).
+                // Inochi2D does not yet support this, and its file format lacks the dimensions of
+                // BC7 textures. They are stored by `rhino2d-io` (`Texture::dimensions`), but
+                // uploading block-compressed textures requires `Features::TEXTURE_COMPRESSION_BC`.
                 #[allow(unused_assignments)]
                 {
                     tex_fmt = TextureFormat::Bc7RgbaUnormSrgb;
@@ -109,3 +405,116 @@ impl<'a> TextureInfo<'a> {
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn texture_array_grouping() {
+        let key = |width, height| {
+            (
+                Extent3d {
+                    width,
+                    height,
+                    depth_or_array_layers: 1,
+                },
+                TextureFormat::Rgba8UnormSrgb,
+            )
+        };
+        let keys = [
+            key(64, 64),
+            key(32, 64),
+            key(64, 64),
+            key(16, 16),
+            key(64, 64),
+        ];
+
+        assert_eq!(
+            group_textures(&keys, true),
+            [vec![0, 2, 4], vec![1], vec![3]]
+        );
+        assert_eq!(
+            group_textures(&keys, false),
+            [vec![0], vec![1], vec![2], vec![3], vec![4]]
+        );
+        assert!(group_textures(&[], true).is_empty());
+    }
+
+    #[test]
+    fn texture_sampling() {
+        let texture = rhino2d_io::Texture::new(TextureEncoding::Png, Vec::new());
+        let desc = sampler_descriptor(&texture, false);
+        assert_eq!(desc.address_mode_u, AddressMode::ClampToEdge);
+        assert_eq!(desc.mag_filter, FilterMode::Linear);
+        let desc = sampler_descriptor(&texture, true);
+        assert_eq!(desc.min_filter, FilterMode::Nearest);
+
+        // Settings stored in a model file are picked up.
+        let mut puppet = rhino2d_io::testutil::puppet(r#"{ "type": "Node", "uuid": 1 }"#, "[]");
+        puppet.metadata_mut().set_preserve_pixels(true);
+        let mut texture = rhino2d_io::Texture::new(TextureEncoding::Png, Vec::new());
+        texture.set_filter(Some(TextureFilter::Linear));
+        texture.set_wrap(TextureWrap::Repeat);
+        puppet.push_texture(texture);
+
+        let mut saved = Vec::new();
+        puppet.write(&mut saved).unwrap();
+        let puppet = rhino2d_io::InochiPuppet::from_read(&mut &*saved).unwrap();
+        let desc = sampler_descriptor(&puppet.textures()[0], puppet.metadata().preserve_pixels());
+        assert_eq!(desc.address_mode_u, AddressMode::Repeat);
+        assert_eq!(desc.address_mode_v, AddressMode::Repeat);
+        assert_eq!(desc.mag_filter, FilterMode::Linear);
+    }
+
+    #[test]
+    fn texture_region_validation() {
+        let size = Extent3d {
+            width: 16,
+            height: 8,
+            depth_or_array_layers: 1,
+        };
+
+        check_region(size, TextureFormat::Rgba8UnormSrgb, 0, 0, 16, 8, 16 * 8 * 4).unwrap();
+        check_region(size, TextureFormat::Rgba8UnormSrgb, 4, 2, 3, 5, 3 * 5 * 4).unwrap();
+        check_region(size, TextureFormat::Rgba8UnormSrgb, 16, 8, 0, 0, 0).unwrap();
+
+        let err = |x, y, width, height, len| {
+            check_region(
+                size,
+                TextureFormat::Rgba8UnormSrgb,
+                x,
+                y,
+                width,
+                height,
+                len,
+            )
+            .unwrap_err()
+            .kind()
+        };
+        assert_eq!(err(1, 0, 16, 8, 16 * 8 * 4), io::ErrorKind::InvalidInput);
+        assert_eq!(err(0, 4, 1, 5, 5 * 4), io::ErrorKind::InvalidInput);
+        assert_eq!(err(17, 0, 0, 0, 0), io::ErrorKind::InvalidInput);
+        assert_eq!(err(u32::MAX, 0, 2, 1, 8), io::ErrorKind::InvalidInput);
+        assert_eq!(err(0, 0, 2, 2, 2 * 2 * 4 - 1), io::ErrorKind::InvalidInput);
+        assert_eq!(err(0, 0, 2, 2, 2 * 2 * 4 + 4), io::ErrorKind::InvalidInput);
+
+        // BC7 stores 4x4 texel blocks of 16 bytes each.
+        let bc7 = |x, y, width, height, len| {
+            check_region(
+                size,
+                TextureFormat::Bc7RgbaUnormSrgb,
+                x,
+                y,
+                width,
+                height,
+                len,
+            )
+        };
+        bc7(0, 0, 16, 8, 4 * 2 * 16).unwrap();
+        bc7(4, 4, 8, 4, 2 * 16).unwrap();
+        assert!(bc7(0, 0, 16, 8, 16 * 8 * 4 + 1).is_err());
+        assert!(bc7(2, 0, 4, 4, 16).is_err());
+        assert!(bc7(0, 0, 2, 4, 16).is_err());
+    }
+}
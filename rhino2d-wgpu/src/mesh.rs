@@ -0,0 +1,172 @@
+//! Mesh data uploaded to the GPU.
+
+use std::collections::HashMap;
+
+use rhino2d_io::{
+    node::{Drawable, MeshData, Node},
+    Uuid,
+};
+use wgpu::{util::DeviceExt, Buffer, BufferUsages, Device};
+
+/// Vertex, tangent, and index buffer of a mesh.
+// Rendering is not implemented yet, so nothing reads the buffers.
+#[allow(dead_code)]
+pub(crate) struct MeshBuffers {
+    pub(crate) vertices: Buffer,
+    pub(crate) tangents: Buffer,
+    pub(crate) indices: Buffer,
+    pub(crate) index_count: u32,
+}
+
+/// The data of a mesh, in the layout it is uploaded in.
+///
+/// Every vertex consists of its position and UV coordinates, as 4 `f32`s. Tangents are stored in
+/// a separate vertex buffer, as 3 `f32`s per vertex (see [`MeshData::compute_tangents`]), so that
+/// only pipelines doing bump mapping need to bind them. Indices are `u16`s.
+#[derive(Debug, PartialEq, Eq, Hash)]
+pub(crate) struct MeshBytes {
+    vertices: Vec<u8>,
+    tangents: Vec<u8>,
+    indices: Vec<u8>,
+}
+
+impl MeshBytes {
+    fn new(mesh: &MeshData) -> Self {
+        let mut uvs = mesh.uvs().into_iter().flatten();
+        let vertices = mesh
+            .verts()
+            .flat_map(|[x, y]| {
+                let [u, v] = uvs.next().unwrap_or_default();
+                [x, y, u, v]
+            })
+            .flat_map(f32::to_ne_bytes)
+            .collect();
+        // Meshes without UVs can't be bump mapped, but get a tangent buffer of matching size.
+        let tangents = mesh
+            .compute_tangents()
+            .unwrap_or_else(|| vec![[1.0, 0.0, 0.0]; mesh.verts().count()])
+            .into_iter()
+            .flatten()
+            .flat_map(f32::to_ne_bytes)
+            .collect();
+        let indices = mesh
+            .indices()
+            .iter()
+            .copied()
+            .flat_map(u16::to_ne_bytes)
+            .collect();
+        Self {
+            vertices,
+            tangents,
+            indices,
+        }
+    }
+
+    pub(crate) fn upload(&self, device: &Device) -> MeshBuffers {
+        use wgpu::util::BufferInitDescriptor;
+
+        MeshBuffers {
+            vertices: device.create_buffer_init(&BufferInitDescriptor {
+                label: None,
+                contents: &self.vertices,
+                usage: BufferUsages::VERTEX,
+            }),
+            tangents: device.create_buffer_init(&BufferInitDescriptor {
+                label: None,
+                contents: &self.tangents,
+                usage: BufferUsages::VERTEX,
+            }),
+            indices: device.create_buffer_init(&BufferInitDescriptor {
+                label: None,
+                contents: &self.indices,
+                usage: BufferUsages::INDEX,
+            }),
+            index_count: (self.indices.len() / 2) as u32,
+        }
+    }
+}
+
+/// Collects the meshes of all drawable nodes below (and including) `root`.
+///
+/// Drawables with identical meshes share a single entry. Returns the distinct meshes, and the index
+/// of the mesh of every drawable node.
+pub(crate) fn collect_meshes(root: &Node) -> (Vec<MeshBytes>, HashMap<Uuid, usize>) {
+    fn visit(
+        node: &Node,
+        indices: &mut HashMap<MeshBytes, usize>,
+        node_meshes: &mut HashMap<Uuid, usize>,
+    ) {
+        let drawable: Option<&Drawable> = match node {
+            Node::Drawable(drawable) => Some(drawable),
+            Node::Part(part) => Some(part),
+            Node::Mask(mask) => Some(mask),
+            _ => None,
+        };
+        if let Some(drawable) = drawable {
+            let bytes = MeshBytes::new(drawable.mesh_data());
+            let next = indices.len();
+            let index = *indices.entry(bytes).or_insert(next);
+            node_meshes.insert(node.uuid(), index);
+        }
+
+        for child in node.children() {
+            visit(child, indices, node_meshes);
+        }
+    }
+
+    let mut indices = HashMap::new();
+    let mut node_meshes = HashMap::new();
+    visit(root, &mut indices, &mut node_meshes);
+
+    let mut meshes = indices.into_iter().collect::<Vec<_>>();
+    meshes.sort_unstable_by_key(|(_, index)| *index);
+    let meshes = meshes.into_iter().map(|(bytes, _)| bytes).collect();
+    (meshes, node_meshes)
+}
+
+#[cfg(test)]
+mod tests {
+    use rhino2d_io::testutil;
+    use serde_json::json;
+
+    use super::*;
+
+    fn part(uuid: u64, verts: [f32; 6]) -> serde_json::Value {
+        json!({
+            "type": "Part",
+            "uuid": uuid,
+            "mesh": {
+                "verts": verts,
+                "uvs": [0.0, 0.0, 1.0, 0.0, 0.0, 1.0],
+                "indices": [0, 1, 2],
+                "origin": [0.0, 0.0],
+            },
+        })
+    }
+
+    #[test]
+    fn shared_meshes() {
+        let triangle = [0.0, 0.0, 1.0, 0.0, 0.0, 1.0];
+        let root = json!({
+            "type": "Node",
+            "uuid": 1,
+            "children": [
+                part(2, triangle),
+                part(3, [0.0, 0.0, 2.0, 0.0, 0.0, 2.0]),
+                part(4, triangle),
+            ],
+        });
+        let puppet = testutil::puppet(&root.to_string(), "[]");
+        let root = puppet.root_node();
+
+        let (meshes, node_meshes) = collect_meshes(root);
+        assert_eq!(meshes.len(), 2);
+        assert_eq!(node_meshes.len(), 3);
+        assert_eq!(node_meshes[&root.children()[0].uuid()], 0);
+        assert_eq!(node_meshes[&root.children()[1].uuid()], 1);
+        assert_eq!(node_meshes[&root.children()[2].uuid()], 0);
+        assert_eq!(meshes[0].vertices.len(), 3 * 4 * 4);
+        assert_eq!(meshes[0].tangents.len(), 3 * 3 * 4);
+        assert_eq!(meshes[0].indices.len(), 3 * 2);
+    }
+}